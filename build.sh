@@ -2,6 +2,10 @@
 
 rm -rf pkg/node
 rm -rf pkg/standalone
+rm -rf pkg/node-lean
+rm -rf pkg/standalone-lean
+rm -rf pkg/node-locate
+rm -rf pkg/standalone-locate
 
 # wasm-pack knows to use wasm-opt, when present
 # NOTE: wasm-pack does not support multi-target building
@@ -10,8 +14,22 @@ rm -rf pkg/standalone
 wasm-pack build --target web --out-dir pkg/standalone --weak-refs --no-pack --release
 wasm-pack build --target nodejs --out-dir pkg/node --weak-refs --no-pack --release
 
+# The lean variants (`content-tag/lean`) leave out the graphical error reporter
+wasm-pack build --target web --out-dir pkg/standalone-lean --weak-refs --no-pack --release -- --no-default-features --features wasm,transform
+wasm-pack build --target nodejs --out-dir pkg/node-lean --weak-refs --no-pack --release -- --no-default-features --features wasm,transform
+
+# The locate-only variants (`content-tag/locate`) have no `process` at all
+wasm-pack build --target web --out-dir pkg/standalone-locate --weak-refs --no-pack --release -- --no-default-features --features wasm
+wasm-pack build --target nodejs --out-dir pkg/node-locate --weak-refs --no-pack --release -- --no-default-features --features wasm
+
 # Rename the node js file to cjs, because we emit type=module
 mv pkg/node/content_tag.js pkg/node/content_tag.cjs
+mv pkg/node-lean/content_tag.js pkg/node-lean/content_tag.cjs
+mv pkg/node-locate/content_tag.js pkg/node-locate/content_tag.cjs
 
 rm pkg/node/.gitignore
 rm pkg/standalone/.gitignore
+rm pkg/node-lean/.gitignore
+rm pkg/standalone-lean/.gitignore
+rm pkg/node-locate/.gitignore
+rm pkg/standalone-locate/.gitignore
@@ -6,6 +6,7 @@
 interface Parsed {
   type: 'expression' | 'class-member';
   tagName: 'template';
+  /** Left out when parsing with `include_content: false`. */
   contents: string;
   range: {
     start: number;
@@ -15,14 +16,187 @@ interface Parsed {
     start: number;
     end: number;
   };
+  /**
+   * The opening tag, from its `<` to just past its `>`, so any attributes
+   * are inside it.
+   */
   startRange: {
     end: number;
     start: number;
   };
+  /** The closing tag. */
   endRange: {
     start: number;
     end: number;
   };
+  /**
+   * The whitespace at the start of the line with the opening tag, for
+   * re-indenting content to match. Left out when there is none.
+   */
+  indentation?: string;
+  leadingComments?: {
+    kind: 'line' | 'block';
+    text: string;
+    range: {
+      start: number;
+      end: number;
+    };
+  }[];
+}
+
+interface Range {
+  start: number;
+  end: number;
+}
+
+/**
+ * Problems that didn't stop `process` from producing output.
+ */
+interface Warning {
+  /** The kind of warning, like `"unresolved-reference"`. */
+  code: string;
+  message: string;
+  /** Byte range of the template the warning is about. */
+  range: Range;
+  location: string;
+  /** `'warning'`, unless `severity_overrides` makes this code an `'error'`. */
+  severity: 'warning' | 'error';
+}
+
+interface InsertedImport {
+  module: string;
+  specifier: string;
+  local: string;
+  range: Range;
+}
+
+interface ProcessMetadata {
+  insertedImport: InsertedImport | null;
+  /** Imports added with the transformer's `context.addImport`. */
+  requestedImports: InsertedImport[];
+  replacements: {
+    originalRange: Range;
+    range: Range;
+  }[];
+  /** Local names of inserted imports dropped by `prune_unused_imports`. */
+  removedImports: string[];
+  /**
+   * Every module the output imports, including the ones content-tag
+   * inserted, deduplicated and in order.
+   */
+  dependencies: string[];
+  /**
+   * Each `eval` generated to give a template access to its scope, which a
+   * Content-Security-Policy without `unsafe-eval` would block. `line` is
+   * 1-based and `column` is 0-based, both in `code`.
+   */
+  evalUsages: {
+    originalRange: Range;
+    range: Range;
+    line: number;
+    column: number;
+  }[];
+  /**
+   * The input had no templates but already calls the template compiler, so
+   * it has probably been through `process` before. Only set with
+   * `detect_already_transformed`.
+   */
+  alreadyTransformed: boolean;
+  /** What the module exports, with `collect_exports`. */
+  exports?: ExportMap;
+}
+
+/**
+ * What a module exports, as written: types are listed even when
+ * `strip_types` removes them, and a bare `<template>` is the default export.
+ */
+interface ExportMap {
+  /** Names exported from the module's own bindings, in order. */
+  named: {
+    name: string;
+    /** The binding exported, which differs from `name` for `export { a as b }`. */
+    local: string;
+    kind: 'variable' | 'function' | 'class' | 'enum' | 'type' | 'namespace' | 'binding';
+  }[];
+  default: {
+    kind: 'template' | 'class' | 'function' | 'type' | 'binding' | 'expression';
+    /** The class or function's name, or the binding for `'binding'`. */
+    name?: string;
+  } | null;
+  /**
+   * `export { a } from "x"` and `export * from "x"`. `name` is `null` for
+   * `export *`, and `imported` is `null` for `export *` and `export * as ns`.
+   */
+  reExports: {
+    module: string;
+    name: string | null;
+    imported: string | null;
+  }[];
+}
+
+/**
+ * Attached as `sourceInfo` to errors thrown by `Preprocessor`, so that callers
+ * can render their own code frames.
+ */
+interface SourceInfo {
+  file: string;
+  sourceText: string;
+  /** The byte offset at which each line starts. */
+  lineStarts: number[];
+}
+
+/**
+ * Attached as `loc` to parse errors thrown by `Preprocessor`. Lines are
+ * 1-based, columns are 0-based.
+ */
+interface ErrorLoc {
+  file: string;
+  startLine: number;
+  /** In characters. */
+  startColumn: number;
+  /** With each tab reaching the next multiple of `tab_width`. */
+  startVisualColumn: number;
+  endLine: number;
+  endColumn: number;
+  endVisualColumn: number;
+  byteStart: number;
+  byteEnd: number;
+}
+
+interface Capabilities {
+  version: string;
+  schemaVersion: number;
+  /** How class-member templates can be emitted. */
+  classMemberStrategies: ("static-block" | "after-class")[];
+  syntaxes: ("javascript" | "typescript")[];
+  decorators: "legacy";
+  sourceMapModes: ("separate" | "inline")[];
+  hashAlgorithms: ("xxh3" | "sha256")[];
+  /** The names of the `PreprocessorOptions` this build understands. */
+  options: string[];
+  features: string[];
+}
+
+interface TemplateMetrics {
+  range: {
+    start: number;
+    end: number;
+  };
+  /** Of the content. */
+  bytes: number;
+  lines: number;
+  /** `{{...}}`, including block openers and closers but not comments. */
+  mustaches: number;
+  /** `{{#...}}`. */
+  blocks: number;
+  /** Opening tags, like `<div>` or `<Foo />`. */
+  elements: number;
+  /** The subset of `elements` that are components. */
+  components: number;
+  /** `{{!...}}` and `<!--...-->`. */
+  comments: number;
+  /** The content isn't empty, but it's all whitespace. */
+  whitespaceOnly: boolean;
 }
 
 interface PreprocessorOptions {
@@ -30,8 +204,335 @@ interface PreprocessorOptions {
   /** Default is `false` */
   inline_source_map?: boolean;
 
+  /**
+   * Print the output without whitespace or comments that aren't needed,
+   * for when content-tag is the last step before serving. Names aren't
+   * mangled and nothing is compressed, so a real minifier will still do
+   * better. Not supported with `emit: 'unified-diff'`. Default is `false`
+   */
+  minify?: boolean;
+
+  /** The source map's `file`, which is left out by default. */
+  source_map_file?: string;
+
+  /**
+   * Write the source map's `sources` relative to this directory (usually the
+   * project root or the output directory), when they're paths.
+   */
+  source_map_relative_to?: string;
+
+  /**
+   * Write the source map's `sources` with `/` instead of `\`, which is what
+   * browser devtools expect, even for paths from Windows. Default is `false`
+   */
+  source_map_forward_slashes?: boolean;
+
+  /**
+   * The file's path, or a bundler's id for the module (like
+   * `'\0virtual:foo.gjs'`) or its URL, which are kept exactly as given in
+   * source maps and error messages.
+   */
   filename?: string;
 
+  /**
+   * Re-parse the generated code and throw if it doesn't match the transformed
+   * program. Meant for debugging and CI. Default is `false`
+   */
+  verify_output?: boolean;
+
+  /**
+   * Include the comments leading up to each template in `parse` results, as
+   * `leadingComments`. Default is `false`
+   */
+  leading_comments?: boolean;
+
+  /**
+   * Include each template's `contents` in `parse` results. Editors that
+   * parse on every keystroke and only need the ranges can turn this off to
+   * skip copying every template. Default is `true`
+   */
+  include_content?: boolean;
+
+  /**
+   * Strip the indentation shared by the lines of each template from the
+   * emitted content. Default is `false`
+   */
+  dedent_content?: boolean;
+
+  /**
+   * Remove whitespace from the start and/or end of each template's content.
+   * Default is to keep it.
+   */
+  trim_content?: 'both' | 'start' | 'end';
+
+  /**
+   * Rewrites each template's content before it is emitted. Throwing marks
+   * that template as failed; the other templates are still processed, and
+   * `process` then throws an error whose `templateErrors` lists every failed
+   * template.
+   *
+   * Output that needs another module can call `context.addImport`, which
+   * adds the import to the module and returns the local name to use for it.
+   * The name starts with `localHint` (or the specifier) and can't collide
+   * with anything else in the module. Requested imports are listed in
+   * `metadata.requestedImports`.
+   */
+  transformer?: (
+    content: string,
+    context: {
+      type: 'expression' | 'class-member';
+      range: { start: number; end: number };
+      addImport(module: string, specifier: string, localHint?: string): string;
+    }
+  ) => string;
+
+  /**
+   * Like `transformer`, but a list that is applied in order, each one
+   * receiving the output of the one before. When both are given,
+   * `transformer` runs first. A template fails at the first transformer that
+   * throws.
+   */
+  transformers?: NonNullable<PreprocessorOptions['transformer']>[];
+
+  /** Wrap the code frames in `source_code` at this many columns. */
+  error_width?: number;
+
+  /**
+   * Draw the code frames in `source_code` with ASCII instead of unicode
+   * box-drawing characters. Default is `false`
+   */
+  error_ascii?: boolean;
+
+  /**
+   * Set to `false` to skip producing the colored `source_code_color`.
+   * Default is `true`
+   */
+  error_color?: boolean;
+
+  /**
+   * Show at most this many characters of each line in the code frames in
+   * `source_code`, around the error, with `…` where a line is cut. Keeps
+   * errors in huge single-line templates readable. Default is `240`
+   */
+  error_line_window?: number;
+
+  /**
+   * Also convert `hbs` tagged templates imported from `ember-cli-htmlbars`
+   * (or `htmlbars-inline-precompile`) into template calls, for files that mix
+   * both syntaxes while migrating. Templates that use `${}` interpolation are
+   * reported as errors. Default is `false`
+   */
+  hbs_tagged_templates?: boolean;
+
+  /**
+   * Also convert `precompileTemplate` calls from `@ember/template-compilation`
+   * into template calls that keep their explicit `scope`. The template must be
+   * a string literal and the options an object literal, with no `strictMode`
+   * other than `true`, since template calls are always in strict mode.
+   * Default is `false`
+   */
+  precompile_template_calls?: boolean;
+
+  /**
+   * Let a `/* @scope a, b *\/` comment right before a template list its scope
+   * bindings, which are emitted as `scope: () => ({ a, b })` instead of the
+   * `eval` trick. Each name must be defined in the module. Default is `false`
+   */
+  explicit_scope?: boolean;
+
+  /**
+   * The oldest ECMAScript version the output needs to run on. Before
+   * `"es2022"`, templates in class bodies are emitted as a call after the
+   * class instead of in a static block, which only works for class
+   * declarations. Default is the latest.
+   */
+  target?:
+    | "es2015"
+    | "es2016"
+    | "es2017"
+    | "es2018"
+    | "es2019"
+    | "es2020"
+    | "es2021"
+    | "es2022";
+
+  /**
+   * Remove TypeScript syntax from the output: type annotations, interfaces
+   * and type-only imports, and class members that only exist for the type
+   * checker (`declare` fields, index signatures). Without it, all of these
+   * are kept as they were, templates in the same class included. Not
+   * supported with `emit: 'unified-diff'`. Default is `false`
+   */
+  strip_types?: boolean;
+
+  /**
+   * Pass `component: this` to expression templates inside class methods,
+   * constructors and property initializers (including arrow functions within
+   * them), where `this` is the instance. Default is `false`
+   */
+  component_this_in_methods?: boolean;
+
+  /**
+   * Add a warning to the result of `process` for each name that a template
+   * uses (like `{{foo}}` or `<Foo />`) which isn't defined where the template
+   * is. Default is `false`
+   */
+  warn_unresolved?: boolean;
+
+  /**
+   * Drop imports that content-tag inserted but that nothing ends up using,
+   * listing them in `metadata.removedImports`. Default is `false`
+   */
+  prune_unused_imports?: boolean;
+
+  /**
+   * Route each tag to its own transformers and compiler import, run after
+   * `transformer` and `transformers`. Once any tag is listed, every tag that
+   * can be parsed needs an entry; currently that is just `template`. The
+   * default import is `template` from `@ember/template-compiler`.
+   */
+  tags?: Record<
+    string,
+    {
+      transformer?: PreprocessorOptions['transformer'];
+      transformers?: PreprocessorOptions['transformers'];
+      import_module?: string;
+      import_specifier?: string;
+    }
+  >;
+
+  /**
+   * Print imports whose name and local name are the same, like
+   * `import { a as a }` or `import { "a" as a }`, as `import { a }`. This
+   * applies to every import in a module that has templates, so it is off by
+   * default to keep output stable. Default is `false`
+   */
+  simplify_imports?: boolean;
+
+  /**
+   * Set to `false` to skip swc's resolver, for pipelines that run their own
+   * later and don't want names resolved twice. Default is `true`. Even
+   * then, it only runs for the options that need it: `explicit_scope`,
+   * `forbid_eval`, `warn_unresolved` and `strip_types`, which can't be
+   * combined with `run_resolver_hygiene: false`.
+   */
+  run_resolver_hygiene?: boolean;
+
+  /**
+   * Called with what `process` is doing: once for each phase (`parse`,
+   * `transform`, `resolver`, `print`, `verify`) when it finishes, with how
+   * long it took, and for each diagnostic event. Meant for finding out why a
+   * file is slow.
+   */
+  log?: (entry: {
+    level: "TRACE" | "DEBUG" | "INFO" | "WARN" | "ERROR";
+    message: string;
+    phase?: string;
+    durationMs?: number;
+  }) => void;
+
+  /**
+   * Fail with a template error, instead of falling back to the `eval` trick,
+   * for templates without a `/* @scope ... *\/` comment. Turns on
+   * `explicit_scope`, so it can't be combined with
+   * `run_resolver_hygiene: false`. Default is `false`
+   */
+  forbid_eval?: boolean;
+  /**
+   * `'unified-diff'` makes `process` return, as `code`, a unified diff from
+   * `src` to the output instead. The diff is built by splicing the templates
+   * into the source, so the formatting everywhere else is left as it was.
+   * `map` and `metadata` are then empty, and the options that need the whole
+   * module (like `explicit_scope` or `target` before `'es2022'`) throw.
+   * Default is `'code'`
+   */
+  emit?: 'code' | 'unified-diff';
+  /**
+   * Emit the content of templates that are nothing but whitespace (usually
+   * left over from formatting) as an empty string, with a
+   * `whitespace-only-template` warning for each. Empty templates are left
+   * alone. Default is `false`
+   */
+  collapse_whitespace_only?: boolean;
+  /**
+   * Warn (with the code `template-too-large`) about each template whose
+   * content is over this many bytes, or this many lines, as a hint to split
+   * it into smaller components. Both are off by default.
+   */
+  max_template_bytes?: number;
+  max_template_lines?: number;
+  /**
+   * Where the `export default` that a bare top-level `<template>` becomes
+   * goes: `'in-place'` where the template was, or `'end'` after everything
+   * else in the module, so that it reads after the helpers it uses. The
+   * inserted import always comes first, and everything else keeps its order.
+   * `process` with `emit: 'unified-diff'` only supports `'in-place'`.
+   * Default is `'in-place'`
+   */
+  default_export_placement?: 'in-place' | 'end';
+  /**
+   * Make each class with a `<template>` member that doesn't extend anything
+   * extend a component base class, imported from `'@glimmer/component'`
+   * (for `true`) or from the module given. The import is listed in
+   * `metadata.requestedImports`. Not supported with `emit: 'unified-diff'`.
+   * Default is `false`
+   */
+  implicit_component_base?: boolean | string;
+  /**
+   * The shape of the options object passed to each template call: `1` is
+   * `{ eval() { ... } }` for every template, `2` also passes
+   * `component: this` for class member templates. Pin one so that upgrading
+   * content-tag doesn't change your compiled output; new shapes only come as
+   * new versions. `1` can't be used with `component_this_in_methods`.
+   * Default is the latest, `2`
+   */
+  snippet_version?: 1 | 2;
+  /**
+   * Set `metadata.alreadyTransformed` for files with no templates that import
+   * the template compiler and call it, the way content-tag's own output
+   * does, to catch pipelines that process the same file twice. Not supported
+   * with `emit: 'unified-diff'`.
+   * Default is `false`
+   */
+  detect_already_transformed?: boolean;
+  /**
+   * List what the module exports in `metadata.exports`, from the same parse,
+   * for tools that index components and routes. Not supported with
+   * `emit: 'unified-diff'`.
+   * Default is `false`
+   */
+  collect_exports?: boolean;
+  /**
+   * Wrap each generated template call in `/*#__CONTENT_TAG_START__*/` and
+   * `/*#__CONTENT_TAG_END__*/` comments, so tools that read the output can
+   * find the code content-tag generated. The markers are kept in minified
+   * output, and `metadata.replacements` still gives the range of each call
+   * without them.
+   * Default is `false`
+   */
+  mark_generated_regions?: boolean;
+  /**
+   * Put `/*#__PURE__*/` before each template call that is the value of a
+   * variable or assignment, or the default export, so that minifiers like
+   * terser and esbuild can drop template-only components nothing uses. With
+   * `mark_generated_regions` too, it goes inside the region.
+   * Default is `false`
+   */
+  pure_annotations?: boolean;
+  /**
+   * The severity to give warnings with each code, like
+   * `{ "template-too-large": "error" }` in CI, or `"off"` to leave warnings
+   * with that code out. Warnings are `"warning"` otherwise.
+   * `content-tag <dir>` fails when a file has a warning that is an error.
+   * Default is `{}`
+   */
+  severity_overrides?: Record<string, 'off' | 'warning' | 'error'>;
+  /**
+   * How many columns a tab takes up, for the `startVisualColumn` and
+   * `endVisualColumn` of an error's `loc`.
+   * Default is `4`
+   */
+  tab_width?: number;
 }
 
 /**
@@ -39,6 +540,16 @@ interface PreprocessorOptions {
 export class Preprocessor {
   free(): void;
 /**
+* The version of the `Parsed` schema returned by `parse`.
+* @returns {number}
+*/
+  static schemaVersion(): number;
+/**
+* What this build supports, for feature negotiation.
+* @returns {Capabilities}
+*/
+  static capabilities(): Capabilities;
+/**
 */
   constructor();
 /**
@@ -46,11 +557,34 @@ export class Preprocessor {
 * @param {PreprocessorOptions | undefined} options
 * @returns {string}
 */
-  process(src: string, options?: PreprocessorOptions): { code: string; map: string; };
+  process(src: string, options?: PreprocessorOptions): { code: string; map: string; metadata: ProcessMetadata; warnings: Warning[]; };
+/**
+* @param {Uint8Array} src
+* @param {PreprocessorOptions | undefined} options
+* @returns {string}
+*/
+  processBytes(src: Uint8Array, options?: PreprocessorOptions): { code: string; map: string; metadata: ProcessMetadata; warnings: Warning[]; };
+/**
+* @param {string[]} sources
+* @param {PreprocessorOptions | undefined} options
+* @returns {any[]}
+*/
+  processMany(sources: string[], options?: PreprocessorOptions): ({ code: string; map: string; metadata: ProcessMetadata; warnings: Warning[]; } | { error: Error; })[];
 /**
 * @param {string} src
 * @param {PreprocessorOptions | undefined} options
 * @returns {any}
 */
   parse(src: string, options?: PreprocessorOptions): Parsed[];
+/**
+* @param {string} src
+* @param {'xxh3' | 'sha256' | undefined} algorithm
+* @returns {any}
+*/
+  contentHashes(src: string, algorithm?: 'xxh3' | 'sha256'): { range: { start: number; end: number; }; hash: string; }[];
+/**
+* @param {string} src
+* @returns {any}
+*/
+  analyzeMetrics(src: string): TemplateMetrics[];
 }
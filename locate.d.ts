@@ -0,0 +1,43 @@
+/*
+ * The locate-only build, `content-tag/locate`, which has everything but
+ * `process`.
+ * this file must be manually kept in sync with ./locate.d.cts
+ */
+
+import type { Preprocessor as FullPreprocessor } from "./index.js";
+
+/**
+*/
+export class Preprocessor {
+  free(): void;
+/**
+* The version of the `Parsed` schema returned by `parse`.
+* @returns {number}
+*/
+  static schemaVersion(): number;
+/**
+* What this build supports, for feature negotiation.
+* @returns {Capabilities}
+*/
+  static capabilities(): ReturnType<typeof FullPreprocessor.capabilities>;
+/**
+*/
+  constructor();
+/**
+* @param {string} src
+* @param {PreprocessorOptions | undefined} options
+* @returns {any}
+*/
+  parse(...args: Parameters<FullPreprocessor["parse"]>): ReturnType<FullPreprocessor["parse"]>;
+/**
+* @param {string} src
+* @param {'xxh3' | 'sha256' | undefined} algorithm
+* @returns {any}
+*/
+  contentHashes(...args: Parameters<FullPreprocessor["contentHashes"]>): ReturnType<FullPreprocessor["contentHashes"]>;
+/**
+* @param {string} src
+* @returns {any}
+*/
+  analyzeMetrics(...args: Parameters<FullPreprocessor["analyzeMetrics"]>): ReturnType<FullPreprocessor["analyzeMetrics"]>;
+}
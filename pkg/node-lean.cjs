@@ -0,0 +1,3 @@
+const { Preprocessor } = require("./node-lean/content_tag.cjs");
+
+module.exports.Preprocessor = Preprocessor;
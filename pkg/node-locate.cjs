@@ -0,0 +1,3 @@
+const { Preprocessor } = require("./node-locate/content_tag.cjs");
+
+module.exports.Preprocessor = Preprocessor;
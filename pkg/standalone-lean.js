@@ -0,0 +1,4 @@
+import init from "./standalone-lean/content_tag.js";
+export { Preprocessor  } from "./standalone-lean/content_tag.js";
+
+await init();
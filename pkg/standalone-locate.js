@@ -0,0 +1,4 @@
+import init from "./standalone-locate/content_tag.js";
+export { Preprocessor  } from "./standalone-locate/content_tag.js";
+
+await init();
@@ -0,0 +1,11 @@
+import { template as template_UUID } from "@ember/template-compiler";
+class X {
+    static {
+        template_UUID(`Hello`, {
+            component: this,
+            eval () {
+                return eval(arguments[0]);
+            }
+        });
+    }
+}
@@ -0,0 +1,6 @@
+import { template as template_UUID } from "@ember/template-compiler";
+export default template_UUID(`Hi`, {
+    eval () {
+        return eval(arguments[0]);
+    }
+});
@@ -0,0 +1,179 @@
+// Comments we put around and before the template calls we generate:
+// region markers with `Options::mark_generated_regions`, so that tools
+// reading our output can tell the code we wrote from the code that was
+// there, and `#__PURE__` with `Options::pure_annotations`, so that minifiers
+// can drop templates nothing uses. We add them to the printed text (rather
+// than giving the emitter comments of its own) so that they sit exactly
+// around and before the call, whatever the emitter does with comments at the
+// start of a statement, and minified output has them too.
+
+use swc_common::source_map::LineCol;
+use swc_common::BytePos;
+
+use crate::{metadata, Options};
+
+pub const GENERATED_REGION_START: &str = "/*#__CONTENT_TAG_START__*/";
+pub const GENERATED_REGION_END: &str = "/*#__CONTENT_TAG_END__*/";
+const PURE: &str = "/*#__PURE__*/";
+
+pub(crate) fn wanted(options: &Options) -> bool {
+    options.mark_generated_regions || options.pure_annotations
+}
+
+// Adds the comments `options` ask for to each call to `local` in `code`,
+// moving the columns of any `mappings` that come after one on its line.
+pub(crate) fn annotate(
+    code: &str,
+    local: &str,
+    options: &Options,
+    mappings: &mut [(BytePos, LineCol)],
+) -> String {
+    let mut comments = vec![];
+    for call in metadata::generated_calls(code, local) {
+        if options.mark_generated_regions {
+            comments.push((call.start, GENERATED_REGION_START));
+        }
+        if options.pure_annotations && is_assigned(&code[..call.start]) {
+            comments.push((call.start, PURE));
+        }
+        if options.mark_generated_regions {
+            comments.push((call.end, GENERATED_REGION_END));
+        }
+    }
+    if comments.is_empty() {
+        return code.to_owned();
+    }
+
+    let mut annotated =
+        String::with_capacity(code.len() + comments.iter().map(|(_, c)| c.len()).sum::<usize>());
+    // where each comment went, as a line and a column in UTF-16 code units
+    // (which is what the mappings count in) of `code`, and its length
+    let mut inserted: Vec<(u32, u32, u32)> = Vec::with_capacity(comments.len());
+    let mut copied = 0;
+    let mut line = 0;
+    let mut line_start = 0;
+    for (offset, comment) in comments {
+        let before = &code[copied..offset];
+        if let Some(last) = before.rfind('\n') {
+            line += before.matches('\n').count() as u32;
+            line_start = copied + last + 1;
+        }
+        let col = code[line_start..offset].encode_utf16().count() as u32;
+        inserted.push((line, col, comment.len() as u32));
+        annotated.push_str(before);
+        annotated.push_str(comment);
+        copied = offset;
+    }
+    annotated.push_str(&code[copied..]);
+
+    for (_, line_col) in mappings.iter_mut() {
+        let moved: u32 = inserted
+            .iter()
+            .filter(|(line, col, _)| *line == line_col.line && *col <= line_col.col)
+            .map(|(_, _, len)| len)
+            .sum();
+        line_col.col += moved;
+    }
+    annotated
+}
+
+// Whether the call after `before` is the value of a variable or of an
+// assignment, or the default export, looking through parentheses. Those are
+// the calls whose result a minifier can see go unused.
+fn is_assigned(before: &str) -> bool {
+    let before = before.trim_end_matches(|c: char| c == '(' || c.is_whitespace());
+    if let Some(rest) = before.strip_suffix("export default") {
+        return !rest.ends_with(|c: char| c.is_alphanumeric() || c == '_' || c == '$');
+    }
+    match before.strip_suffix('=') {
+        Some(rest) => !rest.ends_with([
+            '=', '!', '<', '>', '+', '-', '*', '/', '%', '&', '|', '^', '?',
+        ]),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+use crate::{Preprocessor, IMPORT_ALIAS};
+
+#[test]
+fn test_marks_each_generated_call() {
+    let src = "let a = <template>(Hi</template>;\nexport default <template>Bye</template>;";
+    let options = || Options {
+        mark_generated_regions: true,
+        ..Default::default()
+    };
+    let p = Preprocessor::new();
+    let marked = p.process(src, options()).unwrap();
+    let plain = Preprocessor::new()
+        .process(src, Default::default())
+        .unwrap();
+    assert_eq!(
+        marked
+            .code
+            .replace(GENERATED_REGION_START, "")
+            .replace(GENERATED_REGION_END, ""),
+        plain.code
+    );
+    let starts = marked
+        .code
+        .matches(&format!("{GENERATED_REGION_START}{IMPORT_ALIAS}(`"))
+        .count();
+    assert_eq!(starts, 2);
+    for replacement in &marked.metadata.replacements {
+        let range = replacement.range;
+        assert!(marked.code[..range.start].ends_with(GENERATED_REGION_START));
+        assert!(marked.code[range.end..].starts_with(GENERATED_REGION_END));
+    }
+}
+
+#[test]
+fn test_marks_assigned_calls_pure() {
+    let src = "let a = <template>A</template>;\n\
+               b = (<template>B</template>);\n\
+               f(<template>C</template>);\n\
+               if (x == <template>D</template>) {}\n\
+               <template>E</template>";
+    let output = Preprocessor::new()
+        .process(
+            src,
+            Options {
+                pure_annotations: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let pure: Vec<bool> = output
+        .metadata
+        .replacements
+        .iter()
+        .map(|replacement| output.code[..replacement.range.start].ends_with(PURE))
+        .collect();
+    assert_eq!(pure, vec![true, true, false, false, true]);
+}
+
+#[test]
+fn test_moves_mappings_after_comments() {
+    let code = format!("let é = {IMPORT_ALIAS}(`a`), b = 1;");
+    let column = |text: &str| code.split(text).next().unwrap().encode_utf16().count() as u32;
+    let at = |col| (BytePos(0), LineCol { line: 0, col });
+    let mut mappings = vec![at(0), at(column(IMPORT_ALIAS)), at(column(", b"))];
+    let options = Options {
+        mark_generated_regions: true,
+        pure_annotations: true,
+        ..Default::default()
+    };
+    let annotated = annotate(&code, IMPORT_ALIAS, &options, &mut mappings);
+    assert!(annotated.contains(&format!("= {GENERATED_REGION_START}{PURE}{IMPORT_ALIAS}(")));
+    let annotated_column =
+        |text: &str| annotated.split(text).next().unwrap().encode_utf16().count() as u32;
+    let cols: Vec<u32> = mappings.iter().map(|(_, line_col)| line_col.col).collect();
+    assert_eq!(
+        cols,
+        vec![
+            0,
+            annotated_column(&format!("{IMPORT_ALIAS}(")),
+            annotated_column(", b")
+        ]
+    );
+}
@@ -1,12 +1,31 @@
-use crate::{Options, Preprocessor as CorePreprocessor};
-use js_sys::Reflect;
-use std::{fmt, path::PathBuf, str};
-use swc_common::{
-    errors::Handler,
-    sync::{Lock, Lrc},
-    SourceMap, Spanned,
+use crate::error::{cut_line, window_around};
+use crate::{
+    Capabilities, ContentTagKind, DefaultExportPlacement, Emit, ErrorRendering, EsVersion, FileId,
+    HashAlgorithm, Options, ParseOutput, Preprocessor as CorePreprocessor, Severity,
+    SnippetVersion, TagHandler, TemplateContext, Transformer, TrimContent, GLIMMER_COMPONENT,
+    SCHEMA_VERSION,
 };
+#[cfg(feature = "transform")]
+use crate::{Error, ProcessedFile};
+use js_sys::Reflect;
+use std::{cell::RefCell, collections::BTreeMap, path::PathBuf, rc::Rc, str};
+#[cfg(feature = "pretty-errors")]
+use swc_common::{errors::Handler, sync::Lock, BytePos};
+use swc_common::{sync::Lrc, SourceMap, Spanned};
+#[cfg(feature = "pretty-errors")]
 use swc_error_reporters::{GraphicalReportHandler, GraphicalTheme, PrettyEmitter};
+#[cfg(feature = "transform")]
+use tracing::field::{Field, Visit};
+#[cfg(feature = "transform")]
+use tracing::span::{Attributes, Id};
+#[cfg(feature = "transform")]
+use tracing::{Event, Subscriber};
+#[cfg(feature = "transform")]
+use tracing_subscriber::layer::{Context, Layer};
+#[cfg(feature = "transform")]
+use tracing_subscriber::prelude::*;
+#[cfg(feature = "transform")]
+use tracing_subscriber::registry::LookupSpan;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -24,47 +43,539 @@ extern "C" {
     fn js_string(value: &JsValue) -> String;
 }
 
+// The types of what crosses the wasm boundary, for the `.d.ts` that
+// wasm-bindgen generates, so that it has more than `any` to say about options
+// and results. `index.d.ts` documents the same types for the npm package.
+//
+// These are written by hand rather than generated. `PreprocessorOptions` is
+// read key by key in `Options::new`, since it holds JS functions, so there's no
+// Rust type to generate it from, and a derive for the rest (ts-rs, tsify)
+// would be a new dependency of the wasm build. Instead `test_typescript_types`
+// checks the keys of every interface here and in both `index.d.ts` files, and
+// the string values we declare, against what the Rust types serialize.
+#[wasm_bindgen(typescript_custom_section)]
+const TYPESCRIPT_TYPES: &'static str = r#"
+export interface Parsed {
+  type: 'expression' | 'class-member';
+  tagName: 'template';
+  contents: string;
+  range: {
+    start: number;
+    end: number;
+  };
+  contentRange: {
+    start: number;
+    end: number;
+  };
+  startRange: {
+    end: number;
+    start: number;
+  };
+  endRange: {
+    start: number;
+    end: number;
+  };
+  indentation?: string;
+  leadingComments?: {
+    kind: 'line' | 'block';
+    text: string;
+    range: {
+      start: number;
+      end: number;
+    };
+  }[];
+}
+export interface Range {
+  start: number;
+  end: number;
+}
+export interface Warning {
+  code: string;
+  message: string;
+  range: Range;
+  location: string;
+  severity: 'warning' | 'error';
+}
+export interface InsertedImport {
+  module: string;
+  specifier: string;
+  local: string;
+  range: Range;
+}
+export interface ProcessMetadata {
+  insertedImport: InsertedImport | null;
+  requestedImports: InsertedImport[];
+  replacements: {
+    originalRange: Range;
+    range: Range;
+  }[];
+  removedImports: string[];
+  dependencies: string[];
+  evalUsages: {
+    originalRange: Range;
+    range: Range;
+    line: number;
+    column: number;
+  }[];
+  alreadyTransformed: boolean;
+  exports?: ExportMap;
+}
+export interface ExportMap {
+  named: {
+    name: string;
+    local: string;
+    kind: 'variable' | 'function' | 'class' | 'enum' | 'type' | 'namespace' | 'binding';
+  }[];
+  default: {
+    kind: 'template' | 'class' | 'function' | 'type' | 'binding' | 'expression';
+    name?: string;
+  } | null;
+  reExports: {
+    module: string;
+    name: string | null;
+    imported: string | null;
+  }[];
+}
+export interface SourceInfo {
+  file: string;
+  sourceText: string;
+  lineStarts: number[];
+}
+export interface ErrorLoc {
+  file: string;
+  startLine: number;
+  startColumn: number;
+  startVisualColumn: number;
+  endLine: number;
+  endColumn: number;
+  endVisualColumn: number;
+  byteStart: number;
+  byteEnd: number;
+}
+export interface Capabilities {
+  version: string;
+  schemaVersion: number;
+  classMemberStrategies: ("static-block" | "after-class")[];
+  syntaxes: ("javascript" | "typescript")[];
+  decorators: "legacy";
+  sourceMapModes: ("separate" | "inline")[];
+  hashAlgorithms: ("xxh3" | "sha256")[];
+  options: string[];
+  features: string[];
+}
+export interface TemplateMetrics {
+  range: {
+    start: number;
+    end: number;
+  };
+  bytes: number;
+  lines: number;
+  mustaches: number;
+  blocks: number;
+  elements: number;
+  components: number;
+  comments: number;
+  whitespaceOnly: boolean;
+}
+export interface PreprocessorOptions {
+  inline_source_map?: boolean;
+  minify?: boolean;
+  source_map_file?: string;
+  source_map_relative_to?: string;
+  source_map_forward_slashes?: boolean;
+  filename?: string;
+  verify_output?: boolean;
+  leading_comments?: boolean;
+  include_content?: boolean;
+  dedent_content?: boolean;
+  trim_content?: 'both' | 'start' | 'end';
+  transformer?: (
+    content: string,
+    context: {
+      type: 'expression' | 'class-member';
+      range: { start: number; end: number };
+      addImport(module: string, specifier: string, localHint?: string): string;
+    }
+  ) => string;
+  transformers?: NonNullable<PreprocessorOptions['transformer']>[];
+  error_width?: number;
+  error_ascii?: boolean;
+  error_color?: boolean;
+  error_line_window?: number;
+  hbs_tagged_templates?: boolean;
+  precompile_template_calls?: boolean;
+  explicit_scope?: boolean;
+  target?:
+    | "es2015"
+    | "es2016"
+    | "es2017"
+    | "es2018"
+    | "es2019"
+    | "es2020"
+    | "es2021"
+    | "es2022";
+  strip_types?: boolean;
+  component_this_in_methods?: boolean;
+  warn_unresolved?: boolean;
+  prune_unused_imports?: boolean;
+  tags?: Record<
+    string,
+    {
+      transformer?: PreprocessorOptions['transformer'];
+      transformers?: PreprocessorOptions['transformers'];
+      import_module?: string;
+      import_specifier?: string;
+    }
+  >;
+  simplify_imports?: boolean;
+  run_resolver_hygiene?: boolean;
+  log?: (entry: {
+    level: "TRACE" | "DEBUG" | "INFO" | "WARN" | "ERROR";
+    message: string;
+    phase?: string;
+    durationMs?: number;
+  }) => void;
+  forbid_eval?: boolean;
+  emit?: 'code' | 'unified-diff';
+  collapse_whitespace_only?: boolean;
+  max_template_bytes?: number;
+  max_template_lines?: number;
+  default_export_placement?: 'in-place' | 'end';
+  implicit_component_base?: boolean | string;
+  snippet_version?: 1 | 2;
+  detect_already_transformed?: boolean;
+  collect_exports?: boolean;
+  mark_generated_regions?: boolean;
+  pure_annotations?: boolean;
+  severity_overrides?: Record<string, 'off' | 'warning' | 'error'>;
+  tab_width?: number;
+}
+export interface TextEdit {
+  range: Range;
+  newText: string;
+}
+export interface TemplateError {
+  range: Range;
+  location: string;
+  message: string;
+  suggestedEdits?: TextEdit[];
+  labels?: {
+    range: Range;
+    location: string;
+    message: string;
+  }[];
+}
+export interface ContentTagError extends Error {
+  loc?: ErrorLoc;
+  suggestedEdits?: TextEdit[];
+  templateErrors?: TemplateError[];
+  sourceInfo?: SourceInfo;
+  source_code?: string;
+  source_code_color?: string;
+}
+export type ProcessManyResult =
+  | { code: string; map: string; metadata: ProcessMetadata; warnings: Warning[] }
+  | { error: ContentTagError };
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "PreprocessorOptions")]
+    pub type JsPreprocessorOptions;
+
+    #[wasm_bindgen(typescript_type = "Parsed[]")]
+    pub type JsParsed;
+
+    #[wasm_bindgen(typescript_type = "ProcessMetadata")]
+    pub type JsProcessMetadata;
+
+    #[wasm_bindgen(typescript_type = "Warning[]")]
+    pub type JsWarnings;
+
+    #[wasm_bindgen(typescript_type = "Capabilities")]
+    pub type JsCapabilities;
+
+    #[wasm_bindgen(typescript_type = "TemplateMetrics[]")]
+    pub type JsTemplateMetrics;
+
+    #[wasm_bindgen(typescript_type = "ProcessManyResult[]")]
+    pub type JsProcessManyResults;
+}
+
 impl Options {
-    pub fn new(options: JsValue) -> Self {
+    // Fails with what JS threw when reading an option does, like a getter
+    // that throws, or `options` that aren't an object.
+    pub fn new(options: JsValue) -> Result<Self, JsValue> {
         if js_boolean(&options) {
-            // unwrapping here beacuse we already checked truthiness of
-            // `options`, so the normal case of not passing any options has been
-            // handled and this will only fail in unusual cases (like a
-            // Javascript getter throwing)
-            let option_filename = Reflect::get(&options, &"filename".into()).unwrap();
+            let option_filename = get_option(&options, "filename")?;
             let filename = if js_boolean(&option_filename) {
-                Some(PathBuf::from(js_string(&option_filename)))
+                Some(FileId::from(js_string(&option_filename)))
             } else {
                 None
             };
 
-            Self {
-                // unwrap is justified here for the same reasons as commented above
-                inline_source_map: js_boolean(
-                    &Reflect::get(&options, &"inline_source_map".into()).unwrap(),
-                ),
+            Ok(Self {
+                inline_source_map: bool_option(&options, "inline_source_map")?,
+                minify: bool_option(&options, "minify")?,
+                source_map_file: string_option(&options, "source_map_file")?,
+                source_map_relative_to: string_option(&options, "source_map_relative_to")?
+                    .map(PathBuf::from),
+                source_map_forward_slashes: bool_option(&options, "source_map_forward_slashes")?,
+                verify_output: bool_option(&options, "verify_output")?,
+                leading_comments: bool_option(&options, "leading_comments")?,
+                include_content: get_option(&options, "include_content")?
+                    .as_bool()
+                    .unwrap_or(true),
+                dedent_content: bool_option(&options, "dedent_content")?,
+                trim_content: match string_option(&options, "trim_content")?.as_deref() {
+                    Some("both") => TrimContent::Both,
+                    Some("start") => TrimContent::Start,
+                    Some("end") => TrimContent::End,
+                    _ => TrimContent::None,
+                },
+                transformers: js_transformers(&options)?,
+                error_rendering: ErrorRendering {
+                    width: get_option(&options, "error_width")?
+                        .as_f64()
+                        .map(|width| width as usize),
+                    ascii: bool_option(&options, "error_ascii")?,
+                    // colors are on unless explicitly turned off
+                    no_color: get_option(&options, "error_color")?.as_bool() == Some(false),
+                    line_window: get_option(&options, "error_line_window")?
+                        .as_f64()
+                        .map(|window| window as usize),
+                },
+                hbs_tagged_templates: bool_option(&options, "hbs_tagged_templates")?,
+                precompile_template_calls: bool_option(&options, "precompile_template_calls")?,
+                explicit_scope: bool_option(&options, "explicit_scope")?,
+                strip_types: bool_option(&options, "strip_types")?,
+                component_this_in_methods: bool_option(&options, "component_this_in_methods")?,
+                warn_unresolved: bool_option(&options, "warn_unresolved")?,
+                prune_unused_imports: bool_option(&options, "prune_unused_imports")?,
+                tags: tags_option(&options)?,
+                simplify_imports: bool_option(&options, "simplify_imports")?,
+                forbid_eval: bool_option(&options, "forbid_eval")?,
+                collapse_whitespace_only: bool_option(&options, "collapse_whitespace_only")?,
+                max_template_bytes: get_option(&options, "max_template_bytes")?
+                    .as_f64()
+                    .map(|max| max as usize),
+                max_template_lines: get_option(&options, "max_template_lines")?
+                    .as_f64()
+                    .map(|max| max as usize),
+                default_export_placement: match string_option(&options, "default_export_placement")?
+                    .as_deref()
+                {
+                    Some("end") => DefaultExportPlacement::End,
+                    _ => DefaultExportPlacement::InPlace,
+                },
+                // `true` for the usual base, or the module to import it from
+                implicit_component_base: {
+                    let value = get_option(&options, "implicit_component_base")?;
+                    match value.as_string() {
+                        Some(module) => Some(module),
+                        None => js_boolean(&value).then(|| GLIMMER_COMPONENT.to_owned()),
+                    }
+                },
+                snippet_version: match get_option(&options, "snippet_version")?
+                    .as_f64()
+                    .map(|version| version as u32)
+                {
+                    Some(1) => SnippetVersion::V1,
+                    _ => SnippetVersion::V2,
+                },
+                detect_already_transformed: bool_option(&options, "detect_already_transformed")?,
+                collect_exports: bool_option(&options, "collect_exports")?,
+                mark_generated_regions: bool_option(&options, "mark_generated_regions")?,
+                pure_annotations: bool_option(&options, "pure_annotations")?,
+                severity_overrides: severity_overrides_option(&options)?,
+                tab_width: get_option(&options, "tab_width")?
+                    .as_f64()
+                    .map(|width| width as usize),
+                emit: match string_option(&options, "emit")?.as_deref() {
+                    Some("unified-diff") => Emit::UnifiedDiff,
+                    _ => Emit::Code,
+                },
+                // the resolver runs unless explicitly turned off
+                run_resolver_hygiene: get_option(&options, "run_resolver_hygiene")?
+                    .as_bool()
+                    .unwrap_or(true),
+                target: match string_option(&options, "target")?.as_deref() {
+                    Some("es2015") => Some(EsVersion::Es2015),
+                    Some("es2016") => Some(EsVersion::Es2016),
+                    Some("es2017") => Some(EsVersion::Es2017),
+                    Some("es2018") => Some(EsVersion::Es2018),
+                    Some("es2019") => Some(EsVersion::Es2019),
+                    Some("es2020") => Some(EsVersion::Es2020),
+                    Some("es2021") => Some(EsVersion::Es2021),
+                    Some("es2022") => Some(EsVersion::Es2022),
+                    _ => None,
+                },
                 filename,
-            }
+            })
         } else {
-            Self {
-                inline_source_map: false,
-                filename: None,
+            Ok(Self::default())
+        }
+    }
+}
+
+fn get_option(options: &JsValue, name: &str) -> Result<JsValue, JsValue> {
+    Reflect::get(options, &name.into())
+}
+
+fn bool_option(options: &JsValue, name: &str) -> Result<bool, JsValue> {
+    Ok(js_boolean(&get_option(options, name)?))
+}
+
+fn string_option(options: &JsValue, name: &str) -> Result<Option<String>, JsValue> {
+    let value = get_option(options, name)?;
+    Ok(js_boolean(&value).then(|| js_string(&value)))
+}
+
+fn function_option(options: &JsValue, name: &str) -> Result<Option<js_sys::Function>, JsValue> {
+    Ok(get_option(options, name)?
+        .dyn_into::<js_sys::Function>()
+        .ok())
+}
+
+fn js_transformers(options: &JsValue) -> Result<Vec<Box<dyn Transformer>>, JsValue> {
+    Ok(function_option(options, "transformer")?
+        .into_iter()
+        .chain(function_list_option(options, "transformers")?)
+        .map(|f| Box::new(JsTransformer(f)) as Box<dyn Transformer>)
+        .collect())
+}
+
+// `tags: { template: { transformer, transformers, import_module,
+// import_specifier } }`, where every field of a handler is optional.
+fn tags_option(options: &JsValue) -> Result<BTreeMap<String, TagHandler>, JsValue> {
+    let tags = get_option(options, "tags")?;
+    if !tags.is_object() {
+        return Ok(BTreeMap::new());
+    }
+    js_sys::Object::entries(tags.unchecked_ref())
+        .iter()
+        .map(|entry| {
+            let entry: js_sys::Array = entry.unchecked_into();
+            let name = js_string(&entry.get(0));
+            let handler = entry.get(1);
+            if !handler.is_object() {
+                return Ok((name, TagHandler::default()));
             }
+            let default = TagHandler::default();
+            let handler = TagHandler {
+                transformers: js_transformers(&handler)?,
+                import_module: string_option(&handler, "import_module")?
+                    .unwrap_or(default.import_module),
+                import_specifier: string_option(&handler, "import_specifier")?
+                    .unwrap_or(default.import_specifier),
+            };
+            Ok((name, handler))
+        })
+        .collect()
+}
+
+// Codes with a severity we don't know are left alone.
+fn severity_overrides_option(options: &JsValue) -> Result<BTreeMap<String, Severity>, JsValue> {
+    let overrides = get_option(options, "severity_overrides")?;
+    if !overrides.is_object() {
+        return Ok(BTreeMap::new());
+    }
+    Ok(js_sys::Object::entries(overrides.unchecked_ref())
+        .iter()
+        .filter_map(|entry| {
+            let entry: js_sys::Array = entry.unchecked_into();
+            let severity = match entry.get(1).as_string()?.as_str() {
+                "off" => Severity::Off,
+                "warning" => Severity::Warning,
+                "error" => Severity::Error,
+                _ => return None,
+            };
+            Some((js_string(&entry.get(0)), severity))
+        })
+        .collect())
+}
+
+fn function_list_option(options: &JsValue, name: &str) -> Result<Vec<js_sys::Function>, JsValue> {
+    Ok(
+        match get_option(options, name)?.dyn_into::<js_sys::Array>() {
+            Ok(list) => list
+                .iter()
+                .filter_map(|f| f.dyn_into::<js_sys::Function>().ok())
+                .collect(),
+            Err(_) => vec![],
+        },
+    )
+}
+
+// Lets JS pass `transformer: (content, { type, range, addImport }) => string`.
+// Throwing from it marks that template as failed.
+struct JsTransformer(js_sys::Function);
+
+impl Transformer for JsTransformer {
+    fn transform(&self, content: &str, context: &TemplateContext) -> Result<String, String> {
+        let kind = match context.kind {
+            ContentTagKind::Expression => "expression",
+            ContentTagKind::ClassMember => "class-member",
+        };
+        let js_context = json_parse(
+            serde_json::json!({ "type": kind, "range": context.range })
+                .to_string()
+                .into(),
+        );
+
+        // `addImport` can't borrow `context`, so we collect its requests and
+        // replay them once the transformer returns. Both sides get the local
+        // name from the module's `ImportNames`, so it's the same.
+        let requests: Rc<RefCell<Vec<(String, String, String)>>> = Default::default();
+        let add_import = {
+            let requests = requests.clone();
+            let names = context.names();
+            Closure::<dyn Fn(String, String, Option<String>) -> String>::new(
+                move |module: String, specifier: String, local_hint: Option<String>| {
+                    let local_hint = local_hint.unwrap_or_default();
+                    let local = names.local(&module, &specifier, &local_hint);
+                    requests.borrow_mut().push((module, specifier, local_hint));
+                    local
+                },
+            )
+        };
+        Reflect::set(&js_context, &"addImport".into(), add_import.as_ref())
+            .map_err(|err| js_string(&err))?;
+
+        let result = self.0.call2(&JsValue::NULL, &content.into(), &js_context);
+        for (module, specifier, local_hint) in requests.borrow().iter() {
+            context.request_import(module, specifier, local_hint);
+        }
+        match result {
+            Ok(value) => Ok(js_string(&value)),
+            Err(err) => match Reflect::get(&err, &"message".into()) {
+                Ok(message) if !message.is_undefined() => Err(js_string(&message)),
+                _ => Err(js_string(&err)),
+            },
         }
     }
 }
 
+#[cfg(feature = "transform")]
 #[wasm_bindgen(getter_with_clone)]
 pub struct CodeMapPair {
     pub code: String,
     pub map: String,
+    pub metadata: JsProcessMetadata,
+    pub warnings: JsWarnings,
 }
 
+#[cfg(feature = "transform")]
 #[wasm_bindgen]
 impl CodeMapPair {
     #[wasm_bindgen(constructor)]
     pub fn new(code: String, map: String) -> Self {
-        Self { code, map }
+        Self {
+            code,
+            map,
+            metadata: JsValue::UNDEFINED.unchecked_into(),
+            warnings: JsValue::UNDEFINED.unchecked_into(),
+        }
     }
 }
 
@@ -77,25 +588,46 @@ pub struct Preprocessor {
     // core: Box<CorePreprocessor>,
 }
 
+#[cfg(feature = "pretty-errors")]
 #[derive(Clone, Default)]
 struct Writer(Lrc<Lock<String>>);
 
-impl fmt::Write for Writer {
-    fn write_str(&mut self, s: &str) -> fmt::Result {
+#[cfg(feature = "pretty-errors")]
+impl std::fmt::Write for Writer {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
         self.0.lock().write_str(s)
     }
 }
 
+#[cfg(feature = "pretty-errors")]
 fn capture_err_detail(
     err: swc_ecma_parser::error::Error,
     source_map: Lrc<SourceMap>,
-    theme: GraphicalTheme,
+    rendering: ErrorRendering,
+    color: bool,
 ) -> JsValue {
+    let theme = GraphicalTheme {
+        characters: if rendering.ascii {
+            GraphicalTheme::ascii().characters
+        } else {
+            GraphicalTheme::unicode().characters
+        },
+        styles: if color {
+            GraphicalTheme::unicode().styles
+        } else {
+            GraphicalTheme::unicode_nocolor().styles
+        },
+    };
+    let mut report_handler = GraphicalReportHandler::new_themed(theme);
+    if let Some(width) = rendering.width {
+        report_handler = report_handler.with_width(width);
+    }
+    let (err, source_map) = windowed(err, source_map, rendering.line_window());
     let wr = Writer::default();
     let emitter = PrettyEmitter::new(
         source_map,
         Box::new(wr.clone()),
-        GraphicalReportHandler::new_themed(theme),
+        report_handler,
         Default::default(),
     );
     let handler = Handler::with_emitter(true, false, Box::new(emitter));
@@ -104,28 +636,161 @@ fn capture_err_detail(
     s.into()
 }
 
-fn as_javascript_error(err: swc_ecma_parser::error::Error, source_map: Lrc<SourceMap>) -> JsValue {
-    let short_desc = format!("Parse Error at {}", source_map.span_to_string(err.span()));
+// The lines of context the graphical reporter shows around an error's line.
+#[cfg(feature = "pretty-errors")]
+const CONTEXT_LINES: usize = 1;
+
+// When the line with `err` is over `window` characters, a copy of its file
+// with that line and the ones shown around it cut down to the characters
+// around the error, and `err` moved to match. A code frame for an error in
+// a 1MB single-line template is otherwise just as big.
+#[cfg(feature = "pretty-errors")]
+fn windowed(
+    err: swc_ecma_parser::error::Error,
+    source_map: Lrc<SourceMap>,
+    window: usize,
+) -> (swc_ecma_parser::error::Error, Lrc<SourceMap>) {
+    let span = err.span();
+    let lo = source_map.lookup_char_pos(span.lo);
+    let hi = source_map.lookup_char_pos(span.hi);
+    let file = lo.file.clone();
+    let line_index = lo.line - 1;
+    let len = match file.get_line(line_index) {
+        Some(line) => line.chars().count(),
+        None => return (err, source_map),
+    };
+    if len <= window {
+        return (err, source_map);
+    }
+    let range = window_around(len, lo.col.0, window);
+    // where a column of the error's line ends up in the cut line, in bytes
+    let cut_offset = |cut: &str, column: usize| {
+        let column = column.clamp(range.start, range.end) - range.start;
+        let column = column + usize::from(range.start > 0);
+        cut.char_indices()
+            .nth(column)
+            .map_or(cut.len(), |(offset, _)| offset)
+    };
+
+    let mut src = String::new();
+    let (mut new_lo, mut new_hi) = (0, 0);
+    for index in 0..file.count_lines() {
+        let line = file.get_line(index).unwrap_or_default();
+        if index.abs_diff(line_index) > CONTEXT_LINES {
+            src.push_str(&line);
+        } else {
+            let cut = cut_line(&line, &range);
+            if index == line_index {
+                new_lo = src.len() + cut_offset(&cut, lo.col.0);
+                new_hi = if hi.line == lo.line {
+                    src.len() + cut_offset(&cut, hi.col.0)
+                } else {
+                    src.len() + cut.len()
+                };
+            }
+            src.push_str(&cut);
+        }
+        src.push('\n');
+    }
+
+    let cut_map: Lrc<SourceMap> = Default::default();
+    let cut_file = cut_map.new_source_file(file.name.clone(), src);
+    let span = span
+        .with_lo(cut_file.start_pos + BytePos(new_lo as u32))
+        .with_hi(cut_file.start_pos + BytePos(new_hi.max(new_lo) as u32));
+    let err = swc_ecma_parser::error::Error::new(span, err.kind().clone());
+    (err, cut_map)
+}
+
+// The lean build has no graphical reporter: just `file:line:column: message`
+// and the offending line, never colored.
+#[cfg(not(feature = "pretty-errors"))]
+fn capture_err_detail(
+    err: swc_ecma_parser::error::Error,
+    source_map: Lrc<SourceMap>,
+    rendering: ErrorRendering,
+    _color: bool,
+) -> JsValue {
+    let loc = source_map.lookup_char_pos(err.span().lo);
+    let mut s = format!(
+        "{}:{}:{}: {}\n",
+        crate::file_id::display_name(&loc.file.name),
+        loc.line,
+        loc.col.0 + 1,
+        err.kind().msg()
+    );
+    if let Some(line) = loc.file.get_line(loc.line - 1) {
+        let window = window_around(line.chars().count(), loc.col.0, rendering.line_window());
+        let caret = loc.col.0 - window.start + usize::from(window.start > 0);
+        s.push_str(&format!(
+            "{}\n{}^\n",
+            cut_line(&line, &window),
+            " ".repeat(caret)
+        ));
+    }
+    s.into()
+}
+
+fn as_javascript_error(
+    err: swc_ecma_parser::error::Error,
+    preprocessor: &CorePreprocessor,
+    rendering: ErrorRendering,
+) -> JsValue {
+    let source_map = preprocessor.source_map();
+    let short_desc = format!("Parse Error at {}", preprocessor.location(err.span()));
     let js_err = js_error(short_desc.into());
-    js_sys::Reflect::set(
-        &js_err,
-        &"source_code".into(),
-        &capture_err_detail(
-            err.clone(),
-            source_map.clone(),
-            GraphicalTheme::unicode_nocolor(),
-        ),
-    )
-    .unwrap();
-    js_sys::Reflect::set(
+    if let Ok(loc) = to_js_value(&preprocessor.error_loc(err.span())) {
+        set_property(&js_err, "loc", &loc);
+    }
+    let suggested_edits = preprocessor.suggested_edits(&err);
+    if !suggested_edits.is_empty() {
+        if let Ok(edits) = to_js_value(&suggested_edits) {
+            set_property(&js_err, "suggestedEdits", &edits);
+        }
+    }
+    set_property(
         &js_err,
-        &"source_code_color".into(),
-        &capture_err_detail(err, source_map, GraphicalTheme::unicode()),
-    )
-    .unwrap();
+        "source_code",
+        &capture_err_detail(err.clone(), source_map.clone(), rendering, false),
+    );
+    if !rendering.no_color {
+        set_property(
+            &js_err,
+            "source_code_color",
+            &capture_err_detail(err, source_map, rendering, true),
+        );
+    }
     return js_err;
 }
 
+// Lets JS render its own code frames instead of using `source_code`.
+fn attach_source_info(js_err: &JsValue, preprocessor: &CorePreprocessor) {
+    if let Some(info) = preprocessor.source_info() {
+        if let Ok(info) = to_js_value(&info) {
+            set_property(js_err, "sourceInfo", &info);
+        }
+    }
+}
+
+// For the extra fields of the errors we throw. Setting them only fails on
+// objects that can't be changed, which a fresh `Error` isn't, and the error
+// is still worth throwing without them.
+fn set_property(target: &JsValue, name: &str, value: &JsValue) {
+    let _ = Reflect::set(target, &name.into(), value);
+}
+
+// `undefined` for options that weren't given, like `Options::new` expects.
+fn js_options(options: Option<JsPreprocessorOptions>) -> JsValue {
+    options.map_or(JsValue::UNDEFINED, Into::into)
+}
+
+fn to_js_value<T: serde::Serialize>(value: &T) -> Result<JsValue, JsValue> {
+    match serde_json::to_string(value) {
+        Ok(serialized) => Ok(json_parse(serialized.into())),
+        Err(err) =>  Err(js_error(format!("Unexpected serialization error; please open an issue with the following debug info: {err:#?}").into()))
+    }
+}
+
 #[wasm_bindgen]
 impl Preprocessor {
     #[wasm_bindgen(constructor)]
@@ -137,30 +802,562 @@ impl Preprocessor {
         Self {}
     }
 
-    pub fn process(&self, src: String, options: JsValue) -> Result<CodeMapPair, JsValue> {
-        let options = Options::new(options);
+    #[wasm_bindgen(js_name = schemaVersion)]
+    pub fn schema_version() -> u32 {
+        SCHEMA_VERSION
+    }
+
+    pub fn capabilities() -> Result<JsCapabilities, JsValue> {
+        to_js_value(&Capabilities::current()).map(JsCast::unchecked_into)
+    }
+
+    pub fn parse(
+        &self,
+        src: String,
+        options: Option<JsPreprocessorOptions>,
+    ) -> Result<JsParsed, JsValue> {
+        let options = Options::new(js_options(options))?;
+        let rendering = options.error_rendering;
         let preprocessor = CorePreprocessor::new();
-        let result = preprocessor.process(&src, options);
+        let result = preprocessor.parse(&src, options);
 
         match result {
-            Ok(output) => Ok(CodeMapPair::new(output.code, output.map)),
-            Err(err) => Err(as_javascript_error(err, preprocessor.source_map()).into()),
+            // the JS API has always returned a bare array, which is the
+            // `templates` of schema version 1
+            Ok(parsed) => to_js_value(&ParseOutput::from(parsed.as_slice()).templates)
+                .map(JsCast::unchecked_into),
+            Err(err) => {
+                let js_err = as_javascript_error(err, &preprocessor, rendering);
+                attach_source_info(&js_err, &preprocessor);
+                Err(js_err)
+            }
         }
     }
 
-    pub fn parse(&self, src: String, options: JsValue) -> Result<JsValue, JsValue> {
-        let options = Options::new(options);
+    #[wasm_bindgen(js_name = analyzeMetrics)]
+    pub fn analyze_metrics(&self, src: String) -> Result<JsTemplateMetrics, JsValue> {
         let preprocessor = CorePreprocessor::new();
-        let result = preprocessor.parse(&src, options);
+        match preprocessor.analyze_metrics(&src) {
+            Ok(metrics) => to_js_value(&metrics).map(JsCast::unchecked_into),
+            Err(err) => {
+                let js_err = as_javascript_error(err, &preprocessor, Default::default());
+                attach_source_info(&js_err, &preprocessor);
+                Err(js_err)
+            }
+        }
+    }
 
-        match result {
-            Ok(parsed) => {
-                match serde_json::to_string(&parsed) {
-                    Ok(serialized) => Ok(json_parse(serialized.into())),
-                    Err(err) =>  Err(js_error(format!("Unexpected serialization error; please open an issue with the following debug info: {err:#?}").into()))
+    #[wasm_bindgen(js_name = contentHashes)]
+    pub fn content_hashes(
+        &self,
+        src: String,
+        algorithm: Option<String>,
+    ) -> Result<JsValue, JsValue> {
+        let algorithm = match algorithm.as_deref() {
+            None | Some("xxh3") => HashAlgorithm::Xxh3,
+            Some("sha256") => HashAlgorithm::Sha256,
+            Some(other) => {
+                return Err(js_error(
+                    format!("Unknown hash algorithm {other:?}, expected \"xxh3\" or \"sha256\"")
+                        .into(),
+                ))
+            }
+        };
+        let preprocessor = CorePreprocessor::new();
+        match preprocessor.content_hashes(&src, algorithm) {
+            Ok(hashes) => to_js_value(&hashes),
+            Err(err) => {
+                let js_err = as_javascript_error(err, &preprocessor, Default::default());
+                attach_source_info(&js_err, &preprocessor);
+                Err(js_err)
+            }
+        }
+    }
+}
+
+// `process` is left out of the locate-only build.
+#[cfg(feature = "transform")]
+#[wasm_bindgen]
+impl Preprocessor {
+    pub fn process(
+        &self,
+        src: String,
+        options: Option<JsPreprocessorOptions>,
+    ) -> Result<CodeMapPair, JsValue> {
+        run_process(options, |preprocessor, options| {
+            preprocessor.process(&src, options)
+        })
+    }
+
+    // `process` for a `Uint8Array` that might not be valid UTF-8: invalid
+    // sequences become U+FFFD, with an `invalid-utf8` warning each.
+    #[wasm_bindgen(js_name = processBytes)]
+    pub fn process_bytes(
+        &self,
+        src: Vec<u8>,
+        options: Option<JsPreprocessorOptions>,
+    ) -> Result<CodeMapPair, JsValue> {
+        run_process(options, |preprocessor, options| {
+            preprocessor.process_bytes(&src, options)
+        })
+    }
+
+    // `process` for each of `sources`, with the same options, in one call
+    // across the wasm boundary. Each result is what `process` returns, or
+    // `{ error }` with what it would have thrown. There's no `log`.
+    #[wasm_bindgen(js_name = processMany)]
+    pub fn process_many(
+        &self,
+        sources: Vec<String>,
+        options: Option<JsPreprocessorOptions>,
+    ) -> Result<JsProcessManyResults, JsValue> {
+        let options = Options::new(js_options(options))?;
+        let rendering = options.error_rendering;
+        let results = js_sys::Array::new();
+        for src in &sources {
+            let file = ProcessedFile::process(src, &options, options.filename.as_ref());
+            let result = js_sys::Object::new();
+            match file.result {
+                Ok(output) => {
+                    set_property(&result, "code", &output.code.into());
+                    set_property(&result, "map", &output.map.into());
+                    set_property(&result, "metadata", &to_js_value(&output.metadata)?);
+                    set_property(&result, "warnings", &to_js_value(&output.warnings)?);
                 }
-            },
-            Err(err) => Err(as_javascript_error(err, preprocessor.source_map()))
+                Err(err) => {
+                    let js_err = process_error(err, file.preprocessor(), rendering)?;
+                    set_property(&result, "error", &js_err);
+                }
+            }
+            results.push(&result);
+        }
+        Ok(results.unchecked_into())
+    }
+}
+
+// `process` and `processBytes`, which differ in how they call `process`: the
+// options from JS, with the `log` option, and the result or error for JS.
+#[cfg(feature = "transform")]
+fn run_process(
+    options: Option<JsPreprocessorOptions>,
+    process: impl FnOnce(&CorePreprocessor, Options) -> Result<crate::CodeMapPair, Error>,
+) -> Result<CodeMapPair, JsValue> {
+    let options = js_options(options);
+    let log = if js_boolean(&options) {
+        function_option(&options, "log")?
+    } else {
+        None
+    };
+    let options = Options::new(options)?;
+    let rendering = options.error_rendering;
+    let preprocessor = CorePreprocessor::new();
+    let result = match log {
+        Some(log) => tracing::subscriber::with_default(
+            tracing_subscriber::registry().with(JsLogLayer(log)),
+            || process(&preprocessor, options),
+        ),
+        None => process(&preprocessor, options),
+    };
+
+    match result {
+        Ok(output) => Ok(CodeMapPair {
+            metadata: to_js_value(&output.metadata)?.unchecked_into(),
+            warnings: to_js_value(&output.warnings)?.unchecked_into(),
+            code: output.code,
+            map: output.map,
+        }),
+        Err(err) => Err(process_error(err, &preprocessor, rendering)?),
+    }
+}
+
+// What `process` throws for `err`.
+#[cfg(feature = "transform")]
+fn process_error(
+    err: Error,
+    preprocessor: &CorePreprocessor,
+    rendering: ErrorRendering,
+) -> Result<JsValue, JsValue> {
+    let js_err = match err {
+        Error::Parse(err) => as_javascript_error(err, preprocessor, rendering),
+        Error::Transformer(errors) => {
+            let js_err = js_error(Error::Transformer(errors.clone()).to_string().into());
+            set_property(&js_err, "templateErrors", &to_js_value(&errors)?);
+            js_err
+        }
+        err => js_error(err.to_string().into()),
+    };
+    attach_source_info(&js_err, preprocessor);
+    Ok(js_err)
+}
+
+#[cfg(feature = "transform")]
+// Forwards the tracing spans and events from `process` to the `log` option:
+// each phase once it finishes, with how long it took, and each event.
+struct JsLogLayer(js_sys::Function);
+
+#[cfg(feature = "transform")]
+// when a span started, in milliseconds
+struct SpanStart(f64);
+
+#[cfg(feature = "transform")]
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LogEntry {
+    level: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    phase: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<f64>,
+}
+
+#[cfg(feature = "transform")]
+impl JsLogLayer {
+    fn log(&self, entry: LogEntry) {
+        if let Ok(entry) = to_js_value(&entry) {
+            // a throwing logger shouldn't break processing
+            let _ = self.0.call1(&JsValue::NULL, &entry);
+        }
+    }
+}
+
+#[cfg(feature = "transform")]
+impl<S> Layer<S> for JsLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanStart(js_sys::Date::now()));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(start) = span.extensions().get::<SpanStart>().map(|start| start.0) else {
+            return;
+        };
+        self.log(LogEntry {
+            level: span.metadata().level().as_str(),
+            message: format!("{} finished", span.name()),
+            phase: Some(span.name()),
+            duration_ms: Some(js_sys::Date::now() - start),
+        });
+    }
+
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        let mut message = EventMessage::default();
+        event.record(&mut message);
+        self.log(LogEntry {
+            level: event.metadata().level().as_str(),
+            message: message.message + &message.fields,
+            phase: None,
+            duration_ms: None,
+        });
+    }
+}
+
+#[cfg(feature = "transform")]
+// An event's message followed by its other fields, like `transformed
+// templates=2`.
+#[derive(Default)]
+struct EventMessage {
+    message: String,
+    fields: String,
+}
+
+#[cfg(feature = "transform")]
+impl Visit for EventMessage {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        use std::fmt::Write;
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
         }
     }
 }
+
+#[cfg(test)]
+use crate::{
+    schema::ParsedCommentKind, DefaultExportKind, ErrorLoc, ExportKind, Label, Metadata,
+    ParsedKind, Range, SourceInfo, TemplateError, TextEdit, Warning,
+};
+
+// The fields `interface` declares at its top level in `ts`, in order.
+#[cfg(test)]
+fn interface_keys<'a>(ts: &'a str, interface: &str) -> Vec<&'a str> {
+    let start = ts
+        .find(&format!("interface {interface} "))
+        .unwrap_or_else(|| panic!("no interface {interface}"));
+    let mut keys = vec![];
+    let mut depth = 0;
+    for line in ts[start..].lines() {
+        let line = line.trim();
+        // doc comments mention `<template>` and `{{...}}`
+        if line.starts_with('*') || line.starts_with("/*") || line.starts_with("//") {
+            continue;
+        }
+        if depth == 1 {
+            if let Some((key, _)) = line.split_once(':') {
+                keys.push(key.trim_end_matches('?'));
+            }
+        }
+        let line = line.replace("=>", "");
+        depth += line.matches(['{', '(', '<']).count() as i32;
+        depth -= line.matches(['}', ')', '>']).count() as i32;
+        if depth == 0 {
+            return keys;
+        }
+    }
+    panic!("interface {interface} isn't closed")
+}
+
+// The keys that `value` serializes with, in order.
+#[cfg(test)]
+fn serialized_keys(value: &impl serde::Serialize) -> Vec<String> {
+    match serde_json::to_value(value).unwrap() {
+        serde_json::Value::Object(object) => object.keys().cloned().collect(),
+        other => panic!("{other} isn't an object"),
+    }
+}
+
+// The declarations `test_typescript_types` checks: ours and the npm
+// package's, which leaves out some of the error types.
+#[cfg(test)]
+const DECLARATIONS: [(&str, &str); 3] = [
+    ("TYPESCRIPT_TYPES", TYPESCRIPT_TYPES),
+    ("index.d.ts", include_str!("../index.d.ts")),
+    ("index.d.cts", include_str!("../index.d.cts")),
+];
+
+#[cfg(test)]
+fn assert_same_keys(interface: &str, value: &impl serde::Serialize) {
+    let mut serialized = serialized_keys(value);
+    serialized.sort_unstable();
+    for (name, ts) in DECLARATIONS {
+        if name != "TYPESCRIPT_TYPES" && !ts.contains(&format!("interface {interface} ")) {
+            continue;
+        }
+        let mut declared = interface_keys(ts, interface);
+        declared.sort_unstable();
+        assert_eq!(declared, serialized, "interface {interface} in {name}");
+    }
+}
+
+// The string literals that the field at `path` in `interface` is a union of,
+// like `['a', 'b']` for `kind: 'a' | 'b';`, or for `("a" | "b")[]` and
+// `Record<string, 'a' | 'b'>`.
+#[cfg(test)]
+fn declared_literals(ts: &str, interface: &str, path: &[&str]) -> Vec<String> {
+    let start = ts
+        .find(&format!("interface {interface} "))
+        .unwrap_or_else(|| panic!("no interface {interface}"));
+    let mut lines = ts[start..].lines().map(str::trim);
+    let mut line = "";
+    for key in path {
+        line = lines
+            .find(|line| {
+                line.starts_with(&format!("{key}:")) || line.starts_with(&format!("{key}?:"))
+            })
+            .unwrap_or_else(|| panic!("no {key} in interface {interface}"));
+    }
+    let (_, value) = line.split_once(':').unwrap();
+    let value = value.trim().trim_end_matches(';');
+    let value = value
+        .strip_prefix("Record<string,")
+        .and_then(|value| value.strip_suffix('>'))
+        .unwrap_or(value);
+    let value = value
+        .strip_prefix('(')
+        .and_then(|value| value.strip_suffix(")[]"))
+        .unwrap_or(value);
+    value
+        .split('|')
+        .map(|literal| literal.trim().trim_matches(['\'', '"']).to_owned())
+        .collect()
+}
+
+#[cfg(test)]
+fn assert_same_literals<T: serde::Serialize>(interface: &str, path: &[&str], values: &[T]) {
+    let serialized: Vec<String> = values
+        .iter()
+        .map(|value| match serde_json::to_value(value).unwrap() {
+            serde_json::Value::String(value) => value,
+            other => panic!("{other} isn't a string"),
+        })
+        .collect();
+    for (name, ts) in DECLARATIONS {
+        if !ts.contains(&format!("interface {interface} ")) {
+            continue;
+        }
+        assert_eq!(
+            declared_literals(ts, interface, path),
+            serialized,
+            "{interface}.{} in {name}",
+            path.join(".")
+        );
+    }
+}
+
+#[test]
+fn test_typescript_types() {
+    let mut options = Capabilities::current().options;
+    options.sort_unstable();
+    for (name, ts) in DECLARATIONS {
+        let mut declared = interface_keys(ts, "PreprocessorOptions");
+        declared.sort_unstable();
+        assert_eq!(declared, options, "PreprocessorOptions in {name}");
+    }
+
+    // every optional field filled in, so that each one is serialized
+    let src = "  // says hi\n  let t = <template>{{#if x}}Hi{{/if}}</template>;";
+    let p = CorePreprocessor::new();
+    let parsed = p
+        .parse(
+            src,
+            Options {
+                leading_comments: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_same_keys("Parsed", &ParseOutput::from(parsed.as_slice()).templates[0]);
+    assert_same_keys("TemplateMetrics", &p.analyze_metrics(src).unwrap()[0]);
+    assert_same_keys("Capabilities", &Capabilities::current());
+    let (_, exports) = p
+        .parse_with_exports("export default class A {}", Default::default())
+        .unwrap();
+    assert_same_keys("ExportMap", &exports);
+    assert_same_keys(
+        "ProcessMetadata",
+        &Metadata {
+            exports: Some(exports),
+            ..Default::default()
+        },
+    );
+
+    let range = Range { start: 0, end: 1 };
+    assert_same_keys(
+        "Warning",
+        &Warning {
+            code: "unresolved-reference".to_owned(),
+            message: String::new(),
+            range,
+            location: String::new(),
+            severity: Severity::Error,
+        },
+    );
+    let edit = TextEdit {
+        range,
+        new_text: String::new(),
+    };
+    assert_same_keys("TextEdit", &edit);
+    assert_same_keys(
+        "TemplateError",
+        &TemplateError {
+            range,
+            location: String::new(),
+            message: String::new(),
+            suggested_edits: vec![edit],
+            labels: vec![Label {
+                range,
+                location: String::new(),
+                message: String::new(),
+            }],
+        },
+    );
+    assert_same_keys(
+        "ErrorLoc",
+        &ErrorLoc {
+            file: String::new(),
+            start_line: 1,
+            start_column: 0,
+            start_visual_column: 0,
+            end_line: 1,
+            end_column: 1,
+            end_visual_column: 1,
+            byte_start: 0,
+            byte_end: 1,
+        },
+    );
+    assert_same_keys(
+        "SourceInfo",
+        &SourceInfo {
+            file: String::new(),
+            source_text: String::new(),
+            line_starts: vec![0],
+        },
+    );
+
+    assert_same_literals(
+        "Parsed",
+        &["type"],
+        &[ParsedKind::Expression, ParsedKind::ClassMember],
+    );
+    assert_same_literals(
+        "Parsed",
+        &["leadingComments", "kind"],
+        &[ParsedCommentKind::Line, ParsedCommentKind::Block],
+    );
+    // `off` warnings aren't reported
+    assert_same_literals(
+        "Warning",
+        &["severity"],
+        &[Severity::Warning, Severity::Error],
+    );
+    assert_same_literals(
+        "PreprocessorOptions",
+        &["severity_overrides"],
+        &[Severity::Off, Severity::Warning, Severity::Error],
+    );
+    assert_same_literals(
+        "ExportMap",
+        &["named", "kind"],
+        &[
+            ExportKind::Variable,
+            ExportKind::Function,
+            ExportKind::Class,
+            ExportKind::Enum,
+            ExportKind::Type,
+            ExportKind::Namespace,
+            ExportKind::Binding,
+        ],
+    );
+    assert_same_literals(
+        "ExportMap",
+        &["default", "kind"],
+        &[
+            DefaultExportKind::Template,
+            DefaultExportKind::Class,
+            DefaultExportKind::Function,
+            DefaultExportKind::Type,
+            DefaultExportKind::Binding,
+            DefaultExportKind::Expression,
+        ],
+    );
+}
+
+#[cfg(feature = "pretty-errors")]
+#[test]
+fn test_windows_long_lines() {
+    let p = CorePreprocessor::new();
+    let err = p.parse("let y = ;", Default::default()).unwrap_err();
+    let (short, short_map) = windowed(err.clone(), p.source_map(), 80);
+    assert_eq!(short.span(), err.span());
+    assert!(Lrc::ptr_eq(&short_map, &p.source_map()));
+
+    let src = format!("let x = <template>{}</template> +;", "a".repeat(100_000));
+    let p = CorePreprocessor::new();
+    let err = p.parse(&src, Default::default()).unwrap_err();
+    let (cut, cut_map) = windowed(err.clone(), p.source_map(), 80);
+    let file = cut_map.lookup_char_pos(cut.span().lo).file;
+    assert!(file.src.chars().count() <= 80 + 3, "{}", file.src);
+    assert!(file.src.starts_with('…'), "{}", file.src);
+    assert_eq!(
+        cut_map.span_to_snippet(cut.span()).unwrap(),
+        p.source_map().span_to_snippet(err.span()).unwrap()
+    );
+}
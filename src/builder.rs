@@ -0,0 +1,305 @@
+// Building a `Preprocessor` with its options up front, so that combinations
+// that can't work are reported when it is built instead of partway through
+// processing a file.
+
+use std::path::PathBuf;
+
+use swc_common::{sync::Lrc, SourceMap};
+
+use crate::{
+    tags, DefaultExportPlacement, Emit, Error, ErrorRendering, EsVersion, FileId, Options,
+    Preprocessor, Severity, SnippetVersion, TagHandler, Transformer, TrimContent,
+};
+
+#[derive(Default)]
+pub struct PreprocessorBuilder {
+    options: Options,
+    // the embedder's, for `transform_module`
+    source_map: Option<Lrc<SourceMap>>,
+}
+
+impl Preprocessor {
+    pub fn builder() -> PreprocessorBuilder {
+        PreprocessorBuilder::default()
+    }
+}
+
+impl PreprocessorBuilder {
+    pub fn filename(mut self, filename: impl Into<FileId>) -> Self {
+        self.options.filename = Some(filename.into());
+        self
+    }
+
+    pub fn inline_source_map(mut self, inline_source_map: bool) -> Self {
+        self.options.inline_source_map = inline_source_map;
+        self
+    }
+
+    pub fn minify(mut self, minify: bool) -> Self {
+        self.options.minify = minify;
+        self
+    }
+
+    pub fn source_map_file(mut self, file: impl Into<String>) -> Self {
+        self.options.source_map_file = Some(file.into());
+        self
+    }
+
+    pub fn source_map_relative_to(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.options.source_map_relative_to = Some(dir.into());
+        self
+    }
+
+    pub fn source_map_forward_slashes(mut self, forward_slashes: bool) -> Self {
+        self.options.source_map_forward_slashes = forward_slashes;
+        self
+    }
+
+    pub fn verify_output(mut self, verify_output: bool) -> Self {
+        self.options.verify_output = verify_output;
+        self
+    }
+
+    pub fn leading_comments(mut self, leading_comments: bool) -> Self {
+        self.options.leading_comments = leading_comments;
+        self
+    }
+
+    pub fn include_content(mut self, include_content: bool) -> Self {
+        self.options.include_content = include_content;
+        self
+    }
+
+    pub fn dedent_content(mut self, dedent_content: bool) -> Self {
+        self.options.dedent_content = dedent_content;
+        self
+    }
+
+    pub fn trim_content(mut self, trim_content: TrimContent) -> Self {
+        self.options.trim_content = trim_content;
+        self
+    }
+
+    // Adds a transformer after the ones already added.
+    pub fn transformer(mut self, transformer: impl Transformer + 'static) -> Self {
+        self.options.transformers.push(Box::new(transformer));
+        self
+    }
+
+    pub fn error_rendering(mut self, error_rendering: ErrorRendering) -> Self {
+        self.options.error_rendering = error_rendering;
+        self
+    }
+
+    pub fn hbs_tagged_templates(mut self, hbs_tagged_templates: bool) -> Self {
+        self.options.hbs_tagged_templates = hbs_tagged_templates;
+        self
+    }
+
+    pub fn precompile_template_calls(mut self, precompile_template_calls: bool) -> Self {
+        self.options.precompile_template_calls = precompile_template_calls;
+        self
+    }
+
+    pub fn explicit_scope(mut self, explicit_scope: bool) -> Self {
+        self.options.explicit_scope = explicit_scope;
+        self
+    }
+
+    pub fn target(mut self, target: EsVersion) -> Self {
+        self.options.target = Some(target);
+        self
+    }
+
+    pub fn strip_types(mut self, strip_types: bool) -> Self {
+        self.options.strip_types = strip_types;
+        self
+    }
+
+    pub fn component_this_in_methods(mut self, component_this_in_methods: bool) -> Self {
+        self.options.component_this_in_methods = component_this_in_methods;
+        self
+    }
+
+    pub fn warn_unresolved(mut self, warn_unresolved: bool) -> Self {
+        self.options.warn_unresolved = warn_unresolved;
+        self
+    }
+
+    pub fn prune_unused_imports(mut self, prune_unused_imports: bool) -> Self {
+        self.options.prune_unused_imports = prune_unused_imports;
+        self
+    }
+
+    pub fn simplify_imports(mut self, simplify_imports: bool) -> Self {
+        self.options.simplify_imports = simplify_imports;
+        self
+    }
+
+    pub fn run_resolver_hygiene(mut self, run_resolver_hygiene: bool) -> Self {
+        self.options.run_resolver_hygiene = run_resolver_hygiene;
+        self
+    }
+
+    pub fn forbid_eval(mut self, forbid_eval: bool) -> Self {
+        self.options.forbid_eval = forbid_eval;
+        self
+    }
+
+    pub fn collapse_whitespace_only(mut self, collapse_whitespace_only: bool) -> Self {
+        self.options.collapse_whitespace_only = collapse_whitespace_only;
+        self
+    }
+
+    pub fn max_template_bytes(mut self, max_template_bytes: usize) -> Self {
+        self.options.max_template_bytes = Some(max_template_bytes);
+        self
+    }
+
+    pub fn max_template_lines(mut self, max_template_lines: usize) -> Self {
+        self.options.max_template_lines = Some(max_template_lines);
+        self
+    }
+
+    pub fn default_export_placement(mut self, placement: DefaultExportPlacement) -> Self {
+        self.options.default_export_placement = placement;
+        self
+    }
+
+    pub fn implicit_component_base(mut self, module: impl Into<String>) -> Self {
+        self.options.implicit_component_base = Some(module.into());
+        self
+    }
+
+    pub fn snippet_version(mut self, snippet_version: SnippetVersion) -> Self {
+        self.options.snippet_version = snippet_version;
+        self
+    }
+
+    pub fn detect_already_transformed(mut self, detect_already_transformed: bool) -> Self {
+        self.options.detect_already_transformed = detect_already_transformed;
+        self
+    }
+
+    pub fn collect_exports(mut self, collect_exports: bool) -> Self {
+        self.options.collect_exports = collect_exports;
+        self
+    }
+
+    pub fn mark_generated_regions(mut self, mark_generated_regions: bool) -> Self {
+        self.options.mark_generated_regions = mark_generated_regions;
+        self
+    }
+
+    pub fn pure_annotations(mut self, pure_annotations: bool) -> Self {
+        self.options.pure_annotations = pure_annotations;
+        self
+    }
+
+    pub fn emit(mut self, emit: Emit) -> Self {
+        self.options.emit = emit;
+        self
+    }
+
+    pub fn tab_width(mut self, tab_width: usize) -> Self {
+        self.options.tab_width = Some(tab_width);
+        self
+    }
+
+    pub fn severity_override(mut self, code: impl Into<String>, severity: Severity) -> Self {
+        self.options
+            .severity_overrides
+            .insert(code.into(), severity);
+        self
+    }
+
+    pub fn tag(mut self, name: impl Into<String>, handler: TagHandler) -> Self {
+        self.options.tags.insert(name.into(), handler);
+        self
+    }
+
+    pub fn source_map(mut self, source_map: Lrc<SourceMap>) -> Self {
+        self.source_map = Some(source_map);
+        self
+    }
+
+    pub fn build(self) -> Result<Preprocessor, Error> {
+        validate(&self.options)?;
+        let mut preprocessor = Preprocessor::new();
+        preprocessor.options = self.options;
+        if let Some(source_map) = self.source_map {
+            preprocessor.source_map = source_map;
+        }
+        Ok(preprocessor)
+    }
+}
+
+// Also run by `Preprocessor::process`, for options that didn't come through
+// the builder.
+pub(crate) fn validate(options: &Options) -> Result<(), Error> {
+    tags::validate(&options.tags)?;
+    if let Some(target) = options.target {
+        // templates are emitted as template literals
+        if target < EsVersion::Es2015 {
+            return Err(Error::InvalidOptions(format!(
+                "`target` is {target:?}, but the output needs at least Es2015"
+            )));
+        }
+    }
+    if !options.run_resolver_hygiene {
+        // these work by looking at what the resolver decided
+        let needs_resolver = [
+            ("explicit_scope", options.explicit_scope),
+            ("warn_unresolved", options.warn_unresolved),
+            ("forbid_eval", options.forbid_eval),
+            ("strip_types", options.strip_types),
+        ];
+        if let Some((name, _)) = needs_resolver.iter().find(|(_, enabled)| *enabled) {
+            return Err(Error::InvalidOptions(format!(
+                "`{name}` needs the resolver, which `run_resolver_hygiene: false` turns off"
+            )));
+        }
+    }
+    if options.component_this_in_methods && options.snippet_version < SnippetVersion::V2 {
+        return Err(Error::InvalidOptions(format!(
+            "`component_this_in_methods` passes `component`, which `snippet_version` {:?} doesn't have",
+            options.snippet_version
+        )));
+    }
+    if options.error_rendering.width == Some(0) {
+        return Err(Error::InvalidOptions(
+            "`error_rendering.width` must be at least 1".to_owned(),
+        ));
+    }
+    if options.error_rendering.line_window == Some(0) {
+        return Err(Error::InvalidOptions(
+            "`error_rendering.line_window` must be at least 1".to_owned(),
+        ));
+    }
+    if options.tab_width == Some(0) {
+        return Err(Error::InvalidOptions(
+            "`tab_width` must be at least 1".to_owned(),
+        ));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_build_rejects_old_targets() {
+    let result = Preprocessor::builder().target(EsVersion::Es5).build();
+    assert_eq!(
+        result.err().unwrap().to_string(),
+        "Invalid options: `target` is Es5, but the output needs at least Es2015"
+    );
+}
+
+#[test]
+fn test_build_and_run() {
+    let p = Preprocessor::builder()
+        .target(EsVersion::Es2021)
+        .verify_output(true)
+        .build()
+        .unwrap();
+    let output = p.run("export class A { <template>Hi</template> }").unwrap();
+    assert!(output.code.contains("component: A"));
+}
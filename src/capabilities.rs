@@ -0,0 +1,103 @@
+use serde::Serialize;
+
+use crate::SCHEMA_VERSION;
+
+// What this build of content-tag supports, so that wrappers can negotiate
+// features up front instead of probing with try/catch.
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    pub version: &'static str,
+    pub schema_version: u32,
+    // how class-member templates can be emitted: `static-block` is the
+    // default, `after-class` is used for targets before ES2022
+    pub class_member_strategies: Vec<&'static str>,
+    // the input syntaxes we parse, besides `<template>` itself
+    pub syntaxes: Vec<&'static str>,
+    // which decorators proposal we parse
+    pub decorators: &'static str,
+    pub source_map_modes: Vec<&'static str>,
+    pub hash_algorithms: Vec<&'static str>,
+    // the names of the options that `process` and `parse` understand
+    pub options: Vec<&'static str>,
+    pub features: Vec<&'static str>,
+}
+
+impl Capabilities {
+    pub fn current() -> Self {
+        Capabilities {
+            version: env!("CARGO_PKG_VERSION"),
+            schema_version: SCHEMA_VERSION,
+            class_member_strategies: vec!["static-block", "after-class"],
+            syntaxes: vec!["javascript", "typescript"],
+            decorators: "legacy",
+            source_map_modes: vec!["separate", "inline"],
+            hash_algorithms: vec!["xxh3", "sha256"],
+            options: vec![
+                "filename",
+                "inline_source_map",
+                "minify",
+                "source_map_file",
+                "source_map_relative_to",
+                "source_map_forward_slashes",
+                "verify_output",
+                "leading_comments",
+                "include_content",
+                "dedent_content",
+                "trim_content",
+                "transformer",
+                "transformers",
+                "error_width",
+                "error_ascii",
+                "error_color",
+                "error_line_window",
+                "hbs_tagged_templates",
+                "precompile_template_calls",
+                "explicit_scope",
+                "target",
+                "strip_types",
+                "component_this_in_methods",
+                "warn_unresolved",
+                "prune_unused_imports",
+                "tags",
+                "simplify_imports",
+                "run_resolver_hygiene",
+                "log",
+                "forbid_eval",
+                "emit",
+                "collapse_whitespace_only",
+                "max_template_bytes",
+                "max_template_lines",
+                "default_export_placement",
+                "implicit_component_base",
+                "snippet_version",
+                "detect_already_transformed",
+                "collect_exports",
+                "mark_generated_regions",
+                "pure_annotations",
+                "severity_overrides",
+                "tab_width",
+            ],
+            features: [
+                cfg!(feature = "wasm").then_some("wasm"),
+                cfg!(feature = "transform").then_some("transform"),
+                cfg!(feature = "cli").then_some("cli"),
+                cfg!(feature = "pretty-errors").then_some("pretty-errors"),
+                cfg!(feature = "grep").then_some("grep"),
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+        }
+    }
+}
+
+#[test]
+fn test_current() {
+    let capabilities = Capabilities::current();
+    assert_eq!(capabilities.schema_version, SCHEMA_VERSION);
+    assert!(capabilities.options.contains(&"target"));
+    assert!(capabilities
+        .class_member_strategies
+        .contains(&"after-class"));
+}
@@ -0,0 +1,87 @@
+// Which names a project's templates refer to, and where, for finding the
+// components and helpers that nothing uses any more before deleting them.
+// The names come from the same scan of template content that
+// `Options::warn_unresolved` uses, so they're the heads of paths that could
+// be JS bindings (`<Foo />`, `{{format when}}`, `<ui.Button />` as `ui`),
+// not `@args`, `this` or the template's own keywords.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::locate::Occurrence;
+use crate::references;
+
+#[derive(Serialize, Debug, Default, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Census {
+    // every name some template refers to
+    pub names: BTreeMap<String, NameUsage>,
+    // files that didn't parse, so their templates weren't counted
+    pub unparsed: Vec<PathBuf>,
+}
+
+#[derive(Serialize, Debug, Default, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NameUsage {
+    // how many times templates refer to it, in all files
+    pub total: usize,
+    // the same, for each file that refers to it
+    pub files: BTreeMap<PathBuf, usize>,
+}
+
+impl Census {
+    pub fn usage(&self, name: &str) -> Option<&NameUsage> {
+        self.names.get(name)
+    }
+
+    pub(crate) fn count(&mut self, path: &Path, occurrences: &[Occurrence]) {
+        for occurrence in occurrences {
+            for name in references::reference_occurrences(occurrence.contents()) {
+                let usage = self.names.entry(name.to_owned()).or_default();
+                usage.total += 1;
+                *usage.files.entry(path.to_owned()).or_default() += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+use crate::{Options, Project};
+
+#[test]
+fn test_counts_names_in_each_file() {
+    let project = Project::with_files(
+        Options::default(),
+        [
+            (
+                PathBuf::from("a.gjs"),
+                "<template><Card /> {{format when}} <Card>{{@title}}</Card></template>".to_owned(),
+            ),
+            (
+                PathBuf::from("b.gjs"),
+                "export const B = <template>{{#each items as |item|}}<Card @item={{item}} />{{/each}}</template>;"
+                    .to_owned(),
+            ),
+            (
+                PathBuf::from("c.gjs"),
+                "export default <template><Card />".to_owned(),
+            ),
+        ],
+    )
+    .unwrap();
+    let census = project.census();
+
+    let card = census.usage("Card").unwrap();
+    assert_eq!(card.total, 3);
+    assert_eq!(
+        card.files.iter().collect::<Vec<_>>(),
+        vec![(&PathBuf::from("a.gjs"), &2), (&PathBuf::from("b.gjs"), &1)]
+    );
+    assert_eq!(
+        census.names.keys().collect::<Vec<_>>(),
+        vec!["Card", "format", "items", "when"]
+    );
+    assert_eq!(census.unparsed, vec![PathBuf::from("c.gjs")]);
+}
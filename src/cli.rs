@@ -0,0 +1,53 @@
+// Subcommands of the `content-tag` binary, besides processing a single file.
+
+pub mod cache;
+pub mod diff;
+pub mod dir;
+pub mod generate;
+pub mod migrate;
+pub mod scan;
+pub mod serve;
+pub mod split;
+pub mod strict;
+pub mod worker;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Every file under `dir` with one of `extensions`, sorted, skipping
+// `node_modules` and hidden directories.
+pub fn walk(dir: &Path, extensions: &[&str]) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut pending = vec![dir.to_owned()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("");
+            if path.is_dir() {
+                if name != "node_modules" && !name.starts_with('.') {
+                    pending.push(path);
+                }
+            } else if path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map_or(false, |ext| extensions.contains(&ext))
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+// `path` relative to `root`, for printing.
+pub fn display_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
@@ -0,0 +1,140 @@
+// `--cache-dir <dir>`: keeps the output for each file, keyed by a hash of its
+// source and of everything else that decides the output, so that repeated
+// runs (like from a Makefile) skip the files that haven't changed. Entries
+// live in a directory per content-tag version, and opening the cache removes
+// the directories of other versions, since their output may be different.
+//
+// `content-tag cache stats <dir>` counts what's there, and
+// `content-tag cache clear <dir>` removes them.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use xxhash_rust::xxh3::xxh3_64;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub struct Cache {
+    // the directory for this version
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub fn open(root: &Path) -> io::Result<Self> {
+        let dir = root.join(format!("v{VERSION}"));
+        fs::create_dir_all(&dir)?;
+        for path in version_dirs(root)? {
+            if path != dir {
+                fs::remove_dir_all(&path)?;
+            }
+        }
+        Ok(Cache { dir })
+    }
+
+    // `fingerprint` describes the options, and anything else besides `src`
+    // that the output depends on.
+    fn entry(&self, src: &str, fingerprint: &str) -> PathBuf {
+        let key = xxh3_64(format!("{fingerprint}\0{src}").as_bytes());
+        self.dir.join(format!("{key:016x}.js"))
+    }
+
+    pub fn get(&self, src: &str, fingerprint: &str) -> Option<String> {
+        fs::read_to_string(self.entry(src, fingerprint)).ok()
+    }
+
+    // Written to a temporary file first, so that a concurrent run never sees
+    // half an entry.
+    pub fn put(&self, src: &str, fingerprint: &str, code: &str) -> io::Result<()> {
+        let entry = self.entry(src, fingerprint);
+        let partial = entry.with_extension(format!("{}.tmp", std::process::id()));
+        fs::write(&partial, code)?;
+        fs::rename(&partial, &entry)
+    }
+}
+
+pub fn run(args: &[String], cache_dir: Option<&Path>) -> i32 {
+    let (cmd, rest) = match args.split_first() {
+        Some((cmd, rest)) => (cmd.as_str(), rest),
+        None => ("", args),
+    };
+    let Some(root) = cache_dir.or_else(|| rest.first().map(Path::new)) else {
+        eprintln!("Usage: content-tag cache stats|clear <dir>");
+        return 2;
+    };
+    match cmd {
+        "stats" => match stats(root) {
+            Ok((entries, bytes)) => {
+                println!("{entries} entries, {bytes} bytes in {}", root.display());
+                0
+            }
+            Err(err) => {
+                eprintln!("Can't read {}: {err}", root.display());
+                1
+            }
+        },
+        "clear" => match clear(root) {
+            Ok(()) => 0,
+            Err(err) => {
+                eprintln!("Can't remove {}: {err}", root.display());
+                1
+            }
+        },
+        _ => {
+            eprintln!("Usage: content-tag cache stats|clear <dir>");
+            2
+        }
+    }
+}
+
+// The cache's own directories in `root`, leaving alone anything else that
+// happens to be there.
+fn version_dirs(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let listing = match fs::read_dir(root) {
+        Ok(listing) => listing,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err),
+    };
+    let mut dirs = vec![];
+    for entry in listing {
+        let path = entry?.path();
+        let is_version = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix('v'))
+            .map_or(false, |version| {
+                version.starts_with(|c: char| c.is_ascii_digit()) && version.split('.').count() == 3
+            });
+        if is_version && path.is_dir() {
+            dirs.push(path);
+        }
+    }
+    Ok(dirs)
+}
+
+fn clear(root: &Path) -> io::Result<()> {
+    for path in version_dirs(root)? {
+        fs::remove_dir_all(path)?;
+    }
+    Ok(())
+}
+
+// The number of entries for this version, and their total size.
+fn stats(root: &Path) -> io::Result<(usize, u64)> {
+    let dir = root.join(format!("v{VERSION}"));
+    let mut entries = 0;
+    let mut bytes = 0;
+    let listing = match fs::read_dir(dir) {
+        Ok(listing) => listing,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok((0, 0)),
+        Err(err) => return Err(err),
+    };
+    for entry in listing {
+        let entry = entry?;
+        if entry.path().extension().map_or(false, |ext| ext == "js") {
+            entries += 1;
+            bytes += entry.metadata()?.len();
+        }
+    }
+    Ok((entries, bytes))
+}
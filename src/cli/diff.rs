@@ -0,0 +1,189 @@
+// `content-tag diff-templates <old> <new> [--json]`: which templates changed
+// between two versions of a file, and whether anything outside them did, so
+// that test selection can skip rendering tests when only JS moved around.
+//
+// Templates are identified by their content hash, so one that is unchanged
+// but moved still counts as unchanged. The templates left over on each side
+// are paired up in order as changed, and the rest are added or removed. Like
+// `diff`, the exit status is 0 when nothing changed and 1 when something did.
+
+use std::fs;
+
+use content_tag::{ContentHash, HashAlgorithm, Preprocessor, Range};
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+enum Status {
+    Unchanged,
+    Changed,
+    Added,
+    Removed,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TemplateDiff {
+    status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old: Option<Side>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new: Option<Side>,
+}
+
+#[derive(Serialize)]
+struct Side {
+    hash: String,
+    range: Range,
+    // 1-based, where the template starts
+    line: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiffReport {
+    templates: Vec<TemplateDiff>,
+    // whether the code outside the templates changed
+    js_changed: bool,
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let json = args.iter().any(|arg| arg == "--json");
+    let files: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+    let [old_path, new_path] = files[..] else {
+        eprintln!("Usage: content-tag diff-templates <old> <new> [--json]");
+        return 2;
+    };
+    let (old, new) = match (read(old_path), read(new_path)) {
+        (Ok(old), Ok(new)) => (old, new),
+        (Err(err), _) | (_, Err(err)) => {
+            eprintln!("{err}");
+            return 2;
+        }
+    };
+
+    let report = diff(&old, &new);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        print_text(&report);
+    }
+    let changed = report.js_changed
+        || report
+            .templates
+            .iter()
+            .any(|template| template.status != Status::Unchanged);
+    i32::from(changed)
+}
+
+struct Source {
+    src: String,
+    hashes: Vec<ContentHash>,
+}
+
+fn read(path: &str) -> Result<Source, String> {
+    let src = fs::read_to_string(path).map_err(|err| format!("Can't read {path}: {err}"))?;
+    let preprocessor = Preprocessor::builder()
+        .filename(path)
+        .build()
+        .map_err(|err| err.to_string())?;
+    let hashes = preprocessor
+        .content_hashes(&src, HashAlgorithm::Xxh3)
+        .map_err(|err| preprocessor.describe_error(&err.into()))?;
+    Ok(Source { src, hashes })
+}
+
+fn diff(old: &Source, new: &Source) -> DiffReport {
+    let side = |source: &Source, hash: &ContentHash| Side {
+        hash: hash.hash.clone(),
+        range: hash.range,
+        line: source.src[..hash.range.start].matches('\n').count() + 1,
+    };
+
+    let mut unmatched_old: Vec<&ContentHash> = old.hashes.iter().collect();
+    let mut unmatched_new = vec![];
+    let mut templates = vec![];
+    for hash in &new.hashes {
+        match unmatched_old.iter().position(|old| old.hash == hash.hash) {
+            Some(index) => {
+                let old_hash = unmatched_old.remove(index);
+                templates.push(TemplateDiff {
+                    status: Status::Unchanged,
+                    old: Some(side(old, old_hash)),
+                    new: Some(side(new, hash)),
+                });
+            }
+            None => unmatched_new.push(hash),
+        }
+    }
+    let mut unmatched_old = unmatched_old.into_iter();
+    for hash in unmatched_new {
+        let old_hash = unmatched_old.next();
+        templates.push(TemplateDiff {
+            status: if old_hash.is_some() {
+                Status::Changed
+            } else {
+                Status::Added
+            },
+            old: old_hash.map(|old_hash| side(old, old_hash)),
+            new: Some(side(new, hash)),
+        });
+    }
+    for old_hash in unmatched_old {
+        templates.push(TemplateDiff {
+            status: Status::Removed,
+            old: Some(side(old, old_hash)),
+            new: None,
+        });
+    }
+    templates.sort_by_key(|template| {
+        (
+            template
+                .new
+                .as_ref()
+                .map_or(usize::MAX, |new| new.range.start),
+            template.old.as_ref().map_or(0, |old| old.range.start),
+        )
+    });
+
+    DiffReport {
+        templates,
+        js_changed: outside_templates(old) != outside_templates(new),
+    }
+}
+
+// The source with every template taken out.
+fn outside_templates(source: &Source) -> Vec<&str> {
+    let mut pieces = vec![];
+    let mut pos = 0;
+    for hash in &source.hashes {
+        pieces.push(&source.src[pos..hash.range.start]);
+        pos = hash.range.end;
+    }
+    pieces.push(&source.src[pos..]);
+    pieces
+}
+
+fn print_text(report: &DiffReport) {
+    for template in &report.templates {
+        let line = |side: &Option<Side>| side.as_ref().map(|side| side.line);
+        match (template.status, line(&template.old), line(&template.new)) {
+            (Status::Unchanged, Some(old), Some(new)) if old != new => {
+                println!("unchanged  line {old} -> {new}")
+            }
+            (Status::Unchanged, _, Some(new)) => println!("unchanged  line {new}"),
+            (Status::Changed, Some(old), Some(new)) => println!("changed    line {old} -> {new}"),
+            (Status::Added, _, Some(new)) => println!("added      line {new}"),
+            (Status::Removed, Some(old), _) => println!("removed    line {old}"),
+            _ => {}
+        }
+    }
+    println!(
+        "JS outside templates {}",
+        if report.js_changed {
+            "changed"
+        } else {
+            "unchanged"
+        }
+    );
+}
@@ -0,0 +1,282 @@
+// `content-tag <dir> --out-dir <out>`: processes every `.gjs` and `.gts` file
+// under `<dir>` into the same place under `<out>`, as `.js` and `.ts`.
+//
+// `--report <file>` also writes a JSON summary of the run, with a line per
+// file (how many templates it has, how many were converted, its warnings or
+// error, how long it took and whether it came from the cache) and totals,
+// for dashboards that track build health and how much of an app is `.gjs`.
+//
+// `--options <file>` takes the options from a JSON file, with the ones for
+// every file under `default` and the ones that differ by extension under
+// `extensions`, on top of the default ones:
+//
+//   {"default": {"warn_unresolved": true},
+//    "extensions": {"gts": {"strip_types": true}, "stories.gjs": {"minify": true}}}
+//
+// A file with a warning that `severity_overrides` makes an error counts as
+// failed, though its output is still written. Files from the cache have no
+// warnings, so CI runs that rely on this shouldn't use one.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use content_tag::{ExtensionOptions, Options, Preprocessor, Severity, Warning};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::cache::Cache;
+use super::serve::RequestOptions;
+use super::{display_path, walk};
+
+const EXTENSIONS: &[&str] = &["gjs", "gts"];
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Report {
+    files: Vec<FileReport>,
+    total_files: usize,
+    total_templates: usize,
+    failed_files: usize,
+    cached_files: usize,
+    total_warnings: usize,
+    duration_ms: f64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FileReport {
+    path: String,
+    // found by parsing, so counted even when the file failed later on
+    templates: usize,
+    // the templates that became template calls
+    converted: usize,
+    // the cache only keeps the code, so a cached file has none
+    warnings: Vec<Warning>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    duration_ms: f64,
+    cached: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct OptionsFile {
+    default: Map<String, Value>,
+    extensions: BTreeMap<String, Map<String, Value>>,
+}
+
+// The options from `--options`, and each extension's as JSON, for the cache
+// key.
+struct Config {
+    options: ExtensionOptions,
+    default: String,
+    extensions: BTreeMap<String, String>,
+}
+
+impl Config {
+    fn load(path: &Path) -> Result<Self, String> {
+        let file = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let file: OptionsFile = serde_json::from_str(&file).map_err(|err| err.to_string())?;
+        let mut config = Config {
+            options: ExtensionOptions::new(file_options(Value::Object(file.default.clone()))?),
+            default: Value::Object(file.default.clone()).to_string(),
+            extensions: BTreeMap::new(),
+        };
+        for (extension, overrides) in file.extensions {
+            let mut merged = file.default.clone();
+            merged.extend(overrides);
+            let merged = Value::Object(merged);
+            let extension = extension.trim_start_matches('.').to_owned();
+            config
+                .options
+                .insert(&extension, file_options(merged.clone())?);
+            config.extensions.insert(extension, merged.to_string());
+        }
+        Ok(config)
+    }
+
+    fn fingerprint(&self, path: &Path) -> &str {
+        self.options
+            .extension_for(path)
+            .and_then(|extension| self.extensions.get(extension))
+            .unwrap_or(&self.default)
+    }
+}
+
+// Every file gets an inline source map, whatever the options file says.
+fn file_options(value: Value) -> Result<Options, String> {
+    let options: RequestOptions = serde_json::from_value(value).map_err(|err| err.to_string())?;
+    Ok(Options {
+        filename: None,
+        inline_source_map: true,
+        ..Options::from(options)
+    })
+}
+
+pub fn run(
+    root: &Path,
+    out_dir: Option<&Path>,
+    cache_dir: Option<&Path>,
+    report_path: Option<&Path>,
+    options_path: Option<&Path>,
+) -> i32 {
+    let Some(out_dir) = out_dir else {
+        eprintln!("Processing a directory needs --out-dir <dir> to write the output to");
+        return 2;
+    };
+    let config = match options_path {
+        Some(path) => match Config::load(path) {
+            Ok(config) => Some(config),
+            Err(err) => {
+                eprintln!("Can't use the options in {}: {err}", path.display());
+                return 2;
+            }
+        },
+        None => None,
+    };
+    let paths = match walk(root, EXTENSIONS) {
+        Ok(paths) => paths,
+        Err(err) => {
+            eprintln!("Can't read {}: {err}", root.display());
+            return 1;
+        }
+    };
+    let cache = cache_dir.and_then(|dir| match Cache::open(dir) {
+        Ok(cache) => Some(cache),
+        Err(err) => {
+            eprintln!("Can't use the cache in {}: {err}", dir.display());
+            None
+        }
+    });
+
+    let started = Instant::now();
+    let mut report = Report::default();
+    for path in paths {
+        let file = process_file(root, &path, out_dir, cache.as_ref(), config.as_ref());
+        let errors: Vec<&Warning> = file
+            .warnings
+            .iter()
+            .filter(|warning| warning.severity == Severity::Error)
+            .collect();
+        for warning in &errors {
+            eprintln!("{}: {}", warning.location, warning.message);
+        }
+        if let Some(error) = &file.error {
+            eprintln!("{}: {error}", file.path);
+        }
+        if file.error.is_some() || !errors.is_empty() {
+            report.failed_files += 1;
+        }
+        report.total_files += 1;
+        report.total_templates += file.templates;
+        report.total_warnings += file.warnings.len();
+        report.cached_files += usize::from(file.cached);
+        report.files.push(file);
+    }
+    report.duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    if let Some(out) = report_path {
+        if let Err(err) = fs::write(out, serde_json::to_string_pretty(&report).unwrap()) {
+            eprintln!("Can't write {}: {err}", out.display());
+            return 1;
+        }
+    }
+    if report.failed_files > 0 {
+        1
+    } else {
+        0
+    }
+}
+
+fn process_file(
+    root: &Path,
+    path: &Path,
+    out_dir: &Path,
+    cache: Option<&Cache>,
+    config: Option<&Config>,
+) -> FileReport {
+    let started = Instant::now();
+    let mut file = FileReport {
+        path: display_path(root, path),
+        templates: 0,
+        converted: 0,
+        warnings: vec![],
+        error: None,
+        duration_ms: 0.0,
+        cached: false,
+    };
+    let out = output_path(root, path, out_dir);
+    if let Err(error) = convert(path, &out, cache, config, &mut file) {
+        file.error = Some(error);
+    }
+    file.duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+    file
+}
+
+// Fills in `file` as far as it gets.
+fn convert(
+    path: &Path,
+    out: &Path,
+    cache: Option<&Cache>,
+    config: Option<&Config>,
+    file: &mut FileReport,
+) -> Result<(), String> {
+    let src = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    file.templates = Preprocessor::new()
+        .parse(&src, Default::default())
+        .map_err(|err| format!("Parse Error: {}", err.kind().msg()))?
+        .len();
+
+    // the same key as for a single file, so the two share entries, unless
+    // there are options from a file
+    let mut fingerprint = format!("filename={}\ninline_source_map=true", path.display());
+    if let Some(config) = config {
+        fingerprint.push_str(&format!("\noptions={}", config.fingerprint(path)));
+    }
+    let code = match cache.and_then(|cache| cache.get(&src, &fingerprint)) {
+        Some(code) => {
+            file.cached = true;
+            file.converted = file.templates;
+            code
+        }
+        None => {
+            let result = match config {
+                Some(config) => config.options.process(path, &src).result,
+                None => Preprocessor::new().process(
+                    &src,
+                    Options {
+                        filename: Some(path.into()),
+                        inline_source_map: true,
+                        ..Default::default()
+                    },
+                ),
+            };
+            let output = result.map_err(|err| err.to_string())?;
+            file.converted = output.metadata.replacements.len();
+            file.warnings = output.warnings;
+            if let Some(cache) = cache {
+                if let Err(err) = cache.put(&src, &fingerprint, &output.code) {
+                    eprintln!("Can't write to the cache: {err}");
+                }
+            }
+            output.code
+        }
+    };
+
+    if let Some(parent) = out.parent() {
+        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    fs::write(out, code).map_err(|err| format!("Can't write {}: {err}", out.display()))
+}
+
+// `root/a/b.gjs` becomes `out_dir/a/b.js`, and `.gts` becomes `.ts`.
+fn output_path(root: &Path, path: &Path, out_dir: &Path) -> PathBuf {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let extension = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("gts") => "ts",
+        _ => "js",
+    };
+    out_dir.join(relative).with_extension(extension)
+}
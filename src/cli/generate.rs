@@ -0,0 +1,54 @@
+// `content-tag generate component <name> [--class] [--ts] [--stdout]`: writes
+// the skeleton of a new component to `<name>.gjs` (or `.gts`), or prints it.
+
+use std::fs;
+use std::path::Path;
+
+use content_tag::{component_skeleton, ComponentSkeleton};
+
+const USAGE: &str = "Usage: content-tag generate component <name> [--class] [--ts] [--stdout]";
+
+pub fn run(args: &[String]) -> i32 {
+    let positional: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+    let [kind, name] = positional[..] else {
+        eprintln!("{USAGE}");
+        return 2;
+    };
+    if kind != "component" {
+        eprintln!("Can't generate a {kind:?}, only a component\n{USAGE}");
+        return 2;
+    }
+    let flag = |flag: &str| args.iter().any(|arg| arg == flag);
+    let skeleton = ComponentSkeleton {
+        class: flag("--class"),
+        typescript: flag("--ts"),
+    };
+    let src = match component_skeleton(name, skeleton) {
+        Ok(src) => src,
+        Err(err) => {
+            eprintln!("{err}");
+            return 2;
+        }
+    };
+    if flag("--stdout") {
+        print!("{src}");
+        return 0;
+    }
+
+    let extension = if skeleton.typescript { "gts" } else { "gjs" };
+    let path = format!("{name}.{extension}");
+    if Path::new(&path).exists() {
+        eprintln!("{path} already exists");
+        return 1;
+    }
+    match fs::write(&path, src) {
+        Ok(()) => {
+            println!("created {path}");
+            0
+        }
+        Err(err) => {
+            eprintln!("Can't write {path}: {err}");
+            1
+        }
+    }
+}
@@ -0,0 +1,91 @@
+// `content-tag migrate <dir> [--dry-run]`: merges each co-located component
+// (`foo.js` or `foo.ts` next to `foo.hbs`) into `foo.gjs` or `foo.gts`, and
+// removes the originals. A `.hbs` without JS becomes a template-only
+// component, but only inside a `components` directory, since templates
+// elsewhere (like routes) aren't components.
+
+use std::fs;
+use std::path::Path;
+
+use content_tag::merge_colocated;
+
+use super::{display_path, walk};
+
+pub fn run(args: &[String]) -> i32 {
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+    let Some(dir) = args.iter().find(|arg| !arg.starts_with("--")) else {
+        eprintln!("Usage: content-tag migrate <dir> [--dry-run]");
+        return 2;
+    };
+    let root = Path::new(dir);
+    let templates = match walk(root, &["hbs"]) {
+        Ok(templates) => templates,
+        Err(err) => {
+            eprintln!("Can't read {dir}: {err}");
+            return 1;
+        }
+    };
+
+    let mut failed = 0;
+    for hbs_path in templates {
+        let js_path = ["js", "ts"]
+            .iter()
+            .map(|ext| hbs_path.with_extension(ext))
+            .find(|path| path.is_file());
+        if js_path.is_none() && !in_components_dir(root, &hbs_path) {
+            continue;
+        }
+        let out_path = hbs_path.with_extension(match &js_path {
+            Some(path) if path.extension().map_or(false, |ext| ext == "ts") => "gts",
+            _ => "gjs",
+        });
+        match migrate(&hbs_path, js_path.as_deref(), &out_path, dry_run) {
+            Ok(()) => println!(
+                "{} -> {}",
+                display_path(root, &hbs_path),
+                display_path(root, &out_path)
+            ),
+            Err(err) => {
+                failed += 1;
+                eprintln!("{}: {err}", display_path(root, &hbs_path));
+            }
+        }
+    }
+    if failed > 0 {
+        eprintln!("{failed} component(s) couldn't be migrated");
+        return 1;
+    }
+    0
+}
+
+fn migrate(
+    hbs_path: &Path,
+    js_path: Option<&Path>,
+    out_path: &Path,
+    dry_run: bool,
+) -> Result<(), String> {
+    if out_path.exists() {
+        return Err(format!("{} already exists", out_path.display()));
+    }
+    let hbs = fs::read_to_string(hbs_path).map_err(|err| err.to_string())?;
+    let js = js_path
+        .map(fs::read_to_string)
+        .transpose()
+        .map_err(|err| err.to_string())?;
+    let merged = merge_colocated(js.as_deref(), &hbs).map_err(|err| err.to_string())?;
+    if dry_run {
+        return Ok(());
+    }
+    fs::write(out_path, merged).map_err(|err| err.to_string())?;
+    for path in std::iter::once(hbs_path).chain(js_path) {
+        fs::remove_file(path).map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+fn in_components_dir(root: &Path, path: &Path) -> bool {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .any(|component| component.as_os_str() == "components")
+}
@@ -0,0 +1,149 @@
+// `content-tag scan <dir> [--json]`: which files contain templates, for
+// auditing how far a codebase has moved from classic components to `.gjs`.
+
+use std::fs;
+use std::path::Path;
+
+use content_tag::{Options, Parsed, ParsedKind, Preprocessor};
+use serde::Serialize;
+
+use super::{display_path, walk};
+
+const EXTENSIONS: &[&str] = &["gjs", "gts", "js", "ts", "hbs"];
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScanReport {
+    files: Vec<FileReport>,
+    total_templates: usize,
+    // files with at least one `<template>`
+    template_files: usize,
+    // classic templates that still have to be migrated
+    hbs_files: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FileReport {
+    path: String,
+    templates: Vec<TemplateReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TemplateReport {
+    kind: ParsedKind,
+    // the size of the whole tag, in bytes
+    bytes: usize,
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let json = args.iter().any(|arg| arg == "--json");
+    let Some(dir) = args.iter().find(|arg| !arg.starts_with("--")) else {
+        eprintln!("Usage: content-tag scan <dir> [--json]");
+        return 2;
+    };
+    let root = Path::new(dir);
+    let paths = match walk(root, EXTENSIONS) {
+        Ok(paths) => paths,
+        Err(err) => {
+            eprintln!("Can't read {dir}: {err}");
+            return 1;
+        }
+    };
+
+    let mut report = ScanReport {
+        files: vec![],
+        total_templates: 0,
+        template_files: 0,
+        hbs_files: 0,
+    };
+    for path in paths {
+        if path.extension().map_or(false, |ext| ext == "hbs") {
+            report.hbs_files += 1;
+            continue;
+        }
+        let file = scan_file(root, &path);
+        report.total_templates += file.templates.len();
+        if !file.templates.is_empty() {
+            report.template_files += 1;
+        }
+        report.files.push(file);
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        print_text(&report);
+    }
+    0
+}
+
+fn scan_file(root: &Path, path: &Path) -> FileReport {
+    let mut file = FileReport {
+        path: display_path(root, path),
+        templates: vec![],
+        error: None,
+    };
+    let result = fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|src| {
+            Preprocessor::new()
+                .parse(
+                    &src,
+                    Options {
+                        filename: Some(path.into()),
+                        ..Default::default()
+                    },
+                )
+                .map_err(|err| err.kind().msg().to_string())
+        });
+    match result {
+        Ok(occurrences) => {
+            file.templates = occurrences
+                .iter()
+                .map(Parsed::from)
+                .map(|parsed| TemplateReport {
+                    kind: parsed.kind,
+                    bytes: parsed.range.end - parsed.range.start,
+                })
+                .collect()
+        }
+        Err(err) => file.error = Some(err),
+    }
+    file
+}
+
+fn print_text(report: &ScanReport) {
+    for file in &report.files {
+        if let Some(error) = &file.error {
+            println!("{}: error: {error}", file.path);
+            continue;
+        }
+        if file.templates.is_empty() {
+            continue;
+        }
+        let count = |kind: ParsedKind| {
+            file.templates
+                .iter()
+                .filter(|template| template.kind == kind)
+                .count()
+        };
+        let bytes: usize = file.templates.iter().map(|template| template.bytes).sum();
+        println!(
+            "{}: {} template(s) ({} expression, {} class-member), {bytes} bytes",
+            file.path,
+            file.templates.len(),
+            count(ParsedKind::Expression),
+            count(ParsedKind::ClassMember),
+        );
+    }
+    println!(
+        "{} template(s) in {} of {} file(s), {} .hbs file(s) left",
+        report.total_templates,
+        report.template_files,
+        report.files.len(),
+        report.hbs_files
+    );
+}
@@ -0,0 +1,349 @@
+// `content-tag serve --stdio`: a long-running server for tools that can't
+// load the npm package, so they only pay for startup once. Each line on stdin
+// is a JSON request and gets one line of JSON back on stdout, in order:
+//
+//   {"id": 1, "cmd": "process", "src": "...", "options": {"filename": "a.gjs"}}
+//   {"id": 1, "result": {"code": "...", "map": "...", "metadata": {...}, "warnings": []}}
+//
+//   {"id": 2, "cmd": "parse", "src": "..."}
+//   {"id": 2, "result": {"schemaVersion": 1, "templates": [...]}}
+//
+// `locate` is another name for `parse`. A failed request gets
+// `{"id": ..., "error": {"message": "...", "loc": {...}}}` instead, where
+// `loc` is only there for parse errors. `id` is optional and echoed back as
+// is. The server exits at the end of stdin.
+//
+// With the `server` feature, `content-tag serve --http 127.0.0.1:7433` takes
+// the same requests as `POST /process`, `/parse` or `/locate`, with the body
+// `{"src": ..., "options": ...}`. The response body is the `result` on
+// success, or the `error` with a 4xx or 5xx status.
+
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use content_tag::{
+    DefaultExportPlacement, Emit, Error, ErrorLoc, FileId, Options, ParseOutput, Preprocessor,
+    Severity, SnippetVersion, TextEdit, TrimContent, GLIMMER_COMPONENT,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use swc_common::Spanned;
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    cmd: String,
+    #[serde(flatten)]
+    input: Input,
+}
+
+#[derive(Deserialize)]
+struct Input {
+    src: String,
+    #[serde(default)]
+    options: RequestOptions,
+}
+
+// The subset of `PreprocessorOptions` that makes sense without JS callbacks,
+// with the same names. `--options` files use it too.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub(super) struct RequestOptions {
+    // a path, or a bundler's id for the module
+    filename: Option<String>,
+    inline_source_map: bool,
+    minify: bool,
+    source_map_file: Option<String>,
+    source_map_relative_to: Option<PathBuf>,
+    source_map_forward_slashes: bool,
+    leading_comments: bool,
+    include_content: Option<bool>,
+    dedent_content: bool,
+    trim_content: Option<String>,
+    hbs_tagged_templates: bool,
+    precompile_template_calls: bool,
+    explicit_scope: bool,
+    strip_types: bool,
+    component_this_in_methods: bool,
+    warn_unresolved: bool,
+    prune_unused_imports: bool,
+    simplify_imports: bool,
+    forbid_eval: bool,
+    emit: Option<String>,
+    collapse_whitespace_only: bool,
+    max_template_bytes: Option<usize>,
+    max_template_lines: Option<usize>,
+    default_export_placement: Option<String>,
+    // `true`, or the module to import the base class from
+    implicit_component_base: Option<Value>,
+    snippet_version: Option<u32>,
+    detect_already_transformed: bool,
+    collect_exports: bool,
+    mark_generated_regions: bool,
+    pure_annotations: bool,
+    severity_overrides: BTreeMap<String, Severity>,
+    tab_width: Option<usize>,
+}
+
+impl From<RequestOptions> for Options {
+    fn from(options: RequestOptions) -> Self {
+        Options {
+            filename: options.filename.map(FileId::from),
+            inline_source_map: options.inline_source_map,
+            minify: options.minify,
+            source_map_file: options.source_map_file,
+            source_map_relative_to: options.source_map_relative_to,
+            source_map_forward_slashes: options.source_map_forward_slashes,
+            leading_comments: options.leading_comments,
+            include_content: options.include_content.unwrap_or(true),
+            dedent_content: options.dedent_content,
+            trim_content: match options.trim_content.as_deref() {
+                Some("both") => TrimContent::Both,
+                Some("start") => TrimContent::Start,
+                Some("end") => TrimContent::End,
+                _ => TrimContent::None,
+            },
+            hbs_tagged_templates: options.hbs_tagged_templates,
+            precompile_template_calls: options.precompile_template_calls,
+            explicit_scope: options.explicit_scope,
+            strip_types: options.strip_types,
+            component_this_in_methods: options.component_this_in_methods,
+            warn_unresolved: options.warn_unresolved,
+            prune_unused_imports: options.prune_unused_imports,
+            simplify_imports: options.simplify_imports,
+            forbid_eval: options.forbid_eval,
+            emit: match options.emit.as_deref() {
+                Some("unified-diff") => Emit::UnifiedDiff,
+                _ => Emit::Code,
+            },
+            collapse_whitespace_only: options.collapse_whitespace_only,
+            max_template_bytes: options.max_template_bytes,
+            max_template_lines: options.max_template_lines,
+            default_export_placement: match options.default_export_placement.as_deref() {
+                Some("end") => DefaultExportPlacement::End,
+                _ => DefaultExportPlacement::InPlace,
+            },
+            implicit_component_base: match options.implicit_component_base {
+                Some(Value::String(module)) => Some(module),
+                Some(Value::Bool(true)) => Some(GLIMMER_COMPONENT.to_owned()),
+                _ => None,
+            },
+            snippet_version: match options.snippet_version {
+                Some(1) => SnippetVersion::V1,
+                _ => SnippetVersion::V2,
+            },
+            detect_already_transformed: options.detect_already_transformed,
+            collect_exports: options.collect_exports,
+            mark_generated_regions: options.mark_generated_regions,
+            pure_annotations: options.pure_annotations,
+            severity_overrides: options.severity_overrides,
+            tab_width: options.tab_width,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    loc: Option<ErrorLoc>,
+    #[serde(rename = "suggestedEdits", skip_serializing_if = "Vec::is_empty")]
+    suggested_edits: Vec<TextEdit>,
+}
+
+// Why a request failed, which decides the HTTP status.
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
+enum FailureKind {
+    InvalidRequest,
+    UnknownCmd,
+    // the source didn't parse, or a template couldn't be processed
+    Rejected,
+    Internal,
+}
+
+struct Failure {
+    #[cfg_attr(not(feature = "server"), allow(dead_code))]
+    kind: FailureKind,
+    error: ErrorResponse,
+}
+
+impl Failure {
+    fn new(kind: FailureKind, message: String) -> Self {
+        Failure {
+            kind,
+            error: ErrorResponse {
+                message,
+                loc: None,
+                suggested_edits: vec![],
+            },
+        }
+    }
+
+    fn parse(err: swc_ecma_parser::error::Error, preprocessor: &Preprocessor) -> Self {
+        Failure {
+            kind: FailureKind::Rejected,
+            error: ErrorResponse {
+                message: format!("Parse Error: {}", err.kind().msg()),
+                loc: Some(preprocessor.error_loc(err.span())),
+                suggested_edits: preprocessor.suggested_edits(&err),
+            },
+        }
+    }
+}
+
+pub fn run(args: &[String]) -> i32 {
+    if args.iter().any(|arg| arg == "--stdio") {
+        return serve_stdio();
+    }
+    if let Some(index) = args.iter().position(|arg| arg == "--http") {
+        let Some(addr) = args.get(index + 1) else {
+            eprintln!("--http needs an address, like --http 127.0.0.1:7433");
+            return 2;
+        };
+        #[cfg(feature = "server")]
+        return serve_http(addr);
+        #[cfg(not(feature = "server"))]
+        {
+            eprintln!("Can't serve {addr}: this build doesn't have the `server` feature");
+            return 1;
+        }
+    }
+    eprintln!("Usage: content-tag serve --stdio | --http <addr>");
+    2
+}
+
+fn serve_stdio() -> i32 {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout().lock();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                eprintln!("Can't read stdin: {err}");
+                return 1;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = respond(&line);
+        if writeln!(stdout, "{response}")
+            .and_then(|_| stdout.flush())
+            .is_err()
+        {
+            // whoever was reading has gone away
+            return 0;
+        }
+    }
+    0
+}
+
+// The response line for one request line.
+fn respond(line: &str) -> Value {
+    let request: Request = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(err) => {
+            // still echo the id if there is one, so the client can match it up
+            let id = serde_json::from_str::<Value>(line)
+                .ok()
+                .and_then(|value| value.get("id").cloned())
+                .unwrap_or_default();
+            return json!({ "id": id, "error": ErrorResponse {
+                message: format!("Invalid request: {err}"),
+                loc: None,
+            } });
+        }
+    };
+    match handle(&request.cmd, request.input) {
+        Ok(result) => json!({ "id": request.id, "result": result }),
+        Err(failure) => json!({ "id": request.id, "error": failure.error }),
+    }
+}
+
+fn handle(cmd: &str, input: Input) -> Result<Value, Failure> {
+    let preprocessor = Preprocessor::new();
+    match cmd {
+        "process" => match preprocessor.process(&input.src, input.options.into()) {
+            Ok(output) => Ok(json!({
+                "code": output.code,
+                "map": output.map,
+                "metadata": output.metadata,
+                "warnings": output.warnings,
+            })),
+            Err(Error::Parse(err)) => Err(Failure::parse(err, &preprocessor)),
+            Err(err @ Error::Internal { .. }) => Err(Failure::new(
+                FailureKind::Internal,
+                preprocessor.describe_error(&err),
+            )),
+            Err(err) => Err(Failure::new(FailureKind::Rejected, err.to_string())),
+        },
+        "parse" | "locate" => match preprocessor.parse(&input.src, input.options.into()) {
+            Ok(occurrences) => Ok(json!(ParseOutput::from(occurrences.as_slice()))),
+            Err(err) => Err(Failure::parse(err, &preprocessor)),
+        },
+        other => Err(Failure::new(
+            FailureKind::UnknownCmd,
+            format!("Unknown cmd {other:?}, expected \"process\", \"parse\" or \"locate\""),
+        )),
+    }
+}
+
+#[cfg(feature = "server")]
+fn serve_http(addr: &str) -> i32 {
+    use std::io::Read;
+    use tiny_http::{Header, Method, Response, Server};
+
+    let server = match Server::http(addr) {
+        Ok(server) => server,
+        Err(err) => {
+            eprintln!("Can't listen on {addr}: {err}");
+            return 1;
+        }
+    };
+    eprintln!("Listening on http://{addr}");
+    for mut request in server.incoming_requests() {
+        let cmd = request.url().trim_start_matches('/').to_owned();
+        let result = if *request.method() != Method::Post {
+            Err(Failure::new(
+                FailureKind::InvalidRequest,
+                "Requests have to be POSTs".to_owned(),
+            ))
+        } else {
+            let mut body = String::new();
+            request
+                .as_reader()
+                .read_to_string(&mut body)
+                .map_err(|err| err.to_string())
+                .and_then(|_| serde_json::from_str::<Input>(&body).map_err(|err| err.to_string()))
+                .map_err(|err| {
+                    Failure::new(
+                        FailureKind::InvalidRequest,
+                        format!("Invalid request: {err}"),
+                    )
+                })
+                .and_then(|input| handle(&cmd, input))
+        };
+        let (status, body) = match result {
+            Ok(result) => (200, result),
+            Err(failure) => (
+                match failure.kind {
+                    FailureKind::InvalidRequest => 400,
+                    FailureKind::UnknownCmd => 404,
+                    FailureKind::Rejected => 422,
+                    FailureKind::Internal => 500,
+                },
+                json!(failure.error),
+            ),
+        };
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(Header::from_bytes("Content-Type", "application/json").unwrap());
+        if let Err(err) = request.respond(response) {
+            eprintln!("Can't respond: {err}");
+        }
+    }
+    0
+}
@@ -0,0 +1,74 @@
+// `content-tag split <file.gjs>... [--dry-run]`: the inverse of `migrate`.
+// Each file becomes `.js` (or `.ts` for `.gts`) plus one `.hbs` per template,
+// and the original is removed.
+
+use std::fs;
+use std::path::Path;
+
+use content_tag::split_gjs;
+
+pub fn run(args: &[String]) -> i32 {
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+    let files: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+    if files.is_empty() {
+        eprintln!("Usage: content-tag split <file.gjs>... [--dry-run]");
+        return 2;
+    }
+
+    let mut failed = 0;
+    for file in files {
+        match split(Path::new(file), dry_run) {
+            Ok(warnings) => {
+                println!("split {file}");
+                for warning in warnings {
+                    eprintln!("  warning: {warning}");
+                }
+            }
+            Err(err) => {
+                failed += 1;
+                eprintln!("{file}: {err}");
+            }
+        }
+    }
+    if failed > 0 {
+        eprintln!("{failed} file(s) couldn't be split");
+        return 1;
+    }
+    0
+}
+
+fn split(path: &Path, dry_run: bool) -> Result<Vec<String>, String> {
+    let js_extension = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("gjs") => "js",
+        Some("gts") => "ts",
+        _ => return Err("not a .gjs or .gts file".to_owned()),
+    };
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or("the file name isn't valid unicode")?;
+    let src = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let split = split_gjs(&src, stem).map_err(|err| err.to_string())?;
+
+    let js_path = path.with_extension(js_extension);
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let outputs: Vec<_> = std::iter::once((js_path, split.js))
+        .chain(
+            split
+                .templates
+                .into_iter()
+                .map(|template| (dir.join(template.file_name), template.contents)),
+        )
+        .collect();
+    if let Some((existing, _)) = outputs.iter().find(|(path, _)| path.exists()) {
+        return Err(format!("{} already exists", existing.display()));
+    }
+    if dry_run {
+        return Ok(split.warnings);
+    }
+    for (path, contents) in outputs {
+        fs::write(path, contents).map_err(|err| err.to_string())?;
+    }
+    fs::remove_file(path).map_err(|err| err.to_string())?;
+    Ok(split.warnings)
+}
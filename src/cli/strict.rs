@@ -0,0 +1,150 @@
+// `content-tag strict-report <dir> [--json]`: for each `.gjs` and `.gts` file
+// under `<dir>`, which templates could list their scope instead of using
+// `eval` (every name they use is defined in the module) and which can't yet,
+// for dashboards that track a migration to strict-mode templates.
+
+use std::fs;
+use std::path::Path;
+
+use content_tag::{Preprocessor, Range};
+use serde::Serialize;
+
+use super::{display_path, walk};
+
+const EXTENSIONS: &[&str] = &["gjs", "gts"];
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct StrictReport {
+    files: Vec<FileReport>,
+    total_templates: usize,
+    strict_templates: usize,
+    // the templates that still need `eval`
+    eval_templates: usize,
+}
+
+#[derive(Serialize)]
+struct FileReport {
+    path: String,
+    templates: Vec<TemplateReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TemplateReport {
+    range: Range,
+    line: usize,
+    strict: bool,
+    // the names that aren't defined where the template is
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    unresolved: Vec<String>,
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let json = args.iter().any(|arg| arg == "--json");
+    let Some(dir) = args.iter().find(|arg| !arg.starts_with("--")) else {
+        eprintln!("Usage: content-tag strict-report <dir> [--json]");
+        return 2;
+    };
+    let root = Path::new(dir);
+    let paths = match walk(root, EXTENSIONS) {
+        Ok(paths) => paths,
+        Err(err) => {
+            eprintln!("Can't read {dir}: {err}");
+            return 1;
+        }
+    };
+
+    let mut report = StrictReport::default();
+    for path in paths {
+        let file = report_file(root, &path);
+        for template in &file.templates {
+            report.total_templates += 1;
+            if template.strict {
+                report.strict_templates += 1;
+            } else {
+                report.eval_templates += 1;
+            }
+        }
+        report.files.push(file);
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        print_text(&report);
+    }
+    0
+}
+
+fn report_file(root: &Path, path: &Path) -> FileReport {
+    let mut file = FileReport {
+        path: display_path(root, path),
+        templates: vec![],
+        error: None,
+    };
+    let src = match fs::read_to_string(path) {
+        Ok(src) => src,
+        Err(err) => {
+            file.error = Some(err.to_string());
+            return file;
+        }
+    };
+    let preprocessor = match Preprocessor::builder().filename(path).build() {
+        Ok(preprocessor) => preprocessor,
+        Err(err) => {
+            file.error = Some(err.to_string());
+            return file;
+        }
+    };
+    match preprocessor.strict_report(&src) {
+        Ok(templates) => {
+            file.templates = templates
+                .into_iter()
+                .map(|template| TemplateReport {
+                    range: template.range,
+                    line: src[..template.range.start].matches('\n').count() + 1,
+                    strict: template.is_strict(),
+                    unresolved: template.unresolved,
+                })
+                .collect()
+        }
+        Err(err) => file.error = Some(preprocessor.describe_error(&err)),
+    }
+    file
+}
+
+fn print_text(report: &StrictReport) {
+    for file in &report.files {
+        if let Some(error) = &file.error {
+            println!("{}: error: {error}", file.path);
+            continue;
+        }
+        if file.templates.is_empty() {
+            continue;
+        }
+        let strict = file
+            .templates
+            .iter()
+            .filter(|template| template.strict)
+            .count();
+        println!(
+            "{}: {strict} of {} template(s) can do without eval",
+            file.path,
+            file.templates.len()
+        );
+        for template in file.templates.iter().filter(|template| !template.strict) {
+            let names: Vec<String> = template
+                .unresolved
+                .iter()
+                .map(|name| format!("`{name}`"))
+                .collect();
+            println!("  line {}: {} not defined", template.line, names.join(", "));
+        }
+    }
+    println!(
+        "{} of {} template(s) can do without eval, {} still need it",
+        report.strict_templates, report.total_templates, report.eval_templates
+    );
+}
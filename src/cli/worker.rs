@@ -0,0 +1,244 @@
+// `content-tag --persistent_worker`: Bazel's persistent worker protocol, so
+// that rules can keep one content-tag running instead of starting it for
+// every file. Bazel passes `--persistent_worker` itself and then sends one
+// work request per action on stdin. Requests are length-delimited protobuf
+// by default, or newline-delimited JSON when the rule sets
+// `requires-worker-protocol: json` and passes `--worker_protocol=json` to us.
+//
+// The arguments of each action are `<input> --out <file> [--map <file>]`:
+// the output is written to `--out`, and its source map to `--map` if given.
+// Otherwise the map is inlined.
+
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::PathBuf;
+
+use content_tag::{Options, Preprocessor};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct WorkRequest {
+    arguments: Vec<String>,
+    request_id: i32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WorkResponse {
+    exit_code: i32,
+    output: String,
+    request_id: i32,
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let json = args.iter().any(|arg| arg == "--worker_protocol=json");
+    let stdin = io::stdin();
+    let mut stdin = stdin.lock();
+    let mut stdout = io::stdout().lock();
+    loop {
+        let request = if json {
+            read_json(&mut stdin)
+        } else {
+            read_proto(&mut stdin)
+        };
+        let request = match request {
+            Ok(Some(request)) => request,
+            // Bazel closes stdin when it's done with us
+            Ok(None) => return 0,
+            Err(err) => {
+                eprintln!("Can't read work request: {err}");
+                return 1;
+            }
+        };
+        let response = match action(&request.arguments) {
+            Ok(()) => WorkResponse {
+                exit_code: 0,
+                output: String::new(),
+                request_id: request.request_id,
+            },
+            Err(output) => WorkResponse {
+                exit_code: 1,
+                output,
+                request_id: request.request_id,
+            },
+        };
+        let written = if json {
+            serde_json::to_writer(&mut stdout, &response)
+                .map_err(io::Error::from)
+                .and_then(|_| writeln!(stdout))
+        } else {
+            write_proto(&mut stdout, &response)
+        };
+        if let Err(err) = written.and_then(|_| stdout.flush()) {
+            eprintln!("Can't write work response: {err}");
+            return 1;
+        }
+    }
+}
+
+// One action: the same processing as `content-tag <file>`, into files.
+fn action(args: &[String]) -> Result<(), String> {
+    let mut input = None;
+    let mut out = None;
+    let mut map = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => out = args.next().map(PathBuf::from),
+            "--map" => map = args.next().map(PathBuf::from),
+            _ if input.is_none() => input = Some(PathBuf::from(arg)),
+            _ => return Err(format!("Unexpected argument {arg:?}")),
+        }
+    }
+    let (Some(input), Some(out)) = (input, out) else {
+        return Err("Usage: <input> --out <file> [--map <file>]".to_owned());
+    };
+
+    let src = fs::read_to_string(&input)
+        .map_err(|err| format!("Can't read {}: {err}", input.display()))?;
+    let preprocessor = Preprocessor::new();
+    let output = preprocessor
+        .process(
+            &src,
+            Options {
+                filename: Some(input.into()),
+                inline_source_map: map.is_none(),
+                ..Default::default()
+            },
+        )
+        .map_err(|err| preprocessor.describe_error(&err))?;
+    fs::write(&out, output.code).map_err(|err| format!("Can't write {}: {err}", out.display()))?;
+    if let Some(map) = map {
+        fs::write(&map, output.map)
+            .map_err(|err| format!("Can't write {}: {err}", map.display()))?;
+    }
+    Ok(())
+}
+
+fn read_json(input: &mut impl BufRead) -> io::Result<Option<WorkRequest>> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if !line.trim().is_empty() {
+            return Ok(Some(serde_json::from_str(&line)?));
+        }
+    }
+}
+
+// The `WorkRequest` message, of which we only need `arguments` (field 1) and
+// `request_id` (field 3). Everything else is skipped.
+fn read_proto(input: &mut impl Read) -> io::Result<Option<WorkRequest>> {
+    let Some(len) = read_varint(input)? else {
+        return Ok(None);
+    };
+    let mut message = vec![0; len as usize];
+    input.read_exact(&mut message)?;
+
+    let mut request = WorkRequest::default();
+    let mut fields = &message[..];
+    while let Some(key) = read_varint(&mut fields)? {
+        match (key >> 3, key & 7) {
+            (1, 2) => {
+                let bytes = read_bytes(&mut fields)?;
+                request.arguments.push(
+                    String::from_utf8(bytes)
+                        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+                );
+            }
+            (3, 0) => request.request_id = varint(&mut fields)? as i32,
+            (_, 0) => {
+                varint(&mut fields)?;
+            }
+            (_, 1) => skip(&mut fields, 8)?,
+            (_, 2) => {
+                read_bytes(&mut fields)?;
+            }
+            (_, 5) => skip(&mut fields, 4)?,
+            (field, wire_type) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("field {field} has unsupported wire type {wire_type}"),
+                ))
+            }
+        }
+    }
+    Ok(Some(request))
+}
+
+// The `WorkResponse` message: `exit_code` (1), `output` (2), `request_id` (3).
+fn write_proto(out: &mut impl Write, response: &WorkResponse) -> io::Result<()> {
+    let mut message = vec![];
+    if response.exit_code != 0 {
+        write_varint(&mut message, 1 << 3);
+        write_varint(&mut message, response.exit_code as i64 as u64);
+    }
+    if !response.output.is_empty() {
+        write_varint(&mut message, 2 << 3 | 2);
+        write_varint(&mut message, response.output.len() as u64);
+        message.extend_from_slice(response.output.as_bytes());
+    }
+    if response.request_id != 0 {
+        write_varint(&mut message, 3 << 3);
+        write_varint(&mut message, response.request_id as i64 as u64);
+    }
+    let mut len = vec![];
+    write_varint(&mut len, message.len() as u64);
+    out.write_all(&len)?;
+    out.write_all(&message)
+}
+
+// `None` at the end of the input, but only if it ends between varints.
+fn read_varint(input: &mut impl Read) -> io::Result<Option<u64>> {
+    let mut value = 0;
+    for (i, shift) in (0..64).step_by(7).enumerate() {
+        let mut byte = [0];
+        if input.read(&mut byte)? == 0 {
+            if i == 0 {
+                return Ok(None);
+            }
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "varint is too long",
+    ))
+}
+
+fn varint(input: &mut impl Read) -> io::Result<u64> {
+    read_varint(input)?.ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+}
+
+fn read_bytes(input: &mut &[u8]) -> io::Result<Vec<u8>> {
+    let len = varint(input)? as usize;
+    if len > input.len() {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes.to_vec())
+}
+
+fn skip(input: &mut &[u8], len: usize) -> io::Result<()> {
+    if len > input.len() {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    *input = &input[len..];
+    Ok(())
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
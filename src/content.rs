@@ -0,0 +1,132 @@
+// Adjustments to template content that happen before it is emitted.
+
+// Which whitespace to remove from the ends of each template's content. The
+// newline right after `<template>` is otherwise part of the content.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum TrimContent {
+    #[default]
+    None,
+    Both,
+    Start,
+    End,
+}
+
+// How many bytes `mode` removes from the start and the end of `content`.
+#[cfg(feature = "transform")]
+pub(crate) fn trim_amounts(content: &str, mode: TrimContent) -> (usize, usize) {
+    let start = match mode {
+        TrimContent::Both | TrimContent::Start => content.len() - content.trim_start().len(),
+        _ => 0,
+    };
+    let end = match mode {
+        TrimContent::Both | TrimContent::End => content.len() - content.trim_end().len(),
+        _ => 0,
+    };
+    // an all-whitespace template would otherwise be counted twice
+    (start, end.min(content.len() - start))
+}
+
+// Content that is there but is nothing but whitespace, which is usually left
+// over from formatting rather than meant. An empty template isn't.
+pub(crate) fn is_whitespace_only(content: &str) -> bool {
+    !content.is_empty() && content.trim().is_empty()
+}
+
+#[cfg(feature = "transform")]
+pub(crate) fn trim(content: &str, mode: TrimContent) -> &str {
+    let (start, end) = trim_amounts(content, mode);
+    &content[start..content.len() - end]
+}
+
+// Strips the indentation shared by every line after the first. The first line
+// starts right after the opening tag, so its indentation (if any) isn't
+// comparable to the rest. Lines that are entirely whitespace don't count
+// towards the shared indentation, and are emptied out as far as possible.
+#[cfg(feature = "transform")]
+pub(crate) fn dedent(content: &str) -> String {
+    let mut lines = content.split('\n');
+    let first = lines.next().unwrap_or_default();
+    let rest: Vec<&str> = lines.collect();
+
+    let common = rest
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| leading_whitespace(line))
+        .reduce(common_prefix)
+        .unwrap_or("");
+
+    let mut output = String::with_capacity(content.len());
+    output.push_str(first);
+    for line in rest {
+        output.push('\n');
+        if let Some(stripped) = line.strip_prefix(common) {
+            output.push_str(stripped);
+        } else {
+            // only whitespace-only lines can be shorter than the common prefix
+            output.push_str(line.trim_start_matches([' ', '\t']));
+        }
+    }
+    output
+}
+
+#[cfg(feature = "transform")]
+fn leading_whitespace(line: &str) -> &str {
+    let end = line.len() - line.trim_start_matches([' ', '\t']).len();
+    &line[..end]
+}
+
+#[cfg(feature = "transform")]
+fn common_prefix<'a>(a: &'a str, b: &'a str) -> &'a str {
+    let len = a.bytes().zip(b.bytes()).take_while(|(x, y)| x == y).count();
+    &a[..len]
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_dedent() {
+    assert_eq!(
+        dedent("\n    <div>\n      hi\n    </div>\n  "),
+        "\n<div>\n  hi\n</div>\n"
+    );
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_dedent_keeps_first_line() {
+    assert_eq!(dedent("Hello\n    there\n      you"), "Hello\nthere\n  you");
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_dedent_ignores_blank_lines() {
+    assert_eq!(dedent("\n    a\n\n  \n    b"), "\na\n\n\nb");
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_dedent_mixed_indentation() {
+    assert_eq!(dedent("\n\t  a\n\t b"), "\n a\nb");
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_dedent_single_line() {
+    assert_eq!(dedent("  Hello  "), "  Hello  ");
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_trim() {
+    let content = "\n  <h1>Hi</h1>\n";
+    assert_eq!(trim(content, TrimContent::None), content);
+    assert_eq!(trim(content, TrimContent::Both), "<h1>Hi</h1>");
+    assert_eq!(trim(content, TrimContent::Start), "<h1>Hi</h1>\n");
+    assert_eq!(trim(content, TrimContent::End), "\n  <h1>Hi</h1>");
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_trim_whitespace_only() {
+    assert_eq!(trim_amounts(" \n ", TrimContent::Both), (3, 0));
+    assert_eq!(trim(" \n ", TrimContent::Both), "");
+}
@@ -0,0 +1,239 @@
+// Rewriting the content of templates in place, for lint autofixers. Edits
+// are given relative to a template's content, and everything outside the
+// edited content stays byte-for-byte as it was. The templates come back with
+// their ranges in the new source, so they can be edited again or handed to
+// `process_with_ranges` without parsing the file a second time.
+
+use std::fmt;
+
+use crate::error::TextEdit;
+use crate::locate::{self, Occurrence, Range, SliceError};
+
+pub struct TemplateEditor<'a> {
+    src: &'a str,
+    // as `parse` returned them for `src`
+    templates: &'a [Occurrence],
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EditedSource {
+    pub code: String,
+    // every template, in the same order, with its ranges in `code`
+    pub templates: Vec<Occurrence>,
+    // the same edits with ranges in the original source, sorted, for
+    // editors that apply them to their own buffer
+    pub edits: Vec<TextEdit>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum EditError {
+    NoTemplate(usize),
+    // an edit's range doesn't fit in the content of its template
+    OutsideContent { template: usize, range: Range },
+    // two edits change the same bytes, given as the second one's range in
+    // the original source
+    Overlapping(Range),
+    Slice(SliceError),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::NoTemplate(template) => write!(f, "there is no template {template}"),
+            EditError::OutsideContent { template, range } => write!(
+                f,
+                "range {}..{} is outside the content of template {template}",
+                range.start, range.end
+            ),
+            EditError::Overlapping(range) => write!(
+                f,
+                "the edit of {}..{} overlaps another edit",
+                range.start, range.end
+            ),
+            EditError::Slice(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<SliceError> for EditError {
+    fn from(err: SliceError) -> Self {
+        EditError::Slice(err)
+    }
+}
+
+impl<'a> TemplateEditor<'a> {
+    pub fn new(src: &'a str, templates: &'a [Occurrence]) -> Self {
+        TemplateEditor { src, templates }
+    }
+
+    // Applies `edits`, with ranges relative to the start of the content of
+    // `template` (an index into the templates).
+    pub fn edit(&self, template: usize, edits: &[TextEdit]) -> Result<EditedSource, EditError> {
+        let edits: Vec<(usize, TextEdit)> =
+            edits.iter().map(|edit| (template, edit.clone())).collect();
+        self.edit_many(&edits)
+    }
+
+    // Applies edits to any number of templates at once, each with the index
+    // of its template and a range relative to that template's content.
+    pub fn edit_many(&self, edits: &[(usize, TextEdit)]) -> Result<EditedSource, EditError> {
+        let mut absolute: Vec<(usize, TextEdit)> = Vec::with_capacity(edits.len());
+        for (template, edit) in edits {
+            let occurrence = self
+                .templates
+                .get(*template)
+                .ok_or(EditError::NoTemplate(*template))?;
+            let content = occurrence.content_range;
+            let content_src = content.slice(self.src)?;
+            let Range { start, end } = edit.range;
+            if start > end || end > content_src.len() {
+                return Err(EditError::OutsideContent {
+                    template: *template,
+                    range: edit.range,
+                });
+            }
+            let range = Range {
+                start: content.start + start,
+                end: content.start + end,
+            };
+            range.slice(self.src)?;
+            absolute.push((
+                *template,
+                TextEdit {
+                    range,
+                    new_text: edit.new_text.clone(),
+                },
+            ));
+        }
+        // insertions at the same offset keep the order they were given in
+        absolute.sort_by_key(|(_, edit)| (edit.range.start, edit.range.end));
+        for pair in absolute.windows(2) {
+            if pair[0].1.range.end > pair[1].1.range.start {
+                return Err(EditError::Overlapping(pair[1].1.range));
+            }
+        }
+
+        let mut code = String::with_capacity(self.src.len());
+        let mut copied = 0;
+        // how much longer each template got
+        let mut growth = vec![0isize; self.templates.len()];
+        for (template, edit) in &absolute {
+            code.push_str(&self.src[copied..edit.range.start]);
+            code.push_str(&edit.new_text);
+            copied = edit.range.end;
+            growth[*template] += edit.new_text.len() as isize - edit.range.len() as isize;
+        }
+        code.push_str(&self.src[copied..]);
+
+        // Edits only ever change content, so a template moves by how much
+        // the templates before it grew, and its own growth only moves what
+        // comes after its content.
+        let shift = |offset: usize, by: isize| (offset as isize + by) as usize;
+        let moved = |range: Range, start_by: isize, end_by: isize| Range {
+            start: shift(range.start, start_by),
+            end: shift(range.end, end_by),
+        };
+        let mut templates = Vec::with_capacity(self.templates.len());
+        let mut before = 0isize;
+        for (occurrence, grew) in self.templates.iter().zip(&growth) {
+            let after = before + grew;
+            let mut edited = occurrence.clone();
+            edited.range = moved(occurrence.range, before, after);
+            edited.start_range = moved(occurrence.start_range, before, before);
+            edited.content_range = moved(occurrence.content_range, before, after);
+            edited.end_range = moved(occurrence.end_range, after, after);
+            if let Some(comments) = &mut edited.leading_comments {
+                for comment in comments {
+                    comment.range = moved(comment.range, before, before);
+                }
+            }
+            if edited.contents.is_some() {
+                edited.contents =
+                    Some(code[edited.content_range.start..edited.content_range.end].to_owned());
+            }
+            templates.push(edited);
+            before = after;
+        }
+        locate::set_line_prefixes(&mut templates, &code);
+
+        Ok(EditedSource {
+            code,
+            templates,
+            edits: absolute.into_iter().map(|(_, edit)| edit).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+use crate::Preprocessor;
+
+#[cfg(test)]
+fn replace(start: usize, end: usize, new_text: &str) -> TextEdit {
+    TextEdit {
+        range: Range { start, end },
+        new_text: new_text.to_owned(),
+    }
+}
+
+#[cfg(test)]
+fn insert_at(at: usize, new_text: &str) -> TextEdit {
+    replace(at, at, new_text)
+}
+
+#[test]
+fn test_edits_content_and_moves_later_templates() {
+    let src = "let a = <template>{{t 'hi'}}</template>;\nlet b = <template>{{t 'bye'}}</template>;";
+    let p = Preprocessor::new();
+    let templates = p.parse(src, Default::default()).unwrap();
+    let edited = TemplateEditor::new(src, &templates)
+        .edit(0, &[replace(2, 3, "translate"), insert_at(0, "<p>")])
+        .unwrap();
+    assert_eq!(
+        edited.code,
+        "let a = <template><p>{{translate 'hi'}}</template>;\nlet b = <template>{{t 'bye'}}</template>;"
+    );
+    assert_eq!(
+        edited.templates,
+        p.parse(&edited.code, Default::default()).unwrap()
+    );
+    assert_eq!(edited.edits[0].range, Range { start: 18, end: 18 });
+}
+
+#[test]
+fn test_edits_many_templates() {
+    let src = "<template>a</template>\nclass A {\n  <template>\n    b\n  </template>\n}";
+    let p = Preprocessor::new();
+    let templates = p.parse(src, Default::default()).unwrap();
+    let edited = TemplateEditor::new(src, &templates)
+        .edit_many(&[(1, replace(5, 6, "bbb")), (0, replace(0, 1, ""))])
+        .unwrap();
+    assert_eq!(
+        edited.code,
+        "<template></template>\nclass A {\n  <template>\n    bbb\n  </template>\n}"
+    );
+    assert_eq!(
+        edited.templates,
+        p.parse(&edited.code, Default::default()).unwrap()
+    );
+}
+
+#[test]
+fn test_rejects_bad_edits() {
+    let src = "<template>abc</template>";
+    let templates = Preprocessor::new().parse(src, Default::default()).unwrap();
+    let editor = TemplateEditor::new(src, &templates);
+    assert_eq!(editor.edit(1, &[]).unwrap_err(), EditError::NoTemplate(1));
+    assert_eq!(
+        editor.edit(0, &[replace(2, 4, "x")]).unwrap_err(),
+        EditError::OutsideContent {
+            template: 0,
+            range: Range { start: 2, end: 4 }
+        }
+    );
+    assert_eq!(
+        editor
+            .edit(0, &[replace(0, 2, "x"), replace(1, 3, "y")])
+            .unwrap_err(),
+        EditError::Overlapping(Range { start: 11, end: 13 })
+    );
+}
@@ -0,0 +1,188 @@
+// Transforming a module that an embedder parsed and resolved themselves, as
+// one pass of their own pipeline. Running our resolver with fresh marks over
+// a module that already has theirs would disagree with them, so the code we
+// add is resolved with the embedder's marks instead, and the rest is left as
+// it was. Nothing is printed: that's up to the embedder too.
+
+use swc_common::Mark;
+use swc_ecma_ast::Module;
+use swc_ecma_transforms::typescript;
+use swc_ecma_utils::private_ident;
+use swc_ecma_visit::VisitMutWith;
+
+use crate::transform::{TransformResults, TransformVisitor};
+use crate::{
+    builder, imports, insert_import, references, swc_compat, warnings, Error, Options,
+    Preprocessor, Severity, TagHandler, Warning, IMPORT_ALIAS,
+};
+
+pub struct TransformedModule {
+    // how many templates became template calls
+    pub templates: usize,
+    // the local name of the import we inserted for the template compiler,
+    // unless nothing needed it
+    pub inserted_import: Option<String>,
+    pub warnings: Vec<Warning>,
+}
+
+// `@scope` comments are found through the comments we collect while parsing,
+// and we didn't parse this module.
+fn check_options(options: &Options) -> Result<(), Error> {
+    let needs_comments = [
+        ("explicit_scope", options.explicit_scope),
+        ("forbid_eval", options.forbid_eval),
+    ];
+    match needs_comments.iter().find(|(_, enabled)| *enabled) {
+        Some((name, _)) => Err(Error::InvalidOptions(format!(
+            "`{name}` needs the comments from parsing the module, which `transform_module` doesn't do"
+        ))),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn transform_module(
+    preprocessor: &Preprocessor,
+    module: &mut Module,
+    unresolved_mark: Mark,
+    top_level_mark: Mark,
+) -> Result<TransformedModule, Error> {
+    let options = &preprocessor.options;
+    builder::validate(options)?;
+    check_options(options)?;
+    let default_handler = TagHandler::default();
+    let handler = options.tags.get("template").unwrap_or(&default_handler);
+
+    // we don't have the source to pick a name that isn't in it, but the
+    // import has a mark of its own for the embedder's hygiene pass
+    let id = private_ident!(IMPORT_ALIAS);
+    let mut needs_import = false;
+    let mut visitor = TransformVisitor::with_config(
+        &id,
+        Some(&mut needs_import),
+        preprocessor.transform_config(options, handler),
+    );
+    module.visit_mut_with(&mut visitor);
+    let TransformResults {
+        replaced,
+        errors,
+        requested_imports,
+        suggested_edits,
+        labels,
+        warnings: content_warnings,
+        ..
+    } = visitor.finish();
+    if !errors.is_empty() {
+        return Err(preprocessor.template_errors_with_edits(errors, &suggested_edits, &labels));
+    }
+
+    if needs_import {
+        for request in requested_imports.iter().rev() {
+            let local = swc_ecma_ast::Ident::new(request.local.as_str().into(), Default::default());
+            insert_import(module, &request.module, &request.specifier, &local);
+        }
+        insert_import(
+            module,
+            &handler.import_module,
+            &handler.import_specifier,
+            &id,
+        );
+    }
+
+    // The resolver leaves identifiers that already have a syntax context
+    // alone, so this only resolves the ones we added.
+    swc_compat::resolve(module, unresolved_mark, top_level_mark);
+
+    let mut warnings: Vec<Warning> = references::check_probes(module, unresolved_mark)
+        .into_iter()
+        .map(|(span, name)| Warning {
+            code: "unresolved-reference".to_owned(),
+            message: references::unresolved_message(&name),
+            range: preprocessor.range(span),
+            location: preprocessor.location(span),
+            severity: Severity::Warning,
+        })
+        .collect();
+    warnings.extend(preprocessor.content_warnings(content_warnings));
+    warnings::apply_severity_overrides(&mut warnings, &options.severity_overrides);
+
+    if options.strip_types {
+        module.visit_mut_with(&mut typescript::strip(top_level_mark));
+    }
+    let removed_imports = if options.prune_unused_imports && needs_import {
+        imports::prune_unused(module, &[id.to_id()])
+    } else {
+        vec![]
+    };
+    if options.simplify_imports {
+        imports::simplify(module);
+    }
+
+    Ok(TransformedModule {
+        templates: replaced.len(),
+        inserted_import: (needs_import && removed_imports.is_empty()).then(|| id.sym.to_string()),
+        warnings,
+    })
+}
+
+#[cfg(test)]
+use swc_common::{sync::Lrc, FileName, SourceMap, GLOBALS};
+#[cfg(test)]
+use swc_ecma_parser::{Parser, StringInput, Syntax};
+#[cfg(test)]
+use swc_ecma_transforms::resolver;
+
+#[test]
+fn test_transforms_with_the_callers_marks() {
+    let source_map: Lrc<SourceMap> = Default::default();
+    let file = source_map.new_source_file(
+        FileName::Anon,
+        "let known = 1;\nexport default <template>{{known}} {{unknown}}</template>;".into(),
+    );
+    let preprocessor = Preprocessor::builder()
+        .source_map(source_map.clone())
+        .warn_unresolved(true)
+        .build()
+        .unwrap();
+
+    GLOBALS.set(&Default::default(), || {
+        let mut module = Parser::new(
+            Syntax::Typescript(Default::default()),
+            StringInput::from(&*file),
+            None,
+        )
+        .parse_module()
+        .unwrap();
+        let unresolved_mark = Mark::new();
+        let top_level_mark = Mark::new();
+        module.visit_mut_with(&mut resolver(unresolved_mark, top_level_mark, false));
+
+        let transformed = preprocessor
+            .transform_module(&mut module, unresolved_mark, top_level_mark)
+            .unwrap();
+        assert_eq!(transformed.templates, 1);
+        assert_eq!(transformed.inserted_import.as_deref(), Some(IMPORT_ALIAS));
+        let messages: Vec<String> = transformed
+            .warnings
+            .into_iter()
+            .map(|warning| warning.message)
+            .collect();
+        assert_eq!(messages, vec![references::unresolved_message("unknown")]);
+    });
+}
+
+#[test]
+fn test_rejects_options_that_need_comments() {
+    let preprocessor = Preprocessor::builder()
+        .explicit_scope(true)
+        .build()
+        .unwrap();
+    let mut module = Module {
+        span: Default::default(),
+        body: vec![],
+        shebang: None,
+    };
+    let result = GLOBALS.set(&Default::default(), || {
+        preprocessor.transform_module(&mut module, Mark::new(), Mark::new())
+    });
+    assert!(matches!(result, Err(Error::InvalidOptions(_))));
+}
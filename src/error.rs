@@ -0,0 +1,168 @@
+use serde::Serialize;
+use std::fmt;
+
+use crate::locate::Range;
+
+#[derive(Debug)]
+pub enum Error {
+    Parse(swc_ecma_parser::error::Error),
+    // One of the `Options::transformers` failed for one or more templates.
+    Transformer(Vec<TemplateError>),
+    // The `Options` don't make sense together, before anything was parsed.
+    InvalidOptions(String),
+    // A file couldn't be converted between co-located and `.gjs` form.
+    Migration(String),
+    // Something went wrong inside content-tag itself. These are always bugs,
+    // so `detail` should carry enough context to open an issue with.
+    Internal {
+        message: String,
+        detail: Option<String>,
+    },
+    // `process_async` was cancelled through its `CancelHandle` (or dropped)
+    // before it finished.
+    Cancelled,
+}
+
+#[cfg(feature = "transform")]
+impl Error {
+    pub(crate) fn internal(message: impl Into<String>, detail: Option<String>) -> Self {
+        Error::Internal {
+            message: message.into(),
+            detail,
+        }
+    }
+}
+
+// How code frames in error messages are drawn, for environments (like CI
+// logs) that mangle the default output.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct ErrorRendering {
+    // wrap code frames at this many columns instead of the renderer's default
+    pub width: Option<usize>,
+    // use ASCII instead of unicode box-drawing characters
+    pub ascii: bool,
+    // don't produce a colored rendering at all
+    pub no_color: bool,
+    // show at most this many characters of each line around the error, with
+    // `…` where they're cut, so that an error in a huge single-line template
+    // stays readable. `None` means 240.
+    pub line_window: Option<usize>,
+}
+
+#[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+pub(crate) const DEFAULT_LINE_WINDOW: usize = 240;
+
+impl ErrorRendering {
+    #[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+    pub(crate) fn line_window(&self) -> usize {
+        self.line_window.unwrap_or(DEFAULT_LINE_WINDOW)
+    }
+}
+
+// The characters of a line of `len` characters that a code frame shows
+// around `column`, at most `window` of them.
+#[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+pub(crate) fn window_around(len: usize, column: usize, window: usize) -> std::ops::Range<usize> {
+    if len <= window {
+        return 0..len;
+    }
+    let start = column.saturating_sub(window / 2).min(len - window);
+    start..start + window
+}
+
+// The characters of `line` in `window`, with `…` where some were cut.
+#[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+pub(crate) fn cut_line(line: &str, window: &std::ops::Range<usize>) -> String {
+    let len = line.chars().count();
+    let mut cut = String::new();
+    if window.start > 0 && len > window.start {
+        cut.push('…');
+    }
+    cut.extend(line.chars().skip(window.start).take(window.len()));
+    if len > window.end {
+        cut.push('…');
+    }
+    cut
+}
+
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+pub struct TemplateError {
+    // the span of the whole content tag in the input
+    pub range: Range,
+    // where the template is, like `path/to/file.gjs:2:15: 2:30`
+    pub location: String,
+    pub message: String,
+    // edits of the input that would fix this, for editors to offer as a
+    // quick fix. Usually empty.
+    #[serde(rename = "suggestedEdits", skip_serializing_if = "Vec::is_empty")]
+    pub suggested_edits: Vec<TextEdit>,
+    // other places in the input that explain the error, like the decorator a
+    // template is in. Usually empty.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<Label>,
+}
+
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+pub struct Label {
+    pub range: Range,
+    pub location: String,
+    pub message: String,
+}
+
+// Replacing `range` in the input with `new_text`, like in LSP.
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+pub struct TextEdit {
+    pub range: Range,
+    #[serde(rename = "newText")]
+    pub new_text: String,
+}
+
+impl From<swc_ecma_parser::error::Error> for Error {
+    fn from(err: swc_ecma_parser::error::Error) -> Self {
+        Error::Parse(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(err) => write!(f, "Parse Error: {}", err.kind().msg()),
+            Error::Transformer(errors) => {
+                write!(f, "Failed to transform {} template(s):", errors.len())?;
+                for error in errors {
+                    write!(f, "\n  {}: {}", error.location, error.message)?;
+                    for label in &error.labels {
+                        write!(f, "\n    {}: {}", label.location, label.message)?;
+                    }
+                }
+                Ok(())
+            }
+            Error::InvalidOptions(message) => write!(f, "Invalid options: {message}"),
+            Error::Migration(message) => write!(f, "Can't migrate: {message}"),
+            Error::Internal { message, detail } => {
+                write!(f, "Internal error in content-tag: {message}")?;
+                if let Some(detail) = detail {
+                    write!(f, "\n{detail}")?;
+                }
+                Ok(())
+            }
+            Error::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+#[test]
+fn test_window_around() {
+    assert_eq!(window_around(10, 5, 20), 0..10);
+    assert_eq!(window_around(1000, 500, 100), 450..550);
+    assert_eq!(window_around(1000, 10, 100), 0..100);
+    assert_eq!(window_around(1000, 990, 100), 900..1000);
+}
+
+#[test]
+fn test_cut_line() {
+    let line = "a".repeat(10) + "bc" + &"d".repeat(10);
+    assert_eq!(cut_line(&line, &(9..13)), "…abcd…");
+    assert_eq!(cut_line(&line, &(0..22)), line);
+    assert_eq!(cut_line("short", &(9..13)), "");
+}
@@ -0,0 +1,131 @@
+// A readable account of what `process` did to a file, for teaching and for
+// debugging integrations: the output with a numbered marker where each
+// change starts, and a legend that says what each marker is.
+
+use std::fmt;
+
+use crate::CodeMapPair;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Explanation {
+    // the output with `/*[n]*/` in front of each change. Its source map no
+    // longer lines up, so this is only for reading.
+    pub code: String,
+    // what marker `n` is, at index `n - 1`, followed by the changes that
+    // left nothing in the output to mark
+    pub legend: Vec<String>,
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.code)?;
+        for line in &self.legend {
+            writeln!(f, "// {line}")?;
+        }
+        Ok(())
+    }
+}
+
+impl CodeMapPair {
+    // `src` is what was processed into this output.
+    pub fn explain(&self, src: &str) -> Explanation {
+        let metadata = &self.metadata;
+        let mut marked: Vec<(usize, String)> = vec![];
+        if let Some(import) = &metadata.inserted_import {
+            marked.push((
+                import.range.start,
+                format!(
+                    "inserted the import of `{}` from \"{}\" as `{}` at {}",
+                    import.specifier,
+                    import.module,
+                    import.local,
+                    position(&self.code, import.range.start)
+                ),
+            ));
+        }
+        for import in &metadata.requested_imports {
+            marked.push((
+                import.range.start,
+                format!(
+                    "inserted the import of `{}` from \"{}\" that a transformer asked for at {}",
+                    import.specifier,
+                    import.module,
+                    position(&self.code, import.range.start)
+                ),
+            ));
+        }
+        for replacement in &metadata.replacements {
+            let evals = metadata
+                .eval_usages
+                .iter()
+                .any(|usage| usage.original_range == replacement.original_range);
+            marked.push((
+                replacement.range.start,
+                format!(
+                    "the template at {} became a template call at {}{}",
+                    position(src, replacement.original_range.start),
+                    position(&self.code, replacement.range.start),
+                    if evals {
+                        ", which uses eval to see its scope"
+                    } else {
+                        ""
+                    }
+                ),
+            ));
+        }
+        marked.sort_by_key(|(offset, _)| *offset);
+
+        let mut code = self.code.clone();
+        for (index, (offset, _)) in marked.iter().enumerate().rev() {
+            code.insert_str(*offset, &format!("/*[{}]*/", index + 1));
+        }
+        let mut legend: Vec<String> = marked
+            .into_iter()
+            .enumerate()
+            .map(|(index, (_, note))| format!("[{}] {note}", index + 1))
+            .collect();
+        legend.extend(
+            metadata
+                .removed_imports
+                .iter()
+                .map(|local| format!("removed the unused import of `{local}`")),
+        );
+        Explanation { code, legend }
+    }
+}
+
+// `line:column` of `offset`, both 1-based like in editors.
+fn position(text: &str, offset: usize) -> String {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+    format!(
+        "{}:{}",
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1
+    )
+}
+
+#[cfg(test)]
+use crate::Preprocessor;
+
+#[test]
+fn test_explains_each_change() {
+    let src = "let x = 1;\nlet y = <template>Hi</template>;";
+    let output = Preprocessor::new()
+        .process(src, Default::default())
+        .unwrap();
+    let explanation = output.explain(src);
+    assert_eq!(explanation.legend.len(), 2);
+    assert!(explanation.legend[0].starts_with(
+        "[1] inserted the import of `template` from \"@ember/template-compiler\" as `template_"
+    ));
+    assert!(explanation.legend[0].ends_with("at 1:1"));
+    assert!(explanation.legend[1].starts_with("[2] the template at 2:9 became a template call at "));
+    assert!(explanation.legend[1].ends_with(", which uses eval to see its scope"));
+    assert!(explanation.code.starts_with("/*[1]*/import"));
+    let call = explanation.code.find("/*[2]*/").unwrap();
+    assert!(explanation.code[call..].starts_with(&format!(
+        "/*[2]*/{}(",
+        output.metadata.inserted_import.as_ref().unwrap().local
+    )));
+}
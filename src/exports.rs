@@ -0,0 +1,360 @@
+// What a module exports, read from the same parse as its templates, for
+// tools that index routes and components. It describes the source as
+// written: types are listed even when `strip_types` removes them from the
+// output, and a bare `<template>` is the default export it becomes.
+
+use serde::Serialize;
+use swc_ecma_ast::{
+    Decl, DefaultDecl, ExportSpecifier, Expr, ExprStmt, Module, ModuleDecl, ModuleExportName,
+    ModuleItem, ObjectPatProp, Pat, Stmt,
+};
+
+#[derive(Serialize, Debug, Default, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportMap {
+    // the names exported from this module's own bindings, in order
+    pub named: Vec<NamedExport>,
+    pub default: Option<DefaultExport>,
+    // `export { a } from "x"` and `export * from "x"`, including
+    // `export { default } from "x"`, which doesn't count as `default`
+    pub re_exports: Vec<ReExport>,
+}
+
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NamedExport {
+    pub name: String,
+    // the binding it exports, which differs from `name` for
+    // `export { a as b }`
+    pub local: String,
+    pub kind: ExportKind,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportKind {
+    Variable,
+    Function,
+    Class,
+    Enum,
+    // interfaces, type aliases and `export type { ... }`
+    Type,
+    Namespace,
+    // `export { a }`, whatever `a` is
+    Binding,
+}
+
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DefaultExport {
+    pub kind: DefaultExportKind,
+    // the class or function's name, or the binding for `binding`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DefaultExportKind {
+    // `export default <template>`, or a bare `<template>` at the top level
+    Template,
+    Class,
+    Function,
+    Type,
+    // `export default foo` and `export { foo as default }`
+    Binding,
+    Expression,
+}
+
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReExport {
+    pub module: String,
+    // the name it's exported as, or `None` for `export *`
+    pub name: Option<String>,
+    // the name in `module`, or `None` for `export *` and `export * as ns`
+    pub imported: Option<String>,
+}
+
+pub(crate) fn exports(module: &Module) -> ExportMap {
+    let mut map = ExportMap::default();
+    for item in &module.body {
+        match item {
+            ModuleItem::ModuleDecl(decl) => add_decl(&mut map, decl),
+            ModuleItem::Stmt(Stmt::Expr(ExprStmt { expr, .. }))
+                if matches!(&**expr, Expr::ContentTagExpression(_)) =>
+            {
+                map.default = Some(DefaultExport {
+                    kind: DefaultExportKind::Template,
+                    name: None,
+                });
+            }
+            ModuleItem::Stmt(_) => {}
+        }
+    }
+    map
+}
+
+fn add_decl(map: &mut ExportMap, decl: &ModuleDecl) {
+    match decl {
+        ModuleDecl::ExportDecl(export) => {
+            let mut add = |name: &str, kind| {
+                map.named.push(NamedExport {
+                    name: name.to_owned(),
+                    local: name.to_owned(),
+                    kind,
+                })
+            };
+            match &export.decl {
+                Decl::Class(class) => add(&class.ident.sym, ExportKind::Class),
+                Decl::Fn(function) => add(&function.ident.sym, ExportKind::Function),
+                Decl::Var(var) => {
+                    let mut names = vec![];
+                    for declarator in &var.decls {
+                        binding_names(&declarator.name, &mut names);
+                    }
+                    for name in names {
+                        add(&name, ExportKind::Variable);
+                    }
+                }
+                Decl::TsInterface(interface) => add(&interface.id.sym, ExportKind::Type),
+                Decl::TsTypeAlias(alias) => add(&alias.id.sym, ExportKind::Type),
+                Decl::TsEnum(ts_enum) => add(&ts_enum.id.sym, ExportKind::Enum),
+                Decl::TsModule(ts_module) => {
+                    add(&module_name(&ts_module.id), ExportKind::Namespace)
+                }
+                _ => {}
+            }
+        }
+        ModuleDecl::ExportNamed(export) => {
+            for specifier in &export.specifiers {
+                match (&export.src, specifier) {
+                    (Some(src), ExportSpecifier::Named(named)) => map.re_exports.push(ReExport {
+                        module: src.value.to_string(),
+                        name: Some(export_name(named.exported.as_ref().unwrap_or(&named.orig))),
+                        imported: Some(export_name(&named.orig)),
+                    }),
+                    (Some(src), ExportSpecifier::Namespace(namespace)) => {
+                        map.re_exports.push(ReExport {
+                            module: src.value.to_string(),
+                            name: Some(export_name(&namespace.name)),
+                            imported: None,
+                        })
+                    }
+                    (Some(src), ExportSpecifier::Default(default)) => {
+                        map.re_exports.push(ReExport {
+                            module: src.value.to_string(),
+                            name: Some(default.exported.sym.to_string()),
+                            imported: Some("default".to_owned()),
+                        })
+                    }
+                    (None, ExportSpecifier::Named(named)) => {
+                        let local = export_name(&named.orig);
+                        let name = named.exported.as_ref().map_or(local.clone(), export_name);
+                        if name == "default" {
+                            map.default = Some(DefaultExport {
+                                kind: DefaultExportKind::Binding,
+                                name: Some(local),
+                            });
+                        } else {
+                            let kind = if export.type_only || named.is_type_only {
+                                ExportKind::Type
+                            } else {
+                                ExportKind::Binding
+                            };
+                            map.named.push(NamedExport { name, local, kind });
+                        }
+                    }
+                    (None, _) => {}
+                }
+            }
+        }
+        ModuleDecl::ExportAll(export) => map.re_exports.push(ReExport {
+            module: export.src.value.to_string(),
+            name: None,
+            imported: None,
+        }),
+        ModuleDecl::ExportDefaultDecl(export) => {
+            map.default = Some(match &export.decl {
+                DefaultDecl::Class(class) => DefaultExport {
+                    kind: DefaultExportKind::Class,
+                    name: class.ident.as_ref().map(|ident| ident.sym.to_string()),
+                },
+                DefaultDecl::Fn(function) => DefaultExport {
+                    kind: DefaultExportKind::Function,
+                    name: function.ident.as_ref().map(|ident| ident.sym.to_string()),
+                },
+                DefaultDecl::TsInterfaceDecl(interface) => DefaultExport {
+                    kind: DefaultExportKind::Type,
+                    name: Some(interface.id.sym.to_string()),
+                },
+            })
+        }
+        ModuleDecl::ExportDefaultExpr(export) => {
+            let mut expr = &*export.expr;
+            while let Expr::Paren(paren) = expr {
+                expr = &paren.expr;
+            }
+            map.default = Some(match expr {
+                Expr::ContentTagExpression(_) => DefaultExport {
+                    kind: DefaultExportKind::Template,
+                    name: None,
+                },
+                Expr::Ident(ident) => DefaultExport {
+                    kind: DefaultExportKind::Binding,
+                    name: Some(ident.sym.to_string()),
+                },
+                Expr::Class(class) => DefaultExport {
+                    kind: DefaultExportKind::Class,
+                    name: class.ident.as_ref().map(|ident| ident.sym.to_string()),
+                },
+                Expr::Fn(function) => DefaultExport {
+                    kind: DefaultExportKind::Function,
+                    name: function.ident.as_ref().map(|ident| ident.sym.to_string()),
+                },
+                Expr::Arrow(_) => DefaultExport {
+                    kind: DefaultExportKind::Function,
+                    name: None,
+                },
+                _ => DefaultExport {
+                    kind: DefaultExportKind::Expression,
+                    name: None,
+                },
+            })
+        }
+        _ => {}
+    }
+}
+
+fn export_name(name: &ModuleExportName) -> String {
+    match name {
+        ModuleExportName::Ident(ident) => ident.sym.to_string(),
+        ModuleExportName::Str(name) => name.value.to_string(),
+    }
+}
+
+fn module_name(name: &swc_ecma_ast::TsModuleName) -> String {
+    match name {
+        swc_ecma_ast::TsModuleName::Ident(ident) => ident.sym.to_string(),
+        swc_ecma_ast::TsModuleName::Str(name) => name.value.to_string(),
+    }
+}
+
+// The names a declaration like `const { a, b: [c] } = x` binds.
+fn binding_names(pat: &Pat, names: &mut Vec<String>) {
+    match pat {
+        Pat::Ident(ident) => names.push(ident.id.sym.to_string()),
+        Pat::Array(array) => {
+            for elem in array.elems.iter().flatten() {
+                binding_names(elem, names);
+            }
+        }
+        Pat::Object(object) => {
+            for prop in &object.props {
+                match prop {
+                    ObjectPatProp::KeyValue(prop) => binding_names(&prop.value, names),
+                    ObjectPatProp::Assign(prop) => names.push(prop.key.sym.to_string()),
+                    ObjectPatProp::Rest(rest) => binding_names(&rest.arg, names),
+                }
+            }
+        }
+        Pat::Rest(rest) => binding_names(&rest.arg, names),
+        Pat::Assign(assign) => binding_names(&assign.left, names),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+use crate::Preprocessor;
+
+#[test]
+fn test_lists_exports() {
+    let src = r#"import Component from "@glimmer/component";
+export const a = 1, { b, c: [d] } = x;
+export function f() {}
+export class A extends Component { <template>Hi</template> }
+export interface Args {}
+export enum Size { Small }
+const e = 2;
+export { e, e as renamed };
+export { g as h } from "./g";
+export * from "./all";
+export * as ns from "./ns";
+<template>Hello</template>"#;
+    let (_, exports) = Preprocessor::new()
+        .parse_with_exports(src, Default::default())
+        .unwrap();
+    let named: Vec<(&str, &str, ExportKind)> = exports
+        .named
+        .iter()
+        .map(|export| (export.name.as_str(), export.local.as_str(), export.kind))
+        .collect();
+    assert_eq!(
+        named,
+        vec![
+            ("a", "a", ExportKind::Variable),
+            ("b", "b", ExportKind::Variable),
+            ("d", "d", ExportKind::Variable),
+            ("f", "f", ExportKind::Function),
+            ("A", "A", ExportKind::Class),
+            ("Args", "Args", ExportKind::Type),
+            ("Size", "Size", ExportKind::Enum),
+            ("e", "e", ExportKind::Binding),
+            ("renamed", "e", ExportKind::Binding),
+        ]
+    );
+    assert_eq!(
+        exports.default,
+        Some(DefaultExport {
+            kind: DefaultExportKind::Template,
+            name: None
+        })
+    );
+    assert_eq!(
+        exports.re_exports,
+        vec![
+            ReExport {
+                module: "./g".to_owned(),
+                name: Some("h".to_owned()),
+                imported: Some("g".to_owned()),
+            },
+            ReExport {
+                module: "./all".to_owned(),
+                name: None,
+                imported: None,
+            },
+            ReExport {
+                module: "./ns".to_owned(),
+                name: Some("ns".to_owned()),
+                imported: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_default_exports() {
+    let default = |src: &str| {
+        let (_, exports) = Preprocessor::new()
+            .parse_with_exports(src, Default::default())
+            .unwrap();
+        exports.default.map(|default| (default.kind, default.name))
+    };
+    assert_eq!(
+        default("export default class Foo { <template>Hi</template> }"),
+        Some((DefaultExportKind::Class, Some("Foo".to_owned())))
+    );
+    assert_eq!(
+        default("export default (<template>Hi</template>);"),
+        Some((DefaultExportKind::Template, None))
+    );
+    assert_eq!(
+        default("const Foo = <template>Hi</template>;\nexport { Foo as default };"),
+        Some((DefaultExportKind::Binding, Some("Foo".to_owned())))
+    );
+    assert_eq!(
+        default("export default 1 + 1;"),
+        Some((DefaultExportKind::Expression, None))
+    );
+    assert_eq!(default("export { default } from './x';"), None);
+}
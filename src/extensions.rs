@@ -0,0 +1,150 @@
+// Options that depend on a file's extension, for projects where `.gts` files
+// need `strip_types` and `.gjs` files don't, or where stories are built
+// differently from the app. Extensions are what comes after a dot in the
+// file name, so `.stories.gjs` can have options of its own; the longest one
+// that matches wins, and files that match none get the default options.
+
+use std::path::Path;
+
+use crate::{builder, Error, FileId, Options, ProcessedFile};
+
+#[derive(Default)]
+pub struct ExtensionOptions {
+    default: Options,
+    // longest first, so the first match is the most specific
+    overrides: Vec<(String, Options)>,
+}
+
+impl ExtensionOptions {
+    pub fn new(default: Options) -> Self {
+        ExtensionOptions {
+            default,
+            overrides: vec![],
+        }
+    }
+
+    // `extension` can be given with or without its leading dot, like `gts`
+    // or `.stories.gjs`. Replaces the options of one that is already there.
+    pub fn insert(&mut self, extension: &str, options: Options) {
+        let extension = extension.trim_start_matches('.').to_owned();
+        self.overrides
+            .retain(|(existing, _)| *existing != extension);
+        let at = self
+            .overrides
+            .partition_point(|(existing, _)| existing.len() >= extension.len());
+        self.overrides.insert(at, (extension, options));
+    }
+
+    // The extension whose options `path` gets, without its leading dot, or
+    // `None` when it gets the default options.
+    pub fn extension_for(&self, path: &Path) -> Option<&str> {
+        self.matching(path).map(|(extension, _)| extension.as_str())
+    }
+
+    pub fn options_for(&self, path: &Path) -> &Options {
+        self.matching(path)
+            .map_or(&self.default, |(_, options)| options)
+    }
+
+    fn matching(&self, path: &Path) -> Option<&(String, Options)> {
+        let name = path.file_name()?.to_str()?;
+        self.overrides.iter().find(|(extension, _)| {
+            name.strip_suffix(extension.as_str())
+                .and_then(|stem| stem.strip_suffix('.'))
+                .map_or(false, |stem| !stem.is_empty())
+        })
+    }
+
+    // Processes `src` as the file at `path`, which also names it, with the
+    // options for its extension.
+    pub fn process(&self, path: &Path, src: &str) -> ProcessedFile {
+        ProcessedFile::process(src, self.options_for(path), Some(&FileId::from(path)))
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        builder::validate(&self.default)?;
+        for (extension, options) in &self.overrides {
+            builder::validate(options).map_err(|err| match err {
+                Error::InvalidOptions(message) => {
+                    Error::InvalidOptions(format!("For .{extension} files: {message}"))
+                }
+                err => err,
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Options> for ExtensionOptions {
+    fn from(default: Options) -> Self {
+        ExtensionOptions::new(default)
+    }
+}
+
+#[cfg(test)]
+use crate::EsVersion;
+
+#[test]
+fn test_longest_extension_wins() {
+    let mut options = ExtensionOptions::default();
+    options.insert(
+        "gts",
+        Options {
+            strip_types: true,
+            ..Default::default()
+        },
+    );
+    options.insert(
+        ".stories.gjs",
+        Options {
+            minify: true,
+            ..Default::default()
+        },
+    );
+    options.insert("gjs", Options::default());
+
+    assert_eq!(options.extension_for(Path::new("src/a.gts")), Some("gts"));
+    assert_eq!(
+        options.extension_for(Path::new("src/a.stories.gjs")),
+        Some("stories.gjs")
+    );
+    assert_eq!(options.extension_for(Path::new("src/a.gjs")), Some("gjs"));
+    assert_eq!(options.extension_for(Path::new("src/a.js")), None);
+    // a file called just `.gjs` has no extension
+    assert_eq!(options.extension_for(Path::new(".gjs")), None);
+
+    assert!(options.options_for(Path::new("a.gts")).strip_types);
+    assert!(options.options_for(Path::new("a.stories.gjs")).minify);
+    assert!(!options.options_for(Path::new("a.gjs")).minify);
+}
+
+#[test]
+fn test_processes_with_the_options_for_the_extension() {
+    let mut options = ExtensionOptions::default();
+    options.insert(
+        "gts",
+        Options {
+            strip_types: true,
+            ..Default::default()
+        },
+    );
+    let src = "let x: number = 1;\n<template>Hi</template>";
+    let gts = options.process(Path::new("a.gts"), src).result.unwrap();
+    assert!(!gts.code.contains("number"), "{}", gts.code);
+    let gjs = options.process(Path::new("a.gjs"), src).result.unwrap();
+    assert!(gjs.code.contains("number"), "{}", gjs.code);
+}
+
+#[test]
+fn test_says_which_extension_has_invalid_options() {
+    let mut options = ExtensionOptions::default();
+    options.insert(
+        "gjs",
+        Options {
+            target: Some(EsVersion::Es5),
+            ..Default::default()
+        },
+    );
+    let err = options.validate().unwrap_err();
+    assert!(err.to_string().contains("For .gjs files: "), "{err}");
+}
@@ -0,0 +1,158 @@
+// What a file is called in source maps and diagnostics. Bundlers don't only
+// hand us paths: virtual modules have ids like `\0virtual:foo.gjs`, and some
+// files are only known by a URL. Those are kept exactly as given, instead of
+// going through `PathBuf` (and swc's `<...>` for names that aren't paths).
+
+use std::fmt;
+#[cfg(feature = "transform")]
+use std::path::Component;
+use std::path::{Path, PathBuf};
+
+use swc_common::FileName;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum FileId {
+    Real(PathBuf),
+    // a bundler's id for a module that isn't a file, like `\0virtual:foo.gjs`
+    Virtual(String),
+    Url(String),
+}
+
+impl FileId {
+    pub(crate) fn file_name(&self) -> FileName {
+        match self {
+            FileId::Real(path) => FileName::Real(path.clone()),
+            FileId::Virtual(id) | FileId::Url(id) => FileName::Custom(id.clone()),
+        }
+    }
+}
+
+impl fmt::Display for FileId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileId::Real(path) => write!(f, "{}", path.display()),
+            FileId::Virtual(id) | FileId::Url(id) => f.write_str(id),
+        }
+    }
+}
+
+// A string is a URL when it starts with a scheme followed by `//` (or is a
+// `file:` or `data:` URL), and a virtual id when it starts with `\0` or
+// another scheme. Anything else, Windows paths included, is a path.
+impl From<&str> for FileId {
+    fn from(id: &str) -> Self {
+        if id.starts_with('\0') {
+            return FileId::Virtual(id.to_owned());
+        }
+        match url_scheme(id) {
+            Some(scheme) if id[scheme.len() + 1..].starts_with("//") => FileId::Url(id.to_owned()),
+            Some("file" | "data") => FileId::Url(id.to_owned()),
+            Some(_) => FileId::Virtual(id.to_owned()),
+            None => FileId::Real(id.into()),
+        }
+    }
+}
+
+impl From<String> for FileId {
+    fn from(id: String) -> Self {
+        FileId::from(id.as_str())
+    }
+}
+
+impl From<PathBuf> for FileId {
+    fn from(path: PathBuf) -> Self {
+        FileId::Real(path)
+    }
+}
+
+impl From<&Path> for FileId {
+    fn from(path: &Path) -> Self {
+        FileId::Real(path.to_owned())
+    }
+}
+
+// The scheme `id` starts with, if any. It takes at least two characters, so
+// that `C:\` is a drive letter rather than a scheme.
+fn url_scheme(id: &str) -> Option<&str> {
+    let (scheme, _) = id.split_once(':')?;
+    let mut chars = scheme.chars();
+    let valid = chars.next().map_or(false, |c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    (valid && scheme.len() > 1).then_some(scheme)
+}
+
+// How a file's name is written in source maps and diagnostics: ids that
+// aren't paths as they were given.
+pub(crate) fn display_name(name: &FileName) -> String {
+    match name {
+        FileName::Custom(id) => id.clone(),
+        name => name.to_string(),
+    }
+}
+
+// `path` as seen from `dir`, going up with `..` where they differ. A relative
+// path can't be compared with an absolute one, so that stays as it is.
+#[cfg(feature = "transform")]
+pub(crate) fn relative_path(path: &Path, dir: &Path) -> PathBuf {
+    if path.is_absolute() != dir.is_absolute() {
+        return path.to_owned();
+    }
+    let mut path_components = path.components().peekable();
+    let mut dir_components = dir.components().peekable();
+    while let (Some(a), Some(b)) = (path_components.peek(), dir_components.peek()) {
+        if a != b {
+            break;
+        }
+        path_components.next();
+        dir_components.next();
+    }
+    dir_components
+        .map(|_| Component::ParentDir)
+        .chain(path_components)
+        .collect()
+}
+
+#[test]
+fn test_classifies_strings() {
+    assert_eq!(
+        FileId::from("src/a.gjs"),
+        FileId::Real(PathBuf::from("src/a.gjs"))
+    );
+    assert_eq!(
+        FileId::from(r"C:\app\a.gjs"),
+        FileId::Real(PathBuf::from(r"C:\app\a.gjs"))
+    );
+    assert_eq!(
+        FileId::from("\0virtual:a.gjs"),
+        FileId::Virtual("\0virtual:a.gjs".to_owned())
+    );
+    assert_eq!(
+        FileId::from("virtual:a.gjs"),
+        FileId::Virtual("virtual:a.gjs".to_owned())
+    );
+    assert_eq!(
+        FileId::from("https://example.com/a.gjs"),
+        FileId::Url("https://example.com/a.gjs".to_owned())
+    );
+    assert_eq!(
+        FileId::from("file:///app/a.gjs"),
+        FileId::Url("file:///app/a.gjs".to_owned())
+    );
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_relative_path() {
+    assert_eq!(
+        relative_path(Path::new("/app/src/a.gjs"), Path::new("/app")),
+        PathBuf::from("src/a.gjs")
+    );
+    assert_eq!(
+        relative_path(Path::new("/app/src/a.gjs"), Path::new("/app/dist")),
+        PathBuf::from("../src/a.gjs")
+    );
+    assert_eq!(
+        relative_path(Path::new("src/a.gjs"), Path::new("/app")),
+        PathBuf::from("src/a.gjs")
+    );
+}
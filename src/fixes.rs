@@ -0,0 +1,133 @@
+// Quick fixes for parse errors that come from a content tag, found by
+// scanning the source for tags, since the parser only tells us where it gave
+// up. Tags inside strings and comments count too, so these are only ever
+// suggestions.
+
+use crate::locate::Range;
+use crate::TextEdit;
+
+const OPENING: &str = "<template";
+const CLOSING: &str = "</template>";
+
+enum Tag {
+    // up to and including its `>`
+    Opening(Range),
+    Closing(Range),
+}
+
+fn tags(src: &str) -> Vec<Tag> {
+    let mut tags = vec![];
+    let mut pos = 0;
+    while let Some(found) = src[pos..].find('<') {
+        let start = pos + found;
+        let rest = &src[start..];
+        if rest.starts_with(CLOSING) {
+            let end = start + CLOSING.len();
+            tags.push(Tag::Closing(Range { start, end }));
+            pos = end;
+            continue;
+        }
+        let after = &rest[OPENING.len().min(rest.len())..];
+        if rest.starts_with(OPENING) && after.starts_with(|c: char| c == '>' || c.is_whitespace()) {
+            if let Some(close) = after.find('>') {
+                let end = start + OPENING.len() + close + 1;
+                tags.push(Tag::Opening(Range { start, end }));
+                pos = end;
+                continue;
+            }
+        }
+        pos = start + 1;
+    }
+    tags
+}
+
+// The edits that would fix a parse error at `at` (a byte offset in `src`),
+// when it looks like one we know: a `<template>` that is never closed, or
+// one nested in another, which ends the outer one early.
+pub(crate) fn for_parse_error(src: &str, at: usize) -> Vec<TextEdit> {
+    let tags = tags(src);
+
+    // the closing tag the parser tripped over, and the nested opening tag
+    // whose content it was meant to end
+    let stray = tags.iter().find_map(|tag| match tag {
+        Tag::Closing(range) if range.start <= at && at < range.end => Some(*range),
+        _ => None,
+    });
+    if let Some(stray) = stray {
+        let mut depth: usize = 0;
+        let mut nested = None;
+        for tag in &tags {
+            match tag {
+                Tag::Opening(range) if range.start < stray.start => {
+                    if depth > 0 {
+                        nested = Some(*range);
+                    }
+                    depth += 1;
+                }
+                Tag::Closing(range) if range.start < stray.start => {
+                    depth = depth.saturating_sub(1);
+                }
+                _ => {}
+            }
+        }
+        if let Some(nested) = nested {
+            return vec![remove(nested), remove(stray)];
+        }
+    }
+
+    let openings = tags
+        .iter()
+        .filter(|tag| matches!(tag, Tag::Opening(_)))
+        .count();
+    let end = src.trim_end().len();
+    if openings > tags.len() - openings && at >= end {
+        return vec![TextEdit {
+            range: Range { start: end, end },
+            new_text: CLOSING.to_owned(),
+        }];
+    }
+    vec![]
+}
+
+fn remove(range: Range) -> TextEdit {
+    TextEdit {
+        range,
+        new_text: String::new(),
+    }
+}
+
+#[cfg(test)]
+use crate::Preprocessor;
+
+#[cfg(test)]
+fn suggested_edits(src: &str) -> Vec<TextEdit> {
+    let p = Preprocessor::new();
+    let err = p.parse(src, Default::default()).unwrap_err();
+    p.suggested_edits(&err)
+}
+
+#[test]
+fn test_suggests_a_closing_tag() {
+    assert_eq!(
+        suggested_edits("const thing = \"face\";\n  <template>Hi\n"),
+        vec![TextEdit {
+            range: Range { start: 36, end: 36 },
+            new_text: "</template>".to_owned(),
+        }]
+    );
+}
+
+#[test]
+fn test_suggests_removing_a_nested_template() {
+    let src = "let x = <template><template>Hi</template></template>;";
+    let mut fixed = src.to_owned();
+    for edit in suggested_edits(src).iter().rev() {
+        fixed.replace_range(edit.range.start..edit.range.end, &edit.new_text);
+    }
+    assert_eq!(fixed, "let x = <template>Hi</template>;");
+}
+
+#[test]
+fn test_no_suggestion_for_other_errors() {
+    assert!(suggested_edits("let y = ;").is_empty());
+}
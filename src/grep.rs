@@ -0,0 +1,135 @@
+use regex::Regex;
+use serde::Serialize;
+
+use crate::locate::{self, Occurrence, Range};
+
+// One match of `Preprocessor::grep`. The pattern is matched against one line
+// of template content at a time, like `grep` does, so `^` and `$` are the
+// start and end of a line.
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateMatch {
+    // which template, in the order `parse` returns them
+    pub template: usize,
+    // the span of the match in the file
+    pub range: Range,
+    // where the match starts in the file: a 1-based line and a 0-based
+    // column counted in characters, like `ErrorLoc`
+    pub line: usize,
+    pub column: usize,
+    // the same column with each tab reaching the next multiple of
+    // `Options::tab_width`
+    pub visual_column: usize,
+    // the whole line of the file that the match is on, without its line
+    // break, which can include code around the template
+    pub line_text: String,
+}
+
+pub(crate) fn grep(
+    src: &str,
+    occurrences: &[Occurrence],
+    pattern: &Regex,
+    tab_width: usize,
+) -> Vec<TemplateMatch> {
+    let mut matches = vec![];
+    for (template, occurrence) in occurrences.iter().enumerate() {
+        let content = occurrence.content_range;
+        let mut line = src[..content.start].matches('\n').count() + 1;
+        let mut at = content.start;
+        for content_line in src[content.start..content.end].split_inclusive('\n') {
+            let text = content_line.trim_end_matches(['\n', '\r']);
+            for found in pattern.find_iter(text) {
+                let start = at + found.start();
+                let line_start = src[..start].rfind('\n').map_or(0, |i| i + 1);
+                let line_end = src[start..].find('\n').map_or(src.len(), |i| start + i);
+                let column = src[line_start..start].chars().count();
+                matches.push(TemplateMatch {
+                    template,
+                    range: Range {
+                        start,
+                        end: at + found.end(),
+                    },
+                    line,
+                    column,
+                    visual_column: locate::visual_column(&src[line_start..], column, tab_width),
+                    line_text: src[line_start..line_end].trim_end_matches('\r').to_owned(),
+                });
+            }
+            at += content_line.len();
+            line += 1;
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+use crate::Preprocessor;
+
+#[test]
+fn test_only_matches_template_content() {
+    let src = "const label = 'Save';\n\
+               export const A = <template>\n  <button>Save</button>\n</template>;\n\
+               export const B = <template>Save é Save</template>;";
+    let matches = Preprocessor::new().grep(src, "Save").unwrap();
+    let found: Vec<(usize, usize, usize)> = matches
+        .iter()
+        .map(|m| (m.template, m.line, m.column))
+        .collect();
+    assert_eq!(found, vec![(0, 3, 10), (1, 5, 27), (1, 5, 34)]);
+    for m in &matches {
+        assert_eq!(&src[m.range.start..m.range.end], "Save");
+    }
+    assert_eq!(matches[0].line_text, "  <button>Save</button>");
+    assert_eq!(
+        matches[1].line_text,
+        "export const B = <template>Save é Save</template>;"
+    );
+}
+
+#[test]
+fn test_anchors_match_lines_of_content() {
+    let src = "<template>\n{{#if a}}\n  {{b}}\n{{/if}}\n</template>";
+    let matches = Preprocessor::new().grep(src, r"^\{\{").unwrap();
+    let lines: Vec<usize> = matches.iter().map(|m| m.line).collect();
+    assert_eq!(lines, vec![2, 4]);
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_visual_columns_expand_tabs() {
+    let src = "<template>\n\t\t<b>x</b>\n  \t<b>y</b>\n</template>";
+    let columns = |p: Preprocessor| {
+        p.grep(src, "<b>")
+            .unwrap()
+            .iter()
+            .map(|m| (m.column, m.visual_column))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(columns(Preprocessor::new()), vec![(2, 8), (3, 4)]);
+    let p = Preprocessor::builder().tab_width(8).build().unwrap();
+    assert_eq!(columns(p), vec![(2, 16), (3, 8)]);
+}
+
+#[test]
+fn test_grep_again() {
+    let src = "let a = 1;\nlet t = <template>\n  Hi\n</template>;";
+    let p = Preprocessor::new();
+    let first = p.grep(src, "Hi").unwrap();
+    assert_eq!(p.grep(src, "Hi").unwrap(), first);
+    assert_eq!((first[0].line, first[0].column), (3, 2));
+    assert_eq!(first[0].range.slice(src).unwrap(), "Hi");
+}
+
+#[test]
+fn test_rejects_invalid_patterns() {
+    let err = Preprocessor::new()
+        .grep("<template>Hi</template>", "(Hi")
+        .unwrap_err();
+    let crate::Error::InvalidOptions(message) = err else {
+        panic!("{err}");
+    };
+    assert!(
+        message.starts_with("`(Hi` isn't a valid regex"),
+        "{message}"
+    );
+}
@@ -0,0 +1,75 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::locate::{Occurrence, Range};
+
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum HashAlgorithm {
+    // fast, and plenty for cache keys within one build
+    #[default]
+    Xxh3,
+    // for caches shared across machines or exposed to untrusted content
+    Sha256,
+}
+
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentHash {
+    // the span of the whole content tag in the source
+    pub range: Range,
+    // lowercase hex
+    pub hash: String,
+}
+
+// Hashes only the raw template content, so that the result is stable across
+// changes to the surrounding JavaScript, different file locations, and
+// different machines.
+pub(crate) fn content_hash(occurrence: &Occurrence, algorithm: HashAlgorithm) -> ContentHash {
+    let bytes = occurrence.contents().as_bytes();
+    let hash = match algorithm {
+        HashAlgorithm::Xxh3 => format!("{:016x}", xxh3_64(bytes)),
+        HashAlgorithm::Sha256 => format!("{:x}", Sha256::digest(bytes)),
+    };
+    ContentHash {
+        range: occurrence.range,
+        hash,
+    }
+}
+
+#[cfg(test)]
+use crate::Preprocessor;
+
+#[test]
+fn test_hashes_are_stable_across_surrounding_code() {
+    let p = Preprocessor::new();
+    let a = p
+        .content_hashes("<template>Hi</template>", HashAlgorithm::Sha256)
+        .unwrap();
+    let b = p
+        .content_hashes(
+            "import x from 'y';\nexport const Foo = <template>Hi</template>;",
+            HashAlgorithm::Sha256,
+        )
+        .unwrap();
+    assert_eq!(a[0].hash, b[0].hash);
+    assert_eq!(
+        a[0].hash,
+        "3639efcd08abb273b1619e82e78c29a7df02c1051b1820e99fc395dcaa3326b8"
+    );
+    assert_ne!(a[0].range, b[0].range);
+}
+
+#[test]
+fn test_xxh3_hashes() {
+    let p = Preprocessor::new();
+    let hashes = p
+        .content_hashes(
+            "<template>Hi</template>\nclass A { <template>Bye</template> }",
+            HashAlgorithm::Xxh3,
+        )
+        .unwrap();
+    assert_eq!(hashes.len(), 2);
+    assert_eq!(hashes[0].hash.len(), 16);
+    assert_ne!(hashes[0].hash, hashes[1].hash);
+}
@@ -0,0 +1,319 @@
+// Housekeeping for the imports that `process` inserts.
+
+use swc_ecma_ast::{
+    CallExpr, Callee, Expr, Id, Ident, ImportDecl, ImportSpecifier, Lit, Module, ModuleDecl,
+    ModuleExportName, ModuleItem,
+};
+
+use crate::scope::is_identifier;
+use swc_ecma_visit::{Visit, VisitWith};
+
+// Drops the specifiers in `candidates` that nothing refers to, along with any
+// import that is left empty because of that, and returns their local names.
+// This has to run after the resolver, so that a shadowing binding elsewhere
+// doesn't count as a use.
+pub(crate) fn prune_unused(module: &mut Module, candidates: &[Id]) -> Vec<String> {
+    let mut finder = UseFinder { used: vec![] };
+    module.visit_with(&mut finder);
+    let unused: Vec<&Id> = candidates
+        .iter()
+        .filter(|id| !finder.used.contains(id))
+        .collect();
+    if unused.is_empty() {
+        return vec![];
+    }
+
+    let mut removed = vec![];
+    module.body.retain_mut(|item| {
+        if let ModuleItem::ModuleDecl(ModuleDecl::Import(import)) = item {
+            let before = import.specifiers.len();
+            import.specifiers.retain(|specifier| {
+                let local = specifier.local();
+                let keep = !unused.contains(&&local.to_id());
+                if !keep {
+                    removed.push(local.sym.to_string());
+                }
+                keep
+            });
+            return before == 0 || !import.specifiers.is_empty();
+        }
+        true
+    });
+    removed
+}
+
+struct UseFinder {
+    used: Vec<Id>,
+}
+
+impl Visit for UseFinder {
+    fn visit_import_decl(&mut self, _: &ImportDecl) {}
+
+    fn visit_ident(&mut self, n: &Ident) {
+        self.used.push(n.to_id());
+    }
+}
+
+// Rewrites `import { a as a }` and `import { "a" as a }` to `import { a }`.
+pub(crate) fn simplify(module: &mut Module) {
+    for item in &mut module.body {
+        let ModuleItem::ModuleDecl(ModuleDecl::Import(import)) = item else {
+            continue;
+        };
+        for specifier in &mut import.specifiers {
+            let ImportSpecifier::Named(named) = specifier else {
+                continue;
+            };
+            let same = match &named.imported {
+                Some(ModuleExportName::Ident(imported)) => imported.sym == named.local.sym,
+                Some(ModuleExportName::Str(imported)) => {
+                    imported.value == named.local.sym && is_identifier(&imported.value)
+                }
+                None => false,
+            };
+            if same {
+                named.imported = None;
+            }
+        }
+    }
+}
+
+// The modules that `module` loads at runtime, in order and without
+// duplicates: static imports and re-exports, plus `import()` of a string
+// literal. Type-only imports and exports are left out.
+pub(crate) fn dependencies(module: &Module) -> Vec<String> {
+    let mut finder = DependencyFinder {
+        dependencies: vec![],
+    };
+    module.visit_with(&mut finder);
+    finder.dependencies
+}
+
+struct DependencyFinder {
+    dependencies: Vec<String>,
+}
+
+impl DependencyFinder {
+    fn add(&mut self, specifier: &str) {
+        if !self.dependencies.iter().any(|dep| dep == specifier) {
+            self.dependencies.push(specifier.to_owned());
+        }
+    }
+}
+
+impl Visit for DependencyFinder {
+    fn visit_module_decl(&mut self, n: &ModuleDecl) {
+        match n {
+            ModuleDecl::Import(import) if !import.type_only => self.add(&import.src.value),
+            ModuleDecl::ExportNamed(export) if !export.type_only => {
+                if let Some(src) = &export.src {
+                    self.add(&src.value)
+                }
+            }
+            ModuleDecl::ExportAll(export) if !export.type_only => self.add(&export.src.value),
+            _ => {}
+        }
+        n.visit_children_with(self);
+    }
+
+    fn visit_call_expr(&mut self, n: &CallExpr) {
+        if let (Callee::Import(_), Some(arg)) = (&n.callee, n.args.first()) {
+            if let Expr::Lit(Lit::Str(src)) = &*arg.expr {
+                self.add(&src.value);
+            }
+        }
+        n.visit_children_with(self);
+    }
+}
+
+// Whether `module` imports `specifier` from `import_module` and calls it,
+// like our own output does. Shadowing isn't taken into account, which is
+// fine for telling that a file has already been through `process`.
+pub(crate) fn calls_import(module: &Module, import_module: &str, specifier: &str) -> bool {
+    let locals: Vec<&Ident> = module
+        .body
+        .iter()
+        .filter_map(|item| match item {
+            ModuleItem::ModuleDecl(ModuleDecl::Import(import))
+                if !import.type_only && &*import.src.value == import_module =>
+            {
+                Some(import)
+            }
+            _ => None,
+        })
+        .flat_map(|import| &import.specifiers)
+        .filter_map(|specifier_| match specifier_ {
+            ImportSpecifier::Named(named) if !named.is_type_only => {
+                let imported = match &named.imported {
+                    Some(ModuleExportName::Ident(ident)) => &*ident.sym,
+                    Some(ModuleExportName::Str(name)) => &*name.value,
+                    None => &*named.local.sym,
+                };
+                (imported == specifier).then_some(&named.local)
+            }
+            _ => None,
+        })
+        .collect();
+    if locals.is_empty() {
+        return false;
+    }
+    let mut finder = CallFinder {
+        locals,
+        found: false,
+    };
+    module.visit_with(&mut finder);
+    finder.found
+}
+
+struct CallFinder<'a> {
+    locals: Vec<&'a Ident>,
+    found: bool,
+}
+
+impl Visit for CallFinder<'_> {
+    fn visit_call_expr(&mut self, n: &CallExpr) {
+        if let Callee::Expr(callee) = &n.callee {
+            if let Expr::Ident(ident) = &**callee {
+                if self.locals.iter().any(|local| local.sym == ident.sym) {
+                    self.found = true;
+                    return;
+                }
+            }
+        }
+        n.visit_children_with(self);
+    }
+}
+
+#[test]
+fn test_prune_unused() {
+    use swc_common::{sync::Lrc, FileName, Mark, SourceMap, GLOBALS};
+    use swc_ecma_parser::{Parser, StringInput, Syntax};
+    use swc_ecma_transforms::resolver;
+    use swc_ecma_visit::VisitMutWith;
+
+    let source_map: Lrc<SourceMap> = Default::default();
+    let source_file = source_map.new_source_file(
+        FileName::Anon,
+        r#"import { a, b } from "x";
+           import { c } from "y";
+           import "z";
+           function f(b) { return b; }
+           a();"#
+            .into(),
+    );
+    GLOBALS.set(&Default::default(), || {
+        let mut module = Parser::new(
+            Syntax::Es(Default::default()),
+            StringInput::from(&*source_file),
+            None,
+        )
+        .parse_module()
+        .unwrap();
+        module.visit_mut_with(&mut resolver(Mark::new(), Mark::new(), false));
+
+        let candidates: Vec<Id> = module
+            .body
+            .iter()
+            .filter_map(|item| item.as_module_decl()?.as_import())
+            .flat_map(|import| import.specifiers.iter().map(|s| s.local().to_id()))
+            .collect();
+        let removed = prune_unused(&mut module, &candidates);
+
+        assert_eq!(removed, vec!["b", "c"]);
+        assert_eq!(module.body.len(), 4);
+    });
+}
+
+#[test]
+fn test_dependencies() {
+    use swc_common::{sync::Lrc, FileName, SourceMap};
+    use swc_ecma_parser::{Parser, StringInput, Syntax};
+
+    let source_map: Lrc<SourceMap> = Default::default();
+    let source_file = source_map.new_source_file(
+        FileName::Anon,
+        r#"import { a } from "x";
+           import type { T } from "types";
+           import "y";
+           export * from "z";
+           export { b } from "x";
+           const lazy = () => import("./lazy");
+           const dynamic = (name) => import(name);"#
+            .into(),
+    );
+    let module = Parser::new(
+        Syntax::Typescript(Default::default()),
+        StringInput::from(&*source_file),
+        None,
+    )
+    .parse_module()
+    .unwrap();
+    assert_eq!(dependencies(&module), vec!["x", "y", "z", "./lazy"]);
+}
+
+#[test]
+fn test_calls_import() {
+    use swc_common::{sync::Lrc, FileName, SourceMap};
+    use swc_ecma_parser::{Parser, StringInput, Syntax};
+
+    let calls = |src: &str| {
+        let source_map: Lrc<SourceMap> = Default::default();
+        let source_file = source_map.new_source_file(FileName::Anon, src.into());
+        let module = Parser::new(
+            Syntax::Typescript(Default::default()),
+            StringInput::from(&*source_file),
+            None,
+        )
+        .parse_module()
+        .unwrap();
+        calls_import(&module, "@ember/template-compiler", "template")
+    };
+    assert!(calls(
+        r#"import { template as t } from "@ember/template-compiler";
+           export default t(`Hi`, { eval() { return eval(arguments[0]); } });"#
+    ));
+    assert!(calls(
+        r#"import { template } from "@ember/template-compiler";
+           class A { static { template(`Hi`, { component: this }); } }"#
+    ));
+    // imported but never called
+    assert!(!calls(
+        r#"import { template } from "@ember/template-compiler"; export { template };"#
+    ));
+    assert!(!calls(
+        r#"import { template } from "elsewhere"; template(`Hi`);"#
+    ));
+    assert!(!calls("let x = 1;"));
+}
+
+#[test]
+fn test_simplify() {
+    use swc_common::{sync::Lrc, FileName, SourceMap};
+    use swc_ecma_parser::{Parser, StringInput, Syntax};
+
+    let source_map: Lrc<SourceMap> = Default::default();
+    let source_file = source_map.new_source_file(
+        FileName::Anon,
+        r#"import { a as a, "b" as b, "c d" as c, e as f } from "x";"#.into(),
+    );
+    let mut module = Parser::new(
+        Syntax::Es(Default::default()),
+        StringInput::from(&*source_file),
+        None,
+    )
+    .parse_module()
+    .unwrap();
+    simplify(&mut module);
+
+    let import = module.body[0]
+        .as_module_decl()
+        .unwrap()
+        .as_import()
+        .unwrap();
+    let simplified: Vec<bool> = import
+        .specifiers
+        .iter()
+        .map(|specifier| specifier.as_named().unwrap().imported.is_none())
+        .collect();
+    assert_eq!(simplified, vec![true, true, false, false]);
+}
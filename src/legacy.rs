@@ -0,0 +1,155 @@
+// Support for converting the ways of authoring templates that came before
+// content-tag, in the same pass as `<template>` tags.
+
+use swc_atoms::JsWord;
+use swc_ecma_ast::{Ident, ImportDecl, ImportSpecifier, ModuleDecl, ModuleExportName, ModuleItem};
+use swc_ecma_visit::{Visit, VisitWith};
+
+// A module that a legacy API is imported from, with the name it's exported as
+// (`None` for the default export).
+pub(crate) type Source = (&'static str, Option<&'static str>);
+
+// The modules that `hbs` has historically been imported from.
+pub(crate) const HBS_SOURCES: &[Source] = &[
+    ("ember-cli-htmlbars", Some("hbs")),
+    ("ember-cli-htmlbars-inline-precompile", None),
+    ("htmlbars-inline-precompile", None),
+];
+
+pub(crate) const PRECOMPILE_TEMPLATE_SOURCES: &[Source] =
+    &[("@ember/template-compilation", Some("precompileTemplate"))];
+
+// The local names that any of `sources` are imported as in this module.
+pub(crate) fn imported_locals(items: &[ModuleItem], sources: &[Source]) -> Vec<JsWord> {
+    items
+        .iter()
+        .filter_map(|item| match item {
+            ModuleItem::ModuleDecl(ModuleDecl::Import(import)) => Some(import),
+            _ => None,
+        })
+        .flat_map(|import| {
+            import
+                .specifiers
+                .iter()
+                .filter(|specifier| imports_source(import, specifier, sources))
+                .map(|specifier| specifier.local().sym.clone())
+        })
+        .collect()
+}
+
+// Whether anything other than the imports themselves still mentions one of
+// `locals`. This is conservative: `obj.hbs` counts too.
+pub(crate) fn still_referenced(items: &[ModuleItem], locals: &[JsWord]) -> bool {
+    let mut finder = ReferenceFinder {
+        locals,
+        found: false,
+    };
+    items.visit_with(&mut finder);
+    finder.found
+}
+
+struct ReferenceFinder<'a> {
+    locals: &'a [JsWord],
+    found: bool,
+}
+
+impl<'a> Visit for ReferenceFinder<'a> {
+    fn visit_import_decl(&mut self, _: &ImportDecl) {}
+
+    fn visit_ident(&mut self, n: &Ident) {
+        if self.locals.contains(&n.sym) {
+            self.found = true;
+        }
+    }
+}
+
+// Drops the specifiers of `sources` from their imports, and any import that
+// is left empty because of that.
+pub(crate) fn remove_imports(items: &mut Vec<ModuleItem>, sources: &[Source]) {
+    items.retain_mut(|item| {
+        if let ModuleItem::ModuleDecl(ModuleDecl::Import(import)) = item {
+            let before = import.specifiers.len();
+            let mut specifiers = std::mem::take(&mut import.specifiers);
+            specifiers.retain(|specifier| !imports_source(import, specifier, sources));
+            import.specifiers = specifiers;
+            return before == 0 || !import.specifiers.is_empty();
+        }
+        true
+    })
+}
+
+fn imports_source(import: &ImportDecl, specifier: &ImportSpecifier, sources: &[Source]) -> bool {
+    if import.type_only {
+        return false;
+    }
+    sources
+        .iter()
+        .filter(|(module, _)| import.src.value == *module)
+        .any(|(_, export)| match (specifier, export) {
+            (ImportSpecifier::Named(named), Some(export)) => {
+                let imported = match &named.imported {
+                    Some(ModuleExportName::Ident(ident)) => &ident.sym,
+                    Some(ModuleExportName::Str(str)) => &str.value,
+                    None => &named.local.sym,
+                };
+                !named.is_type_only && imported == export
+            }
+            (ImportSpecifier::Default(_), None) => true,
+            _ => false,
+        })
+}
+
+#[cfg(test)]
+fn parse_module(src: &str) -> swc_ecma_ast::Module {
+    use swc_common::{sync::Lrc, FileName, SourceMap};
+    use swc_ecma_parser::{lexer::Lexer, Parser, StringInput, Syntax};
+
+    let source_map: Lrc<SourceMap> = Default::default();
+    let source_file = source_map.new_source_file(FileName::Anon, src.to_string());
+    let lexer = Lexer::new(
+        Syntax::Es(Default::default()),
+        Default::default(),
+        StringInput::from(&*source_file),
+        None,
+    );
+    Parser::new_from(lexer).parse_module().unwrap()
+}
+
+#[test]
+fn test_imported_locals() {
+    let module = parse_module(
+        r#"import { hbs, other } from "ember-cli-htmlbars";
+           import { hbs as h2 } from "ember-cli-htmlbars";
+           import h3 from "htmlbars-inline-precompile";
+           import { hbs as notIt } from "somewhere-else";"#,
+    );
+    let locals: Vec<String> = imported_locals(&module.body, HBS_SOURCES)
+        .iter()
+        .map(|local| local.to_string())
+        .collect();
+    assert_eq!(locals, vec!["hbs", "h2", "h3"]);
+}
+
+#[test]
+fn test_remove_imports() {
+    let mut module = parse_module(
+        r#"import { hbs, other } from "ember-cli-htmlbars";
+           import h3 from "htmlbars-inline-precompile";
+           import "ember-cli-htmlbars";
+           import x from "x";"#,
+    );
+    remove_imports(&mut module.body, HBS_SOURCES);
+    assert_eq!(module.body.len(), 3);
+    assert!(imported_locals(&module.body, HBS_SOURCES).is_empty());
+}
+
+#[test]
+fn test_still_referenced() {
+    let module = parse_module(
+        r#"import { hbs } from "ember-cli-htmlbars";
+           export const compile = hbs;"#,
+    );
+    let locals = imported_locals(&module.body, HBS_SOURCES);
+    assert!(still_referenced(&module.body, &locals));
+    assert!(!still_referenced(&module.body[..1], &locals));
+}
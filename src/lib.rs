@@ -1,51 +1,441 @@
 #![feature(box_patterns)]
 
+#[cfg(feature = "transform")]
 #[macro_use]
 extern crate lazy_static;
 
+#[cfg(feature = "transform")]
 use base64::{engine::general_purpose, Engine as _};
+use serde::Serialize;
+#[cfg(feature = "transform")]
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+#[cfg(feature = "transform")]
+use std::path::Path;
 use std::path::PathBuf;
+#[cfg(feature = "transform")]
+use std::sync::atomic::AtomicBool;
+#[cfg(feature = "transform")]
+use std::sync::atomic::Ordering;
+#[cfg(feature = "transform")]
+use std::sync::Arc;
 use swc_common::comments::SingleThreadedComments;
-use swc_common::source_map::SourceMapGenConfig;
-use swc_common::{self, sync::Lrc, FileName, Mark, SourceMap};
-use swc_core::common::GLOBALS;
+#[cfg(feature = "transform")]
+use swc_common::source_map::{LineCol, SourceMapGenConfig};
+use swc_common::{self, sync::Lrc, FileName, SourceFile, SourceMap, Span, GLOBALS};
+#[cfg(feature = "transform")]
+use swc_common::{BytePos, Mark};
+#[cfg(feature = "transform")]
 use swc_ecma_ast::{
     Ident, ImportDecl, ImportNamedSpecifier, ImportSpecifier, Module, ModuleDecl, ModuleExportName,
     ModuleItem,
 };
-use swc_ecma_codegen::Emitter;
-use swc_ecma_parser::{lexer::Lexer, Parser, StringInput, Syntax, TsConfig};
-use swc_ecma_transforms::resolver;
+use swc_ecma_parser::{lexer::Lexer, Parser, StringInput};
+#[cfg(feature = "transform")]
+use swc_ecma_transforms::typescript;
+#[cfg(feature = "transform")]
 use swc_ecma_utils::private_ident;
-use swc_ecma_visit::{as_folder, VisitMutWith, VisitWith};
+#[cfg(feature = "transform")]
+use swc_ecma_visit::VisitMutWith;
+use swc_ecma_visit::VisitWith;
 
+#[cfg(feature = "transform")]
+mod annotations;
+#[cfg(feature = "wasm")]
 mod bindings;
+#[cfg(feature = "transform")]
+mod builder;
+mod capabilities;
+#[cfg(feature = "transform")]
+mod census;
+mod content;
+mod editor;
+#[cfg(feature = "transform")]
+mod embed;
+mod error;
+#[cfg(feature = "transform")]
+mod explain;
+mod exports;
+#[cfg(feature = "transform")]
+mod extensions;
+mod file_id;
+mod fixes;
+#[cfg(feature = "grep")]
+mod grep;
+mod hashing;
+#[cfg(feature = "transform")]
+mod imports;
+#[cfg(feature = "transform")]
+mod legacy;
 mod locate;
+#[cfg(feature = "transform")]
+mod lossy;
+mod metadata;
+mod metrics;
+mod migrate;
+#[cfg(feature = "transform")]
+mod patch;
+#[cfg(feature = "profile")]
+mod profile;
+#[cfg(feature = "transform")]
+mod project;
+mod references;
+mod scaffold;
+mod schema;
+mod scope;
+#[cfg(feature = "transform")]
 mod snippets;
+#[cfg(feature = "transform")]
+mod splice;
+#[cfg(feature = "transform")]
+mod strict;
+mod swc_compat;
+mod tags;
+#[cfg(feature = "async")]
+mod task;
+#[cfg(feature = "transform")]
 mod transform;
+mod transformer;
+#[cfg(feature = "transform")]
+mod verify;
+mod warnings;
+#[cfg(feature = "transform")]
+mod watch;
+
+#[cfg(feature = "transform")]
+pub use annotations::{GENERATED_REGION_END, GENERATED_REGION_START};
+#[cfg(feature = "transform")]
+pub use builder::PreprocessorBuilder;
+pub use capabilities::Capabilities;
+#[cfg(feature = "transform")]
+pub use census::{Census, NameUsage};
+pub use content::TrimContent;
+pub use editor::{EditError, EditedSource, TemplateEditor};
+#[cfg(feature = "transform")]
+pub use embed::TransformedModule;
+pub use error::{Error, ErrorRendering, Label, TemplateError, TextEdit};
+#[cfg(feature = "transform")]
+pub use explain::Explanation;
+pub use exports::{DefaultExport, DefaultExportKind, ExportKind, ExportMap, NamedExport, ReExport};
+#[cfg(feature = "transform")]
+pub use extensions::ExtensionOptions;
+pub use file_id::FileId;
+#[cfg(feature = "grep")]
+pub use grep::TemplateMatch;
+pub use hashing::{ContentHash, HashAlgorithm};
+pub use locate::{ContentTagKind, ExpressionRole, Occurrence, Range, SliceError};
+pub use metadata::{EvalUsage, InsertedImport, Metadata, Replacement};
+pub use metrics::TemplateMetrics;
+pub use migrate::{merge_colocated, split_gjs, SplitComponent, SplitTemplate};
+#[cfg(feature = "profile")]
+pub use profile::Profiler;
+#[cfg(feature = "transform")]
+pub use project::{ProcessedFile, Project};
+pub use scaffold::{component_skeleton, ComponentSkeleton};
+pub use schema::{ParseOutput, Parsed, ParsedKind, SCHEMA_VERSION};
+#[cfg(feature = "transform")]
+pub use strict::StrictTemplate;
+pub use swc_ecma_ast::EsVersion;
+pub use tags::{TagHandler, SUPPORTED_TAGS};
+#[cfg(feature = "async")]
+pub use task::{process_async, CancelHandle};
+pub use transformer::{ImportRequest, TemplateContext, Transformer};
+pub use warnings::{Severity, Warning};
+#[cfg(feature = "transform")]
+pub use watch::WatchSession;
 
-#[derive(Default)]
 pub struct Options {
-    pub filename: Option<PathBuf>,
+    // a path, or a bundler's id for the module, kept as is in source maps and
+    // diagnostics
+    pub filename: Option<FileId>,
     pub inline_source_map: bool,
+    // re-parse the output and check it against the transformed AST, failing
+    // with `Error::Internal` if they disagree
+    pub verify_output: bool,
+    // collect the comments leading up to each template in `parse`
+    pub leading_comments: bool,
+    // include each template's content in `parse` results. Editors that parse
+    // on every keystroke and only need the ranges can turn it off;
+    // `content_slice` still works. On by default.
+    pub include_content: bool,
+    // strip the indentation shared by the lines of each template's content
+    pub dedent_content: bool,
+    // remove whitespace from the start and/or end of each template's content
+    pub trim_content: TrimContent,
+    // rewrite each template's content before it is emitted, each one
+    // receiving the output of the one before
+    pub transformers: Vec<Box<dyn Transformer>>,
+    // how code frames are drawn when reporting errors
+    pub error_rendering: ErrorRendering,
+    // also convert `hbs` tagged templates imported from `ember-cli-htmlbars`
+    // (or its predecessors) into template calls
+    pub hbs_tagged_templates: bool,
+    // also convert `precompileTemplate` calls from
+    // `@ember/template-compilation` into template calls with the same scope
+    pub precompile_template_calls: bool,
+    // a `/* @scope a, b */` comment right before a template lists its scope
+    // bindings, which must be in scope, instead of using the `eval` trick
+    pub explicit_scope: bool,
+    // the oldest ECMAScript version the output has to run on. Targets before
+    // ES2022 don't get static blocks. `None` means the latest.
+    pub target: Option<EsVersion>,
+    // remove TypeScript syntax from the output (types, `declare` fields,
+    // index signatures and the like), for `.gts` files that go straight to
+    // something that only reads JavaScript
+    pub strip_types: bool,
+    // pass `component: this` for expression templates inside class methods
+    // and property initializers, where `this` is the instance
+    pub component_this_in_methods: bool,
+    // warn about names used in templates that aren't defined where the
+    // template is
+    pub warn_unresolved: bool,
+    // drop imports we inserted that end up unused, reporting them in
+    // `Metadata::removed_imports`
+    pub prune_unused_imports: bool,
+    // the transformers and compiler import for each tag name. Empty means
+    // every tag uses `transformers` and `@ember/template-compiler`.
+    pub tags: BTreeMap<String, TagHandler>,
+    // print `import { a as a }` (including string-literal names like
+    // `import { "a" as a }`) as `import { a }`, everywhere in the module
+    pub simplify_imports: bool,
+    // run swc's resolver, for the options that need it. Turn it off for
+    // callers that run their own later and don't want names resolved twice.
+    // On by default.
+    pub run_resolver_hygiene: bool,
+    // fail, instead of falling back to the `eval` trick, for templates that
+    // don't list their scope. Implies `explicit_scope`.
+    pub forbid_eval: bool,
+    // what `process` returns as `code`
+    pub emit: Emit,
+    // emit the content of templates that are all whitespace as an empty
+    // string, with a `whitespace-only-template` warning for each
+    pub collapse_whitespace_only: bool,
+    // a `template-too-large` warning for each template whose content is
+    // over this many bytes, or this many lines
+    pub max_template_bytes: Option<usize>,
+    pub max_template_lines: Option<usize>,
+    // where the `export default` that a bare top-level template becomes goes
+    pub default_export_placement: DefaultExportPlacement,
+    // print the output with swc's minified codegen: no whitespace or comments
+    // that aren't needed. Names aren't mangled and nothing is compressed.
+    pub minify: bool,
+    // the source map's `file`
+    pub source_map_file: Option<String>,
+    // write the source map's `sources` relative to this directory, when
+    // they're paths
+    pub source_map_relative_to: Option<PathBuf>,
+    // write the source map's `sources` with `/` instead of `\`
+    pub source_map_forward_slashes: bool,
+    // make each class with a template member that doesn't extend anything
+    // extend the default export of this module (usually
+    // `GLIMMER_COMPONENT`), which gets imported
+    pub implicit_component_base: Option<String>,
+    // the shape of the options object passed to each template call, for
+    // tools that diff our output and don't want an upgrade to change it
+    pub snippet_version: SnippetVersion,
+    // set `Metadata::already_transformed` for files with no templates that
+    // already call the template compiler, and so were probably processed
+    // before, for pipelines that want to catch being run twice
+    pub detect_already_transformed: bool,
+    // list what the module exports in `Metadata::exports`, for tools that
+    // index components and routes
+    pub collect_exports: bool,
+    // wrap each generated template call in `GENERATED_REGION_START` and
+    // `GENERATED_REGION_END` comments, for tools that need to find the code
+    // we generated in the output
+    pub mark_generated_regions: bool,
+    // put `/*#__PURE__*/` before each template call that's the value of a
+    // variable or the default export, so minifiers can drop the templates
+    // nothing uses
+    pub pure_annotations: bool,
+    // the severity to give warnings with each code instead of
+    // `Severity::Warning`, like making `template-too-large` an error in CI,
+    // or `Severity::Off` to leave them out
+    pub severity_overrides: BTreeMap<String, Severity>,
+    // how many columns a tab takes up, for the visual columns in `ErrorLoc`
+    // and `TemplateMatch`, which editors that expand tabs want; the default
+    // is 4, like VS Code
+    pub tab_width: Option<usize>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            filename: None,
+            inline_source_map: false,
+            verify_output: false,
+            leading_comments: false,
+            include_content: true,
+            dedent_content: false,
+            trim_content: Default::default(),
+            transformers: vec![],
+            error_rendering: Default::default(),
+            hbs_tagged_templates: false,
+            precompile_template_calls: false,
+            explicit_scope: false,
+            target: None,
+            strip_types: false,
+            component_this_in_methods: false,
+            warn_unresolved: false,
+            prune_unused_imports: false,
+            tags: Default::default(),
+            simplify_imports: false,
+            run_resolver_hygiene: true,
+            forbid_eval: false,
+            emit: Default::default(),
+            collapse_whitespace_only: false,
+            max_template_bytes: None,
+            max_template_lines: None,
+            default_export_placement: Default::default(),
+            minify: false,
+            source_map_file: None,
+            source_map_relative_to: None,
+            source_map_forward_slashes: false,
+            implicit_component_base: None,
+            snippet_version: Default::default(),
+            detect_already_transformed: false,
+            collect_exports: false,
+            mark_generated_regions: false,
+            pure_annotations: false,
+            severity_overrides: Default::default(),
+            tab_width: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum Emit {
+    #[default]
+    Code,
+    // a unified diff from the input to what `process_with_ranges` makes of
+    // it, which keeps the formatting outside the templates. `map` and
+    // `metadata` are empty, and the options `process_with_ranges` rejects
+    // are rejected here too.
+    UnifiedDiff,
+}
+
+// One output of `Preprocessor::process_multi`. Everything before printing is
+// shared, so a profile only has what can differ after that.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Profile {
+    pub inline_source_map: bool,
+    pub minify: bool,
+    // passed to each template call as `moduleName`, which the template
+    // compiler uses in its error messages, for development builds
+    pub module_name: Option<String>,
+}
+
+// The output always starts with the inserted import, followed by the module's
+// own items in their original order. Only the default export made from a bare
+// `<template>` can move.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum DefaultExportPlacement {
+    // where the template was
+    #[default]
+    InPlace,
+    // after everything else, so that it reads after the helpers it uses
+    End,
+}
+
+// Every shape the options object passed to template calls has had. A new
+// shape only ever comes as a new version, and pinning one keeps the output
+// the same across upgrades that would otherwise change every compiled file.
+// The default is the latest.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub enum SnippetVersion {
+    // `{ eval() { ... } }`, or `{ scope: () => ({ ... }) }` with an explicit
+    // scope, for every template
+    V1,
+    // also `component: this` for class member templates
+    #[default]
+    V2,
 }
 
 pub struct Preprocessor {
+    // used by `run` (and, just the file name, by the methods that parse for
+    // themselves), and checked by `PreprocessorBuilder::build`
+    options: Options,
     source_map: Lrc<SourceMap>,
     comments: SingleThreadedComments,
+    // the file from the most recent call to `parse` or `process`
+    last_file: RefCell<Option<Lrc<SourceFile>>>,
+    // and the tab width from its options
+    last_tab_width: Cell<usize>,
+    // set from another thread to stop `process` at the next phase
+    #[cfg(feature = "transform")]
+    cancelled: Option<Arc<AtomicBool>>,
+}
+
+// What a caller needs to render its own code frames for our errors.
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceInfo {
+    pub file: String,
+    pub source_text: String,
+    // the byte offset at which each line starts
+    pub line_starts: Vec<usize>,
+}
+
+// What a `Preprocessor` holds on to from earlier calls, until `reset`.
+#[derive(Serialize, Debug, Clone, Default, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryStats {
+    pub files: usize,
+    pub source_bytes: usize,
+    pub comments: usize,
+}
+
+// Where an error happened, so editors can underline the exact range. Lines are
+// 1-based, columns are 0-based and count characters. The visual columns
+// count a tab as reaching the next multiple of `Options::tab_width` instead
+// of as one character.
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorLoc {
+    pub file: String,
+    pub start_line: usize,
+    pub start_column: usize,
+    pub start_visual_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+    pub end_visual_column: usize,
+    pub byte_start: usize,
+    pub byte_end: usize,
 }
 
+#[derive(Clone)]
 pub struct CodeMapPair {
     pub code: String,
     pub map: String,
+    pub metadata: Metadata,
+    pub warnings: Vec<Warning>,
 }
 
 pub const IMPORT_ALIAS: &str = "template_fd9b2463e5f141cfb5666b64daa1f11a";
 
-struct SourceMapConfig;
-impl SourceMapGenConfig for SourceMapConfig {
+// The usual `Options::implicit_component_base`.
+pub const GLIMMER_COMPONENT: &str = "@glimmer/component";
+
+#[cfg(feature = "transform")]
+struct SourceMapConfig<'a> {
+    relative_to: Option<&'a Path>,
+    forward_slashes: bool,
+}
+#[cfg(feature = "transform")]
+impl SourceMapGenConfig for SourceMapConfig<'_> {
     fn file_name_to_source(&self, f: &swc_common::FileName) -> String {
-        f.to_string()
+        let source = match (f, self.relative_to) {
+            (FileName::Real(path), Some(dir)) => {
+                file_id::relative_path(path, dir).display().to_string()
+            }
+            _ => file_id::display_name(f),
+        };
+        if self.forward_slashes {
+            source.replace('\\', "/")
+        } else {
+            source
+        }
     }
 
     fn inline_sources_content(&self, _: &swc_common::FileName) -> bool {
@@ -56,8 +446,13 @@ impl SourceMapGenConfig for SourceMapConfig {
 impl Preprocessor {
     pub fn new() -> Self {
         Self {
+            options: Default::default(),
             source_map: Default::default(),
             comments: SingleThreadedComments::default(),
+            last_file: Default::default(),
+            last_tab_width: Cell::new(locate::DEFAULT_TAB_WIDTH),
+            #[cfg(feature = "transform")]
+            cancelled: None,
         }
     }
 
@@ -66,18 +461,43 @@ impl Preprocessor {
         src: &str,
         options: Options,
     ) -> Result<Vec<locate::Occurrence>, swc_ecma_parser::error::Error> {
-        let filename = match options.filename {
-            Some(name) => FileName::Real(name),
+        self.locate(src, options, false)
+            .map(|(_, occurrences, _)| occurrences)
+    }
+
+    // `parse`, along with what the module exports, from the same parse.
+    pub fn parse_with_exports(
+        &self,
+        src: &str,
+        options: Options,
+    ) -> Result<(Vec<locate::Occurrence>, ExportMap), swc_ecma_parser::error::Error> {
+        self.locate(src, options, true)
+            .map(|(_, occurrences, exports)| (occurrences, exports.unwrap_or_default()))
+    }
+
+    // The file `src` was added to the source map as, along with what `parse`
+    // returns.
+    fn locate(
+        &self,
+        src: &str,
+        options: Options,
+        with_exports: bool,
+    ) -> Result<
+        (Lrc<SourceFile>, Vec<locate::Occurrence>, Option<ExportMap>),
+        swc_ecma_parser::error::Error,
+    > {
+        let filename = match &options.filename {
+            Some(id) => id.file_name(),
             None => FileName::Anon,
         };
 
         let source_file = self.source_map.new_source_file(filename, src.to_string());
+        *self.last_file.borrow_mut() = Some(source_file.clone());
+        self.set_tab_width(&options);
+        let _span = tracing::info_span!("locate", file = %source_file.name).entered();
 
         let lexer = Lexer::new(
-            Syntax::Typescript(TsConfig {
-                decorators: true,
-                ..Default::default()
-            }),
+            swc_compat::syntax(),
             Default::default(),
             StringInput::from(&*source_file),
             Some(&self.comments),
@@ -86,84 +506,704 @@ impl Preprocessor {
         GLOBALS.set(&Default::default(), || {
             let parsed_module = parser.parse_module()?;
 
-            let mut visitor = locate::LocateContentTagVisitor::default();
+            let mut visitor = if options.leading_comments {
+                locate::LocateContentTagVisitor::with_comments(&self.comments)
+            } else {
+                locate::LocateContentTagVisitor::default()
+            };
+            visitor.skip_content = !options.include_content;
 
             parsed_module.visit_with(&mut visitor);
+            let mut occurrences = visitor.occurrences;
+            locate::make_relative(&mut occurrences, source_file.start_pos);
+            locate::set_line_prefixes(&mut occurrences, src);
+
+            Ok((
+                source_file.clone(),
+                occurrences,
+                with_exports.then(|| exports::exports(&parsed_module)),
+            ))
+        })
+    }
+
+    // A stable hash of each template's content, for caching compiled
+    // templates across builds.
+    pub fn content_hashes(
+        &self,
+        src: &str,
+        algorithm: HashAlgorithm,
+    ) -> Result<Vec<ContentHash>, swc_ecma_parser::error::Error> {
+        let occurrences = self.parse(src, self.file_options())?;
+        Ok(occurrences
+            .iter()
+            .map(|occurrence| hashing::content_hash(occurrence, algorithm))
+            .collect())
+    }
+
+    // Size and rough complexity of each template, for auditing large apps.
+    pub fn analyze_metrics(
+        &self,
+        src: &str,
+    ) -> Result<Vec<TemplateMetrics>, swc_ecma_parser::error::Error> {
+        let occurrences = self.parse(src, self.file_options())?;
+        Ok(occurrences.iter().map(metrics::template_metrics).collect())
+    }
+
+    // Each match of the regex `pattern` in template content, a line at a
+    // time, for refactoring scripts that search templates and not the
+    // JavaScript around them. A pattern that doesn't compile is
+    // `Error::InvalidOptions`.
+    #[cfg(feature = "grep")]
+    pub fn grep(&self, src: &str, pattern: &str) -> Result<Vec<TemplateMatch>, Error> {
+        let pattern = regex::Regex::new(pattern).map_err(|err| {
+            Error::InvalidOptions(format!("`{pattern}` isn't a valid regex: {err}"))
+        })?;
+        let occurrences = self.parse(src, self.file_options())?;
+        let tab_width = self.options.tab_width.unwrap_or(locate::DEFAULT_TAB_WIDTH);
+        Ok(grep::grep(src, &occurrences, &pattern, tab_width))
+    }
+
+    // Just the file name (and tab width) from the options we were built
+    // with, so that errors from the methods that parse for themselves say
+    // which file.
+    fn file_options(&self) -> Options {
+        Options {
+            filename: self.options.filename.clone(),
+            tab_width: self.options.tab_width,
+            ..Default::default()
+        }
+    }
 
-            Ok(visitor.occurrences)
+    // Moves the imports of `from_module` (like `@ember/template-compiler`, in
+    // a file `process` has already run on) to `to_module`, keeping the local
+    // names and the rest of the file's formatting as they are.
+    pub fn rename_import(
+        &self,
+        src: &str,
+        from_module: &str,
+        to_module: &str,
+    ) -> Result<String, Error> {
+        migrate::rename_import(src, from_module, to_module)
+    }
+
+    // Describes the source from the most recent call to `parse` or `process`.
+    pub fn source_info(&self) -> Option<SourceInfo> {
+        self.last_file.borrow().as_ref().map(|file| SourceInfo {
+            file: file_id::display_name(&file.name),
+            source_text: file.src.to_string(),
+            line_starts: std::iter::once(0)
+                .chain(file.src.match_indices('\n').map(|(i, _)| i + 1))
+                .collect(),
         })
     }
 
-    pub fn process(
+    // `err` with where it happened in front, for tools that print errors
+    // without a code frame. `err` has to be from the most recent call to
+    // `parse` or `process`. Template errors already say where each one is.
+    pub fn describe_error(&self, err: &Error) -> String {
+        use swc_common::Spanned;
+
+        match err {
+            Error::Parse(parse_err) => format!("{}: {err}", self.location(parse_err.span())),
+            // about the whole file, which is the most recent one
+            Error::Internal { .. } => match self.last_file.borrow().as_ref() {
+                Some(file) if file.name != FileName::Anon => {
+                    format!("{}: {err}", file_id::display_name(&file.name))
+                }
+                _ => err.to_string(),
+            },
+            err => err.to_string(),
+        }
+    }
+
+    // Where `span` is in the source of the file it's in. `Range::from` counts
+    // from the start of the source map, which is only the same for its first
+    // file.
+    pub(crate) fn range(&self, span: Span) -> locate::Range {
+        let start = self.source_map.lookup_byte_offset(span.lo).sf.start_pos.0 as usize;
+        locate::Range {
+            start: span.lo.0 as usize - start,
+            end: span.hi.0 as usize - start,
+        }
+    }
+
+    // Like swc's `span_to_string`, but with ids that aren't paths as given.
+    pub(crate) fn location(&self, span: Span) -> String {
+        let lo = self.source_map.lookup_char_pos(span.lo);
+        let hi = self.source_map.lookup_char_pos(span.hi);
+        format!(
+            "{}:{}:{}: {}:{}",
+            file_id::display_name(&lo.file.name),
+            lo.line,
+            lo.col.0 + 1,
+            hi.line,
+            hi.col.0 + 1
+        )
+    }
+
+    // The tab width for the visual columns is the one in the options of the
+    // most recent call to `parse` or `process`.
+    pub fn error_loc(&self, span: Span) -> ErrorLoc {
+        let start = self.source_map.lookup_char_pos(span.lo);
+        let end = self.source_map.lookup_char_pos(span.hi);
+        let visual_column = |loc: &swc_common::Loc| {
+            let line = loc.file.get_line(loc.line - 1).unwrap_or_default();
+            locate::visual_column(&line, loc.col.0, self.last_tab_width.get())
+        };
+        // offsets into the file's source, like `SourceInfo::source_text`
+        let range = self.range(span);
+        ErrorLoc {
+            file: file_id::display_name(&start.file.name),
+            start_line: start.line,
+            start_column: start.col.0,
+            start_visual_column: visual_column(&start),
+            end_line: end.line,
+            end_column: end.col.0,
+            end_visual_column: visual_column(&end),
+            byte_start: range.start,
+            byte_end: range.end,
+        }
+    }
+
+    fn set_tab_width(&self, options: &Options) {
+        self.last_tab_width
+            .set(options.tab_width.unwrap_or(locate::DEFAULT_TAB_WIDTH));
+    }
+
+    // Edits that would fix `err`, which has to be from the most recent call to
+    // `parse` or `process`, for editors to offer as a quick fix. Only some
+    // errors have any, like a `<template>` that is never closed.
+    pub fn suggested_edits(&self, err: &swc_ecma_parser::error::Error) -> Vec<TextEdit> {
+        use swc_common::Spanned;
+
+        let Some(file) = self.last_file.borrow().clone() else {
+            return vec![];
+        };
+        let at = err.span().lo.0.saturating_sub(file.start_pos.0) as usize;
+        fixes::for_parse_error(&file.src, at.min(file.src.len()))
+    }
+
+    pub fn source_map(&self) -> Lrc<SourceMap> {
+        return self.source_map.clone();
+    }
+
+    // Every file passed to `parse` or `process` stays in the source map, with
+    // its comments, so that spans from earlier calls can still be looked up.
+    pub fn memory_stats(&self) -> MemoryStats {
+        let files = self.source_map.files();
+        let (leading, trailing) = self.comments.borrow_all();
+        MemoryStats {
+            files: files.len(),
+            source_bytes: files.iter().map(|file| file.src.len()).sum(),
+            comments: leading
+                .values()
+                .chain(trailing.values())
+                .map(Vec::len)
+                .sum(),
+        }
+    }
+
+    // Drops everything kept from earlier calls. A reset preprocessor gives the
+    // same results as a new one.
+    pub fn reset(&mut self) {
+        self.source_map = Default::default();
+        self.comments = Default::default();
+        self.last_file = Default::default();
+        self.last_tab_width = Cell::new(locate::DEFAULT_TAB_WIDTH);
+    }
+}
+
+#[cfg(feature = "transform")]
+impl Preprocessor {
+    pub fn process(&self, src: &str, options: Options) -> Result<CodeMapPair, Error> {
+        self.process_with(src, &options, options.filename.as_ref())
+    }
+
+    // Like `process`, for sources that might not be valid UTF-8, like ones
+    // from legacy tooling with another encoding. Each invalid sequence
+    // becomes U+FFFD with an `invalid-utf8` warning, instead of the caller
+    // having to check first. Every range, including the warnings', is in the
+    // decoded source; the warnings' messages say where in `src` they are.
+    pub fn process_bytes(&self, src: &[u8], options: Options) -> Result<CodeMapPair, Error> {
+        let (decoded, replaced) = lossy::decode(src);
+        let overrides = options.severity_overrides.clone();
+        let name = match &options.filename {
+            Some(id) => file_id::display_name(&id.file_name()),
+            None => file_id::display_name(&FileName::Anon),
+        };
+        let mut output = self.process(&decoded, options)?;
+        let warnings = replaced.iter().map(|replaced| Warning {
+            code: "invalid-utf8".to_owned(),
+            message: format!(
+                "Replaced bytes {}..{} of the input, which aren't valid UTF-8, with U+FFFD",
+                replaced.input.start, replaced.input.end
+            ),
+            range: replaced.decoded,
+            location: lossy::location(&name, &decoded, replaced.decoded),
+            severity: Severity::Warning,
+        });
+        output.warnings.splice(0..0, warnings);
+        warnings::apply_severity_overrides(&mut output.warnings, &overrides);
+        Ok(output)
+    }
+
+    // Processes `src` with the options given to `PreprocessorBuilder`.
+    pub fn run(&self, src: &str) -> Result<CodeMapPair, Error> {
+        self.process_with(src, &self.options, self.options.filename.as_ref())
+    }
+
+    // For embedders with a pipeline of their own: converts the templates in
+    // a `module` they parsed and resolved with `unresolved_mark` and
+    // `top_level_mark`, with the options given to `PreprocessorBuilder`. The
+    // code we add is resolved with the same marks, and nothing is printed.
+    // Call it inside the `GLOBALS` the marks came from, on a preprocessor
+    // built with the embedder's source map, which warnings and errors look
+    // their spans up in.
+    pub fn transform_module(
+        &self,
+        module: &mut Module,
+        unresolved_mark: Mark,
+        top_level_mark: Mark,
+    ) -> Result<TransformedModule, Error> {
+        embed::transform_module(self, module, unresolved_mark, top_level_mark)
+    }
+
+    // Which templates in `src` could list their scope instead of using
+    // `eval`, for tracking a migration to strict-mode templates. Only the
+    // file name is taken from the options given to `PreprocessorBuilder`.
+    pub fn strict_report(&self, src: &str) -> Result<Vec<StrictTemplate>, Error> {
+        strict::report(self, src, self.options.filename.as_ref())
+    }
+
+    // Like `run` for each of `sources`, which all share the options (and so
+    // the file name), for test suites that have a bundle of fixtures in
+    // hand. The options are only checked once, and one source failing
+    // doesn't stop the others.
+    pub fn process_many(&self, sources: &[&str]) -> Result<Vec<ProcessedFile>, Error> {
+        builder::validate(&self.options)?;
+        Ok(sources
+            .iter()
+            .map(|src| ProcessedFile::process(src, &self.options, self.options.filename.as_ref()))
+            .collect())
+    }
+
+    // Like `run`, but prints an output for each of `profiles`, in the same
+    // order, from one parse and transform of `src`. The options' own
+    // `inline_source_map` is ignored in favor of each profile's.
+    pub fn process_multi(
         &self,
         src: &str,
+        profiles: &[Profile],
+    ) -> Result<Vec<CodeMapPair>, Error> {
+        builder::validate(&self.options)?;
+        if self.options.emit != Emit::Code {
+            return Err(Error::InvalidOptions(
+                "`process_multi` only emits code".to_owned(),
+            ));
+        }
+        self.process_profiles(src, &self.options, self.options.filename.as_ref(), profiles)
+    }
+
+    // Like `process`, but trusts `templates` (from an earlier `parse` of the
+    // same source, say in a watch rebuild) instead of parsing `src` again,
+    // and only splices in the template calls and the import. Options that
+    // need the module's AST are rejected, `verify_output` among them, and
+    // `Metadata::dependencies` is left empty.
+    pub fn process_with_ranges(
+        &self,
+        src: &str,
+        templates: &[Occurrence],
         options: Options,
-    ) -> Result<CodeMapPair, swc_ecma_parser::error::Error> {
-        let target_specifier = "template";
-        let target_module = "@ember/template-compiler";
-        let filename = match options.filename {
-            Some(name) => FileName::Real(name),
+    ) -> Result<CodeMapPair, Error> {
+        builder::validate(&options)?;
+        let (codemap, _) = self.splice_with(src, templates, &options, options.filename.as_ref())?;
+        Ok(codemap)
+    }
+
+    // The output of `process_with_ranges`, and the edits that turn `src`
+    // into it.
+    fn splice_with(
+        &self,
+        src: &str,
+        templates: &[Occurrence],
+        options: &Options,
+        filename: Option<&FileId>,
+    ) -> Result<(CodeMapPair, Vec<splice::Edit>), Error> {
+        splice::check_options(options)?;
+        let filename = match filename {
+            Some(id) => id.file_name(),
             None => FileName::Anon,
         };
 
         let source_file = self.source_map.new_source_file(filename, src.to_string());
+        *self.last_file.borrow_mut() = Some(source_file.clone());
+        self.set_tab_width(options);
+        self.splice_file(&source_file, templates, options)
+    }
 
-        let lexer = Lexer::new(
-            Syntax::Typescript(TsConfig {
-                decorators: true,
+    // `splice_with` for a file that's already in the source map, which
+    // `templates`' ranges are offsets into.
+    fn splice_file(
+        &self,
+        source_file: &SourceFile,
+        templates: &[Occurrence],
+        options: &Options,
+    ) -> Result<(CodeMapPair, Vec<splice::Edit>), Error> {
+        let default_handler = TagHandler::default();
+        let handler = options.tags.get("template").unwrap_or(&default_handler);
+        let src = source_file.src.as_str();
+        let _span = tracing::info_span!("splice", file = %source_file.name).entered();
+        if templates.is_empty() {
+            let codemap = self.unchanged(source_file, options, options.inline_source_map)?;
+            return Ok((codemap, vec![]));
+        }
+
+        let id = unique_alias(src);
+        let config = transform::TransformConfig {
+            dedent_content: options.dedent_content,
+            trim_content: options.trim_content,
+            transformers: options
+                .transformers
+                .iter()
+                .chain(&handler.transformers)
+                .map(|t| t.as_ref())
+                .collect(),
+            collapse_whitespace_only: options.collapse_whitespace_only,
+            max_template_bytes: options.max_template_bytes,
+            max_template_lines: options.max_template_lines,
+            snippet_version: options.snippet_version,
+            ..Default::default()
+        };
+        let import = (
+            handler.import_module.as_str(),
+            handler.import_specifier.as_str(),
+        );
+        let mut spliced = splice::splice(source_file, src, templates, &id, import, &config)?;
+        if !spliced.errors.is_empty() {
+            return Err(self.template_errors(spliced.errors));
+        }
+        if annotations::wanted(options) {
+            spliced.code =
+                annotations::annotate(&spliced.code, &id, options, &mut spliced.mappings);
+            for edit in &mut spliced.edits {
+                edit.text = annotations::annotate(&edit.text, &id, options, &mut []);
+            }
+        }
+
+        let mut codemap = self.with_source_map(
+            spliced.code.into_bytes(),
+            &spliced.mappings,
+            options,
+            options.inline_source_map,
+        )?;
+        codemap.metadata = metadata::collect(
+            &codemap.code,
+            Some(import),
+            &id,
+            &spliced.requested_imports,
+            &self.ranges(&spliced.replaced),
+        );
+        codemap.warnings = self.content_warnings(spliced.warnings);
+        warnings::apply_severity_overrides(&mut codemap.warnings, &options.severity_overrides);
+        Ok((codemap, spliced.edits))
+    }
+
+    // `Emit::UnifiedDiff`: what `process_with_ranges` would change, as a
+    // patch against `src`.
+    fn unified_diff(
+        &self,
+        src: &str,
+        options: &Options,
+        filename: Option<&FileId>,
+    ) -> Result<CodeMapPair, Error> {
+        splice::check_options(options)?;
+        // the same file for both, so that the splice's spans are the parse's
+        let (source_file, templates, _) = self.locate(
+            src,
+            Options {
+                filename: filename.cloned(),
+                tab_width: options.tab_width,
                 ..Default::default()
-            }),
+            },
+            false,
+        )?;
+        let (_, edits) = self.splice_file(&source_file, &templates, options)?;
+        let path = filename.map_or("input".to_owned(), |id| id.to_string());
+        Ok(CodeMapPair {
+            code: patch::unified_diff(&path, src, &edits),
+            map: String::new(),
+            metadata: Default::default(),
+            warnings: vec![],
+        })
+    }
+
+    // `filename` is separate so that a `Project` can share one set of options
+    // between its files.
+    fn process_with(
+        &self,
+        src: &str,
+        options: &Options,
+        filename: Option<&FileId>,
+    ) -> Result<CodeMapPair, Error> {
+        builder::validate(options)?;
+        if options.emit == Emit::UnifiedDiff {
+            return self.unified_diff(src, options, filename);
+        }
+        let profile = Profile {
+            inline_source_map: options.inline_source_map,
+            minify: options.minify,
+            ..Default::default()
+        };
+        let mut outputs = self.process_profiles(src, options, filename, &[profile])?;
+        Ok(outputs.remove(0))
+    }
+
+    // The output of `process_with` for each of `profiles`, sharing the work
+    // up to printing.
+    fn process_profiles(
+        &self,
+        src: &str,
+        options: &Options,
+        filename: Option<&FileId>,
+        profiles: &[Profile],
+    ) -> Result<Vec<CodeMapPair>, Error> {
+        // `<template>` is the only tag the parser produces, so its handler is
+        // the only one that can apply
+        let default_handler = TagHandler::default();
+        let handler = options.tags.get("template").unwrap_or(&default_handler);
+        let target_specifier = handler.import_specifier.as_str();
+        let target_module = handler.import_module.as_str();
+        let filename = match filename {
+            Some(id) => id.file_name(),
+            None => FileName::Anon,
+        };
+
+        let source_file = self.source_map.new_source_file(filename, src.to_string());
+        *self.last_file.borrow_mut() = Some(source_file.clone());
+        self.set_tab_width(options);
+
+        let lexer = Lexer::new(
+            swc_compat::syntax(),
             Default::default(),
             StringInput::from(&*source_file),
             Some(&self.comments),
         );
         let mut parser = Parser::new_from(lexer);
-        GLOBALS.set(&Default::default(), || {
-            let mut parsed_module = parser.parse_module()?;
+        GLOBALS.set(
+            &Default::default(),
+            || -> Result<Vec<CodeMapPair>, Error> {
+                let _process = tracing::info_span!("process", file = %source_file.name).entered();
+                let mut parsed_module = {
+                    let _span = tracing::debug_span!("parse").entered();
+                    parser.parse_module()?
+                };
+                self.check_cancelled()?;
+                let exports = options
+                    .collect_exports
+                    .then(|| exports::exports(&parsed_module));
 
-            let id = private_ident!(IMPORT_ALIAS);
-            let mut needs_import = false;
-            parsed_module.visit_mut_with(&mut as_folder(transform::TransformVisitor::new(
-                &id,
-                Some(&mut needs_import),
-            )));
+                // only the name of the import we insert can change; identifiers
+                // in the user's code are never renamed
+                let id = private_ident!(unique_alias(src));
+                let mut needs_import = false;
+                let mut visitor = transform::TransformVisitor::with_config(
+                    &id,
+                    Some(&mut needs_import),
+                    transform::TransformConfig {
+                        source: src,
+                        ..self.transform_config(options, handler)
+                    },
+                );
+                {
+                    let _span = tracing::debug_span!("transform").entered();
+                    parsed_module.visit_mut_with(&mut visitor);
+                }
+                self.check_cancelled()?;
+                let transform::TransformResults {
+                    replaced,
+                    errors,
+                    explicit_scopes,
+                    requested_imports,
+                    suggested_edits,
+                    labels,
+                    warnings: content_warnings,
+                } = visitor.finish();
 
-            if needs_import {
-                insert_import(&mut parsed_module, target_module, target_specifier, &id)
-            }
+                if !errors.is_empty() {
+                    return Err(self.template_errors_with_edits(errors, &suggested_edits, &labels));
+                }
 
-            let unresolved_mark = Mark::new();
-            let top_level_mark = Mark::new();
+                tracing::debug!(templates = replaced.len(), "transformed");
+                let already_transformed = options.detect_already_transformed
+                    && replaced.is_empty()
+                    && imports::calls_import(&parsed_module, target_module, target_specifier);
+                if replaced.is_empty() && !options.strip_types {
+                    // Nothing to change, so there's no point resolving and
+                    // reprinting (which is slow for big barrel files, and loses
+                    // formatting). The source comes back exactly as it was,
+                    // apart from an inline source map if one was asked for.
+                    let metadata = Metadata {
+                        dependencies: imports::dependencies(&parsed_module),
+                        already_transformed,
+                        exports: exports.clone(),
+                        ..Default::default()
+                    };
+                    return profiles
+                        .iter()
+                        .map(|profile| {
+                            let mut output =
+                                self.unchanged(&source_file, options, profile.inline_source_map)?;
+                            output.metadata = metadata.clone();
+                            Ok(output)
+                        })
+                        .collect();
+                }
 
-            parsed_module.visit_mut_with(&mut resolver(unresolved_mark, top_level_mark, false));
+                if needs_import {
+                    // the transformer refers to these by name from inside template
+                    // content, so they can't be renamed
+                    for request in requested_imports.iter().rev() {
+                        let local = Ident::new(request.local.as_str().into(), Default::default());
+                        insert_import(
+                            &mut parsed_module,
+                            &request.module,
+                            &request.specifier,
+                            &local,
+                        )
+                    }
+                    insert_import(&mut parsed_module, target_module, target_specifier, &id)
+                }
 
-            let codemap = self.print(&parsed_module, options.inline_source_map);
+                let unresolved_mark = Mark::new();
+                let top_level_mark = Mark::new();
 
-            Ok(codemap)
-        })
+                // The import we insert is named so that it can't collide
+                // with anything in `src`, so the output doesn't depend on the
+                // resolver. Only the options that look at what it decided
+                // need it, and for big modules it's most of the work.
+                let needs_resolver = options.explicit_scope
+                    || options.forbid_eval
+                    || options.warn_unresolved
+                    || options.strip_types;
+                if needs_resolver && options.run_resolver_hygiene {
+                    let _span = tracing::debug_span!("resolver").entered();
+                    swc_compat::resolve(&mut parsed_module, unresolved_mark, top_level_mark);
+                }
+                self.check_cancelled()?;
+
+                let errors =
+                    scope::check_resolved(&parsed_module, &explicit_scopes, unresolved_mark);
+                if !errors.is_empty() {
+                    return Err(self.template_errors(errors));
+                }
+
+                let mut warnings: Vec<Warning> =
+                    references::check_probes(&mut parsed_module, unresolved_mark)
+                        .into_iter()
+                        .map(|(span, name)| Warning {
+                            code: "unresolved-reference".to_owned(),
+                            message: references::unresolved_message(&name),
+                            range: self.range(span),
+                            location: self.location(span),
+                            severity: Severity::Warning,
+                        })
+                        .collect();
+                warnings.extend(self.content_warnings(content_warnings));
+                warnings::apply_severity_overrides(&mut warnings, &options.severity_overrides);
+
+                if options.strip_types {
+                    // after the resolver, which tells it which imports are
+                    // only used as types
+                    let _span = tracing::debug_span!("strip_types").entered();
+                    parsed_module.visit_mut_with(&mut typescript::strip(top_level_mark));
+                }
+
+                let removed_imports = if options.prune_unused_imports && needs_import {
+                    imports::prune_unused(&mut parsed_module, &[id.to_id()])
+                } else {
+                    vec![]
+                };
+                let import_kept = needs_import && removed_imports.is_empty();
+                if options.simplify_imports {
+                    imports::simplify(&mut parsed_module);
+                }
+
+                let dependencies = imports::dependencies(&parsed_module);
+                profiles
+                    .iter()
+                    .map(|profile| {
+                        let module = match &profile.module_name {
+                            Some(name) => {
+                                let mut module = parsed_module.clone();
+                                transform::add_module_name(&mut module, &id, name);
+                                Cow::Owned(module)
+                            }
+                            None => Cow::Borrowed(&parsed_module),
+                        };
+                        let mut codemap = {
+                            let _span = tracing::debug_span!("print").entered();
+                            self.print(&module, options, profile, &id.sym)?
+                        };
+                        if options.verify_output {
+                            let _span = tracing::debug_span!("verify").entered();
+                            verify::verify_output(&module, &codemap.code)?;
+                        }
+                        codemap.metadata = metadata::collect(
+                            &codemap.code,
+                            import_kept.then_some((target_module, target_specifier)),
+                            &id.sym,
+                            &requested_imports,
+                            &self.ranges(&metadata::emitted_order(&module, &replaced)),
+                        );
+                        codemap.metadata.removed_imports = removed_imports.clone();
+                        codemap.metadata.dependencies = dependencies.clone();
+                        codemap.metadata.already_transformed = already_transformed;
+                        codemap.metadata.exports = exports.clone();
+                        codemap.warnings = warnings.clone();
+                        Ok(codemap)
+                    })
+                    .collect()
+            },
+        )
     }
 
-    fn print(&self, module: &Module, inline_source_map: bool) -> CodeMapPair {
-        let mut buf = vec![];
+    // `callee` is the local name of the template compiler's import.
+    fn print(
+        &self,
+        module: &Module,
+        options: &Options,
+        profile: &Profile,
+        callee: &str,
+    ) -> Result<CodeMapPair, Error> {
         let mut srcmap = vec![];
-        let mut source_map_buffer = vec![];
-        let mut emitter = Emitter {
-            cfg: Default::default(),
-            cm: self.source_map.clone(),
-            wr: swc_ecma_codegen::text_writer::JsWriter::new(
-                self.source_map().clone(),
-                "\n",
-                &mut buf,
-                Some(&mut srcmap),
-            ),
-            comments: Some(&self.comments),
-        };
-        emitter.emit_module(module).unwrap();
+        let mut buf = swc_compat::print(
+            &self.source_map,
+            module,
+            profile.minify,
+            // minified output has no comments
+            (!profile.minify).then_some(&self.comments),
+            Some(&mut srcmap),
+        )
+        .map_err(|err| Error::internal("the output could not be printed", Some(err.to_string())))?;
+        if annotations::wanted(options) {
+            buf =
+                annotations::annotate(&String::from_utf8_lossy(&buf), callee, options, &mut srcmap)
+                    .into_bytes();
+        }
 
-        self.source_map()
-            .build_source_map_with_config(&srcmap, None, SourceMapConfig {})
-            .to_writer(&mut source_map_buffer)
-            .unwrap();
+        self.with_source_map(buf, &srcmap, options, profile.inline_source_map)
+    }
+
+    // `code` along with the source map for `mappings`, which is also
+    // appended to the code if `inline_source_map` is set.
+    fn with_source_map(
+        &self,
+        mut buf: Vec<u8>,
+        mappings: &[(BytePos, LineCol)],
+        options: &Options,
+        inline_source_map: bool,
+    ) -> Result<CodeMapPair, Error> {
+        let source_map = self.build_source_map(mappings, options)?;
 
         if inline_source_map {
             let mut comment = "//# sourceMappingURL=data:application/json;base64,"
@@ -172,7 +1212,7 @@ impl Preprocessor {
             buf.append(&mut comment);
 
             let mut encoded = general_purpose::URL_SAFE_NO_PAD
-                .encode(source_map_buffer.clone())
+                .encode(&source_map)
                 .into_bytes();
 
             buf.append(&mut encoded);
@@ -180,17 +1220,186 @@ impl Preprocessor {
 
         let s = String::from_utf8_lossy(&buf);
 
-        CodeMapPair {
+        Ok(CodeMapPair {
             code: s.to_string(),
-            map: String::from_utf8(source_map_buffer.clone()).unwrap(),
+            map: source_map,
+            metadata: Default::default(),
+            warnings: vec![],
+        })
+    }
+
+    // `file` as it is, with a source map to itself that has one mapping per
+    // line.
+    fn unchanged(
+        &self,
+        file: &SourceFile,
+        options: &Options,
+        inline_source_map: bool,
+    ) -> Result<CodeMapPair, Error> {
+        let mappings: Vec<(BytePos, LineCol)> = std::iter::once(0)
+            .chain(file.src.match_indices('\n').map(|(i, _)| i + 1))
+            .enumerate()
+            .map(|(line, start)| {
+                (
+                    file.start_pos + BytePos(start as u32),
+                    LineCol {
+                        line: line as u32,
+                        col: 0,
+                    },
+                )
+            })
+            .collect();
+        let mut code = file.src.to_string();
+        if inline_source_map && !code.is_empty() && !code.ends_with('\n') {
+            // the comment goes on a line of its own
+            code.push('\n');
         }
+        self.with_source_map(code.into_bytes(), &mappings, options, inline_source_map)
     }
 
-    pub fn source_map(&self) -> Lrc<SourceMap> {
-        return self.source_map.clone();
+    // The JSON of the source map for `mappings`, with the `source_map_*`
+    // options applied.
+    fn build_source_map(
+        &self,
+        mappings: &[(BytePos, LineCol)],
+        options: &Options,
+    ) -> Result<String, Error> {
+        let config = SourceMapConfig {
+            relative_to: options.source_map_relative_to.as_deref(),
+            forward_slashes: options.source_map_forward_slashes,
+        };
+        let mut map = self
+            .source_map
+            .build_source_map_with_config(mappings, None, config);
+        map.set_file(options.source_map_file.as_deref());
+        let mut buffer = vec![];
+        map.to_writer(&mut buffer).map_err(|err| {
+            Error::internal("the source map could not be written", Some(err.to_string()))
+        })?;
+        String::from_utf8(buffer)
+            .map_err(|err| Error::internal("the source map is not UTF-8", Some(err.to_string())))
+    }
+
+    // `range` for each of `spans`.
+    fn ranges(&self, spans: &[Span]) -> Vec<locate::Range> {
+        spans.iter().map(|span| self.range(*span)).collect()
+    }
+
+    fn content_warnings(&self, warnings: Vec<(Span, &'static str, String)>) -> Vec<Warning> {
+        warnings
+            .into_iter()
+            .map(|(span, code, message)| Warning {
+                code: code.to_owned(),
+                message,
+                range: self.range(span),
+                location: self.location(span),
+                severity: Severity::Warning,
+            })
+            .collect()
+    }
+
+    fn check_cancelled(&self) -> Result<(), Error> {
+        match &self.cancelled {
+            Some(flag) if flag.load(Ordering::Relaxed) => Err(Error::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    fn transform_config<'a>(
+        &'a self,
+        options: &'a Options,
+        handler: &'a TagHandler,
+    ) -> transform::TransformConfig<'a> {
+        transform::TransformConfig {
+            dedent_content: options.dedent_content,
+            trim_content: options.trim_content,
+            transformers: options
+                .transformers
+                .iter()
+                .chain(&handler.transformers)
+                .map(|t| t.as_ref())
+                .collect(),
+            hbs_tagged_templates: options.hbs_tagged_templates,
+            precompile_template_calls: options.precompile_template_calls,
+            explicit_scope: (options.explicit_scope || options.forbid_eval)
+                .then_some(&self.comments),
+            downlevel_static_blocks: options
+                .target
+                .map_or(false, |target| target < EsVersion::Es2022),
+            component_this_in_methods: options.component_this_in_methods,
+            probe_references: options.warn_unresolved,
+            forbid_eval: options.forbid_eval,
+            collapse_whitespace_only: options.collapse_whitespace_only,
+            max_template_bytes: options.max_template_bytes,
+            max_template_lines: options.max_template_lines,
+            default_export_at_end: options.default_export_placement == DefaultExportPlacement::End,
+            component_base: options.implicit_component_base.as_deref(),
+            snippet_version: options.snippet_version,
+            source: "",
+        }
+    }
+
+    fn template_errors(&self, errors: Vec<(Span, String)>) -> Error {
+        self.template_errors_with_edits(errors, &[], &[])
+    }
+
+    // `suggested_edits` are by the span of the error they fix, and `labels`
+    // by the span of the error they explain.
+    fn template_errors_with_edits(
+        &self,
+        errors: Vec<(Span, String)>,
+        suggested_edits: &[(Span, Vec<(Span, String)>)],
+        labels: &[(Span, Span, String)],
+    ) -> Error {
+        Error::Transformer(
+            errors
+                .into_iter()
+                .map(|(span, message)| {
+                    let mut edits: Vec<TextEdit> = suggested_edits
+                        .iter()
+                        .filter(|(error_span, _)| *error_span == span)
+                        .flat_map(|(_, edits)| edits)
+                        .map(|(span, new_text)| TextEdit {
+                            range: self.range(*span),
+                            new_text: new_text.clone(),
+                        })
+                        .collect();
+                    edits.sort_by_key(|edit| edit.range.start);
+                    TemplateError {
+                        range: self.range(span),
+                        location: self.location(span),
+                        message,
+                        suggested_edits: edits,
+                        labels: labels
+                            .iter()
+                            .filter(|(error_span, _, _)| *error_span == span)
+                            .map(|(_, span, message)| Label {
+                                range: self.range(*span),
+                                location: self.location(*span),
+                                message: message.clone(),
+                            })
+                            .collect(),
+                    }
+                })
+                .collect(),
+        )
     }
 }
 
+// `IMPORT_ALIAS`, unless the source already mentions it (say, because it
+// was processed before), in which case a numbered variant that it doesn't.
+#[cfg(feature = "transform")]
+fn unique_alias(src: &str) -> String {
+    let mut alias = IMPORT_ALIAS.to_owned();
+    let mut n = 1;
+    while src.contains(&alias) {
+        alias = format!("{IMPORT_ALIAS}_{n}");
+        n += 1;
+    }
+    alias
+}
+
+#[cfg(feature = "transform")]
 fn insert_import(
     parsed_module: &mut Module,
     target_module: &str,
@@ -217,13 +1426,575 @@ fn insert_import(
     );
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "transform"))]
+mod spec_fixtures;
+#[cfg(all(test, feature = "transform"))]
 mod test_helpers;
 
+#[test]
+fn test_source_info() {
+    let p = Preprocessor::new();
+    assert_eq!(p.source_info(), None);
+    p.parse(
+        "let a = 1;\nlet b = <template>Hi</template>;\n",
+        Options {
+            filename: Some("a.gjs".into()),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        p.source_info(),
+        Some(SourceInfo {
+            file: "a.gjs".into(),
+            source_text: "let a = 1;\nlet b = <template>Hi</template>;\n".into(),
+            line_starts: vec![0, 11, 44],
+        })
+    );
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_reset() {
+    let src = "// hi\nexport default <template>Hi</template>;";
+    let fresh = Preprocessor::new().parse(src, Default::default()).unwrap();
+
+    let mut p = Preprocessor::new();
+    p.process(src, Default::default()).unwrap();
+    p.parse(src, Default::default()).unwrap();
+    let stats = p.memory_stats();
+    assert_eq!(stats.files, 2);
+    assert_eq!(stats.source_bytes, 2 * src.len());
+    assert!(stats.comments > 0);
+
+    p.reset();
+    assert_eq!(p.memory_stats(), MemoryStats::default());
+    assert_eq!(p.parse(src, Default::default()).unwrap(), fresh);
+}
+
+#[test]
+fn test_methods_that_parse_again() {
+    let src = "let a = 1;\nlet t = <template>{{#if x}}Hi{{/if}}</template>;";
+    let p = Preprocessor::new();
+    let metrics = p.analyze_metrics(src).unwrap();
+    assert_eq!(p.analyze_metrics(src).unwrap(), metrics);
+    assert_eq!(
+        metrics[0].range.slice(src).unwrap(),
+        "<template>{{#if x}}Hi{{/if}}</template>"
+    );
+
+    let hashes = p.content_hashes(src, HashAlgorithm::Xxh3).unwrap();
+    assert_eq!(p.content_hashes(src, HashAlgorithm::Xxh3).unwrap(), hashes);
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_describe_error() {
+    let p = Preprocessor::builder()
+        .filename("\0virtual:a.gjs")
+        .build()
+        .unwrap();
+    let err = p
+        .content_hashes("let y = ;", HashAlgorithm::Xxh3)
+        .unwrap_err();
+    let description = p.describe_error(&err.into());
+    assert!(
+        description.starts_with("\0virtual:a.gjs:1:9: "),
+        "{description}"
+    );
+    assert!(description.contains(": Parse Error: "), "{description}");
+}
+
+#[test]
+fn test_error_loc() {
+    use swc_common::Spanned;
+
+    let p = Preprocessor::new();
+    let err = p
+        .parse(
+            "let a = 1;\nlet é = ;\n",
+            Options {
+                filename: Some("a.gjs".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+    assert_eq!(
+        p.error_loc(err.span()),
+        ErrorLoc {
+            file: "a.gjs".into(),
+            start_line: 2,
+            start_column: 8,
+            start_visual_column: 8,
+            end_line: 2,
+            end_column: 9,
+            end_visual_column: 9,
+            // `é` is two bytes but one column
+            byte_start: 20,
+            byte_end: 21,
+        }
+    );
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_error_loc_after_another_file() {
+    use swc_common::Spanned;
+
+    let src = "let a = 1;\nlet é = ;\n";
+    let p = Preprocessor::new();
+    p.process("let x = <template>Hi</template>;", Default::default())
+        .unwrap();
+    let err = p.parse(src, Default::default()).unwrap_err();
+    let loc = p.error_loc(err.span());
+    assert_eq!((loc.start_line, loc.start_column), (2, 8));
+    assert_eq!((loc.byte_start, loc.byte_end), (20, 21));
+    assert_eq!(&src[loc.byte_start..loc.byte_end], ";");
+    assert_eq!(p.source_info().unwrap().source_text, src);
+}
+
+#[test]
+fn test_error_loc_visual_columns() {
+    use swc_common::Spanned;
+
+    let p = Preprocessor::new();
+    let err = p
+        .parse(
+            "let a = 1;\n\tlet b = ;\n",
+            Options {
+                tab_width: Some(8),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+    let loc = p.error_loc(err.span());
+    assert_eq!((loc.start_column, loc.start_visual_column), (9, 16));
+    assert_eq!((loc.end_column, loc.end_visual_column), (10, 17));
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_aggregates_transformer_errors() {
+    let p = Preprocessor::new();
+    let result = p.process(
+        "let a = <template>{{bad}}</template>;\nlet b = <template>ok</template>;\nlet c = <template>{{bad}}</template>;",
+        Options {
+            transformers: vec![Box::new(|content: &str, _: &TemplateContext| {
+                if content.contains("bad") {
+                    Err("unknown helper".to_owned())
+                } else {
+                    Ok(content.to_owned())
+                }
+            })],
+            ..Default::default()
+        },
+    );
+    match result {
+        Err(Error::Transformer(errors)) => {
+            let locations: Vec<&str> = errors.iter().map(|e| e.location.as_str()).collect();
+            assert_eq!(locations, vec!["<anon>:1:9: 1:37", "<anon>:3:9: 3:37"]);
+            assert!(errors.iter().all(|e| e.message == "unknown helper"));
+            assert_eq!(errors[0].range, locate::Range { start: 8, end: 36 });
+        }
+        _ => panic!("expected transformer errors"),
+    }
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_explicit_scope() {
+    let p = Preprocessor::new();
+    let output = p
+        .process(
+            "import Greeting from './greeting';\nexport default /* @scope Greeting */ <template><Greeting /></template>;",
+            Options {
+                explicit_scope: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert!(output.code.contains("scope: "));
+    assert!(output.code.contains("Greeting"));
+    assert!(!output.code.contains("eval"));
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_forbid_eval() {
+    let options = || Options {
+        forbid_eval: true,
+        ..Default::default()
+    };
+    let p = Preprocessor::new();
+    let output = p
+        .process(
+            "import Greeting from './greeting';\nexport default /* @scope Greeting */ <template><Greeting /></template>;",
+            options(),
+        )
+        .unwrap();
+    assert!(!output.code.contains("eval"));
+
+    let result = Preprocessor::new().process(
+        "const a = <template>A</template>;\nexport class B { <template>B</template> }",
+        options(),
+    );
+    match result {
+        Err(Error::Transformer(errors)) => {
+            assert_eq!(errors.len(), 2);
+            assert!(errors[0].message.contains("`forbid_eval`"));
+        }
+        _ => panic!("expected eval to be forbidden"),
+    }
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_collapse_whitespace_only() {
+    let output = Preprocessor::new()
+        .process(
+            "let a = <template>\n  \n</template>;\nlet b = <template></template>;",
+            Options {
+                collapse_whitespace_only: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert!(!output.code.contains("`\n"));
+    assert_eq!(output.warnings.len(), 1);
+    assert_eq!(output.warnings[0].code, "whitespace-only-template");
+    assert_eq!(
+        output.warnings[0].range,
+        locate::Range { start: 8, end: 33 }
+    );
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_max_template_size() {
+    let options = || Options {
+        max_template_lines: Some(2),
+        ..Default::default()
+    };
+    let output = Preprocessor::new()
+        .process("<template>\n  <p>Hi</p>\n</template>", options())
+        .unwrap();
+    assert!(output.warnings.is_empty());
+    let output = Preprocessor::new()
+        .process(
+            "<template>\n  <p>Hi</p>\n  <p>There</p>\n</template>",
+            options(),
+        )
+        .unwrap();
+    assert_eq!(output.warnings.len(), 1);
+    assert_eq!(output.warnings[0].code, "template-too-large");
+    assert!(output.warnings[0]
+        .message
+        .contains("3 lines, over the limit of 2"));
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_suggests_a_scope_instead_of_eval() {
+    let src = "import Greeting from './greeting';\nlet x = <template><Greeting @name={{name}} /></template>;";
+    let result = Preprocessor::new().process(
+        src,
+        Options {
+            forbid_eval: true,
+            ..Default::default()
+        },
+    );
+    let Err(Error::Transformer(errors)) = result else {
+        panic!("expected eval to be forbidden");
+    };
+    assert_eq!(
+        errors[0].suggested_edits,
+        vec![TextEdit {
+            range: locate::Range { start: 43, end: 43 },
+            new_text: "/* @scope Greeting, name */ ".to_owned(),
+        }]
+    );
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_process_many() {
+    let p = Preprocessor::builder().filename("a.gjs").build().unwrap();
+    let outputs = p
+        .process_many(&[
+            "export default <template>A</template>;",
+            "export default <template>B",
+            "let x = 1;\nexport default <template>C</template>;",
+        ])
+        .unwrap();
+    assert_eq!(outputs.len(), 3);
+    assert!(outputs[0].result.as_ref().unwrap().code.contains("`A`"));
+    assert!(matches!(outputs[1].result, Err(Error::Parse(_))));
+    // each source's ranges are its own
+    let c = outputs[2].result.as_ref().unwrap();
+    assert_eq!(c.metadata.replacements[0].original_range.start, 26);
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_process_multi() {
+    let p = Preprocessor::builder()
+        .filename("a.gjs")
+        .verify_output(true)
+        .build()
+        .unwrap();
+    let outputs = p
+        .process_multi(
+            "export default <template>Hi</template>;",
+            &[
+                Profile {
+                    module_name: Some("app/a.gjs".to_owned()),
+                    ..Default::default()
+                },
+                Profile {
+                    inline_source_map: true,
+                    ..Default::default()
+                },
+            ],
+        )
+        .unwrap();
+    assert_eq!(outputs.len(), 2);
+    assert!(outputs[0]
+        .code
+        .contains(r#"{ moduleName: "app/a.gjs", eval() {"#));
+    assert!(!outputs[0].code.contains("sourceMappingURL"));
+    assert!(!outputs[1].code.contains("moduleName"));
+    assert!(outputs[1].code.contains("sourceMappingURL"));
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_minify() {
+    let output = Preprocessor::new()
+        .process(
+            "// a comment\nexport default class X {\n  <template>Hi</template>\n}\n",
+            Options {
+                minify: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert!(!output.code.contains("a comment"));
+    assert!(!output.code.contains('\n'));
+    assert!(output.code.contains("eval(){return eval(arguments[0])}"));
+
+    let import = output.metadata.inserted_import.unwrap();
+    assert_eq!(
+        &output.code[import.range.start..import.range.end],
+        format!(r#"import{{template as {IMPORT_ALIAS}}}from"@ember/template-compiler";"#)
+    );
+    assert_eq!(output.metadata.replacements.len(), 1);
+    let call = output.metadata.replacements[0].range;
+    assert!(output.code[call.start..call.end].starts_with(&format!("{IMPORT_ALIAS}(`Hi`")));
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_source_map_options() {
+    let output = Preprocessor::new()
+        .process(
+            "let x = <template>Hi</template>;",
+            Options {
+                filename: Some(r"/app/src\a.gjs".into()),
+                source_map_file: Some("a.js".to_owned()),
+                source_map_relative_to: Some("/app".into()),
+                source_map_forward_slashes: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let map: serde_json::Value = serde_json::from_str(&output.map).unwrap();
+    assert_eq!(map["file"], "a.js");
+    assert_eq!(map["sources"][0], "src/a.gjs");
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_keeps_virtual_ids_as_given() {
+    let id = "\0virtual:a.gjs";
+    let output = Preprocessor::new()
+        .process(
+            "let x = <template>Hi</template>;",
+            Options {
+                filename: Some(id.into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let map: serde_json::Value = serde_json::from_str(&output.map).unwrap();
+    assert_eq!(map["sources"][0], id);
+
+    let result = Preprocessor::new().process(
+        "let x = <template>{{y}}</template>;",
+        Options {
+            filename: Some("https://example.com/a.gjs".into()),
+            forbid_eval: true,
+            ..Default::default()
+        },
+    );
+    let Err(Error::Transformer(errors)) = result else {
+        panic!("expected eval to be forbidden");
+    };
+    assert_eq!(errors[0].location, "https://example.com/a.gjs:1:9: 1:35");
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_template_in_a_decorator() {
+    let src = "class A {\n  @cached(<template>Hi</template>) get x() {}\n}";
+    let result = Preprocessor::new().process(src, Default::default());
+    let Err(Error::Transformer(errors)) = result else {
+        panic!("expected the template to be rejected");
+    };
+    assert!(errors[0].message.contains("can't be used in a decorator"));
+    assert_eq!(errors[0].range, locate::Range { start: 20, end: 43 });
+    assert_eq!(errors[0].labels.len(), 1);
+    assert_eq!(
+        errors[0].labels[0].range,
+        locate::Range { start: 12, end: 44 }
+    );
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_explicit_scope_must_be_in_scope() {
+    let p = Preprocessor::new();
+    let result = p.process(
+        "export default /* @scope Missing */ <template><Missing /></template>;",
+        Options {
+            explicit_scope: true,
+            ..Default::default()
+        },
+    );
+    match result {
+        Err(Error::Transformer(errors)) => {
+            assert_eq!(errors.len(), 1);
+            assert_eq!(
+                errors[0].message,
+                "`Missing` is listed in @scope but is not in scope"
+            );
+        }
+        _ => panic!("expected a scope error"),
+    }
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_bare_template_beside_named_exports() {
+    let output = Preprocessor::new()
+        .process(
+            "export const name = 'Hi';\nexport { other } from './other';\n<template>{{name}}</template>",
+            Default::default(),
+        )
+        .unwrap();
+    assert!(output.code.contains("export default template_"));
+    assert!(output.code.contains("export { other } from './other'"));
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_bare_template_beside_a_default_export() {
+    for src in [
+        "export default class X {}\n<template>Hi</template>",
+        "export { default } from './x';\n<template>Hi</template>",
+        "const x = 1;\nexport { x as default };\n<template>Hi</template>",
+        "<template>A</template>\n<template>B</template>",
+    ] {
+        match Preprocessor::new().process(src, Default::default()) {
+            Err(Error::Transformer(errors)) => {
+                assert_eq!(errors.len(), 1, "{src}");
+                assert!(errors[0].message.contains("already has one"));
+            }
+            _ => panic!("expected a second default export to fail: {src}"),
+        }
+    }
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_suggests_moving_a_bare_template_into_the_default_class() {
+    let src = "export default class X {}\n<template>Hi</template>;\n";
+    let Err(Error::Transformer(errors)) = Preprocessor::new().process(src, Default::default())
+    else {
+        panic!("expected a second default export to fail");
+    };
+    let mut fixed = src.to_owned();
+    for edit in errors[0].suggested_edits.iter().rev() {
+        fixed.replace_range(edit.range.start..edit.range.end, &edit.new_text);
+    }
+    assert_eq!(
+        fixed,
+        "export default class X {\n  <template>Hi</template>\n}\n\n"
+    );
+}
+
+// Tools diff our output between versions, so what each snippet version
+// emits is pinned here. Changing it means adding a version instead.
+#[cfg(feature = "transform")]
+#[test]
+fn test_snippet_versions_are_stable() {
+    let src =
+        "export class A { <template>Hi</template> }\nexport const B = <template>Bye</template>;";
+    for (snippet_version, expected) in [
+        (
+            SnippetVersion::V1,
+            r#"import { template as template_UUID } from "@ember/template-compiler";
+               export class A {
+                   static {
+                       template_UUID(`Hi`, { eval() { return eval(arguments[0]) } });
+                   }
+               }
+               export const B = template_UUID(`Bye`, { eval() { return eval(arguments[0]) } });"#,
+        ),
+        (
+            SnippetVersion::V2,
+            r#"import { template as template_UUID } from "@ember/template-compiler";
+               export class A {
+                   static {
+                       template_UUID(`Hi`, { component: this, eval() { return eval(arguments[0]) } });
+                   }
+               }
+               export const B = template_UUID(`Bye`, { eval() { return eval(arguments[0]) } });"#,
+        ),
+    ] {
+        let output = Preprocessor::new()
+            .process(
+                src,
+                Options {
+                    snippet_version,
+                    verify_output: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            test_helpers::normalize(&output.code.replace(IMPORT_ALIAS, "template_UUID")),
+            test_helpers::normalize(expected),
+            "{snippet_version:?}"
+        );
+    }
+    assert_eq!(SnippetVersion::default(), SnippetVersion::V2);
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_snippet_version_1_has_no_component() {
+    let result = Preprocessor::builder()
+        .snippet_version(SnippetVersion::V1)
+        .component_this_in_methods(true)
+        .build();
+    assert!(matches!(result, Err(Error::InvalidOptions(_))));
+}
+
 macro_rules! testcase {
     ($test_name:ident, $input:expr, $expected:expr) => {
+        #[cfg(feature = "transform")]
         #[test]
-        fn $test_name() -> Result<(), swc_ecma_parser::error::Error> {
+        fn $test_name() -> Result<(), Error> {
             test_helpers::testcase($input, $expected)
         }
     };
@@ -260,6 +2031,40 @@ testcase! {
   r#"console.log('hello')"#
 }
 
+testcase! {
+  keeps_the_default_export_in_place,
+  r#"export default <template>{{double 2}}</template>;
+     function double(n) { return n * 2; }
+     const half = (n) => n / 2;"#,
+  r#"import { template as template_UUID } from "@ember/template-compiler";
+     export default template_UUID(`{{double 2}}`, { eval() { return eval(arguments[0])} });
+     function double(n) { return n * 2; }
+     const half = (n) => n / 2;"#
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_default_export_at_the_end() {
+    let output = Preprocessor::new()
+        .process(
+            "import Foo from './foo';\n<template><Foo /></template>\nfunction double(n) { return n * 2; }",
+            Options {
+                default_export_placement: DefaultExportPlacement::End,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        test_helpers::normalize(&output.code.replace(IMPORT_ALIAS, "template_UUID")),
+        test_helpers::normalize(
+            r#"import { template as template_UUID } from "@ember/template-compiler";
+               import Foo from './foo';
+               function double(n) { return n * 2; }
+               export default template_UUID(`<Foo />`, { eval() { return eval(arguments[0])} });"#
+        )
+    );
+}
+
 testcase! {
   avoids_top_level_collision,
   r#"function template() {};
@@ -310,3 +2115,575 @@ testcase! {
          return template_UUID(``, { eval() { return eval(arguments[0]) } });
        }"#
 }
+
+testcase! {
+  preserves_import_attributes,
+  r#"import data from "./data.json" with { type: "json" };
+     export default <template>{{data.title}}</template>"#,
+  r#"import { template as template_UUID } from "@ember/template-compiler";
+     import data from "./data.json" with { type: "json" };
+     export default template_UUID(`{{data.title}}`, { eval() { return eval(arguments[0]) } });"#
+}
+
+testcase! {
+  preserves_import_attributes_without_templates,
+  r#"import data from "./data.json" with { type: "json" };
+     export * from "./styles.css" with { type: "css" };
+     console.log(data);"#,
+  r#"import data from "./data.json" with { type: "json" };
+     export * from "./styles.css" with { type: "css" };
+     console.log(data);"#
+}
+
+testcase! {
+  arrow_and_conditional_positions,
+  r#"export const pick = (cond) => cond ? <template>A</template> : <template>B</template>;
+     export function withDefault(x = () => <template>C</template>) { return x; }"#,
+  r#"import { template as template_UUID } from "@ember/template-compiler";
+     export const pick = (cond) => cond ? template_UUID(`A`, { eval() { return eval(arguments[0]) } }) : template_UUID(`B`, { eval() { return eval(arguments[0]) } });
+     export function withDefault(x = () => template_UUID(`C`, { eval() { return eval(arguments[0]) } })) { return x; }"#
+}
+
+testcase! {
+  object_and_array_positions,
+  r#"const routes = { index: <template>Hi</template>, nested: { list: [<template>A</template>, { deep: <template>B</template> }] } };
+     const views = { render() { return <template>C</template>; }, [<template>D</template>]: 1 };"#,
+  r#"import { template as template_UUID } from "@ember/template-compiler";
+     const routes = { index: template_UUID(`Hi`, { eval() { return eval(arguments[0]) } }), nested: { list: [template_UUID(`A`, { eval() { return eval(arguments[0]) } }), { deep: template_UUID(`B`, { eval() { return eval(arguments[0]) } }) }] } };
+     const views = { render() { return template_UUID(`C`, { eval() { return eval(arguments[0]) } }); }, [template_UUID(`D`, { eval() { return eval(arguments[0]) } })]: 1 };"#
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_inserts_imports_requested_by_the_transformer() {
+    let p = Preprocessor::new();
+    let output = p
+        .process(
+            "export default <template>Hi</template>;",
+            Options {
+                transformers: vec![Box::new(|content: &str, context: &TemplateContext| {
+                    let factory = context.request_import(
+                        "@glimmer/opcode-compiler",
+                        "templateFactory",
+                        "templateFactory",
+                    );
+                    Ok(format!("{factory}:{content}"))
+                })],
+                verify_output: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let imports = &output.metadata.requested_imports;
+    assert_eq!(imports.len(), 1);
+    assert_eq!(imports[0].module, "@glimmer/opcode-compiler");
+    assert_eq!(
+        &output.code[imports[0].range.start..imports[0].range.end],
+        format!(
+            r#"import {{ templateFactory as {} }} from "@glimmer/opcode-compiler";"#,
+            imports[0].local
+        )
+    );
+    assert!(output.code.contains(&format!("`{}:Hi`", imports[0].local)));
+    assert_eq!(
+        output.metadata.dependencies,
+        vec!["@ember/template-compiler", "@glimmer/opcode-compiler"]
+    );
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_requested_imports_with_the_same_hint() {
+    let output = Preprocessor::new()
+        .process(
+            "export const A = <template>A</template>;\nexport const B = <template>B</template>;",
+            Options {
+                transformers: vec![Box::new(|content: &str, context: &TemplateContext| {
+                    let module = if content == "A" { "./a" } else { "./b" };
+                    Ok(context.request_import(module, "helper", "helper"))
+                })],
+                verify_output: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let imports = &output.metadata.requested_imports;
+    assert_eq!(imports.len(), 2);
+    assert_ne!(imports[0].local, imports[1].local);
+    for import in imports {
+        assert_eq!(
+            &output.code[import.range.start..import.range.end],
+            format!(
+                r#"import {{ helper as {} }} from "{}";"#,
+                import.local, import.module
+            )
+        );
+    }
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_implicit_component_base_avoids_bound_names() {
+    let taken = format!("Component_{}", transformer::IMPORT_SUFFIX);
+    let src = format!("import {taken} from './component';\nclass A {{ <template>A</template> }}");
+    let output = Preprocessor::new()
+        .process(
+            &src,
+            Options {
+                implicit_component_base: Some(GLIMMER_COMPONENT.to_owned()),
+                verify_output: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let base = &output.metadata.requested_imports[0].local;
+    assert_ne!(base, &taken);
+    assert!(output.code.contains(&format!("import {taken} from")));
+    assert!(output.code.contains(&format!("class A extends {base} {{")));
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_implicit_component_base() {
+    let output = Preprocessor::new()
+        .process(
+            "class A { <template>A</template> }\nclass B extends Base { <template>B</template> }\nclass C {}",
+            Options {
+                implicit_component_base: Some(GLIMMER_COMPONENT.to_owned()),
+                verify_output: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let imports = &output.metadata.requested_imports;
+    assert_eq!(imports.len(), 1);
+    assert_eq!(imports[0].module, "@glimmer/component");
+    let base = &imports[0].local;
+    assert!(output.code.contains(&format!(
+        r#"import {{ default as {base} }} from "@glimmer/component";"#
+    )));
+    assert!(output.code.contains(&format!("class A extends {base} {{")));
+    assert!(output.code.contains("class B extends Base {"));
+    assert!(output.code.contains("class C {"));
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_warns_about_unresolved_references() {
+    let p = Preprocessor::new();
+    let output = p
+        .process(
+            "import Greeting from './greeting';\nfunction f(name) { return <template><Greeting @name={{name}} /> {{nmae}} <Missing /></template>; }\nlet other = 1;",
+            Options {
+                warn_unresolved: true,
+                verify_output: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let messages: Vec<&str> = output.warnings.iter().map(|w| w.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "`nmae` is used in the template but is not defined",
+            "`Missing` is used in the template but is not defined"
+        ]
+    );
+    assert!(output
+        .warnings
+        .iter()
+        .all(|w| w.code == "unresolved-reference"));
+    assert!(!output.code.contains("__content_tag_references__"));
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_downlevels_static_blocks_for_older_targets() {
+    let p = Preprocessor::new();
+    let output = p
+        .process(
+            "export class A { <template>Hi</template> }",
+            Options {
+                target: Some(EsVersion::Es2021),
+                verify_output: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert!(!output.code.contains("static"));
+    assert!(output.code.contains("component: A"));
+    assert_eq!(output.metadata.replacements.len(), 1);
+
+    let result = p.process(
+        "let a = class { <template>Hi</template> }",
+        Options {
+            target: Some(EsVersion::Es2021),
+            ..Default::default()
+        },
+    );
+    assert!(matches!(result, Err(Error::Transformer(_))));
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_typescript_class_members() {
+    let src = "import type { Owner } from './owner';
+export class A {
+  declare owner: Owner;
+  [key: string]: unknown;
+  <template>Hi</template>
+  name: string = 'a';
+}";
+    for (strip_types, target) in [
+        (false, None),
+        (false, Some(EsVersion::Es2021)),
+        (true, None),
+        (true, Some(EsVersion::Es2021)),
+    ] {
+        let output = Preprocessor::new()
+            .process(
+                src,
+                Options {
+                    strip_types,
+                    target,
+                    verify_output: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let case = format!(
+            "strip_types: {strip_types}, target: {target:?}\n{}",
+            output.code
+        );
+        for ts_only in [
+            "import type { Owner }",
+            "declare owner: Owner;",
+            "[key: string]: unknown;",
+            ": string",
+        ] {
+            assert_eq!(output.code.contains(ts_only), !strip_types, "{case}");
+        }
+        assert!(
+            output.code.contains("name = 'a'") || output.code.contains("name = \"a\""),
+            "{case}"
+        );
+        assert_eq!(output.metadata.replacements.len(), 1, "{case}");
+    }
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_strips_types_without_templates() {
+    let output = Preprocessor::new()
+        .process(
+            "let x: number = 1;",
+            Options {
+                strip_types: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert!(!output.code.contains("number"));
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_routes_tags_to_their_handler() {
+    let mut tags = BTreeMap::new();
+    tags.insert(
+        "template".to_owned(),
+        TagHandler {
+            transformers: vec![Box::new(|content: &str, _: &TemplateContext| {
+                Ok(content.to_uppercase())
+            })],
+            import_module: "my-compiler".to_owned(),
+            import_specifier: "compile".to_owned(),
+        },
+    );
+    let p = Preprocessor::new();
+    let output = p
+        .process(
+            "export default <template>Hi</template>;",
+            Options {
+                tags,
+                verify_output: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let import = output.metadata.inserted_import.unwrap();
+    assert_eq!(import.module, "my-compiler");
+    assert_eq!(import.specifier, "compile");
+    assert!(output.code.contains("`HI`"));
+
+    let mut tags = BTreeMap::new();
+    tags.insert("style".to_owned(), TagHandler::default());
+    let result = p.process(
+        "export default <template>Hi</template>;",
+        Options {
+            tags,
+            ..Default::default()
+        },
+    );
+    assert!(matches!(result, Err(Error::InvalidOptions(_))));
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_leaves_files_without_templates_alone() {
+    let src = "export * from './a';\nexport { b as  c } from \"./b\" // é\n";
+    let p = Preprocessor::new();
+    let output = p
+        .process(
+            src,
+            Options {
+                inline_source_map: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    // the code as is, with the source map after it
+    let map = output.code.strip_prefix(src).unwrap();
+    assert!(map.starts_with("//# sourceMappingURL=data:application/json;base64,"));
+    assert!(!map.contains('\n'));
+    assert_eq!(output.metadata.dependencies, vec!["./a", "./b"]);
+    assert!(output.metadata.inserted_import.is_none());
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_skips_the_resolver() {
+    let src = format!("let {IMPORT_ALIAS} = 1;\nexport default <template>Hi</template>;");
+    let output = Preprocessor::new()
+        .process(
+            &src,
+            Options {
+                run_resolver_hygiene: false,
+                verify_output: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let import = output.metadata.inserted_import.unwrap();
+    assert_eq!(import.local, format!("{IMPORT_ALIAS}_1"));
+    assert!(output.code.contains(&format!("let {IMPORT_ALIAS} = 1;")));
+
+    let result = Preprocessor::builder()
+        .run_resolver_hygiene(false)
+        .warn_unresolved(true)
+        .build();
+    assert!(matches!(result, Err(Error::InvalidOptions(_))));
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_strip_types_needs_the_resolver() {
+    let result = Preprocessor::builder()
+        .run_resolver_hygiene(false)
+        .strip_types(true)
+        .build();
+    assert_eq!(
+        result.err().unwrap().to_string(),
+        "Invalid options: `strip_types` needs the resolver, which `run_resolver_hygiene: false` turns off"
+    );
+    let result = Preprocessor::new().process(
+        "let a: number = 1;",
+        Options {
+            run_resolver_hygiene: false,
+            strip_types: true,
+            ..Default::default()
+        },
+    );
+    assert!(matches!(result, Err(Error::InvalidOptions(_))));
+}
+
+// Every identifier in the input has to survive, spelled the same, no matter
+// what it collides with.
+#[cfg(feature = "transform")]
+#[test]
+fn test_never_renames_user_identifiers() {
+    use swc_ecma_ast::Ident;
+    use swc_ecma_parser::Syntax;
+    use swc_ecma_visit::Visit;
+
+    struct Names(Vec<String>);
+    impl Visit for Names {
+        fn visit_ident(&mut self, n: &Ident) {
+            self.0.push(n.sym.to_string());
+        }
+    }
+    fn names(src: &str) -> Vec<String> {
+        let p = Preprocessor::new();
+        let file = p
+            .source_map
+            .new_source_file(FileName::Anon, src.to_string());
+        let module = Parser::new(
+            Syntax::Typescript(Default::default()),
+            StringInput::from(&*file),
+            None,
+        )
+        .parse_module()
+        .unwrap();
+        let mut names = Names(vec![]);
+        module.visit_with(&mut names);
+        names.0
+    }
+
+    for (src, contents) in [
+        (
+            "function template() {};\nconsole.log(template());\nexport default <template>Hi</template>",
+            vec!["Hi".to_owned()],
+        ),
+        (
+            "export default function (template) {\n  console.log(template);\n  return <template>X</template>;\n};",
+            vec!["X".to_owned()],
+        ),
+        (
+            "import { template } from \"@ember/template-compiler\";\nlet x = <template>hello</template>;\nlet y = <template>{{x}}</template>",
+            vec!["hello".to_owned(), "{{x}}".to_owned()],
+        ),
+        (
+            format!("let {IMPORT_ALIAS} = 1;\nexport default <template>{{{{{IMPORT_ALIAS}}}}}</template>;")
+                .as_str(),
+            vec![format!("{{{{{IMPORT_ALIAS}}}}}")],
+        ),
+    ] {
+        let output = Preprocessor::new().process(src, Default::default()).unwrap();
+        let local = output.metadata.inserted_import.unwrap().local;
+        let output_names = names(&output.code);
+        for name in names(src) {
+            assert!(output_names.contains(&name), "`{name}` was renamed");
+        }
+        assert!(
+            !names(src).contains(&local),
+            "the inserted import shadows `{local}`"
+        );
+
+        // the same with the resolver, which only `warn_unresolved` and the
+        // like need, and a call for each template
+        let calls = test_helpers::template_calls(src).unwrap();
+        assert_eq!(calls.len(), contents.len(), "{src}");
+        for (call, content) in calls.iter().zip(&contents) {
+            assert!(
+                call.starts_with(&format!("template_UUID(`{content}`, {{")),
+                "{call} isn't for {content}"
+            );
+        }
+    }
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_detects_already_transformed_output() {
+    let p = Preprocessor::new();
+    let options = || Options {
+        detect_already_transformed: true,
+        ..Default::default()
+    };
+    let once = p
+        .process("export default <template>Hi</template>;", options())
+        .unwrap();
+    assert!(!once.metadata.already_transformed);
+    let twice = p.process(&once.code, options()).unwrap();
+    assert!(twice.metadata.already_transformed);
+    assert_eq!(twice.code, once.code);
+
+    let not_asked = p.process(&once.code, Default::default()).unwrap();
+    assert!(!not_asked.metadata.already_transformed);
+    let plain = p.process("let x = 1;", options()).unwrap();
+    assert!(!plain.metadata.already_transformed);
+}
+
+// Files without templates must come back byte-for-byte, since downstream
+// source maps and content hashes depend on it.
+#[cfg(feature = "transform")]
+#[test]
+fn test_files_without_templates_are_unchanged() {
+    let mut corpus: Vec<(String, String)> = [
+        "// only a comment",
+        "/* a */ let   x =   1 ; /* b */\n\n\n",
+        "const s = 'héllo wörld 👋';\r\nconst t = `${s}`;\r\n",
+        "\u{feff}export {};",
+        "@decorator class A {\n  // this has a comment\n  @foo thing = 1;\n}",
+        "function f(this: Context, x?: number): asserts x {}",
+        "const notATemplate = '<template>Hi</template>';",
+        "",
+    ]
+    .iter()
+    .map(|src| ("inline".to_owned(), src.to_string()))
+    .collect();
+    let samples = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("sample");
+    for entry in std::fs::read_dir(samples).unwrap() {
+        let path = entry.unwrap().path();
+        corpus.push((
+            path.display().to_string(),
+            std::fs::read_to_string(&path).unwrap(),
+        ));
+    }
+
+    for (name, src) in corpus {
+        let occurrences = match Preprocessor::new().parse(&src, Default::default()) {
+            Ok(occurrences) => occurrences,
+            // the corpus includes a file with a syntax error
+            Err(_) => continue,
+        };
+        if !occurrences.is_empty() {
+            continue;
+        }
+        let output = Preprocessor::new()
+            .process(
+                &src,
+                Options {
+                    simplify_imports: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(output.code, src, "{name} was changed");
+    }
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_inline_source_map_without_templates() {
+    let comment = "//# sourceMappingURL=data:application/json;base64,";
+    for src in ["let a = 1;\nlet b = 2;\n", "let a = 1;"] {
+        let output = Preprocessor::new()
+            .process(
+                src,
+                Options {
+                    inline_source_map: true,
+                    source_map_file: Some("a.js".to_owned()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let (code, encoded) = output.code.split_once(comment).unwrap();
+        assert_eq!(code.trim_end(), src.trim_end());
+        assert!(code.ends_with('\n'));
+        let map = general_purpose::URL_SAFE_NO_PAD.decode(encoded).unwrap();
+        assert_eq!(String::from_utf8(map).unwrap(), output.map);
+        let map: serde_json::Value = serde_json::from_str(&output.map).unwrap();
+        assert_eq!(map["file"], "a.js");
+    }
+
+    let spliced = Preprocessor::new()
+        .process_with_ranges(
+            "let a = 1;\n",
+            &[],
+            Options {
+                inline_source_map: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert!(spliced.code.starts_with(&format!("let a = 1;\n{comment}")));
+}
+
+testcase! {
+  parenthesizes_template_constructed_with_new,
+  r#"export const instance = new <template>X</template>();"#,
+  r#"import { template as template_UUID } from "@ember/template-compiler";
+     export const instance = new (template_UUID(`X`, { eval() { return eval(arguments[0]) } }))();"#
+}
@@ -1,28 +1,80 @@
-use serde::Serialize;
-use swc_common::{self, Span};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use swc_common::comments::{CommentKind, Comments, SingleThreadedComments};
+use swc_common::{self, BytePos, Span, Spanned};
 use swc_ecma_ast::{
-    ClassMember, ContentTagContent, ContentTagEnd, ContentTagExpression, ContentTagMember,
-    ContentTagStart,
+    AssignExpr, CallExpr, ClassMember, ContentTagContent, ContentTagEnd, ContentTagExpression,
+    ContentTagMember, ContentTagStart, ExportDefaultExpr, Expr, ExprStmt, ModuleItem, NewExpr,
+    Stmt, VarDeclarator,
 };
 use swc_ecma_visit::{Visit, VisitWith};
 
-
-#[derive(Default, Debug)]
-pub struct LocateContentTagVisitor {
+#[derive(Default)]
+pub struct LocateContentTagVisitor<'a> {
     pub occurrences: Vec<Occurrence>,
+    // when present, we collect the leading comments of each template
+    comments: Option<&'a SingleThreadedComments>,
+    // the start of the innermost statement we're inside of, whose leading
+    // comments also apply to any templates within it
+    statement_start: Option<BytePos>,
+    // the role of the expression we're about to visit, set by its parent
+    role: Option<ExpressionRole>,
+    // leave out each template's content
+    pub skip_content: bool,
 }
 
-#[derive(Eq, PartialEq, Debug, Serialize)]
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Copy)]
 #[serde(rename_all = "kebab-case")]
-enum ContentTagKind {
+pub enum ContentTagKind {
     Expression,
     ClassMember,
 }
 
-impl LocateContentTagVisitor {
+// What an expression template is used for, looking through parentheses.
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExpressionRole {
+    // `export default <template>`, or a bare `<template>` at the top level,
+    // which means the same
+    DefaultExport,
+    // the value of a variable, or the right hand side of an assignment
+    Assignment,
+    // an argument to a function call or `new`
+    Argument,
+    Other,
+}
+
+impl<'a> LocateContentTagVisitor<'a> {
+    pub fn with_comments(comments: &'a SingleThreadedComments) -> Self {
+        Self {
+            comments: Some(comments),
+            ..Default::default()
+        }
+    }
+
+    fn leading_comments(&self, span: &Span) -> Option<Vec<TemplateComment>> {
+        let comments = self.comments?;
+        let mut positions: Vec<BytePos> = self.statement_start.into_iter().collect();
+        if !positions.contains(&span.lo) {
+            positions.push(span.lo);
+        }
+        Some(
+            positions
+                .into_iter()
+                .flat_map(|pos| comments.get_leading(pos).unwrap_or_default())
+                .map(|comment| TemplateComment {
+                    kind: comment.kind,
+                    text: comment.text.to_string(),
+                    range: comment.span.into(),
+                })
+                .collect(),
+        )
+    }
+
     fn add_occurrence(
         &mut self,
         kind: ContentTagKind,
+        role: Option<ExpressionRole>,
         span: &Span,
         opening: &ContentTagStart,
         contents: &ContentTagContent,
@@ -30,35 +82,101 @@ impl LocateContentTagVisitor {
     ) {
         let occurrence = Occurrence {
             kind,
+            role,
             tag_name: "template".to_owned(),
-            contents: contents.value.to_string(),
+            contents: (!self.skip_content).then(|| contents.value.to_string()),
             range: span.into(),
             start_range: opening.span.into(),
             content_range: contents.span.into(),
             end_range: closing.span.into(),
+            leading_comments: self.leading_comments(span),
+            // filled in by `set_line_prefixes`, which has the source
+            line_prefix: String::new(),
         };
 
         self.occurrences.push(occurrence);
     }
 }
 
-impl Visit for LocateContentTagVisitor {
-    fn visit_expr(&mut self, n: &swc_ecma_ast::Expr) {
+impl<'a> Visit for LocateContentTagVisitor<'a> {
+    fn visit_module_item(&mut self, n: &ModuleItem) {
+        let outer = self.statement_start.replace(n.span().lo);
+        if let ModuleItem::Stmt(Stmt::Expr(ExprStmt { expr, .. })) = n {
+            if let Expr::ContentTagExpression(_) = &**expr {
+                self.role = Some(ExpressionRole::DefaultExport);
+            }
+        }
+        n.visit_children_with(self);
+        self.statement_start = outer;
+    }
+
+    fn visit_stmt(&mut self, n: &Stmt) {
+        let outer = self.statement_start.replace(n.span().lo);
+        n.visit_children_with(self);
+        self.statement_start = outer;
+    }
+
+    fn visit_expr(&mut self, n: &Expr) {
+        let role = self.role.take().unwrap_or(ExpressionRole::Other);
         match n {
-            swc_ecma_ast::Expr::ContentTagExpression(ContentTagExpression {
+            Expr::ContentTagExpression(ContentTagExpression {
                 span,
                 opening,
                 contents,
                 closing,
             }) => {
-                self.add_occurrence(ContentTagKind::Expression, span, opening, contents, closing);
+                self.add_occurrence(
+                    ContentTagKind::Expression,
+                    Some(role),
+                    span,
+                    opening,
+                    contents,
+                    closing,
+                );
             }
+            Expr::Paren(_) => self.role = Some(role),
             _ => {}
         }
 
         n.visit_children_with(self);
     }
 
+    fn visit_export_default_expr(&mut self, n: &ExportDefaultExpr) {
+        self.role = Some(ExpressionRole::DefaultExport);
+        n.visit_children_with(self);
+    }
+
+    fn visit_var_declarator(&mut self, n: &VarDeclarator) {
+        n.name.visit_with(self);
+        self.role = Some(ExpressionRole::Assignment);
+        n.init.visit_with(self);
+        self.role = None;
+    }
+
+    fn visit_assign_expr(&mut self, n: &AssignExpr) {
+        n.left.visit_with(self);
+        self.role = Some(ExpressionRole::Assignment);
+        n.right.visit_with(self);
+    }
+
+    fn visit_call_expr(&mut self, n: &CallExpr) {
+        n.callee.visit_with(self);
+        for arg in &n.args {
+            self.role = Some(ExpressionRole::Argument);
+            arg.visit_with(self);
+        }
+        n.type_args.visit_with(self);
+    }
+
+    fn visit_new_expr(&mut self, n: &NewExpr) {
+        n.callee.visit_with(self);
+        for arg in n.args.iter().flatten() {
+            self.role = Some(ExpressionRole::Argument);
+            arg.visit_with(self);
+        }
+        n.type_args.visit_with(self);
+    }
+
     fn visit_class_member(&mut self, n: &ClassMember) {
         match n {
             ClassMember::ContentTagMember(ContentTagMember {
@@ -69,6 +187,7 @@ impl Visit for LocateContentTagVisitor {
             }) => {
                 self.add_occurrence(
                     ContentTagKind::ClassMember,
+                    None,
                     span,
                     opening,
                     contents,
@@ -82,25 +201,258 @@ impl Visit for LocateContentTagVisitor {
     }
 }
 
-#[derive(Serialize, Debug, Eq, PartialEq)]
-#[serde(rename_all = "camelCase")]
+// What VS Code shows tabs as, for `Options::tab_width`.
+pub(crate) const DEFAULT_TAB_WIDTH: usize = 4;
+
+// Where a character column of `line` is in an editor that puts tabs at the
+// next multiple of `tab_width`.
+pub(crate) fn visual_column(line: &str, column: usize, tab_width: usize) -> usize {
+    let tab_width = tab_width.max(1);
+    line.chars().take(column).fold(0, |visual, c| {
+        if c == '\t' {
+            (visual / tab_width + 1) * tab_width
+        } else {
+            visual + 1
+        }
+    })
+}
+
+// The visitor's ranges are from the spans, which count from the start of the
+// source map; this makes them offsets into the source of the file at
+// `start_pos`, which only the first file in a source map gets for free.
+pub(crate) fn make_relative(occurrences: &mut [Occurrence], start_pos: BytePos) {
+    let by = start_pos.0 as usize - 1;
+    for occurrence in occurrences {
+        for range in [
+            &mut occurrence.range,
+            &mut occurrence.start_range,
+            &mut occurrence.content_range,
+            &mut occurrence.end_range,
+        ] {
+            *range = range.shifted_back(by);
+        }
+        for comment in occurrence.leading_comments.iter_mut().flatten() {
+            comment.range = comment.range.shifted_back(by);
+        }
+    }
+}
+
+pub(crate) fn set_line_prefixes(occurrences: &mut [Occurrence], src: &str) {
+    for occurrence in occurrences {
+        let start = occurrence.range.start;
+        let line_start = src[..start].rfind('\n').map_or(0, |pos| pos + 1);
+        occurrence.line_prefix = src[line_start..start].to_owned();
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Occurrence {
-    #[serde(rename = "type")]
-    kind: ContentTagKind,
-    tag_name: String,
-    contents: String,
-    range: Range,
+    pub(crate) kind: ContentTagKind,
+    // only for expression templates
+    pub(crate) role: Option<ExpressionRole>,
+    pub(crate) tag_name: String,
+    // left out when parsing with `Options::include_content: false`
+    pub(crate) contents: Option<String>,
+    pub(crate) range: Range,
     // the span of the opening "<template>" tag
-    start_range: Range,
-    content_range: Range,
+    pub(crate) start_range: Range,
+    pub(crate) content_range: Range,
     // the span of the closing "</template>" tag
-    end_range: Range,
+    pub(crate) end_range: Range,
+    // only collected when `Options::leading_comments` is set
+    pub(crate) leading_comments: Option<Vec<TemplateComment>>,
+    // what comes before the opening tag on its line
+    pub(crate) line_prefix: String,
+}
+
+impl Occurrence {
+    pub fn kind(&self) -> ContentTagKind {
+        self.kind
+    }
+
+    pub fn role(&self) -> Option<ExpressionRole> {
+        self.role
+    }
+
+    pub fn tag_name(&self) -> &str {
+        &self.tag_name
+    }
+
+    // Empty when parsed with `Options::include_content: false`, which
+    // `content_slice` doesn't mind.
+    pub fn contents(&self) -> &str {
+        self.contents.as_deref().unwrap_or_default()
+    }
+
+    pub fn range(&self) -> Range {
+        self.range
+    }
+
+    pub fn content_range(&self) -> Range {
+        self.content_range
+    }
+
+    pub fn start_range(&self) -> Range {
+        self.start_range
+    }
+
+    pub fn end_range(&self) -> Range {
+        self.end_range
+    }
+
+    // The delimiters one at a time, for highlighters and for fixes that only
+    // rewrite one of the tags. The opening tag runs from the `<` to just past
+    // the `>`, so it will include any attributes.
+    pub fn opening_start(&self) -> usize {
+        self.start_range.start
+    }
+
+    pub fn opening_end(&self) -> usize {
+        self.start_range.end
+    }
+
+    pub fn closing_start(&self) -> usize {
+        self.end_range.start
+    }
+
+    pub fn closing_end(&self) -> usize {
+        self.end_range.end
+    }
+
+    // The whitespace that the line with the opening tag starts with, for
+    // re-indenting content to match.
+    pub fn indentation(&self) -> &str {
+        let indented = self.line_prefix.trim_start_matches([' ', '\t']);
+        &self.line_prefix[..self.line_prefix.len() - indented.len()]
+    }
+
+    // Everything before the opening tag on its line, like `  let x = `.
+    pub fn line_prefix(&self) -> &str {
+        &self.line_prefix
+    }
+
+    // The column of the opening tag, in characters.
+    pub fn column(&self) -> usize {
+        self.line_prefix.chars().count()
+    }
+
+    // Just the name in the opening tag, like `template` in `<template>`.
+    pub fn tag_name_range(&self) -> Range {
+        let start = self.start_range.start + 1;
+        Range {
+            start,
+            end: start + self.tag_name.len(),
+        }
+    }
+
+    // The template's content within `src`, which must be the source this
+    // was parsed from.
+    pub fn content_slice<'a>(&self, src: &'a str) -> Result<&'a str, SliceError> {
+        let slice = self.content_range.slice(src)?;
+        if self
+            .contents
+            .as_ref()
+            .map_or(false, |contents| slice != contents)
+        {
+            return Err(SliceError::Mismatch(self.content_range));
+        }
+        Ok(slice)
+    }
+
+    // The whole tag within `src`, from `<template>` to `</template>`.
+    pub fn full_slice<'a>(&self, src: &'a str) -> Result<&'a str, SliceError> {
+        let slice = self.range.slice(src)?;
+        let opening = format!("<{}", self.tag_name);
+        let closing = format!("</{}>", self.tag_name);
+        if !slice.starts_with(&opening) || !slice.ends_with(&closing) {
+            return Err(SliceError::Mismatch(self.range));
+        }
+        Ok(slice)
+    }
+
+    // Whether the byte at `offset` is part of the tag.
+    pub fn contains(&self, offset: usize) -> bool {
+        self.range.contains(offset)
+    }
+}
+
+// Why an `Occurrence` couldn't be sliced out of a source string. These mean
+// that the string isn't the one the occurrence was parsed from.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SliceError {
+    OutOfBounds { range: Range, len: usize },
+    NotCharBoundary(usize),
+    // the slice doesn't have the content we parsed there
+    Mismatch(Range),
+}
+
+impl fmt::Display for SliceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SliceError::OutOfBounds { range, len } => write!(
+                f,
+                "range {}..{} is out of bounds for a source of {len} bytes",
+                range.start, range.end
+            ),
+            SliceError::NotCharBoundary(offset) => {
+                write!(f, "byte {offset} is not on a character boundary")
+            }
+            SliceError::Mismatch(range) => write!(
+                f,
+                "range {}..{} doesn't hold the template it was parsed from",
+                range.start, range.end
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct TemplateComment {
+    pub(crate) kind: CommentKind,
+    pub(crate) text: String,
+    pub(crate) range: Range,
 }
 
-#[derive(Serialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
 pub struct Range {
-    start: usize,
-    end: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Range {
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    fn shifted_back(self, by: usize) -> Range {
+        Range {
+            start: self.start - by,
+            end: self.end - by,
+        }
+    }
+
+    pub fn slice<'a>(&self, src: &'a str) -> Result<&'a str, SliceError> {
+        if self.start > self.end || self.end > src.len() {
+            return Err(SliceError::OutOfBounds {
+                range: *self,
+                len: src.len(),
+            });
+        }
+        for offset in [self.start, self.end] {
+            if !src.is_char_boundary(offset) {
+                return Err(SliceError::NotCharBoundary(offset));
+            }
+        }
+        Ok(&src[self.start..self.end])
+    }
 }
 
 impl From<&Span> for Range {
@@ -129,12 +481,15 @@ fn test_basic_example() {
         .unwrap();
     let expected = Occurrence {
         kind: ContentTagKind::Expression,
+        role: Some(ExpressionRole::DefaultExport),
         tag_name: "template".into(),
-        contents: "Hello!".into(),
+        contents: Some("Hello!".into()),
         range: Range { start: 0, end: 27 },
         start_range: Range { start: 0, end: 10 },
         content_range: Range { start: 10, end: 16 },
         end_range: Range { start: 16, end: 27 },
+        leading_comments: None,
+        line_prefix: "".into(),
     };
     assert_eq!(output, vec![expected]);
 }
@@ -151,12 +506,15 @@ fn test_expression_position() {
 
     let expected = vec![Occurrence {
         kind: ContentTagKind::Expression,
+        role: Some(ExpressionRole::Assignment),
         tag_name: "template".into(),
-        contents: "Hello!".into(),
+        contents: Some("Hello!".into()),
         range: Range { start: 12, end: 39 },
         start_range: Range { start: 12, end: 22 },
         content_range: Range { start: 22, end: 28 },
         end_range: Range { start: 28, end: 39 },
+        leading_comments: None,
+        line_prefix: "const tpl = ".into(),
     }];
 
     assert_eq!(output, expected);
@@ -178,12 +536,15 @@ fn test_inside_class_body() {
 
     let expected = vec![Occurrence {
         kind: ContentTagKind::ClassMember,
+        role: None,
         tag_name: "template".into(),
-        contents: "Hello!".into(),
+        contents: Some("Hello!".into()),
         range: Range { start: 49, end: 76 },
         start_range: Range { start: 49, end: 59 },
         content_range: Range { start: 59, end: 65 },
         end_range: Range { start: 65, end: 76 },
+        leading_comments: None,
+        line_prefix: " ".repeat(20),
     }];
 
     assert_eq!(output, expected);
@@ -207,12 +568,15 @@ fn test_preceded_by_a_slash_character() {
 
     let expected = vec![Occurrence {
         kind: ContentTagKind::Expression,
+        role: Some(ExpressionRole::DefaultExport),
         tag_name: "template".into(),
-        contents: "Hello!".into(),
+        contents: Some("Hello!".into()),
         range: Range { start: 65, end: 92 },
         start_range: Range { start: 65, end: 75 },
         content_range: Range { start: 75, end: 81 },
         end_range: Range { start: 81, end: 92 },
+        leading_comments: None,
+        line_prefix: " ".repeat(18),
     }];
 
     assert_eq!(output, expected);
@@ -233,12 +597,15 @@ fn test_template_inside_a_regexp() {
 
     let expected = vec![Occurrence {
         kind: ContentTagKind::Expression,
+        role: Some(ExpressionRole::DefaultExport),
         tag_name: "template".into(),
-        contents: "Hello!".into(),
+        contents: Some("Hello!".into()),
         range: Range { start: 67, end: 94 },
         start_range: Range { start: 67, end: 77 },
         content_range: Range { start: 77, end: 83 },
         end_range: Range { start: 83, end: 94 },
+        leading_comments: None,
+        line_prefix: " ".repeat(18),
     }];
 
     assert_eq!(output, expected);
@@ -265,11 +632,170 @@ fn test_inner_expression() {
         vec![Occurrence {
             range: Range { start: 13, end: 39 },
             content_range: Range { start: 23, end: 28 },
-            contents: "Hello".into(),
+            contents: Some("Hello".into()),
             end_range: Range { start: 28, end: 39 },
             start_range: Range { start: 13, end: 23 },
             tag_name: "template".into(),
-            kind: ContentTagKind::Expression
+            kind: ContentTagKind::Expression,
+            role: Some(ExpressionRole::Argument),
+            leading_comments: None,
+            line_prefix: "let x = doIt(".into(),
         }]
     );
 }
+
+#[test]
+fn test_leading_comments() {
+    let p = Preprocessor::new();
+    let output = p
+        .parse(
+            r#"// template-lint-disable no-bare-strings
+               /** The greeting */
+               export const Greeting = /* inline */ <template>Hello!</template>;
+               class A {
+                 // member comment
+                 <template>Hi</template>
+               }
+               <template>No comments</template>
+            "#,
+            crate::Options {
+                leading_comments: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let texts: Vec<Vec<&str>> = output
+        .iter()
+        .map(|o| {
+            o.leading_comments
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|c| c.text.as_str())
+                .collect()
+        })
+        .collect();
+    assert_eq!(
+        texts,
+        vec![
+            vec![
+                " template-lint-disable no-bare-strings",
+                "* The greeting ",
+                " inline "
+            ],
+            vec![" member comment"],
+            vec![],
+        ]
+    );
+    let first = &output[0].leading_comments.as_ref().unwrap()[0];
+    assert_eq!(first.kind, CommentKind::Line);
+    assert_eq!(first.range, Range { start: 0, end: 40 });
+}
+
+#[test]
+fn test_expression_roles() {
+    let p = Preprocessor::new();
+    let output = p
+        .parse(
+            r#"export default (<template>A</template>);
+               x = <template>B</template>;
+               render(<template>C</template>);
+               [<template>D</template>];
+               let routes = { index: <template>E</template> };"#,
+            Default::default(),
+        )
+        .unwrap();
+    let roles: Vec<_> = output.iter().map(|o| o.role().unwrap()).collect();
+    assert_eq!(
+        roles,
+        vec![
+            ExpressionRole::DefaultExport,
+            ExpressionRole::Assignment,
+            ExpressionRole::Argument,
+            ExpressionRole::Other,
+            ExpressionRole::Other,
+        ]
+    );
+}
+
+#[test]
+fn test_slices() {
+    let src = "let é = <template>Hé</template>;";
+    let p = Preprocessor::new();
+    let output = p.parse(src, Default::default()).unwrap();
+    let occurrence = &output[0];
+    assert_eq!(occurrence.content_slice(src).unwrap(), "Hé");
+    assert_eq!(
+        occurrence.full_slice(src).unwrap(),
+        "<template>Hé</template>"
+    );
+    assert!(occurrence.contains(occurrence.range().start));
+    assert!(!occurrence.contains(occurrence.range().end));
+
+    assert_eq!(
+        occurrence.content_slice("short"),
+        Err(SliceError::OutOfBounds {
+            range: occurrence.content_range(),
+            len: 5
+        })
+    );
+    let shifted = format!("x{src}");
+    assert_eq!(
+        occurrence.full_slice(&shifted),
+        Err(SliceError::Mismatch(occurrence.range()))
+    );
+    assert_eq!(
+        Range { start: 4, end: 5 }.slice(src),
+        Err(SliceError::NotCharBoundary(5))
+    );
+}
+
+#[test]
+fn test_tag_positions() {
+    let src = "let x = <template>Hi</template>;";
+    let p = Preprocessor::new();
+    let occurrence = &p.parse(src, Default::default()).unwrap()[0];
+    assert_eq!(
+        &src[occurrence.opening_start()..occurrence.opening_end()],
+        "<template>"
+    );
+    assert_eq!(
+        &src[occurrence.closing_start()..occurrence.closing_end()],
+        "</template>"
+    );
+    assert_eq!(occurrence.tag_name_range().slice(src).unwrap(), "template");
+}
+
+#[test]
+fn test_indentation() {
+    let src = "class A {\n\t  static x = <template>Hi</template>;\n}";
+    let p = Preprocessor::new();
+    let occurrence = &p.parse(src, Default::default()).unwrap()[0];
+    assert_eq!(occurrence.indentation(), "\t  ");
+    assert_eq!(occurrence.line_prefix(), "\t  static x = ");
+    assert_eq!(occurrence.column(), 14);
+}
+
+#[test]
+fn test_parse_twice() {
+    let first = "let a = 1;\nlet x = <template>Hi</template>;";
+    let src = "// hi\nlet y = <template>Bye</template>;";
+    let options = || crate::Options {
+        leading_comments: true,
+        ..Default::default()
+    };
+    let fresh = Preprocessor::new().parse(src, options()).unwrap();
+
+    let p = Preprocessor::new();
+    p.parse(first, options()).unwrap();
+    let again = p.parse(src, options()).unwrap();
+    assert_eq!(again, fresh);
+    assert_eq!(
+        again[0].range().slice(src).unwrap(),
+        "<template>Bye</template>"
+    );
+    assert_eq!(again[0].line_prefix(), "let y = ");
+    let comments = again[0].leading_comments.as_ref().unwrap();
+    assert_eq!(comments[0].range.slice(src).unwrap(), "// hi");
+}
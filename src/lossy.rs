@@ -0,0 +1,138 @@
+// Decoding sources that aren't all valid UTF-8, for `process_bytes`. Each
+// invalid sequence becomes U+FFFD, like `String::from_utf8_lossy` does, and
+// we keep track of where, so that callers hear about it instead of the file
+// failing to load or quietly changing.
+
+use std::borrow::Cow;
+
+use crate::locate::Range;
+
+// An invalid sequence and the U+FFFD it became.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) struct Replaced {
+    // in the bytes we were given
+    pub input: Range,
+    // in the decoded source
+    pub decoded: Range,
+}
+
+pub(crate) fn decode(src: &[u8]) -> (Cow<'_, str>, Vec<Replaced>) {
+    if let Ok(valid) = std::str::from_utf8(src) {
+        return (Cow::Borrowed(valid), vec![]);
+    }
+    let mut decoded = String::with_capacity(src.len());
+    let mut replaced = vec![];
+    let mut at = 0;
+    for chunk in src.utf8_chunks() {
+        decoded.push_str(chunk.valid());
+        at += chunk.valid().len();
+        if !chunk.invalid().is_empty() {
+            replaced.push(Replaced {
+                input: Range {
+                    start: at,
+                    end: at + chunk.invalid().len(),
+                },
+                decoded: Range {
+                    start: decoded.len(),
+                    end: decoded.len() + char::REPLACEMENT_CHARACTER.len_utf8(),
+                },
+            });
+            decoded.push(char::REPLACEMENT_CHARACTER);
+            at += chunk.invalid().len();
+        }
+    }
+    (Cow::Owned(decoded), replaced)
+}
+
+// Where `range` of the decoded `src` is, in the format of
+// `Preprocessor::location`: 1-based lines and columns counted in characters.
+// It's worked out from the offsets instead of looked up in a source map, so
+// it doesn't depend on which file `process` added last.
+pub(crate) fn location(name: &str, src: &str, range: Range) -> String {
+    let line_col = |offset: usize| {
+        let line_start = src[..offset].rfind('\n').map_or(0, |i| i + 1);
+        (
+            src[..offset].matches('\n').count() + 1,
+            src[line_start..offset].chars().count() + 1,
+        )
+    };
+    let (start_line, start_column) = line_col(range.start);
+    let (end_line, end_column) = line_col(range.end);
+    format!("{name}:{start_line}:{start_column}: {end_line}:{end_column}")
+}
+
+#[test]
+fn test_decode() {
+    let (decoded, replaced) = decode(b"let a = 1;");
+    assert!(matches!(decoded, Cow::Borrowed("let a = 1;")));
+    assert!(replaced.is_empty());
+
+    let (decoded, replaced) = decode(b"'caf\xe9' + '\xff\xfe'");
+    assert_eq!(decoded, "'caf\u{FFFD}' + '\u{FFFD}\u{FFFD}'");
+    assert_eq!(
+        replaced,
+        vec![
+            Replaced {
+                input: Range { start: 4, end: 5 },
+                decoded: Range { start: 4, end: 7 },
+            },
+            Replaced {
+                input: Range { start: 10, end: 11 },
+                decoded: Range { start: 12, end: 15 },
+            },
+            Replaced {
+                input: Range { start: 11, end: 12 },
+                decoded: Range { start: 15, end: 18 },
+            },
+        ]
+    );
+}
+
+#[cfg(test)]
+use crate::Preprocessor;
+
+#[test]
+fn test_process_bytes() {
+    let p = Preprocessor::new();
+    let output = p
+        .process_bytes(
+            b"let s = 'caf\xe9';\n<template>Hi</template>",
+            Default::default(),
+        )
+        .unwrap();
+    assert!(output.code.contains("'caf\u{FFFD}'"), "{}", output.code);
+    assert_eq!(output.warnings.len(), 1);
+    let warning = &output.warnings[0];
+    assert_eq!(warning.code, "invalid-utf8");
+    assert!(
+        warning.message.contains("bytes 12..13"),
+        "{}",
+        warning.message
+    );
+    assert_eq!(warning.range, Range { start: 12, end: 15 });
+    assert!(
+        warning.location.ends_with(":1:13: 1:14"),
+        "{}",
+        warning.location
+    );
+}
+
+#[test]
+fn test_process_bytes_after_another_file() {
+    let src = b"let a = 1;\nlet s = '\xff';\n<template>Hi</template>";
+    let fresh = Preprocessor::new()
+        .process_bytes(src, Default::default())
+        .unwrap();
+    let p = Preprocessor::new();
+    p.process("let x = <template>Bye</template>;", Default::default())
+        .unwrap();
+    let output = p.process_bytes(src, Default::default()).unwrap();
+    assert_eq!(output.warnings, fresh.warnings);
+    let warning = &output.warnings[0];
+    assert_eq!(warning.range, Range { start: 20, end: 23 });
+    assert!(
+        warning.location.ends_with(":2:10: 2:11"),
+        "{}",
+        warning.location
+    );
+}
@@ -1,37 +1,208 @@
-use content_tag::{Options, Preprocessor};
+use content_tag::{Emit, Error, Options, Preprocessor, Profiler};
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 
 use swc_common::errors::{ColorConfig, Handler};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::EnvFilter;
+
+mod cli;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    let verbose = args.iter().any(|arg| arg == "--verbose");
+    args.retain(|arg| arg != "--verbose");
+    let profile = take_path_flag(&mut args, "--profile", "out.json");
+    let out_dir = take_path_flag(&mut args, "--out-dir", "dist");
+    let report = take_path_flag(&mut args, "--report", "report.json");
+    let options = take_path_flag(&mut args, "--options", "content-tag.json");
+    let flags = FileFlags {
+        explain: take_flag(&mut args, "--explain"),
+        diff: take_flag(&mut args, "--diff"),
+        eval_report: take_path_flag(&mut args, "--eval-report", "evals.json"),
+        cache_dir: take_path_flag(&mut args, "--cache-dir", ".content-tag-cache"),
+    };
+    let profiler = profile.as_ref().map(|_| Profiler::new());
+    init_logging(verbose, profiler.as_ref());
+    // Bazel starts workers with this flag, after any arguments of their own
+    if args.iter().any(|arg| arg == "--persistent_worker") {
+        exit(cli::worker::run(&args[1..]));
+    }
     if args.len() < 2 {
         println!("Must pass input filename");
         exit(-1);
     }
-    let filename: PathBuf = args[1].clone().into();
+    let code = match args[1].as_str() {
+        "cache" => cli::cache::run(&args[2..], flags.cache_dir.as_deref()),
+        "diff-templates" => cli::diff::run(&args[2..]),
+        "generate" => cli::generate::run(&args[2..]),
+        "scan" => cli::scan::run(&args[2..]),
+        "serve" => cli::serve::run(&args[2..]),
+        "migrate" => cli::migrate::run(&args[2..]),
+        "split" => cli::split::run(&args[2..]),
+        "strict-report" => cli::strict::run(&args[2..]),
+        dir if Path::new(dir).is_dir() => cli::dir::run(
+            Path::new(dir),
+            out_dir.as_deref(),
+            flags.cache_dir.as_deref(),
+            report.as_deref(),
+            options.as_deref(),
+        ),
+        _ => process_file(args[1].clone().into(), flags),
+    };
+    if let (Some(out), Some(profiler)) = (profile, profiler) {
+        if let Err(err) = fs::write(&out, profiler.chrome_trace()) {
+            eprintln!("Can't write {}: {err}", out.display());
+            exit(1);
+        }
+    }
+    exit(code);
+}
+
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let present = args.iter().any(|arg| arg == flag);
+    args.retain(|arg| arg != flag);
+    present
+}
+
+// Removes `flag <path>` from the arguments, returning the path.
+fn take_path_flag(args: &mut Vec<String>, flag: &str, example: &str) -> Option<PathBuf> {
+    match args.iter().position(|arg| arg == flag) {
+        Some(index) if index + 1 < args.len() => {
+            let out = PathBuf::from(args.remove(index + 1));
+            args.remove(index);
+            Some(out)
+        }
+        Some(_) => {
+            eprintln!("{flag} needs a path, like {flag} {example}");
+            exit(2);
+        }
+        None => None,
+    }
+}
 
-    let src = fs::read_to_string(filename.clone()).unwrap();
+// Logs go to stderr. `CONTENT_TAG_LOG` takes a filter like `RUST_LOG` does;
+// otherwise `--verbose` shows how long each phase of each file takes. The
+// profiler sees every span regardless of the filter.
+fn init_logging(verbose: bool, profiler: Option<&Profiler>) {
+    let filter = EnvFilter::try_from_env("CONTENT_TAG_LOG")
+        .unwrap_or_else(|_| EnvFilter::new(if verbose { "content_tag=debug" } else { "warn" }));
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_span_events(FmtSpan::CLOSE)
+                .with_writer(std::io::stderr)
+                .with_filter(filter),
+        )
+        .with(profiler.map(|profiler| profiler.layer()))
+        .init();
+}
+
+// How to process a single file.
+struct FileFlags {
+    // print the output with markers, without the source map, and a legend for
+    // the markers on stderr
+    explain: bool,
+    // print a unified diff from the file to the output
+    diff: bool,
+    // also write where the output uses `eval` here as JSON, for reviewing
+    // what a CSP without `unsafe-eval` would break
+    eval_report: Option<PathBuf>,
+    // print unchanged files from the cache in here, when only the output is
+    // needed
+    cache_dir: Option<PathBuf>,
+}
+
+fn process_file(filename: PathBuf, flags: FileFlags) -> i32 {
+    let src = match fs::read_to_string(&filename) {
+        Ok(src) => src,
+        Err(err) => {
+            eprintln!("Can't read {}: {err}", filename.display());
+            return 1;
+        }
+    };
+
+    // the output depends on the file name through the source map
+    let fingerprint = format!("filename={}\ninline_source_map=true", filename.display());
+    let plain = !flags.explain && !flags.diff && flags.eval_report.is_none();
+    let cache = match flags.cache_dir.filter(|_| plain) {
+        Some(dir) => match cli::cache::Cache::open(&dir) {
+            Ok(cache) => Some(cache),
+            Err(err) => {
+                eprintln!("Can't use the cache in {}: {err}", dir.display());
+                None
+            }
+        },
+        None => None,
+    };
+    if let Some(code) = cache
+        .as_ref()
+        .and_then(|cache| cache.get(&src, &fingerprint))
+    {
+        println!("{code}");
+        return 0;
+    }
 
     let p = Preprocessor::new();
 
     let result = p.process(
         &src,
         Options {
-            filename: Some(filename),
-            inline_source_map: true,
+            filename: Some(filename.clone().into()),
+            inline_source_map: !flags.explain,
+            emit: if flags.diff {
+                Emit::UnifiedDiff
+            } else {
+                Emit::Code
+            },
+            ..Default::default()
         },
     );
 
     match result {
-        Ok(output) => println!("{}", output.code),
-        Err(err) => {
+        Ok(output) if flags.diff => {
+            print!("{}", output.code);
+            0
+        }
+        Ok(output) if flags.explain => {
+            let explanation = output.explain(&src);
+            println!("{}", explanation.code);
+            for line in explanation.legend {
+                eprintln!("{line}");
+            }
+            0
+        }
+        Ok(output) => {
+            println!("{}", output.code);
+            if let Some(cache) = cache {
+                if let Err(err) = cache.put(&src, &fingerprint, &output.code) {
+                    eprintln!("Can't write to the cache: {err}");
+                }
+            }
+            if let Some(out) = flags.eval_report {
+                let report = serde_json::json!({
+                    "file": filename,
+                    "evalUsages": output.metadata.eval_usages,
+                });
+                if let Err(err) = fs::write(&out, serde_json::to_string_pretty(&report).unwrap()) {
+                    eprintln!("Can't write {}: {err}", out.display());
+                    return 1;
+                }
+            }
+            0
+        }
+        Err(Error::Parse(err)) => {
             let handler =
                 Handler::with_tty_emitter(ColorConfig::Auto, true, false, Some(p.source_map()));
             err.into_diagnostic(&handler).emit();
+            0
+        }
+        Err(err) => {
+            eprintln!("{}", p.describe_error(&err));
+            1
         }
     }
 }
@@ -0,0 +1,402 @@
+use serde::Serialize;
+#[cfg(feature = "transform")]
+use swc_common::Span;
+#[cfg(feature = "transform")]
+use swc_ecma_ast::{CallExpr, Callee, Expr, Module};
+#[cfg(feature = "transform")]
+use swc_ecma_visit::{Visit, VisitWith};
+
+use crate::exports::ExportMap;
+use crate::locate::Range;
+#[cfg(feature = "transform")]
+use crate::transformer::ImportRequest;
+
+/// Describes what `process` added to the module, so that tools which
+/// post-process our output (for example to merge imports) don't need to
+/// re-parse it. All ranges refer to bytes in the emitted code, except
+/// `Replacement::original_range` which refers to the input.
+#[derive(Serialize, Debug, Default, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Metadata {
+    pub inserted_import: Option<InsertedImport>,
+    // imports that the transformer asked for, after `inserted_import`
+    pub requested_imports: Vec<InsertedImport>,
+    pub replacements: Vec<Replacement>,
+    // the local names of inserted imports that were dropped because nothing
+    // used them, with `Options::prune_unused_imports`
+    pub removed_imports: Vec<String>,
+    // every module the output imports, including the ones we inserted, so
+    // bundlers can record dependencies without parsing `code`
+    pub dependencies: Vec<String>,
+    // each `eval` we generated for a template's scope, for security reviews
+    // and CSP policies
+    pub eval_usages: Vec<EvalUsage>,
+    // the input had no templates but already calls the template compiler's
+    // import, so it looks like it went through `process` before. Only with
+    // `Options::detect_already_transformed`.
+    pub already_transformed: bool,
+    // what the module exports, with `Options::collect_exports`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exports: Option<ExportMap>,
+}
+
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InsertedImport {
+    pub module: String,
+    pub specifier: String,
+    pub local: String,
+    pub range: Range,
+}
+
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Replacement {
+    // the span of the content tag in the input
+    pub original_range: Range,
+    // the span of the generated call in the output
+    pub range: Range,
+}
+
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EvalUsage {
+    // the span of the content tag in the input
+    pub original_range: Range,
+    // the span of the `eval(...)` call in the output
+    pub range: Range,
+    // where `range` starts in the output: a 1-based line and a 0-based
+    // column counted in characters, like `ErrorLoc`
+    pub line: usize,
+    pub column: usize,
+}
+
+// The code that the `eval` trick puts in each template's scope object.
+#[cfg(feature = "transform")]
+const EVAL_CALL: &str = "eval(arguments[0])";
+
+// The inserted identifier is unique, so rather than depending on the details
+// of how the emitter records source map positions we find our generated code
+// by scanning the output for it. `replaced` is the range of each content tag in
+// the input, in the order its call appears in `code`, which isn't the order of the input
+// when the transform moves templates (see `emitted_order`).
+#[cfg(feature = "transform")]
+pub(crate) fn collect(
+    code: &str,
+    import: Option<(&str, &str)>,
+    local: &str,
+    requested: &[ImportRequest],
+    replaced: &[Range],
+) -> Metadata {
+    let inserted_import =
+        import.and_then(|(module, specifier)| find_import(code, module, specifier, local));
+    let requested_imports = requested
+        .iter()
+        .filter_map(|request| {
+            find_import(code, &request.module, &request.specifier, &request.local)
+        })
+        .collect();
+
+    let callee = format!("{local}(");
+    let replacements: Vec<Replacement> = replaced
+        .iter()
+        .zip(generated_calls(code, local))
+        .map(|(original_range, range)| Replacement {
+            original_range: *original_range,
+            range,
+        })
+        .collect();
+    let eval_usages = replacements
+        .iter()
+        .filter_map(|replacement| eval_usage(code, callee.len(), replacement))
+        .collect();
+
+    Metadata {
+        inserted_import,
+        requested_imports,
+        replacements,
+        removed_imports: vec![],
+        dependencies: vec![],
+        eval_usages,
+        already_transformed: false,
+        exports: None,
+    }
+}
+
+// The spans in `replaced` in the order their calls are in `module`, which is
+// the order the emitter prints them in. The calls keep the span of the content
+// tag they replaced, but a default export moved to the end or a template
+// hoisted out of a static block comes after templates that were below it.
+#[cfg(feature = "transform")]
+pub(crate) fn emitted_order(module: &Module, replaced: &[Span]) -> Vec<Span> {
+    let mut finder = CallOrder {
+        replaced,
+        order: Vec::with_capacity(replaced.len()),
+    };
+    module.visit_with(&mut finder);
+    finder.order
+}
+
+#[cfg(feature = "transform")]
+struct CallOrder<'a> {
+    replaced: &'a [Span],
+    order: Vec<Span>,
+}
+
+#[cfg(feature = "transform")]
+impl Visit for CallOrder<'_> {
+    fn visit_call_expr(&mut self, n: &CallExpr) {
+        if let Callee::Expr(callee) = &n.callee {
+            if matches!(&**callee, Expr::Ident(_))
+                && self.replaced.contains(&n.span)
+                && !self.order.contains(&n.span)
+            {
+                self.order.push(n.span);
+            }
+        }
+        n.visit_children_with(self);
+    }
+}
+
+// The range of each call to `local` in `code`, in order.
+#[cfg(feature = "transform")]
+pub(crate) fn generated_calls(code: &str, local: &str) -> Vec<Range> {
+    let callee = format!("{local}(");
+    code.match_indices(&callee)
+        .filter_map(|(start, _)| {
+            call_end(code, start + callee.len()).map(|end| Range { start, end })
+        })
+        .collect()
+}
+
+// The `eval` in one generated call, if it has one. It's after the template
+// literal, which can contain anything.
+#[cfg(feature = "transform")]
+fn eval_usage(code: &str, callee_len: usize, replacement: &Replacement) -> Option<EvalUsage> {
+    let call = &code[replacement.range.start..replacement.range.end];
+    let literal_start = callee_len + call[callee_len..].find('`')?;
+    let literal_end = literal_end(call, literal_start + 1)?;
+    let start = replacement.range.start + literal_end + call[literal_end..].find(EVAL_CALL)?;
+    let line_start = code[..start].rfind('\n').map_or(0, |pos| pos + 1);
+    Some(EvalUsage {
+        original_range: replacement.original_range,
+        range: Range {
+            start,
+            end: start + EVAL_CALL.len(),
+        },
+        line: code[..start].matches('\n').count() + 1,
+        column: code[line_start..start].chars().count(),
+    })
+}
+
+// The offset just past the backtick that closes a template literal, given
+// the offset just past the one that opens it.
+#[cfg(feature = "transform")]
+fn literal_end(code: &str, mut pos: usize) -> Option<usize> {
+    let bytes = code.as_bytes();
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'\\' => pos += 1,
+            b'`' => return Some(pos + 1),
+            _ => {}
+        }
+        pos += 1;
+    }
+    None
+}
+
+// The import that binds `local`, which is the first place it appears as a
+// whole word inside an import declaration. Minified output has no spaces
+// around it: `import{template as template_abc}from"...";`.
+#[cfg(feature = "transform")]
+fn find_import(code: &str, module: &str, specifier: &str, local: &str) -> Option<InsertedImport> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    let (at, start) = code.match_indices(local).find_map(|(at, _)| {
+        if code[..at].ends_with(is_ident) || code[at + local.len()..].starts_with(is_ident) {
+            return None;
+        }
+        let start = code[..at].rfind("import")?;
+        (!code[start..at].contains(';')).then_some((at, start))
+    })?;
+    let end = at + code[at..].find(';')? + 1;
+    Some(InsertedImport {
+        module: module.to_owned(),
+        specifier: specifier.to_owned(),
+        local: local.to_owned(),
+        range: Range { start, end },
+    })
+}
+
+// Given the offset just past the opening paren of one of our generated calls,
+// find the offset just past its closing paren. The first argument is always a
+// template literal and the rest is one of our fixed snippets, with strings
+// like `moduleName` in it, so strings are the only place that can contain
+// unbalanced parens.
+#[cfg(feature = "transform")]
+fn call_end(code: &str, args_start: usize) -> Option<usize> {
+    let bytes = code.as_bytes();
+    let mut pos = args_start;
+    let mut depth = 1;
+    // the quote of the string or template literal we're in
+    let mut quote = None;
+    while pos < bytes.len() {
+        match (quote, bytes[pos]) {
+            (Some(_), b'\\') => pos += 1,
+            (Some(open), byte) if byte == open => quote = None,
+            (Some(_), _) => {}
+            (None, byte @ (b'`' | b'\'' | b'"')) => quote = Some(byte),
+            (None, b'(') => depth += 1,
+            (None, b')') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(pos + 1);
+                }
+            }
+            _ => {}
+        }
+        pos += 1;
+    }
+    None
+}
+
+#[cfg(all(test, feature = "transform"))]
+use crate::{Preprocessor, IMPORT_ALIAS};
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_reports_inserted_import_and_replacements() {
+    let p = Preprocessor::new();
+    let src = "let x = <template>(Hi</template>;\n<template>`)`</template>";
+    let output = p.process(src, Default::default()).unwrap();
+    let metadata = output.metadata;
+
+    let import = metadata.inserted_import.unwrap();
+    assert_eq!(import.module, "@ember/template-compiler");
+    assert_eq!(import.specifier, "template");
+    assert_eq!(import.local, IMPORT_ALIAS);
+    assert_eq!(
+        &output.code[import.range.start..import.range.end],
+        format!(r#"import {{ template as {IMPORT_ALIAS} }} from "@ember/template-compiler";"#)
+    );
+
+    assert_eq!(metadata.replacements.len(), 2);
+    assert_eq!(
+        metadata.replacements[0].original_range,
+        Range { start: 8, end: 32 }
+    );
+    let first = &metadata.replacements[0].range;
+    assert!(output.code[first.start..first.end].starts_with(&format!("{IMPORT_ALIAS}(`(Hi`")));
+    assert!(output.code[first.start..first.end].ends_with("})"));
+
+    assert_eq!(
+        metadata.replacements[1].original_range,
+        Range { start: 34, end: 58 }
+    );
+    let second = &metadata.replacements[1].range;
+    assert!(output.code[second.start..second.end].starts_with(&format!("{IMPORT_ALIAS}(`\\`)\\``")));
+    assert!(output.code[second.start..second.end].ends_with("})"));
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_call_ends_after_strings_with_parens() {
+    let outputs = Preprocessor::new()
+        .process_multi(
+            "export default <template>Hi</template>;",
+            &[crate::Profile {
+                module_name: Some(r#"app/")('(.gjs"#.to_owned()),
+                ..Default::default()
+            }],
+        )
+        .unwrap();
+    let output = &outputs[0];
+    let call = output.metadata.replacements[0].range;
+    assert!(output.code[..call.end].ends_with("})"));
+    assert_eq!(output.code[call.end..].trim(), ";");
+    assert_eq!(output.metadata.eval_usages.len(), 1);
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_reports_eval_usages() {
+    let p = Preprocessor::new();
+    let src = "import Foo from './foo';\nlet x = <template>eval(arguments[0])</template>;\nlet y = /* @scope Foo */ <template><Foo /></template>;";
+    let output = p
+        .process(
+            src,
+            crate::Options {
+                explicit_scope: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let usages = output.metadata.eval_usages;
+    assert_eq!(usages.len(), 1);
+    assert_eq!(usages[0].original_range, Range { start: 33, end: 72 });
+    let range = usages[0].range;
+    assert_eq!(&output.code[range.start..range.end], "eval(arguments[0])");
+    // not the one in the template's content
+    assert!(output.code[..range.start].contains("`eval(arguments[0])`"));
+    let line = output.code.lines().nth(usages[0].line - 1).unwrap();
+    assert_eq!(&line[usages[0].column..usages[0].column + 4], "eval");
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_pairs_moved_templates_with_their_source() {
+    let src = "export default <template>D</template>;\n\
+               export const A = <template>A</template>;\n\
+               export const B = <template>B</template>;";
+    let output = Preprocessor::new()
+        .process(
+            src,
+            crate::Options {
+                default_export_placement: crate::DefaultExportPlacement::End,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let pairs: Vec<(&str, &str)> = output
+        .metadata
+        .replacements
+        .iter()
+        .map(|replacement| {
+            let original = replacement.original_range;
+            let generated = replacement.range;
+            (
+                &src[original.start..original.end],
+                &output.code[generated.start..generated.end],
+            )
+        })
+        .collect();
+    assert_eq!(pairs.len(), 3);
+    for (original, generated) in &pairs {
+        let content = original
+            .trim_start_matches("<template>")
+            .trim_end_matches("</template>");
+        assert!(
+            generated.starts_with(&format!("{IMPORT_ALIAS}(`{content}`")),
+            "{original} paired with {generated}"
+        );
+    }
+    // the default export is printed last
+    assert_eq!(pairs[2].0, "<template>D</template>");
+
+    let usages = &output.metadata.eval_usages;
+    assert_eq!(usages.len(), 3);
+    for (usage, replacement) in usages.iter().zip(&output.metadata.replacements) {
+        assert_eq!(usage.original_range, replacement.original_range);
+        assert!(replacement.range.start < usage.range.start);
+        assert!(usage.range.end < replacement.range.end);
+    }
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_no_metadata_without_templates() {
+    let p = Preprocessor::new();
+    let output = p
+        .process("console.log('hello')", Default::default())
+        .unwrap();
+    assert_eq!(output.metadata, Metadata::default());
+}
@@ -0,0 +1,153 @@
+use serde::Serialize;
+
+use crate::content;
+use crate::locate::{Occurrence, Range};
+
+// Rough statistics about one template, for finding the oversized ones. The
+// counts come from a quick scan of the content rather than a Glimmer parse,
+// so they can be off for unusual input (like `{{` inside an attribute
+// string), but they don't need a template compiler.
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateMetrics {
+    // the span of the whole content tag in the source
+    pub range: Range,
+    // of the content
+    pub bytes: usize,
+    pub lines: usize,
+    // `{{...}}`, including block openers and closers but not comments
+    pub mustaches: usize,
+    // `{{#...}}`
+    pub blocks: usize,
+    // opening tags, like `<div>` or `<Foo />`
+    pub elements: usize,
+    // the subset of `elements` that are components: capitalized names, paths
+    // like `<this.Foo>` and arguments like `<@foo>`
+    pub components: usize,
+    // `{{!...}}` and `<!--...-->`
+    pub comments: usize,
+    // the content isn't empty, but it's all whitespace
+    pub whitespace_only: bool,
+}
+
+pub(crate) fn template_metrics(occurrence: &Occurrence) -> TemplateMetrics {
+    let content = occurrence.contents();
+    let mut metrics = TemplateMetrics {
+        range: occurrence.range,
+        bytes: content.len(),
+        lines: content.lines().count().max(1),
+        mustaches: 0,
+        blocks: 0,
+        elements: 0,
+        components: 0,
+        comments: 0,
+        whitespace_only: content::is_whitespace_only(content),
+    };
+    let mut rest = content;
+    while let Some(start) = rest.find(['{', '<']) {
+        rest = &rest[start..];
+        if let Some(mustache) = rest.strip_prefix("{{") {
+            let mustache = mustache.strip_prefix('~').unwrap_or(mustache);
+            if mustache.starts_with('!') {
+                metrics.comments += 1;
+                // `{{!-- --}}` can contain `}}`
+                let end = if mustache.starts_with("!--") {
+                    "--}}"
+                } else {
+                    "}}"
+                };
+                rest = skip_past(mustache, end);
+                continue;
+            }
+            metrics.mustaches += 1;
+            if mustache.starts_with('#') {
+                metrics.blocks += 1;
+            }
+            rest = skip_past(mustache, "}}");
+        } else if let Some(comment) = rest.strip_prefix("<!--") {
+            metrics.comments += 1;
+            rest = skip_past(comment, "-->");
+        } else if let Some(tag) = rest.strip_prefix('<') {
+            match tag.chars().next() {
+                Some(c) if c.is_ascii_alphabetic() || c == '@' || c == ':' => {
+                    metrics.elements += 1;
+                    let name_end = tag
+                        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+                        .unwrap_or(tag.len());
+                    if is_component(&tag[..name_end]) {
+                        metrics.components += 1;
+                    }
+                }
+                _ => {}
+            }
+            rest = tag;
+        } else {
+            rest = &rest[1..];
+        }
+    }
+    metrics
+}
+
+fn skip_past<'a>(s: &'a str, end: &str) -> &'a str {
+    match s.find(end) {
+        Some(pos) => &s[pos + end.len()..],
+        None => "",
+    }
+}
+
+// Named blocks like `<:header>` are neither HTML nor components.
+fn is_component(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase() || c == '@')
+        || (name.contains('.') && !name.starts_with(':'))
+}
+
+#[cfg(test)]
+use crate::Preprocessor;
+
+#[test]
+fn test_counts() {
+    let src = "<template>
+  {{! a comment }}
+  <div class={{this.cls}}>
+    {{#if @show}}
+      <Foo @a={{1}} />
+      <this.Bar />
+    {{/if}}
+  </div>
+  <!-- <span> -->
+</template>";
+    let metrics = Preprocessor::new().analyze_metrics(src).unwrap();
+    assert_eq!(metrics.len(), 1);
+    let metrics = &metrics[0];
+    assert_eq!(metrics.mustaches, 4);
+    assert_eq!(metrics.blocks, 1);
+    assert_eq!(metrics.elements, 3);
+    assert_eq!(metrics.components, 2);
+    assert_eq!(metrics.comments, 2);
+    assert_eq!(metrics.lines, 9);
+    assert_eq!(
+        metrics.range,
+        Range {
+            start: 0,
+            end: src.len()
+        }
+    );
+}
+
+#[test]
+fn test_block_comments_can_contain_mustaches() {
+    let metrics = Preprocessor::new()
+        .analyze_metrics("<template>{{!-- {{foo}} --}}{{bar}}</template>")
+        .unwrap();
+    assert_eq!(metrics[0].comments, 1);
+    assert_eq!(metrics[0].mustaches, 1);
+}
+
+#[test]
+fn test_whitespace_only() {
+    let metrics = Preprocessor::new()
+        .analyze_metrics("<template>\n  \n</template><template></template><template> a </template>")
+        .unwrap();
+    let flags: Vec<bool> = metrics.iter().map(|m| m.whitespace_only).collect();
+    assert_eq!(flags, vec![true, false, false]);
+}
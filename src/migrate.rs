@@ -0,0 +1,618 @@
+// Converting a co-located component (`foo.js` next to `foo.hbs`) into a
+// single `.gjs` file, and back. We parse the JS to find where templates are
+// or belong, but edit the source text directly so that everything else keeps
+// its formatting.
+
+use swc_common::{self, sync::Lrc, BytePos, FileName, SourceMap, Span, Spanned};
+use swc_ecma_ast::{
+    Callee, Class, ClassDecl, ClassExpr, ClassMember, Decl, DefaultDecl, Expr, Function,
+    ImportSpecifier, Module, ModuleDecl, ModuleItem, Stmt,
+};
+use swc_ecma_parser::{lexer::Lexer, Parser, StringInput};
+use swc_ecma_visit::{Visit, VisitWith};
+
+use crate::references::template_references;
+use crate::{swc_compat, Error};
+
+const TEMPLATE_ONLY_MODULE: &str = "@ember/component/template-only";
+
+// The `.gjs` equivalent of a component's JS (if it has any) and its template.
+// The template content is kept byte-for-byte. It goes into the class body
+// when the default export is a class, and replaces the call when it is
+// `templateOnly()`.
+pub fn merge_colocated(js: Option<&str>, hbs: &str) -> Result<String, Error> {
+    if hbs.contains("</template>") {
+        return Err(Error::Migration(
+            "the template contains `</template>`".to_owned(),
+        ));
+    }
+    let Some(js) = js else {
+        return Ok(format!("<template>{hbs}</template>\n"));
+    };
+
+    let (module, start_pos) = parse(js)?;
+    let offset = |pos: BytePos| (pos - start_pos).0 as usize;
+    let mut edits: Vec<(usize, usize, String)> = vec![];
+
+    match default_export(&module) {
+        Some(DefaultExport::Class(class)) => {
+            let at = class_body_start(js, class, offset);
+            edits.push((at, at, format!("\n  <template>{hbs}</template>\n")));
+        }
+        Some(DefaultExport::TemplateOnly(call_span, local)) => {
+            edits.push((
+                offset(call_span.lo),
+                offset(call_span.hi),
+                format!("<template>{hbs}</template>"),
+            ));
+            if let Some(import_span) = sole_import_of(&module, &local) {
+                let start = offset(import_span.lo);
+                let mut end = offset(import_span.hi);
+                // at the top of the file, take the blank lines after it too
+                while js[end..].starts_with('\n') {
+                    end += 1;
+                    if start != 0 {
+                        break;
+                    }
+                }
+                edits.push((start, end, String::new()));
+            }
+        }
+        None => {
+            return Err(Error::Migration(
+                "the default export isn't a class or a `templateOnly()` call".to_owned(),
+            ))
+        }
+    }
+
+    Ok(splice(js, edits))
+}
+
+enum DefaultExport<'a> {
+    Class(&'a Class),
+    // the span of the call, and the local name of `templateOnly`
+    TemplateOnly(Span, String),
+}
+
+fn default_export(module: &Module) -> Option<DefaultExport> {
+    module.body.iter().find_map(|item| match item {
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(export)) => match &export.decl {
+            DefaultDecl::Class(class) => Some(DefaultExport::Class(&class.class)),
+            _ => None,
+        },
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultExpr(export)) => match &*export.expr {
+            Expr::Class(class) => Some(DefaultExport::Class(&class.class)),
+            // `class Foo {}` followed by `export default Foo`
+            Expr::Ident(ident) => module.body.iter().find_map(|item| match item {
+                ModuleItem::Stmt(Stmt::Decl(Decl::Class(class)))
+                    if class.ident.sym == ident.sym =>
+                {
+                    Some(DefaultExport::Class(&class.class))
+                }
+                _ => None,
+            }),
+            Expr::Call(call) => match &call.callee {
+                Callee::Expr(box Expr::Ident(callee))
+                    if call.args.is_empty()
+                        && imported_from(module, &callee.sym, TEMPLATE_ONLY_MODULE) =>
+                {
+                    Some(DefaultExport::TemplateOnly(
+                        call.span,
+                        callee.sym.to_string(),
+                    ))
+                }
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+fn imported_from(module: &Module, local: &str, source: &str) -> bool {
+    module.body.iter().any(|item| match item {
+        ModuleItem::ModuleDecl(ModuleDecl::Import(import)) => {
+            &*import.src.value == source
+                && import
+                    .specifiers
+                    .iter()
+                    .any(|specifier| &*specifier.local().sym == local)
+        }
+        _ => false,
+    })
+}
+
+// The import declaration that brings in `local` and nothing else.
+fn sole_import_of(module: &Module, local: &str) -> Option<Span> {
+    module.body.iter().find_map(|item| match item {
+        ModuleItem::ModuleDecl(ModuleDecl::Import(import)) => match &import.specifiers[..] {
+            [ImportSpecifier::Default(default)] if &*default.local.sym == local => {
+                Some(import.span)
+            }
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+// The offset just after the `{` that opens `class`'s body.
+fn class_body_start(js: &str, class: &Class, offset: impl Fn(BytePos) -> usize) -> usize {
+    let heritage_end = [
+        class.decorators.last().map(|decorator| decorator.span.hi),
+        class.super_class.as_ref().map(|expr| expr.span().hi),
+        class
+            .super_type_params
+            .as_ref()
+            .map(|params| params.span.hi),
+        class.type_params.as_ref().map(|params| params.span.hi),
+        class.implements.last().map(|implements| implements.span.hi),
+    ]
+    .into_iter()
+    .flatten()
+    .max()
+    .unwrap_or(class.span.lo);
+    let from = offset(heritage_end);
+    from + js[from..].find('{').map_or(0, |at| at + 1)
+}
+
+// A `.gjs` file split back into JS and one `.hbs` file per template.
+pub struct SplitComponent {
+    pub js: String,
+    pub templates: Vec<SplitTemplate>,
+    // things that may behave differently once the templates are no longer
+    // in the module's scope
+    pub warnings: Vec<String>,
+}
+
+pub struct SplitTemplate {
+    // relative to the JS file, like `greeting.hbs`
+    pub file_name: String,
+    pub contents: String,
+}
+
+// The inverse of `merge_colocated`: each template goes to its own `.hbs`
+// file, which the JS imports and attaches with `setComponentTemplate`.
+// Class-member templates are attached to their class, and expression
+// templates become `templateOnly()` components. `stem` names the `.hbs`
+// files: the first is `{stem}.hbs`, the next `{stem}-2.hbs` and so on.
+pub fn split_gjs(src: &str, stem: &str) -> Result<SplitComponent, Error> {
+    let (module, start_pos) = parse(src)?;
+    let offset = |pos: BytePos| (pos - start_pos).0 as usize;
+
+    let mut finder = TemplateFinder::default();
+    module.visit_with(&mut finder);
+    if finder.sites.is_empty() {
+        return Err(Error::Migration("there are no templates".to_owned()));
+    }
+
+    let bindings = top_level_bindings(&module);
+    let mut taken = bindings.clone();
+    let mut unique = |name: &str| {
+        let mut candidate = name.to_owned();
+        let mut n = 2;
+        while taken.contains(&candidate) {
+            candidate = format!("{name}{n}");
+            n += 1;
+        }
+        taken.push(candidate.clone());
+        candidate
+    };
+    let set_component_template = unique("setComponentTemplate");
+    let template_only = finder
+        .sites
+        .iter()
+        .any(|site| matches!(site.kind, TemplateSite::Expression))
+        .then(|| unique("templateOnly"));
+
+    let mut imports = format!(
+        "import {{ setComponentTemplate{} }} from \"@ember/component\";\n",
+        alias("setComponentTemplate", &set_component_template)
+    );
+    if let Some(template_only) = &template_only {
+        imports.push_str(&format!(
+            "import {template_only} from \"{TEMPLATE_ONLY_MODULE}\";\n"
+        ));
+    }
+
+    let mut edits = vec![];
+    let mut templates = vec![];
+    let mut warnings = vec![];
+    for (index, site) in finder.sites.iter().enumerate() {
+        let file_name = match index {
+            0 => format!("{stem}.hbs"),
+            n => format!("{stem}-{}.hbs", n + 1),
+        };
+        let local = unique("template");
+        imports.push_str(&format!("import {local} from \"./{file_name}\";\n"));
+
+        for name in template_references(&site.contents) {
+            if bindings.contains(&name) {
+                warnings.push(format!(
+                    "{file_name} uses `{name}` from the module, which it can't see once split"
+                ));
+            }
+        }
+
+        let (start, end) = (offset(site.span.lo), offset(site.span.hi));
+        match &site.kind {
+            TemplateSite::Expression => edits.push((
+                start,
+                end,
+                format!(
+                    "{set_component_template}({local}, {}())",
+                    template_only.as_deref().unwrap_or_default()
+                ),
+            )),
+            TemplateSite::Member(class) => {
+                let (start, end) = whole_lines(src, start, end);
+                edits.push((start, end, String::new()));
+                match class {
+                    ClassSite::Named(name, class_end) => edits.push((
+                        offset(*class_end),
+                        offset(*class_end),
+                        format!("\n{set_component_template}({local}, {name});"),
+                    )),
+                    ClassSite::Anonymous(class_span) => {
+                        edits.push((
+                            offset(class_span.lo),
+                            offset(class_span.lo),
+                            format!("{set_component_template}({local}, "),
+                        ));
+                        edits.push((offset(class_span.hi), offset(class_span.hi), ")".to_owned()));
+                    }
+                }
+            }
+        }
+        templates.push(SplitTemplate {
+            file_name,
+            contents: site.contents.clone(),
+        });
+    }
+
+    let first = module.body.first().map_or(0, |item| offset(item.span().lo));
+    edits.push((first, first, imports));
+
+    Ok(SplitComponent {
+        js: splice(src, edits),
+        templates,
+        warnings,
+    })
+}
+
+fn alias(name: &str, local: &str) -> String {
+    if name == local {
+        String::new()
+    } else {
+        format!(" as {local}")
+    }
+}
+
+// Widens `start..end` to the whole lines it is on when nothing else shares
+// them, so that removing it doesn't leave a blank line behind.
+fn whole_lines(src: &str, start: usize, end: usize) -> (usize, usize) {
+    let line_start = src[..start].rfind('\n').map_or(0, |at| at + 1);
+    let line_end = src[end..].find('\n').map_or(src.len(), |at| end + at + 1);
+    if src[line_start..start].trim().is_empty() && src[end..line_end].trim().is_empty() {
+        (line_start, line_end)
+    } else {
+        (start, end)
+    }
+}
+
+enum TemplateSite {
+    Expression,
+    Member(ClassSite),
+}
+
+enum ClassSite {
+    // a class declaration, which we can refer to by name after its end
+    Named(String, BytePos),
+    // a class expression (or anonymous default export), which has to be
+    // wrapped
+    Anonymous(Span),
+}
+
+struct Site {
+    span: Span,
+    contents: String,
+    kind: TemplateSite,
+}
+
+#[derive(Default)]
+struct TemplateFinder {
+    sites: Vec<Site>,
+    // the class whose body we're in
+    class: Option<ClassSite>,
+}
+
+impl TemplateFinder {
+    fn in_class(&mut self, site: ClassSite, class: &Class) {
+        let outer = self.class.replace(site);
+        class.visit_children_with(self);
+        self.class = outer;
+    }
+}
+
+impl Visit for TemplateFinder {
+    fn visit_class_decl(&mut self, n: &ClassDecl) {
+        self.in_class(
+            ClassSite::Named(n.ident.sym.to_string(), n.class.span.hi),
+            &n.class,
+        );
+    }
+
+    fn visit_class_expr(&mut self, n: &ClassExpr) {
+        self.in_class(ClassSite::Anonymous(n.class.span), &n.class);
+    }
+
+    fn visit_default_decl(&mut self, n: &DefaultDecl) {
+        match n {
+            DefaultDecl::Class(ClassExpr {
+                ident: Some(ident),
+                class,
+            }) => self.in_class(
+                ClassSite::Named(ident.sym.to_string(), class.span.hi),
+                class,
+            ),
+            _ => n.visit_children_with(self),
+        }
+    }
+
+    fn visit_function(&mut self, n: &Function) {
+        // templates in a method belong to the method, not the class
+        let outer = self.class.take();
+        n.visit_children_with(self);
+        self.class = outer;
+    }
+
+    fn visit_expr(&mut self, n: &Expr) {
+        if let Expr::ContentTagExpression(tag) = n {
+            self.sites.push(Site {
+                span: tag.span,
+                contents: tag.contents.value.to_string(),
+                kind: TemplateSite::Expression,
+            });
+        }
+        n.visit_children_with(self);
+    }
+
+    fn visit_class_member(&mut self, n: &ClassMember) {
+        if let ClassMember::ContentTagMember(tag) = n {
+            let class = match &self.class {
+                Some(ClassSite::Named(name, end)) => ClassSite::Named(name.clone(), *end),
+                Some(ClassSite::Anonymous(span)) => ClassSite::Anonymous(*span),
+                None => return,
+            };
+            self.sites.push(Site {
+                span: tag.span,
+                contents: tag.contents.value.to_string(),
+                kind: TemplateSite::Member(class),
+            });
+        }
+        n.visit_children_with(self);
+    }
+}
+
+// The names declared at the top of the module, which templates in a `.gjs`
+// can use but `.hbs` files can't.
+fn top_level_bindings(module: &Module) -> Vec<String> {
+    let mut names = vec![];
+    for item in &module.body {
+        match item {
+            ModuleItem::ModuleDecl(ModuleDecl::Import(import)) => names.extend(
+                import
+                    .specifiers
+                    .iter()
+                    .map(|specifier| specifier.local().sym.to_string()),
+            ),
+            ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(export)) => {
+                names.extend(declared_names(&export.decl))
+            }
+            ModuleItem::Stmt(Stmt::Decl(decl)) => names.extend(declared_names(decl)),
+            _ => {}
+        }
+    }
+    names
+}
+
+fn declared_names(decl: &Decl) -> Vec<String> {
+    match decl {
+        Decl::Class(class) => vec![class.ident.sym.to_string()],
+        Decl::Fn(function) => vec![function.ident.sym.to_string()],
+        Decl::Var(var) => var
+            .decls
+            .iter()
+            .filter_map(|declarator| declarator.name.as_ident())
+            .map(|ident| ident.id.sym.to_string())
+            .collect(),
+        _ => vec![],
+    }
+}
+
+// Points every import of `from` (and every `export ... from` it) at `to`
+// instead, for when the compiler's runtime package moves. Only the module
+// strings change: the local names stay the same, so nothing that refers to
+// them needs renaming and nothing new can be shadowed.
+pub(crate) fn rename_import(src: &str, from: &str, to: &str) -> Result<String, Error> {
+    let (module, start_pos) = parse(src)?;
+    let offset = |pos: BytePos| (pos - start_pos).0 as usize;
+    let edits = module
+        .body
+        .iter()
+        .filter_map(|item| match item {
+            ModuleItem::ModuleDecl(ModuleDecl::Import(import)) => Some(&import.src),
+            ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(export)) => export.src.as_ref(),
+            ModuleItem::ModuleDecl(ModuleDecl::ExportAll(export)) => Some(&export.src),
+            _ => None,
+        })
+        .filter(|source| &*source.value == from)
+        .map(|source| {
+            let quote = source
+                .raw
+                .as_ref()
+                .and_then(|raw| raw.chars().next())
+                .unwrap_or('"');
+            (
+                offset(source.span.lo),
+                offset(source.span.hi),
+                module_string(to, quote),
+            )
+        })
+        .collect();
+    Ok(splice(src, edits))
+}
+
+// `value` quoted like the string it replaces, when that needs no escaping.
+fn module_string(value: &str, quote: char) -> String {
+    if value.contains(|c: char| c == quote || c == '\\' || c.is_control()) {
+        serde_json::to_string(value).unwrap()
+    } else {
+        format!("{quote}{value}{quote}")
+    }
+}
+
+pub(crate) fn parse(src: &str) -> Result<(Module, BytePos), Error> {
+    let source_map: Lrc<SourceMap> = Default::default();
+    let source_file = source_map.new_source_file(FileName::Anon, src.to_string());
+    let lexer = Lexer::new(
+        swc_compat::syntax(),
+        Default::default(),
+        StringInput::from(&*source_file),
+        None,
+    );
+    let module = Parser::new_from(lexer).parse_module()?;
+    Ok((module, source_file.start_pos))
+}
+
+// Applies non-overlapping `(start, end, replacement)` edits to `src`.
+pub(crate) fn splice(src: &str, mut edits: Vec<(usize, usize, String)>) -> String {
+    edits.sort_by_key(|(start, _, _)| *start);
+    let mut out = String::with_capacity(src.len());
+    let mut at = 0;
+    for (start, end, replacement) in edits {
+        out.push_str(&src[at..start]);
+        out.push_str(&replacement);
+        at = end;
+    }
+    out.push_str(&src[at..]);
+    out
+}
+
+#[test]
+fn test_merge_class() {
+    let js = r#"import Component from "@glimmer/component";
+
+export default class Greeting extends Component {
+  get name() {
+    return "world";
+  }
+}
+"#;
+    assert_eq!(
+        merge_colocated(Some(js), "Hello {{this.name}}\n").unwrap(),
+        r#"import Component from "@glimmer/component";
+
+export default class Greeting extends Component {
+  <template>Hello {{this.name}}
+</template>
+
+  get name() {
+    return "world";
+  }
+}
+"#
+    );
+}
+
+#[test]
+fn test_merge_template_only() {
+    let js = r#"import templateOnly from "@ember/component/template-only";
+
+export default templateOnly();
+"#;
+    assert_eq!(
+        merge_colocated(Some(js), "Hi").unwrap(),
+        "export default <template>Hi</template>;\n"
+    );
+}
+
+#[test]
+fn test_merge_without_js() {
+    assert_eq!(
+        merge_colocated(None, "Hi\n").unwrap(),
+        "<template>Hi\n</template>\n"
+    );
+}
+
+#[test]
+fn test_merge_unsupported() {
+    assert!(matches!(
+        merge_colocated(Some("export default Component.extend({});"), "Hi"),
+        Err(Error::Migration(_))
+    ));
+}
+
+#[test]
+fn test_split_class() {
+    let src = r#"import Component from "@glimmer/component";
+import Greeting from "./greeting";
+
+export default class Card extends Component {
+  <template><Greeting /></template>
+
+  get name() {
+    return "world";
+  }
+}
+"#;
+    let split = split_gjs(src, "card").unwrap();
+    assert_eq!(
+        split.js,
+        r#"import { setComponentTemplate } from "@ember/component";
+import template from "./card.hbs";
+import Component from "@glimmer/component";
+import Greeting from "./greeting";
+
+export default class Card extends Component {
+
+  get name() {
+    return "world";
+  }
+}
+setComponentTemplate(template, Card);
+"#
+    );
+    assert_eq!(split.templates[0].file_name, "card.hbs");
+    assert_eq!(split.templates[0].contents, "<Greeting />");
+    assert_eq!(split.warnings.len(), 1);
+}
+
+#[test]
+fn test_split_expressions() {
+    let split = split_gjs(
+        "const template = 1;\nexport const A = <template>A</template>;\nexport default <template>B</template>;\n",
+        "a",
+    )
+    .unwrap();
+    assert_eq!(
+        split.js,
+        r#"import { setComponentTemplate } from "@ember/component";
+import templateOnly from "@ember/component/template-only";
+import template2 from "./a.hbs";
+import template3 from "./a-2.hbs";
+const template = 1;
+export const A = setComponentTemplate(template2, templateOnly());
+export default setComponentTemplate(template3, templateOnly());
+"#
+    );
+    assert_eq!(split.templates[1].file_name, "a-2.hbs");
+}
+
+#[test]
+fn test_rename_import() {
+    let src = "import { template as t } from '@ember/template-compiler';\nexport { template } from \"@ember/template-compiler\";\nimport x from './x';\nlet y = t('Hi');\n";
+    assert_eq!(
+        rename_import(src, "@ember/template-compiler", "@ember/template-compiler/runtime").unwrap(),
+        "import { template as t } from '@ember/template-compiler/runtime';\nexport { template } from \"@ember/template-compiler/runtime\";\nimport x from './x';\nlet y = t('Hi');\n"
+    );
+}
@@ -0,0 +1,234 @@
+// Unified diffs built straight from the edits that splicing makes, so there's
+// no need for a general diff algorithm: every edit already says exactly which
+// lines it touches.
+
+use crate::locate::Range;
+use crate::splice::Edit;
+
+// lines of unchanged context around each change, like `diff -u`
+const CONTEXT: usize = 3;
+
+// Replacing `old` (a range of 0-based line indices) with `new`, which is the
+// text of those lines after the edits on them.
+struct Change {
+    old: (usize, usize),
+    new: String,
+}
+
+pub(crate) fn unified_diff(path: &str, src: &str, edits: &[Edit]) -> String {
+    let mut line_starts: Vec<usize> = std::iter::once(0)
+        .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    if line_starts.last() == Some(&src.len()) {
+        line_starts.pop();
+    }
+    let line_count = line_starts.len();
+    let line_of = |offset: usize| line_starts.partition_point(|&start| start <= offset) - 1;
+    let line_start = |line: usize| line_starts.get(line).copied().unwrap_or(src.len());
+    let lines = |from: usize, to: usize| split_lines(&src[line_start(from)..line_start(to)]);
+
+    // edits that touch the same lines become one change
+    let mut groups: Vec<((usize, usize), Vec<&Edit>)> = vec![];
+    for edit in edits
+        .iter()
+        .filter(|edit| !edit.range.is_empty() || !edit.text.is_empty())
+    {
+        let Range { start, end } = edit.range;
+        let old = if start == end && (start == src.len() || line_starts.contains(&start)) {
+            // an insertion between lines
+            let line = if start == src.len() {
+                line_count
+            } else {
+                line_of(start)
+            };
+            (line, line)
+        } else {
+            let last = if end > start && src.as_bytes()[end - 1] == b'\n' {
+                line_of(end - 1)
+            } else {
+                line_of(end.min(src.len().saturating_sub(1)))
+            };
+            (line_of(start), last + 1)
+        };
+        match groups.last_mut() {
+            Some((span, group)) if span.1 > old.0 => {
+                span.1 = span.1.max(old.1);
+                group.push(edit);
+            }
+            _ => groups.push((old, vec![edit])),
+        }
+    }
+    let changes: Vec<Change> = groups
+        .into_iter()
+        .map(|(old, group)| {
+            let base = line_start(old.0);
+            let mut new = src[base..line_start(old.1)].to_owned();
+            for edit in group.iter().rev() {
+                new.replace_range(edit.range.start - base..edit.range.end - base, &edit.text);
+            }
+            Change { old, new }
+        })
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    let mut out = format!("--- a/{path}\n+++ b/{path}\n");
+    // how many more lines the new file has before the current hunk
+    let mut offset: isize = 0;
+    let mut rest = &changes[..];
+    while !rest.is_empty() {
+        // the changes close enough together to share a hunk
+        let mut count = 1;
+        while count < rest.len() && rest[count].old.0 - rest[count - 1].old.1 <= 2 * CONTEXT {
+            count += 1;
+        }
+        let (hunk, next) = rest.split_at(count);
+        rest = next;
+
+        let first = hunk[0].old.0.saturating_sub(CONTEXT);
+        let last = (hunk[count - 1].old.1 + CONTEXT).min(line_count);
+        let mut body = String::new();
+        let (mut old_lines, mut new_lines) = (0, 0);
+        let mut line = first;
+        for change in hunk {
+            for context in lines(line, change.old.0) {
+                push_line(&mut body, ' ', context);
+            }
+            let removed = lines(change.old.0, change.old.1);
+            let added = split_lines(&change.new);
+            for removed in &removed {
+                push_line(&mut body, '-', removed);
+            }
+            for added in &added {
+                push_line(&mut body, '+', added);
+            }
+            old_lines += change.old.0 - line + removed.len();
+            new_lines += change.old.0 - line + added.len();
+            line = change.old.1;
+        }
+        let context = lines(line, last);
+        for context in &context {
+            push_line(&mut body, ' ', context);
+        }
+        old_lines += context.len();
+        new_lines += context.len();
+
+        let new_first = (first as isize + offset) as usize;
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(first, old_lines),
+            hunk_range(new_first, new_lines)
+        ));
+        out.push_str(&body);
+        offset += new_lines as isize - old_lines as isize;
+    }
+    out
+}
+
+// Lines with their newlines, except maybe the last.
+fn split_lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+fn push_line(out: &mut String, prefix: char, line: &str) {
+    out.push(prefix);
+    out.push_str(line);
+    if !line.ends_with('\n') {
+        out.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+// `start,count` in 1-based lines, where an empty range names the line
+// before it.
+fn hunk_range(first: usize, count: usize) -> String {
+    if count == 0 {
+        format!("{first},0")
+    } else {
+        format!("{},{count}", first + 1)
+    }
+}
+
+#[cfg(test)]
+use crate::{Emit, Options, Preprocessor};
+
+#[cfg(test)]
+fn diff(src: &str) -> String {
+    let output = Preprocessor::new()
+        .process(
+            src,
+            Options {
+                filename: Some("a.gjs".into()),
+                emit: Emit::UnifiedDiff,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    output.code.replace(crate::IMPORT_ALIAS, "template_UUID")
+}
+
+#[test]
+fn test_diffs_only_the_changed_lines() {
+    let src = "import Foo from './foo';\n\nfunction a() {}\nfunction b() {}\nfunction c() {}\nfunction d() {}\nfunction e() {}\nfunction f() {}\nexport default <template><Foo /></template>;\n";
+    assert_eq!(
+        diff(src),
+        "--- a/a.gjs
++++ b/a.gjs
+@@ -1,3 +1,4 @@
++import { template as template_UUID } from \"@ember/template-compiler\";
+ import Foo from './foo';
+ 
+ function a() {}
+@@ -6,4 +7,4 @@
+ function d() {}
+ function e() {}
+ function f() {}
+-export default <template><Foo /></template>;
++export default template_UUID(`<Foo />`, { eval() { return eval(arguments[0]); } });
+"
+    );
+}
+
+#[test]
+fn test_diffs_the_last_line_without_a_newline() {
+    assert_eq!(
+        diff("let x = <template>Hi</template>"),
+        "--- a/a.gjs
++++ b/a.gjs
+@@ -1,1 +1,2 @@
++import { template as template_UUID } from \"@ember/template-compiler\";
+-let x = <template>Hi</template>
+\\ No newline at end of file
++let x = template_UUID(`Hi`, { eval() { return eval(arguments[0]); } })
+\\ No newline at end of file
+"
+    );
+}
+
+#[test]
+fn test_no_diff_without_templates() {
+    assert_eq!(diff("let x = 1;\n"), "");
+}
+
+#[test]
+fn test_diffs_again() {
+    let src = "let x = <template>Hi</template>";
+    let options = || Options {
+        filename: Some("a.gjs".into()),
+        emit: Emit::UnifiedDiff,
+        ..Default::default()
+    };
+    let p = Preprocessor::new();
+    p.process(
+        "let a = 1;\nlet y = <template>Bye</template>;",
+        Default::default(),
+    )
+    .unwrap();
+    let first = p.process(src, options()).unwrap().code;
+    let second = p.process(src, options()).unwrap().code;
+    assert_eq!(first, second);
+    assert_eq!(
+        first.replace(crate::IMPORT_ALIAS, "template_UUID"),
+        diff(src)
+    );
+}
@@ -0,0 +1,189 @@
+// Recording how long each phase of each file takes, in the Chrome trace event
+// format that Perfetto and `chrome://tracing` load. This listens to the same
+// tracing spans as the `--verbose` logs.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::registry::LookupSpan;
+
+#[derive(Clone)]
+pub struct Profiler {
+    start: Instant,
+    events: Arc<Mutex<Vec<TraceEvent>>>,
+}
+
+// A "complete" event, with times in microseconds since the profiler started.
+#[derive(Serialize, Debug, Clone)]
+struct TraceEvent {
+    name: &'static str,
+    cat: &'static str,
+    ph: &'static str,
+    ts: f64,
+    dur: f64,
+    pid: u32,
+    tid: u32,
+    args: BTreeMap<&'static str, String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Trace<'a> {
+    trace_events: &'a [TraceEvent],
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Profiler {
+            start: Instant::now(),
+            events: Default::default(),
+        }
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // For adding to a subscriber that is already set up, like the CLI's.
+    pub fn layer<S>(&self) -> impl Layer<S>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        ProfileLayer(self.clone())
+    }
+
+    // Runs `f` with only this profiler listening.
+    pub fn record<T>(&self, f: impl FnOnce() -> T) -> T {
+        tracing::subscriber::with_default(
+            tracing_subscriber::registry().with(ProfileLayer(self.clone())),
+            f,
+        )
+    }
+
+    // Everything recorded so far, as a Chrome trace JSON document.
+    pub fn chrome_trace(&self) -> String {
+        // a panic while recording leaves the events as they were, which are
+        // still worth writing
+        let events = self.events.lock().unwrap_or_else(|err| err.into_inner());
+        serde_json::to_string(&Trace {
+            trace_events: &events,
+        })
+        .unwrap_or_default()
+    }
+
+    fn micros_since_start(&self, instant: Instant) -> f64 {
+        instant.duration_since(self.start).as_secs_f64() * 1e6
+    }
+}
+
+struct ProfileLayer(Profiler);
+
+// What we keep on each span until it closes.
+struct SpanTiming {
+    start: Instant,
+    // the span's fields, plus the `file` of the span it is in
+    args: BTreeMap<&'static str, String>,
+}
+
+impl<S> Layer<S> for ProfileLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut args = Args(BTreeMap::new());
+        attrs.record(&mut args);
+        if let Some(parent) = span.parent() {
+            if let Some(file) = parent
+                .extensions()
+                .get::<SpanTiming>()
+                .and_then(|timing| timing.args.get("file"))
+            {
+                args.0.entry("file").or_insert_with(|| file.clone());
+            }
+        }
+        span.extensions_mut().insert(SpanTiming {
+            start: Instant::now(),
+            args: args.0,
+        });
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let end = Instant::now();
+        let extensions = span.extensions();
+        let Some(timing) = extensions.get::<SpanTiming>() else {
+            return;
+        };
+        let event = TraceEvent {
+            name: span.name(),
+            cat: "content-tag",
+            ph: "X",
+            ts: self.0.micros_since_start(timing.start),
+            dur: end.duration_since(timing.start).as_secs_f64() * 1e6,
+            pid: 1,
+            tid: 1,
+            args: timing.args.clone(),
+        };
+        self.0
+            .events
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push(event);
+    }
+}
+
+struct Args(BTreeMap<&'static str, String>);
+
+impl Visit for Args {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name(), value.to_owned());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name(), format!("{value:?}"));
+    }
+}
+
+#[test]
+fn test_records_phases() {
+    use crate::Preprocessor;
+
+    let profiler = Profiler::new();
+    profiler.record(|| {
+        Preprocessor::new()
+            .process(
+                "export default <template>Hi</template>;",
+                crate::Options {
+                    filename: Some("a.gjs".into()),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+    });
+    let trace: serde_json::Value = serde_json::from_str(&profiler.chrome_trace()).unwrap();
+    let events = trace["traceEvents"].as_array().unwrap();
+    let names: Vec<&str> = events
+        .iter()
+        .map(|event| event["name"].as_str().unwrap())
+        .collect();
+    for phase in ["process", "parse", "transform", "resolver", "print"] {
+        assert!(names.contains(&phase), "no {phase} in {names:?}");
+    }
+    assert!(events
+        .iter()
+        .all(|event| event["args"]["file"] == "a.gjs" && event["ph"] == "X"));
+}
@@ -0,0 +1,134 @@
+// Processing a set of in-memory files with one set of options, so that build
+// tools can hand over a whole batch of invalidated files at once. Knowing
+// every file up front is also what later cross-file checks (like finding the
+// sibling module a template's component comes from) will build on.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::{Census, CodeMapPair, Error, ExtensionOptions, FileId, Options, Preprocessor};
+
+pub struct Project {
+    options: ExtensionOptions,
+    files: BTreeMap<PathBuf, String>,
+}
+
+pub struct ProcessedFile {
+    pub result: Result<CodeMapPair, Error>,
+    // the preprocessor that produced `result`, for rendering its errors
+    pub(crate) preprocessor: Preprocessor,
+}
+
+impl ProcessedFile {
+    // with a preprocessor of its own, so that the file's ranges start at 0
+    pub(crate) fn process(src: &str, options: &Options, filename: Option<&FileId>) -> Self {
+        let preprocessor = Preprocessor::new();
+        let result = preprocessor.process_with(src, options, filename);
+        ProcessedFile {
+            result,
+            preprocessor,
+        }
+    }
+
+    pub fn preprocessor(&self) -> &Preprocessor {
+        &self.preprocessor
+    }
+}
+
+impl Project {
+    // `options.filename` is ignored; each file is named by its path. Takes
+    // `Options`, or `ExtensionOptions` for options that depend on each
+    // file's extension.
+    pub fn new(options: impl Into<ExtensionOptions>) -> Result<Self, Error> {
+        let options = options.into();
+        options.validate()?;
+        Ok(Project {
+            options,
+            files: BTreeMap::new(),
+        })
+    }
+
+    pub fn with_files(
+        options: impl Into<ExtensionOptions>,
+        files: impl IntoIterator<Item = (PathBuf, String)>,
+    ) -> Result<Self, Error> {
+        let mut project = Project::new(options)?;
+        project.files.extend(files);
+        Ok(project)
+    }
+
+    // Adds a file, or replaces the source of one that is already there.
+    pub fn insert(&mut self, path: impl Into<PathBuf>, src: impl Into<String>) {
+        self.files.insert(path.into(), src.into());
+    }
+
+    pub fn remove(&mut self, path: &Path) -> Option<String> {
+        self.files.remove(path)
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(|path| path.as_path())
+    }
+
+    // Processes every file. One failing doesn't stop the others.
+    pub fn process(&self) -> BTreeMap<PathBuf, ProcessedFile> {
+        self.files
+            .iter()
+            .map(|(path, src)| (path.clone(), self.process_file(path, src)))
+            .collect()
+    }
+
+    pub fn process_only<'a>(
+        &self,
+        paths: impl IntoIterator<Item = &'a Path>,
+    ) -> BTreeMap<PathBuf, ProcessedFile> {
+        paths
+            .into_iter()
+            .filter_map(|path| self.files.get_key_value(path))
+            .map(|(path, src)| (path.clone(), self.process_file(path, src)))
+            .collect()
+    }
+
+    // Counts the names every file's templates refer to. Files that don't
+    // parse are listed in `Census::unparsed` instead.
+    pub fn census(&self) -> Census {
+        let mut census = Census::default();
+        for (path, src) in &self.files {
+            let options = Options {
+                filename: Some(FileId::from(path.as_path())),
+                ..Default::default()
+            };
+            match Preprocessor::new().parse(src, options) {
+                Ok(occurrences) => census.count(path, &occurrences),
+                Err(_) => census.unparsed.push(path.clone()),
+            }
+        }
+        census
+    }
+
+    fn process_file(&self, path: &Path, src: &str) -> ProcessedFile {
+        self.options.process(path, src)
+    }
+}
+
+#[test]
+fn test_process() {
+    let mut project = Project::new(Options::default()).unwrap();
+    project.insert("a.gjs", "export default <template>A</template>;");
+    project.insert("b.gjs", "export default <template>B");
+    project.insert("c.gjs", "export default <template>C</template>;");
+
+    let output = project.process();
+    assert_eq!(output.len(), 3);
+    let a = output[Path::new("a.gjs")].result.as_ref().unwrap();
+    assert!(a.code.contains("`A`"));
+    assert_eq!(a.metadata.replacements[0].original_range.start, 15);
+    assert!(matches!(
+        output[Path::new("b.gjs")].result,
+        Err(Error::Parse(_))
+    ));
+    assert!(output[Path::new("c.gjs")].result.is_ok());
+
+    let output = project.process_only([Path::new("c.gjs"), Path::new("missing.gjs")]);
+    assert_eq!(output.keys().collect::<Vec<_>>(), vec![Path::new("c.gjs")]);
+}
@@ -0,0 +1,252 @@
+// Finding the JS names a template refers to, so that `Options::warn_unresolved`
+// can check them against the scope at the tag site. This is a scan rather
+// than a real Handlebars parse: it only needs to be good enough to catch
+// typos, and it errs on the side of not reporting.
+
+#[cfg(feature = "transform")]
+use swc_common::{Mark, Span};
+#[cfg(feature = "transform")]
+use swc_ecma_ast::{
+    ArrayLit, Expr, ExprOrSpread, Ident, KeyValueProp, Module, ObjectLit, Prop, PropName,
+    PropOrSpread,
+};
+#[cfg(feature = "transform")]
+use swc_ecma_visit::{VisitMut, VisitMutWith};
+
+use crate::scope::is_identifier;
+
+// Names that templates provide themselves.
+const KEYWORDS: &[&str] = &[
+    "action",
+    "array",
+    "component",
+    "concat",
+    "debugger",
+    "each",
+    "each-in",
+    "else",
+    "fn",
+    "get",
+    "has-block",
+    "has-block-params",
+    "hash",
+    "helper",
+    "if",
+    "in-element",
+    "let",
+    "log",
+    "modifier",
+    "mut",
+    "on",
+    "outlet",
+    "this",
+    "unbound",
+    "unique-id",
+    "unless",
+    "with",
+    "yield",
+];
+
+// The head of each path that could refer to a JS binding, in the order they
+// appear and without duplicates.
+pub(crate) fn template_references(content: &str) -> Vec<String> {
+    let mut references: Vec<String> = vec![];
+    for name in reference_occurrences(content) {
+        if !references.iter().any(|reference| reference == name) {
+            references.push(name.to_owned());
+        }
+    }
+    references
+}
+
+// The same names, but each time it appears, for counting uses.
+pub(crate) fn reference_occurrences(content: &str) -> Vec<&str> {
+    let block_params = block_params(content);
+    let mut references = vec![];
+    let mut add = |name| {
+        if is_identifier(name)
+            && !KEYWORDS.contains(&name)
+            && !block_params.iter().any(|param| *param == name)
+        {
+            references.push(name);
+        }
+    };
+
+    let mut rest = content;
+    while let Some(at) = rest.find(['{', '<']) {
+        rest = &rest[at..];
+        if let Some(inner) = rest.strip_prefix("{{") {
+            if inner.starts_with('!') {
+                let end = if inner.starts_with("!--") {
+                    "--}}"
+                } else {
+                    "}}"
+                };
+                rest = inner.find(end).map_or("", |end_at| &inner[end_at..]);
+                continue;
+            }
+            let end_at = inner.find("}}").unwrap_or(inner.len());
+            for name in mustache_paths(&inner[..end_at]) {
+                add(name);
+            }
+            rest = &inner[end_at..];
+        } else if let Some(inner) = rest.strip_prefix('<') {
+            let tag_end = inner
+                .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+                .unwrap_or(inner.len());
+            let tag = &inner[..tag_end];
+            // `<Foo>` and `<foo.bar>` are components, `<div>` is an element
+            if let Some((head, _)) = tag.split_once('.') {
+                add(head);
+            } else if tag.starts_with(char::is_uppercase) {
+                add(tag);
+            }
+            rest = &inner[tag_end..];
+        } else {
+            rest = &rest[1..];
+        }
+    }
+    references
+}
+
+// The path heads in one mustache, skipping strings, numbers, `@args`,
+// and the names of named arguments.
+fn mustache_paths(inner: &str) -> Vec<&str> {
+    let inner = inner.trim_start_matches(['~', '#', '/', '^', '>']);
+    let inner = inner.trim_end_matches('~');
+    let mut paths = vec![];
+    let mut rest = inner;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '(' || c == ')');
+        let Some(first) = rest.chars().next() else {
+            break;
+        };
+        if first == '"' || first == '\'' {
+            rest = rest[1..].find(first).map_or("", |end| &rest[end + 2..]);
+            continue;
+        }
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '(' || c == ')' || c == '=')
+            .unwrap_or(rest.len());
+        let token = &rest[..end];
+        rest = &rest[end..];
+        if rest.starts_with('=') {
+            // a named argument's name
+            rest = &rest[1..];
+            continue;
+        }
+        if token == "as" {
+            // block params, which `block_params` takes care of
+            break;
+        }
+        let head = token.split('.').next().unwrap_or_default();
+        paths.push(head);
+    }
+    paths
+}
+
+fn block_params(content: &str) -> Vec<&str> {
+    content
+        .match_indices("as |")
+        .filter_map(|(at, pattern)| {
+            let params = &content[at + pattern.len()..];
+            params.find('|').map(|end| &params[..end])
+        })
+        .flat_map(|params| params.split_whitespace())
+        .collect()
+}
+
+// The warning for a reference the resolver couldn't find a binding for.
+#[cfg(feature = "transform")]
+pub(crate) fn unresolved_message(name: &str) -> String {
+    format!("`{name}` is used in the template but is not defined")
+}
+
+// The references are passed through the resolver as an extra property of
+// the template's options, and taken back out by `check_probes` before we
+// print.
+#[cfg(feature = "transform")]
+pub(crate) const PROBE_KEY: &str = "__content_tag_references__";
+
+#[cfg(feature = "transform")]
+pub(crate) fn probe(span: Span, names: &[String]) -> PropOrSpread {
+    PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+        key: PropName::Ident(Ident::new(PROBE_KEY.into(), span)),
+        value: Box::new(Expr::Array(ArrayLit {
+            span,
+            elems: names
+                .iter()
+                .map(|name| {
+                    Some(ExprOrSpread::from(Box::new(Expr::Ident(Ident::new(
+                        name.as_str().into(),
+                        span,
+                    )))))
+                })
+                .collect(),
+        })),
+    })))
+}
+
+// Removes every probe, returning the span of the template and the name for
+// each reference that the resolver couldn't find a binding for.
+#[cfg(feature = "transform")]
+pub(crate) fn check_probes(module: &mut Module, unresolved_mark: Mark) -> Vec<(Span, String)> {
+    let mut checker = ProbeChecker {
+        unresolved_mark,
+        unresolved: vec![],
+    };
+    module.visit_mut_with(&mut checker);
+    checker.unresolved
+}
+
+#[cfg(feature = "transform")]
+struct ProbeChecker {
+    unresolved_mark: Mark,
+    unresolved: Vec<(Span, String)>,
+}
+
+#[cfg(feature = "transform")]
+impl VisitMut for ProbeChecker {
+    fn visit_mut_object_lit(&mut self, n: &mut ObjectLit) {
+        n.visit_mut_children_with(self);
+        n.props.retain(|prop| match prop {
+            PropOrSpread::Prop(box Prop::KeyValue(KeyValueProp {
+                key: PropName::Ident(key),
+                value: box Expr::Array(array),
+            })) if &*key.sym == PROBE_KEY => {
+                for elem in array.elems.iter().flatten() {
+                    if let Expr::Ident(ident) = &*elem.expr {
+                        if ident.span.ctxt.outer() == self.unresolved_mark {
+                            self.unresolved.push((key.span, ident.sym.to_string()));
+                        }
+                    }
+                }
+                false
+            }
+            _ => true,
+        });
+    }
+}
+
+#[test]
+fn test_template_references() {
+    assert_eq!(
+        template_references(
+            r#"{{#each this.items key="id" as |item|}}
+                 <Greeting @name={{item.name}} {{on "click" (fn save item)}} />
+                 <ui.Button />
+                 {{format-date when}} {{! not me }} {{!-- nor {{me}} --}}
+               {{/each}}
+               <div class={{classy}}>{{@arg}} {{yield}}</div>"#
+        ),
+        vec!["Greeting", "save", "ui", "when", "classy"]
+    );
+}
+
+#[test]
+fn test_template_references_skips_strings() {
+    assert_eq!(
+        template_references(r#"{{t "hello world" name=user.name}}"#),
+        vec!["t", "user"]
+    );
+}
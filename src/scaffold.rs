@@ -0,0 +1,133 @@
+// Skeletons for new components, for `content-tag generate` and for editors
+// that want to offer the same snippet. A class-based one extends
+// `GLIMMER_COMPONENT`, like `Options::implicit_component_base` makes classes
+// do, and its template is a class member, like `merge_colocated` puts it.
+
+use crate::scope::is_identifier;
+use crate::{Error, GLIMMER_COMPONENT};
+
+const TEMPLATE_ONLY_TYPES: &str = "@ember/component/template-only";
+
+const BODY: &str = "  {{yield}}\n";
+
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct ComponentSkeleton {
+    // a class extending `@glimmer/component`, instead of a template-only
+    // component
+    pub class: bool,
+    // `.gts`, with a signature interface for the component
+    pub typescript: bool,
+}
+
+// The source of a component called `name`, which can be kebab-case like a
+// file name (`my-button` becomes `MyButton`).
+pub fn component_skeleton(name: &str, skeleton: ComponentSkeleton) -> Result<String, Error> {
+    let name = class_name(name).ok_or_else(|| {
+        Error::InvalidOptions(format!("{name:?} can't be the name of a component"))
+    })?;
+    let signature = format!("{name}Signature");
+    let mut src = String::new();
+    match skeleton {
+        ComponentSkeleton {
+            class: true,
+            typescript,
+        } => {
+            src.push_str(&format!("import Component from '{GLIMMER_COMPONENT}';\n\n"));
+            let generics = if typescript {
+                src.push_str(&signature_interface(&signature));
+                format!("<{signature}>")
+            } else {
+                String::new()
+            };
+            src.push_str(&format!(
+                "export default class {name} extends Component{generics} {{\n  <template>\n  {BODY}  </template>\n}}\n"
+            ));
+        }
+        ComponentSkeleton {
+            class: false,
+            typescript: true,
+        } => {
+            src.push_str(&format!(
+                "import type {{ TOC }} from '{TEMPLATE_ONLY_TYPES}';\n\n"
+            ));
+            src.push_str(&signature_interface(&signature));
+            src.push_str(&format!(
+                "const {name}: TOC<{signature}> = <template>\n{BODY}</template>;\n\nexport default {name};\n"
+            ));
+        }
+        ComponentSkeleton {
+            class: false,
+            typescript: false,
+        } => {
+            src.push_str(&format!("<template>\n{BODY}</template>\n"));
+        }
+    }
+    Ok(src)
+}
+
+fn signature_interface(signature: &str) -> String {
+    format!(
+        "export interface {signature} {{\n  Args: {{}};\n  Blocks: {{\n    default: [];\n  }};\n  Element: null;\n}}\n\n"
+    )
+}
+
+// `my-button` and `my_button` become `MyButton`; `MyButton` stays as it is.
+fn class_name(name: &str) -> Option<String> {
+    let name: String = name
+        .split(['-', '_'])
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map_or(String::new(), |first| {
+                first.to_uppercase().chain(chars).collect()
+            })
+        })
+        .collect();
+    is_identifier(&name).then_some(name)
+}
+
+#[cfg(test)]
+use crate::{ContentTagKind, Preprocessor};
+
+#[test]
+fn test_class_skeleton() {
+    let src = component_skeleton(
+        "my-button",
+        ComponentSkeleton {
+            class: true,
+            typescript: false,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        src,
+        "import Component from '@glimmer/component';
+
+export default class MyButton extends Component {
+  <template>
+    {{yield}}
+  </template>
+}
+"
+    );
+}
+
+#[test]
+fn test_skeletons_parse() {
+    for (class, typescript, kind) in [
+        (true, false, ContentTagKind::ClassMember),
+        (true, true, ContentTagKind::ClassMember),
+        (false, false, ContentTagKind::Expression),
+        (false, true, ContentTagKind::Expression),
+    ] {
+        let src = component_skeleton("Greeting", ComponentSkeleton { class, typescript }).unwrap();
+        let templates = Preprocessor::new().parse(&src, Default::default()).unwrap();
+        assert_eq!(templates.len(), 1, "{src}");
+        assert_eq!(templates[0].kind, kind);
+    }
+}
+
+#[test]
+fn test_rejects_invalid_names() {
+    assert!(component_skeleton("1st", Default::default()).is_err());
+    assert!(component_skeleton("", Default::default()).is_err());
+}
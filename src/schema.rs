@@ -0,0 +1,175 @@
+// The JSON model of `parse` results that we promise to JS consumers. This is
+// deliberately separate from `locate::Occurrence` so that internal changes
+// (including swc AST bumps) can't change what gets serialized. Removing or
+// changing a field must bump `SCHEMA_VERSION`; fields that only appear when
+// the caller opts in to them (like `leadingComments`), or are only left out
+// when the caller asks (like `contents`), don't.
+
+use serde::{Deserialize, Serialize};
+
+use swc_common::comments::CommentKind;
+
+use crate::locate::{ContentTagKind, Occurrence, Range, TemplateComment};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ParseOutput {
+    pub schema_version: u32,
+    pub templates: Vec<Parsed>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Parsed {
+    #[serde(rename = "type")]
+    pub kind: ParsedKind,
+    pub tag_name: String,
+    // left out when the caller asks for no content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contents: Option<String>,
+    pub range: Range,
+    pub start_range: Range,
+    pub content_range: Range,
+    pub end_range: Range,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leading_comments: Option<Vec<ParsedComment>>,
+    // the whitespace at the start of the opening tag's line, left out when
+    // there is none
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub indentation: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct ParsedComment {
+    pub kind: ParsedCommentKind,
+    pub text: String,
+    pub range: Range,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParsedCommentKind {
+    Line,
+    Block,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParsedKind {
+    Expression,
+    ClassMember,
+}
+
+impl From<&Occurrence> for Parsed {
+    fn from(value: &Occurrence) -> Self {
+        Parsed {
+            kind: match value.kind {
+                ContentTagKind::Expression => ParsedKind::Expression,
+                ContentTagKind::ClassMember => ParsedKind::ClassMember,
+            },
+            tag_name: value.tag_name.clone(),
+            contents: value.contents.clone(),
+            range: value.range,
+            start_range: value.start_range,
+            content_range: value.content_range,
+            end_range: value.end_range,
+            leading_comments: value
+                .leading_comments
+                .as_ref()
+                .map(|comments| comments.iter().map(ParsedComment::from).collect()),
+            indentation: value.indentation().to_owned(),
+        }
+    }
+}
+
+impl From<&TemplateComment> for ParsedComment {
+    fn from(value: &TemplateComment) -> Self {
+        ParsedComment {
+            kind: match value.kind {
+                CommentKind::Line => ParsedCommentKind::Line,
+                CommentKind::Block => ParsedCommentKind::Block,
+            },
+            text: value.text.clone(),
+            range: value.range,
+        }
+    }
+}
+
+impl From<&[Occurrence]> for ParseOutput {
+    fn from(value: &[Occurrence]) -> Self {
+        ParseOutput {
+            schema_version: SCHEMA_VERSION,
+            templates: value.iter().map(Parsed::from).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+use crate::Preprocessor;
+
+// These pin the serialized form of schema version 1. If one of them needs to
+// change, so does SCHEMA_VERSION.
+#[cfg(test)]
+const V1_JSON: &str = r#"{"schemaVersion":1,"templates":[{"type":"expression","tagName":"template","contents":"Hi","range":{"start":8,"end":31},"startRange":{"start":8,"end":18},"contentRange":{"start":18,"end":20},"endRange":{"start":20,"end":31}},{"type":"class-member","tagName":"template","contents":"","range":{"start":43,"end":64},"startRange":{"start":43,"end":53},"contentRange":{"start":53,"end":53},"endRange":{"start":53,"end":64}}]}"#;
+
+#[test]
+fn test_serializes_v1() {
+    let p = Preprocessor::new();
+    let occurrences = p
+        .parse(
+            "let x = <template>Hi</template>; class A { <template></template> }",
+            Default::default(),
+        )
+        .unwrap();
+    let output = ParseOutput::from(occurrences.as_slice());
+    assert_eq!(serde_json::to_string(&output).unwrap(), V1_JSON);
+}
+
+#[test]
+fn test_serializes_opt_in_comments() {
+    let p = Preprocessor::new();
+    let occurrences = p
+        .parse(
+            "/* hi */ <template></template>",
+            crate::Options {
+                leading_comments: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let output = ParseOutput::from(occurrences.as_slice());
+    let json = serde_json::to_string(&output.templates[0].leading_comments).unwrap();
+    assert_eq!(
+        json,
+        r#"[{"kind":"block","text":" hi ","range":{"start":0,"end":8}}]"#
+    );
+}
+
+#[test]
+fn test_leaves_out_skipped_content() {
+    let p = Preprocessor::new();
+    let occurrences = p
+        .parse(
+            "<template>Hi</template>",
+            crate::Options {
+                include_content: false,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let output = ParseOutput::from(occurrences.as_slice());
+    let json = serde_json::to_string(&output.templates[0]).unwrap();
+    assert!(!json.contains("contents"), "{json}");
+}
+
+#[test]
+fn test_deserializes_v1() {
+    let output: ParseOutput = serde_json::from_str(V1_JSON).unwrap();
+    assert_eq!(output.schema_version, 1);
+    assert_eq!(output.templates.len(), 2);
+    assert_eq!(output.templates[0].kind, ParsedKind::Expression);
+    assert_eq!(output.templates[1].kind, ParsedKind::ClassMember);
+    assert_eq!(output.templates[1].range, Range { start: 43, end: 64 });
+}
@@ -0,0 +1,105 @@
+// Templates normally get their scope from the `eval` trick. With
+// `Options::explicit_scope`, a `/* @scope a, b */` comment right before the
+// template lists its scope bindings instead.
+
+#[cfg(feature = "transform")]
+use swc_common::comments::{Comments, SingleThreadedComments};
+#[cfg(feature = "transform")]
+use swc_common::{BytePos, Mark, Span};
+#[cfg(feature = "transform")]
+use swc_ecma_ast::{Ident, Module};
+#[cfg(feature = "transform")]
+use swc_ecma_visit::{Visit, VisitWith};
+
+#[cfg(feature = "transform")]
+pub(crate) const DIRECTIVE: &str = "@scope";
+
+// The names listed by a `@scope` comment leading up to `pos`, and the span of
+// that comment. Names that can't be identifiers are an error.
+#[cfg(feature = "transform")]
+pub(crate) fn explicit_scope(
+    comments: &SingleThreadedComments,
+    pos: BytePos,
+) -> Option<Result<(Vec<String>, Span), String>> {
+    let comments = comments.get_leading(pos)?;
+    let comment = comments.iter().rev().find_map(|comment| {
+        comment
+            .text
+            .trim_start_matches(|c: char| c == '*' || c.is_whitespace())
+            .strip_prefix(DIRECTIVE)
+            .filter(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+            .map(|rest| (rest, comment.span))
+    });
+    let (list, span) = comment?;
+
+    let names: Vec<String> = list
+        .split(',')
+        .map(|name| name.trim().to_owned())
+        .filter(|name| !name.is_empty())
+        .collect();
+    match names.iter().find(|name| !is_identifier(name)) {
+        Some(name) => Some(Err(format!("`{name}` in {DIRECTIVE} is not an identifier"))),
+        None => Some(Ok((names, span))),
+    }
+}
+
+pub(crate) fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map_or(false, |c| c.is_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+// Run after the resolver: every name we emitted for an explicit scope carries
+// the span of its comment, so any of those that didn't resolve to a binding
+// are reported against the template (the first span of each pair).
+#[cfg(feature = "transform")]
+pub(crate) fn check_resolved(
+    module: &Module,
+    explicit_scopes: &[(Span, Span)],
+    unresolved_mark: Mark,
+) -> Vec<(Span, String)> {
+    let mut checker = ResolutionChecker {
+        explicit_scopes,
+        unresolved_mark,
+        errors: vec![],
+    };
+    module.visit_with(&mut checker);
+    checker.errors
+}
+
+#[cfg(feature = "transform")]
+struct ResolutionChecker<'a> {
+    explicit_scopes: &'a [(Span, Span)],
+    unresolved_mark: Mark,
+    errors: Vec<(Span, String)>,
+}
+
+#[cfg(feature = "transform")]
+impl<'a> Visit for ResolutionChecker<'a> {
+    fn visit_ident(&mut self, n: &Ident) {
+        if n.span.ctxt.outer() != self.unresolved_mark {
+            return;
+        }
+        let scope = self
+            .explicit_scopes
+            .iter()
+            .find(|(_, comment)| comment.lo == n.span.lo && comment.hi == n.span.hi);
+        if let Some((tag_span, _)) = scope {
+            self.errors.push((
+                *tag_span,
+                format!("`{}` is listed in {DIRECTIVE} but is not in scope", n.sym),
+            ));
+        }
+    }
+}
+
+#[test]
+fn test_is_identifier() {
+    assert!(is_identifier("Greeting"));
+    assert!(is_identifier("$_x1"));
+    assert!(!is_identifier("1x"));
+    assert!(!is_identifier("a.b"));
+    assert!(!is_identifier(""));
+}
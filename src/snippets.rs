@@ -62,6 +62,17 @@ fn generate_expression(span: Span, template_module: &Module) -> Box<Expr> {
         .clone()
 }
 
+// `names` have already been checked to be identifiers, so they can't change
+// the shape of what we parse.
+pub fn explicit_scope_params(span: Span, names: &[String], with_this: bool) -> Box<Expr> {
+    let component = if with_this { "component: this, " } else { "" };
+    let src = format!(
+        "({{ {component}scope: () => ({{ {} }}) }})",
+        names.join(", ")
+    );
+    generate_expression(span, &parse(&src))
+}
+
 pub fn scope_params(span: Span) -> Box<Expr> {
     generate_expression(span, &(*SCOPE_PARAMS))
 }
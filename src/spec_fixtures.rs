@@ -0,0 +1,95 @@
+// Runs a directory of spec-format fixtures: one directory per case, holding
+// `input.gjs` (or `input.gts`) and the expected results, `locate.json` (what
+// `parse` returns, as in the JS API) and/or `output.js` (what `process`
+// returns, with the import alias spelled `template_UUID`). Our own cases are
+// in `spec/fixtures`; set `CONTENT_TAG_SPEC_FIXTURES` to run another suite,
+// like the content-tag spec's.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::test_helpers::{expected_template_calls, template_calls, transform_diff};
+use crate::{ParseOutput, Parsed, Preprocessor};
+
+fn fixtures_dir() -> PathBuf {
+    match std::env::var_os("CONTENT_TAG_SPEC_FIXTURES") {
+        Some(dir) => dir.into(),
+        None => Path::new(env!("CARGO_MANIFEST_DIR")).join("spec/fixtures"),
+    }
+}
+
+// What's wrong with the case in `dir`, if anything.
+fn check(dir: &Path) -> Result<(), String> {
+    let input = ["input.gjs", "input.gts"]
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+        .ok_or("no input.gjs or input.gts")?;
+    let src = fs::read_to_string(&input).map_err(|err| err.to_string())?;
+    let locate = dir.join("locate.json");
+    let output = dir.join("output.js");
+    if !locate.is_file() && !output.is_file() {
+        return Err("no locate.json or output.js to check against".to_owned());
+    }
+
+    if locate.is_file() {
+        let expected: Vec<Parsed> =
+            serde_json::from_str(&fs::read_to_string(&locate).map_err(|err| err.to_string())?)
+                .map_err(|err| format!("locate.json: {err}"))?;
+        let occurrences = Preprocessor::new()
+            .parse(&src, Default::default())
+            .map_err(|err| format!("parse failed: {}", err.kind().msg()))?;
+        let actual = ParseOutput::from(occurrences.as_slice()).templates;
+        if actual != expected {
+            return Err(format!(
+                "parse returned\n{}",
+                serde_json::to_string_pretty(&actual).unwrap()
+            ));
+        }
+    }
+
+    if output.is_file() {
+        let expected = fs::read_to_string(&output).map_err(|err| err.to_string())?;
+        match transform_diff(&src, &expected) {
+            Ok(None) => {}
+            Ok(Some(diff)) => return Err(format!("process output differs:\n{diff}")),
+            Err(err) => return Err(format!("process failed: {err}")),
+        }
+        let calls = template_calls(&src)?;
+        let expected_calls = expected_template_calls(&expected);
+        if calls != expected_calls {
+            return Err(format!(
+                "template calls differ:\n{calls:#?}\nexpected:\n{expected_calls:#?}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_spec_fixtures() {
+    let dir = fixtures_dir();
+    let mut cases: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap_or_else(|err| panic!("can't read {}: {err}", dir.display()))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .collect();
+    cases.sort();
+    assert!(!cases.is_empty(), "no fixtures in {}", dir.display());
+
+    let failures: Vec<String> = cases
+        .iter()
+        .filter_map(|case| {
+            check(case)
+                .err()
+                .map(|err| format!("{}: {err}", case.display()))
+        })
+        .collect();
+    assert!(
+        failures.is_empty(),
+        "{} of {} fixtures failed:\n\n{}",
+        failures.len(),
+        cases.len(),
+        failures.join("\n\n")
+    );
+}
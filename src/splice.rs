@@ -0,0 +1,518 @@
+// `Preprocessor::process_with_ranges`: building the output from templates an
+// earlier `parse` found, by splicing our calls into the source text instead of
+// parsing and printing the whole module again. Everything outside the
+// templates is copied as is, so only the options that work on template
+// content are available.
+
+use swc_common::source_map::LineCol;
+use swc_common::{BytePos, SourceFile, Span};
+
+use crate::locate::{ContentTagKind, ExpressionRole, Occurrence, Range};
+use crate::scope::is_identifier;
+use crate::transform::{self, TransformConfig};
+use crate::transformer::{ImportNames, ImportRequest, TemplateContext};
+use crate::{content, DefaultExportPlacement, Error, EsVersion, Options, SnippetVersion};
+
+// Replacing `range` in the source with `text`.
+pub(crate) struct Edit {
+    pub range: Range,
+    pub text: String,
+}
+
+pub(crate) struct Spliced {
+    pub code: String,
+    // the same changes, as edits of the source in order
+    pub edits: Vec<Edit>,
+    pub mappings: Vec<(BytePos, LineCol)>,
+    // the span of each template, in source order
+    pub replaced: Vec<Span>,
+    pub requested_imports: Vec<ImportRequest>,
+    pub errors: Vec<(Span, String)>,
+    // the span of each template with something to warn about, and the
+    // warning's code and message
+    pub warnings: Vec<(Span, &'static str, String)>,
+}
+
+// The options that need the module's AST, which we don't have.
+pub(crate) fn check_options(options: &Options) -> Result<(), Error> {
+    let needs_ast = [
+        ("verify_output", options.verify_output),
+        ("hbs_tagged_templates", options.hbs_tagged_templates),
+        (
+            "precompile_template_calls",
+            options.precompile_template_calls,
+        ),
+        ("explicit_scope", options.explicit_scope),
+        ("forbid_eval", options.forbid_eval),
+        (
+            "component_this_in_methods",
+            options.component_this_in_methods,
+        ),
+        ("warn_unresolved", options.warn_unresolved),
+        ("prune_unused_imports", options.prune_unused_imports),
+        ("simplify_imports", options.simplify_imports),
+        ("minify", options.minify),
+        (
+            "detect_already_transformed",
+            options.detect_already_transformed,
+        ),
+        ("collect_exports", options.collect_exports),
+        ("strip_types", options.strip_types),
+        (
+            "default_export_placement",
+            options.default_export_placement != DefaultExportPlacement::InPlace,
+        ),
+        (
+            "implicit_component_base",
+            options.implicit_component_base.is_some(),
+        ),
+        (
+            "target",
+            options
+                .target
+                .map_or(false, |target| target < EsVersion::Es2022),
+        ),
+    ];
+    match needs_ast.iter().find(|(_, enabled)| *enabled) {
+        Some((name, _)) => Err(Error::InvalidOptions(format!(
+            "`{name}` needs the module's AST, which isn't parsed when splicing templates into the source"
+        ))),
+        None => Ok(()),
+    }
+}
+
+// The output line and column we're at, in UTF-16 code units like source maps
+// want them.
+struct Output<'a> {
+    file: &'a SourceFile,
+    code: String,
+    line: u32,
+    col: u32,
+    mappings: Vec<(BytePos, LineCol)>,
+}
+
+impl<'a> Output<'a> {
+    fn push(&mut self, text: &str) {
+        for c in text.chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.col = 0;
+            } else {
+                self.col += c.len_utf16() as u32;
+            }
+        }
+        self.code.push_str(text);
+    }
+
+    // Maps the current position to `offset` in the source.
+    fn map(&mut self, offset: usize) {
+        self.mappings.push((
+            self.file.start_pos + BytePos(offset as u32),
+            LineCol {
+                line: self.line,
+                col: self.col,
+            },
+        ));
+    }
+
+    // Copies `src[start..end]`, mapping the start of each of its lines.
+    fn copy(&mut self, src: &str, start: usize, end: usize) {
+        let mut offset = start;
+        for line in src[start..end].split_inclusive('\n') {
+            self.map(offset);
+            self.push(line);
+            offset += line.len();
+        }
+    }
+}
+
+// `templates` have to be the ones `parse` found in `src`, so that their
+// ranges are where the tags are.
+pub(crate) fn splice(
+    file: &SourceFile,
+    src: &str,
+    templates: &[Occurrence],
+    callee: &str,
+    import: (&str, &str),
+    config: &TransformConfig,
+) -> Result<Spliced, Error> {
+    let mut templates: Vec<&Occurrence> = templates.iter().collect();
+    templates.sort_by_key(|template| template.range.start);
+    let mut end = 0;
+    // taken from the source, since `parse` leaves them out with
+    // `Options::include_content: false`
+    let mut contents = Vec::with_capacity(templates.len());
+    for template in &templates {
+        if template.range.start < end {
+            return Err(mismatch(format!(
+                "the template at {}..{} overlaps the one before it",
+                template.range.start, template.range.end
+            )));
+        }
+        let content = template
+            .full_slice(src)
+            .and_then(|_| template.content_slice(src))
+            .map_err(|err| mismatch(err.to_string()))?;
+        contents.push(content);
+        end = template.range.end;
+    }
+
+    let span = |start: usize, end: usize| {
+        Span::new(
+            file.start_pos + BytePos(start as u32),
+            file.start_pos + BytePos(end as u32),
+            Default::default(),
+        )
+    };
+    let mut output = Output {
+        file,
+        code: String::with_capacity(src.len() + templates.len() * 80),
+        line: 0,
+        col: 0,
+        mappings: vec![],
+    };
+    let mut replaced = vec![];
+    let mut requested_imports: Vec<ImportRequest> = vec![];
+    let names = ImportNames::new(src);
+    let mut errors = vec![];
+    let mut edits = vec![];
+    let mut warnings = vec![];
+    let mut pos = 0;
+    for (template, contents) in templates.into_iter().zip(contents) {
+        output.copy(src, pos, template.range.start);
+        let tag_span = span(template.range.start, template.range.end);
+        let context = TemplateContext::with_names(template.kind, template.range, names.clone());
+        for (code, message) in transform::content_warnings(config, contents) {
+            warnings.push((tag_span, code, message));
+        }
+        let (value, requested) = transform::content_value(config, contents, context);
+        for request in requested {
+            if !requested_imports.contains(&request) {
+                requested_imports.push(request);
+            }
+        }
+        let value = value.unwrap_or_else(|message| {
+            errors.push((tag_span, message));
+            contents.to_owned()
+        });
+
+        let prefix = src[..template.range.start].trim_end();
+        let (open, close, scope) = match template.kind {
+            ContentTagKind::ClassMember if config.snippet_version == SnippetVersion::V1 => {
+                ("static { ", "; }", "")
+            }
+            ContentTagKind::ClassMember => ("static { ", "; }", "component: this, "),
+            // a bare `<template>` at the top level is the default export
+            ContentTagKind::Expression
+                if template.role == Some(ExpressionRole::DefaultExport)
+                    && !prefix.ends_with("default")
+                    && !prefix.ends_with('(') =>
+            {
+                ("export default ", ";", "")
+            }
+            // so that `new <template>` calls what the template returns
+            ContentTagKind::Expression if ends_with_keyword(prefix, "new") => ("(", ")", ""),
+            ContentTagKind::Expression => ("", "", ""),
+        };
+        output.map(template.range.start);
+        let generated_start = output.code.len();
+        output.push(open);
+        output.push(callee);
+        output.push("(`");
+        let (trim_start, _) = content::trim_amounts(contents, config.trim_content);
+        let content_start = template.content_range.start + trim_start;
+        output.map(content_start);
+        let (escaped, escapes) = transform::escape_with_offsets(&value);
+        if src[content_start..].starts_with(value.as_str()) {
+            // the content as written, so each of its lines maps to where it
+            // is in the source
+            let mut copied = 0;
+            for (line_break, _) in value.match_indices('\n') {
+                let at = transform::escaped_offset(&escapes, line_break + 1);
+                output.push(&escaped[copied..at]);
+                output.map(content_start + line_break + 1);
+                copied = at;
+            }
+            output.push(&escaped[copied..]);
+        } else {
+            output.push(&escaped);
+        }
+        output.push("`, { ");
+        output.push(scope);
+        output.push("eval() { return eval(arguments[0]); } })");
+        output.push(close);
+        edits.push(Edit {
+            range: template.range,
+            text: output.code[generated_start..].to_owned(),
+        });
+        replaced.push(tag_span);
+        pos = template.range.end;
+    }
+    output.copy(src, pos, src.len());
+
+    let imports = imports(import, callee, &requested_imports);
+    // a hashbang has to stay on the first line
+    let at = if src.starts_with("#!") {
+        src.find('\n').map_or(src.len(), |pos| pos + 1)
+    } else {
+        0
+    };
+    let code = format!("{}{imports}{}", &output.code[..at], &output.code[at..]);
+    edits.insert(
+        0,
+        Edit {
+            range: Range { start: at, end: at },
+            text: imports.clone(),
+        },
+    );
+    let mut mappings = output.mappings;
+    let lines = imports.matches('\n').count() as u32;
+    for (_, line_col) in &mut mappings {
+        if line_col.line > 0 || at == 0 {
+            line_col.line += lines;
+        }
+    }
+    Ok(Spliced {
+        code,
+        edits,
+        mappings,
+        replaced,
+        requested_imports,
+        errors,
+        warnings,
+    })
+}
+
+fn mismatch(detail: String) -> Error {
+    Error::InvalidOptions(format!(
+        "the templates given to `process_with_ranges` don't match the source: {detail}"
+    ))
+}
+
+fn ends_with_keyword(text: &str, keyword: &str) -> bool {
+    text.strip_suffix(keyword).map_or(false, |before| {
+        !before.ends_with(|c: char| c.is_alphanumeric() || c == '_' || c == '$')
+    })
+}
+
+// The same imports `process` inserts, in the same order, one per line.
+fn imports((module, specifier): (&str, &str), local: &str, requested: &[ImportRequest]) -> String {
+    std::iter::once((module, specifier, local))
+        .chain(requested.iter().map(|request| {
+            (
+                request.module.as_str(),
+                request.specifier.as_str(),
+                request.local.as_str(),
+            )
+        }))
+        .map(|(module, specifier, local)| {
+            let specifier = if is_identifier(specifier) {
+                specifier.to_owned()
+            } else {
+                string_literal(specifier)
+            };
+            format!(
+                "import {{ {specifier} as {local} }} from {};\n",
+                string_literal(module)
+            )
+        })
+        .collect()
+}
+
+fn string_literal(value: &str) -> String {
+    serde_json::to_string(value).unwrap()
+}
+
+#[cfg(test)]
+use crate::test_helpers::normalize;
+#[cfg(test)]
+use crate::Preprocessor;
+
+// The same output as `process`, up to formatting.
+#[cfg(test)]
+fn assert_matches_process(src: &str) {
+    assert_matches_process_with(src, Options::default);
+}
+
+#[cfg(test)]
+fn assert_matches_process_with(src: &str, options: impl Fn() -> Options) {
+    let templates = Preprocessor::new().parse(src, Default::default()).unwrap();
+    let spliced = Preprocessor::new()
+        .process_with_ranges(src, &templates, options())
+        .unwrap();
+    let processed = Preprocessor::new().process(src, options()).unwrap();
+    assert_eq!(normalize(&spliced.code), normalize(&processed.code));
+    assert_eq!(
+        spliced.metadata.replacements.len(),
+        processed.metadata.replacements.len()
+    );
+}
+
+#[test]
+fn test_matches_process() {
+    assert_matches_process("let x = <template>Hi `${there}`</template>;");
+    assert_matches_process("export default <template>Hi</template>");
+    assert_matches_process("import Foo from './foo';\n<template><Foo /></template>");
+    assert_matches_process("class X {\n  <template>Hello</template>\n}");
+    assert_matches_process("let x = new <template>X</template>();");
+    assert_matches_process("let x = f(<template>A</template>, (<template>B</template>));");
+    assert_matches_process("let x = { a: <template>A</template>, b: [<template>B</template>] };");
+}
+
+#[test]
+fn test_matches_process_for_each_snippet_version() {
+    for snippet_version in [SnippetVersion::V1, SnippetVersion::V2] {
+        assert_matches_process_with("export class A { <template>Hi</template> }", || Options {
+            snippet_version,
+            ..Default::default()
+        });
+    }
+}
+
+#[test]
+fn test_matches_process_with_region_markers() {
+    for src in [
+        "let x = <template>Hi</template>;",
+        "class X {\n  <template>Hello</template>\n}",
+    ] {
+        assert_matches_process_with(src, || Options {
+            mark_generated_regions: true,
+            ..Default::default()
+        });
+    }
+}
+
+#[test]
+fn test_matches_process_with_pure_annotations() {
+    for src in [
+        "const A = <template>A</template>;\nf(<template>B</template>);",
+        "<template>Hi</template>",
+    ] {
+        assert_matches_process_with(src, || Options {
+            pure_annotations: true,
+            ..Default::default()
+        });
+    }
+}
+
+#[test]
+fn test_reports_metadata() {
+    let src = "#!/usr/bin/env node\nlet x = <template>Hi</template>;\n";
+    let p = Preprocessor::new();
+    let templates = p.parse(src, Default::default()).unwrap();
+    let output = Preprocessor::new()
+        .process_with_ranges(src, &templates, Default::default())
+        .unwrap();
+    assert!(output
+        .code
+        .starts_with("#!/usr/bin/env node\nimport { template as "));
+    let replacement = &output.metadata.replacements[0];
+    assert_eq!(replacement.original_range, templates[0].range());
+    assert!(output.code[replacement.range.start..].starts_with(crate::IMPORT_ALIAS));
+    assert!(output.code[..replacement.range.end].ends_with("} })"));
+    assert!(output.metadata.inserted_import.is_some());
+    assert_eq!(output.metadata.eval_usages.len(), 1);
+}
+
+#[test]
+fn test_ranges_after_another_file() {
+    let src = "let x = <template> </template>;\n";
+    let p = Preprocessor::new();
+    p.process(
+        "let a = 1;\nlet y = <template>Bye</template>;",
+        Default::default(),
+    )
+    .unwrap();
+    let templates = p.parse(src, Default::default()).unwrap();
+    let output = p
+        .process_with_ranges(
+            src,
+            &templates,
+            Options {
+                collapse_whitespace_only: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let range = templates[0].range();
+    assert_eq!(range.slice(src).unwrap(), "<template> </template>");
+    assert_eq!(output.metadata.replacements[0].original_range, range);
+    assert_eq!(output.warnings[0].code, "whitespace-only-template");
+    assert_eq!(output.warnings[0].range, range);
+}
+
+#[test]
+fn test_takes_content_from_the_source() {
+    let src = "let x = <template>Hi</template>;";
+    let templates = Preprocessor::new()
+        .parse(
+            src,
+            Options {
+                include_content: false,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(templates[0].contents(), "");
+    let spliced = Preprocessor::new()
+        .process_with_ranges(src, &templates, Default::default())
+        .unwrap();
+    assert!(spliced.code.contains("(`Hi`"));
+}
+
+#[test]
+fn test_maps_each_line_of_content() {
+    let src = "let x = <template>\n  <p>`a`</p>\n  <p>b</p>\n</template>;";
+    let p = Preprocessor::new();
+    let templates = p.parse(src, Default::default()).unwrap();
+    let file = p.last_file.borrow().clone().unwrap();
+    let spliced = splice(
+        &file,
+        src,
+        &templates,
+        "t",
+        ("m", "template"),
+        &TransformConfig::default(),
+    )
+    .unwrap();
+    // the import is the first line of the output
+    for (source, line) in [("  <p>`a`", 2), ("  <p>b", 3), ("</template>", 4)] {
+        let at = file.start_pos + BytePos(src.find(source).unwrap() as u32);
+        assert!(
+            spliced
+                .mappings
+                .iter()
+                .any(|(pos, line_col)| *pos == at && line_col.line == line && line_col.col == 0),
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn test_rejects_ranges_from_other_sources() {
+    let templates = Preprocessor::new()
+        .parse("let x = <template>Hi</template>;", Default::default())
+        .unwrap();
+    let result = Preprocessor::new().process_with_ranges(
+        "let yy = <template>Hi</template>;",
+        &templates,
+        Default::default(),
+    );
+    assert!(matches!(result, Err(Error::InvalidOptions(_))));
+}
+
+#[test]
+fn test_rejects_options_that_need_the_ast() {
+    let result = Preprocessor::new().process_with_ranges(
+        "",
+        &[],
+        Options {
+            explicit_scope: true,
+            ..Default::default()
+        },
+    );
+    assert_eq!(
+        result.err().unwrap().to_string(),
+        "Invalid options: `explicit_scope` needs the module's AST, which isn't parsed when splicing templates into the source"
+    );
+}
@@ -0,0 +1,87 @@
+// How far a file is from strict-mode templates: which of its templates could
+// list their scope with `explicit_scope`, because every name they use is
+// defined where they are, and which still need `eval` to find theirs. For
+// dashboards that track a migration away from `eval`.
+
+use serde::Serialize;
+
+use crate::locate::Range;
+use crate::references::{template_references, unresolved_message};
+use crate::{Error, FileId, Options, Preprocessor};
+
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+pub struct StrictTemplate {
+    // the span of the whole tag
+    pub range: Range,
+    // the names the template uses from JS, as far as a quick scan can tell
+    pub references: Vec<String>,
+    // the ones of `references` that aren't defined where the template is
+    pub unresolved: Vec<String>,
+}
+
+impl StrictTemplate {
+    // Whether the template can do without `eval`.
+    pub fn is_strict(&self) -> bool {
+        self.unresolved.is_empty()
+    }
+}
+
+pub(crate) fn report(
+    preprocessor: &Preprocessor,
+    src: &str,
+    filename: Option<&FileId>,
+) -> Result<Vec<StrictTemplate>, Error> {
+    let options = Options {
+        warn_unresolved: true,
+        ..Default::default()
+    };
+    let output = preprocessor.process_with(src, &options, filename)?;
+    Ok(output
+        .metadata
+        .replacements
+        .iter()
+        .map(|replacement| {
+            let range = replacement.original_range;
+            let references = template_references(content(&src[range.start..range.end]));
+            let unresolved = references
+                .iter()
+                .filter(|name| {
+                    let message = unresolved_message(name);
+                    output
+                        .warnings
+                        .iter()
+                        .any(|warning| warning.range == range && warning.message == message)
+                })
+                .cloned()
+                .collect();
+            StrictTemplate {
+                range,
+                references,
+                unresolved,
+            }
+        })
+        .collect())
+}
+
+// What's between the opening and closing tags of `tag`.
+fn content(tag: &str) -> &str {
+    let start = tag.find('>').map_or(0, |end| end + 1);
+    let end = tag.rfind("</").unwrap_or(tag.len()).max(start);
+    &tag[start..end]
+}
+
+#[test]
+fn test_report() {
+    let src = "import Greeting from './greeting';
+export const A = <template><Greeting @name={{name}} /></template>;
+export const B = <template><Greeting /></template>;
+export const C = <template>Hi</template>;";
+    let templates = Preprocessor::new().strict_report(src).unwrap();
+    assert_eq!(templates.len(), 3);
+    assert_eq!(templates[0].references, vec!["Greeting", "name"]);
+    assert_eq!(templates[0].unresolved, vec!["name"]);
+    assert!(!templates[0].is_strict());
+    assert!(templates[1].is_strict());
+    assert!(templates[2].is_strict());
+    assert!(src[templates[2].range.start..].starts_with("<template>Hi"));
+}
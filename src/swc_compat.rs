@@ -0,0 +1,67 @@
+// The parts of swc's API that change from one swc release to the next: how
+// the parser is configured, how the code generator is set up and how the
+// resolver is called. Everything outside of tests goes through here, so
+// moving to another swc release (or a rebase of the fork) only changes this
+// file.
+//
+// This is not a way to build against more than one swc release. Content tags
+// only parse with the fork in `Cargo.toml`, and the fork follows a single swc
+// release, so there is no second version for a feature to select. Once the
+// fork has a branch for another release, a feature choosing between the two
+// belongs here and nowhere else.
+
+#[cfg(feature = "transform")]
+use swc_common::comments::SingleThreadedComments;
+#[cfg(feature = "transform")]
+use swc_common::source_map::LineCol;
+#[cfg(feature = "transform")]
+use swc_common::{sync::Lrc, BytePos, Mark, SourceMap};
+#[cfg(feature = "transform")]
+use swc_ecma_ast::Module;
+#[cfg(feature = "transform")]
+use swc_ecma_codegen::{text_writer::JsWriter, Emitter};
+use swc_ecma_parser::{Syntax, TsConfig};
+#[cfg(feature = "transform")]
+use swc_ecma_transforms::resolver;
+#[cfg(feature = "transform")]
+use swc_ecma_visit::VisitMutWith;
+
+// What we parse `.gjs` and `.gts` files as. TypeScript is a superset of what
+// we need for `.gjs`, and decorators are the legacy ones Ember uses.
+pub(crate) fn syntax() -> Syntax {
+    Syntax::Typescript(TsConfig {
+        decorators: true,
+        ..Default::default()
+    })
+}
+
+// Prints `module`, recording where each of its spans ended up in
+// `mappings` when asked to.
+#[cfg(feature = "transform")]
+pub(crate) fn print(
+    source_map: &Lrc<SourceMap>,
+    module: &Module,
+    minify: bool,
+    comments: Option<&SingleThreadedComments>,
+    mappings: Option<&mut Vec<(BytePos, LineCol)>>,
+) -> std::io::Result<Vec<u8>> {
+    let mut buf = vec![];
+    let mut emitter = Emitter {
+        cfg: swc_ecma_codegen::Config {
+            minify,
+            ..Default::default()
+        },
+        cm: source_map.clone(),
+        wr: JsWriter::new(source_map.clone(), "\n", &mut buf, mappings),
+        comments: comments.map(|comments| comments as _),
+    };
+    emitter.emit_module(module)?;
+    Ok(buf)
+}
+
+// Resolves the identifiers in `module` that don't have a syntax context yet,
+// the way swc's resolver does for plain JavaScript.
+#[cfg(feature = "transform")]
+pub(crate) fn resolve(module: &mut Module, unresolved_mark: Mark, top_level_mark: Mark) {
+    module.visit_mut_with(&mut resolver(unresolved_mark, top_level_mark, false));
+}
@@ -0,0 +1,85 @@
+// Routing each kind of content tag to its own transformers and compiler
+// import. The parser only produces `<template>` for now, but `Options::tags`
+// is keyed by tag name so that others (like `<style>`) can be handled
+// separately once it learns them.
+
+#[cfg(feature = "transform")]
+use std::collections::BTreeMap;
+
+#[cfg(feature = "transform")]
+use crate::Error;
+use crate::Transformer;
+
+// The tags the parser understands.
+pub const SUPPORTED_TAGS: &[&str] = &["template"];
+
+pub struct TagHandler {
+    // run after `Options::transformers`, in order
+    pub transformers: Vec<Box<dyn Transformer>>,
+    // the import that the tag's content is passed to
+    pub import_module: String,
+    pub import_specifier: String,
+}
+
+impl Default for TagHandler {
+    fn default() -> Self {
+        TagHandler {
+            transformers: vec![],
+            import_module: "@ember/template-compiler".to_owned(),
+            import_specifier: "template".to_owned(),
+        }
+    }
+}
+
+// Once any tag is configured, every tag we can parse needs a handler, so that
+// a file never silently falls back to the default compiler.
+#[cfg(feature = "transform")]
+pub(crate) fn validate(tags: &BTreeMap<String, TagHandler>) -> Result<(), Error> {
+    if tags.is_empty() {
+        return Ok(());
+    }
+    if let Some(name) = tags
+        .keys()
+        .find(|name| !SUPPORTED_TAGS.contains(&name.as_str()))
+    {
+        return Err(Error::InvalidOptions(format!(
+            "`tags` configures <{name}>, but only {} can be parsed",
+            supported_list()
+        )));
+    }
+    if let Some(name) = SUPPORTED_TAGS
+        .iter()
+        .find(|name| !tags.contains_key(**name))
+    {
+        return Err(Error::InvalidOptions(format!(
+            "`tags` has no handler for <{name}>"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "transform")]
+fn supported_list() -> String {
+    SUPPORTED_TAGS
+        .iter()
+        .map(|name| format!("<{name}>"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_validate() {
+    assert!(validate(&BTreeMap::new()).is_ok());
+
+    let mut tags = BTreeMap::new();
+    tags.insert("style".to_owned(), TagHandler::default());
+    assert_eq!(
+        validate(&tags).unwrap_err().to_string(),
+        "Invalid options: `tags` configures <style>, but only <template> can be parsed"
+    );
+
+    tags.clear();
+    tags.insert("template".to_owned(), TagHandler::default());
+    assert!(validate(&tags).is_ok());
+}
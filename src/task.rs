@@ -0,0 +1,99 @@
+// `process` for async Rust servers (like a playground backend): the work runs
+// on tokio's blocking pool, so that a slow file doesn't hold up the runtime's
+// other tasks, and it can be cancelled between phases.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::{CodeMapPair, Error, Options, Preprocessor};
+
+// Stops a `process_async` call at its next phase (parsing, transforming,
+// resolving), which then fails with `Error::Cancelled`. Cloning shares the
+// same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// Cancels the work when the future is dropped before it finishes, since
+// nothing would see the result.
+struct CancelOnDrop(Option<CancelHandle>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(handle) = self.0.take() {
+            handle.cancel();
+        }
+    }
+}
+
+// `options` is built on the blocking thread, because `Options` holds
+// transformers that don't have to be `Send`. Has to be called from within a
+// tokio runtime.
+pub async fn process_async(
+    src: String,
+    options: impl FnOnce() -> Options + Send + 'static,
+    cancel: CancelHandle,
+) -> Result<CodeMapPair, Error> {
+    let mut guard = CancelOnDrop(Some(cancel.clone()));
+    let flag = cancel.0;
+    let result = tokio::task::spawn_blocking(move || {
+        if flag.load(Ordering::Relaxed) {
+            return Err(Error::Cancelled);
+        }
+        let mut preprocessor = Preprocessor::new();
+        preprocessor.cancelled = Some(flag);
+        preprocessor.process(&src, options())
+    })
+    .await;
+    guard.0 = None;
+    result.unwrap_or_else(|err| {
+        Err(Error::internal(
+            "the blocking task for `process_async` failed",
+            Some(err.to_string()),
+        ))
+    })
+}
+
+#[cfg(test)]
+fn block_on<T>(future: impl std::future::Future<Output = T>) -> T {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[test]
+fn test_process_async() {
+    let output = block_on(process_async(
+        "<template>Hi</template>".to_owned(),
+        Options::default,
+        CancelHandle::new(),
+    ))
+    .unwrap();
+    assert!(output.code.contains("`Hi`"));
+}
+
+#[test]
+fn test_cancelled_before_it_starts() {
+    let cancel = CancelHandle::new();
+    cancel.cancel();
+    let result = block_on(process_async(
+        "<template>Hi</template>".to_owned(),
+        Options::default,
+        cancel,
+    ));
+    assert!(matches!(result, Err(Error::Cancelled)));
+}
@@ -4,27 +4,50 @@ use swc_common::{self, sync::Lrc, FileName, SourceMap};
 use swc_ecma_codegen::Emitter;
 use swc_ecma_parser::{lexer::Lexer, Parser, StringInput, Syntax, TsConfig};
 
-use crate::Preprocessor;
+use crate::locate::Range;
+use crate::metadata;
 use crate::IMPORT_ALIAS;
+use crate::{Error, Options, Preprocessor};
 
-pub fn testcase(input: &str, expected: &str) -> Result<(), swc_ecma_parser::error::Error> {
+pub fn testcase(input: &str, expected: &str) -> Result<(), Error> {
+    if let Some(diff) = transform_diff(input, expected)? {
+        panic!("code differs from expected:\n{diff}");
+    }
+    Ok(())
+}
+
+// How the processed `input` differs from `expected`, ignoring formatting and
+// the generated import alias (which `expected` spells `template_UUID`).
+pub fn transform_diff(input: &str, expected: &str) -> Result<Option<String>, Error> {
     let p = Preprocessor::new();
-    let actual = p.process(input, Default::default())?;
-    let actual_santized = actual.code.replace(IMPORT_ALIAS, "template_UUID");
+    let actual = p.process(
+        input,
+        Options {
+            verify_output: true,
+            ..Default::default()
+        },
+    )?;
+    // files without templates come back as they were instead of reprinted,
+    // so they need the same normalizing as `expected`
+    let actual_code = if actual.code == input {
+        normalize(&actual.code)
+    } else {
+        actual.code.clone()
+    };
+    let actual_santized = actual_code.replace(IMPORT_ALIAS, "template_UUID");
     let normalized_expected = normalize(expected);
-    if actual_santized != normalized_expected {
-        panic!(
-            "code differs from expected:\n{}",
-            format!("{}", Changeset::new(&actual_santized, &normalized_expected, "\n"))
-        );
-    }
 
     assert!(!actual.map.is_empty(), "expected .map to not be empty");
 
-    Ok(())
+    if actual_santized != normalized_expected {
+        return Ok(Some(
+            Changeset::new(&actual_santized, &normalized_expected, "\n").to_string(),
+        ));
+    }
+    Ok(None)
 }
 
-fn normalize(src: &str) -> String {
+pub fn normalize(src: &str) -> String {
     let filename = "test.js".into();
 
     let source_map: Lrc<SourceMap> = Default::default();
@@ -59,3 +82,79 @@ fn normalize(src: &str) -> String {
     let s = String::from_utf8_lossy(&buf);
     s.to_string()
 }
+
+// The template call `process` makes of each template in `input`, in the order
+// they're printed, reprinted by `normalize` with the import alias spelled
+// `template_UUID`. `process` only runs the resolver for the options that need
+// it, so this also checks that running it (with `warn_unresolved`, which only
+// adds warnings) changes neither the code nor the metadata, and that there's
+// a replacement for each template `parse` finds.
+pub fn template_calls(input: &str) -> Result<Vec<String>, String> {
+    let process = |options| {
+        Preprocessor::new()
+            .process(input, options)
+            .map_err(|err| format!("process failed: {err}"))
+    };
+    let without = process(Default::default())?;
+    let with = process(Options {
+        warn_unresolved: true,
+        ..Default::default()
+    })?;
+    if with.code != without.code {
+        return Err(format!(
+            "process output differs with the resolver:\n{}\nwithout it:\n{}",
+            with.code, without.code
+        ));
+    }
+    if with.metadata != without.metadata {
+        return Err(format!(
+            "metadata differs with the resolver:\n{:#?}\nwithout it:\n{:#?}",
+            with.metadata, without.metadata
+        ));
+    }
+
+    let templates = Preprocessor::new()
+        .parse(input, Default::default())
+        .map_err(|err| format!("parse failed: {}", err.kind().msg()))?;
+    let expected: Vec<Range> = templates.iter().map(|template| template.range).collect();
+    let metadata = without.metadata;
+    let mut replaced: Vec<Range> = metadata
+        .replacements
+        .iter()
+        .map(|replacement| replacement.original_range)
+        .collect();
+    // in source order: a template can be printed before one above it
+    replaced.sort_by_key(|range| range.start);
+    if replaced != expected {
+        return Err(format!(
+            "the replacements are for {replaced:?}, but the templates are at {expected:?}"
+        ));
+    }
+    let Some(import) = metadata.inserted_import else {
+        return Ok(vec![]);
+    };
+    let ranges: Vec<Range> = metadata
+        .replacements
+        .iter()
+        .map(|replacement| replacement.range)
+        .collect();
+    let calls = metadata::generated_calls(&without.code, &import.local);
+    if ranges != calls {
+        return Err(format!(
+            "the replacements are at {ranges:?}, but the calls are at {calls:?}"
+        ));
+    }
+    Ok(expected_template_calls(
+        &without.code.replace(&import.local, "template_UUID"),
+    ))
+}
+
+// The template calls in `expected`, the expected output of `process`,
+// reprinted the way `normalize` does.
+pub fn expected_template_calls(expected: &str) -> Vec<String> {
+    let code = normalize(expected);
+    metadata::generated_calls(&code, "template_UUID")
+        .into_iter()
+        .map(|call| code[call.start..call.end].to_owned())
+        .collect()
+}
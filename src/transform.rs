@@ -9,29 +9,265 @@ use swc_core::ecma::{
 };
 
 use swc_ecma_ast::{
-    ContentTagContent, ExportDefaultExpr, ExprOrSpread, ModuleDecl, ModuleItem, Tpl, TplElement,
+    Class, ClassDecl, ClassExpr, ClassMethod, ClassProp, Constructor, Decl, Decorator, DefaultDecl,
+    EmptyStmt, ExportDecl, ExportDefaultDecl, ExportDefaultExpr, ExportSpecifier, ExprOrSpread,
+    Function, GetterProp, KeyValueProp, Lit, MemberExpr, Module, ModuleDecl, ModuleExportName,
+    ModuleItem, NewExpr, ParenExpr, PrivateMethod, PrivateProp, Prop, PropName, PropOrSpread,
+    SetterProp, Str, TaggedTpl, Tpl, TplElement,
 };
 
-use swc_atoms::Atom;
+use std::rc::Rc;
+
+use swc_atoms::{Atom, JsWord};
+use swc_common::comments::SingleThreadedComments;
+use swc_common::{util::take::Take, BytePos, Span, Spanned};
+
+use crate::content::{self, TrimContent};
+use crate::legacy;
+use crate::locate::ContentTagKind;
+use crate::transformer::{ImportNames, ImportRequest, TemplateContext, Transformer};
+use crate::{references, scope, snippets, SnippetVersion};
+
+#[derive(Default, Clone)]
+pub struct TransformConfig<'a> {
+    // strip the indentation shared by the lines of each template
+    pub dedent_content: bool,
+    pub trim_content: TrimContent,
+    // applied to each template's content in order
+    pub transformers: Vec<&'a dyn Transformer>,
+    // also convert `hbs` tagged templates from `ember-cli-htmlbars`
+    pub hbs_tagged_templates: bool,
+    // also convert `precompileTemplate` calls from
+    // `@ember/template-compilation`
+    pub precompile_template_calls: bool,
+    // when present, a `@scope` comment in here right before a template lists
+    // its scope instead of the `eval` trick
+    pub explicit_scope: Option<&'a SingleThreadedComments>,
+    // emit class-member templates after their class instead of in a static
+    // block, for targets older than ES2022
+    pub downlevel_static_blocks: bool,
+    // pass `component: this` for expression templates where `this` is a
+    // class instance
+    pub component_this_in_methods: bool,
+    // pass the names each template refers to through the resolver, for
+    // `references::check_probes` to warn about
+    pub probe_references: bool,
+    // report templates that would use the `eval` trick as errors
+    pub forbid_eval: bool,
+    // emit all-whitespace content as an empty string
+    pub collapse_whitespace_only: bool,
+    // warn about content over this many bytes or lines
+    pub max_template_bytes: Option<usize>,
+    pub max_template_lines: Option<usize>,
+    // put the default export a bare top-level template becomes at the end of
+    // the module instead of where the template was
+    pub default_export_at_end: bool,
+    // the module whose default export classes with a template member and no
+    // superclass are made to extend
+    pub component_base: Option<&'a str>,
+    // which shape of options object template calls get
+    pub snippet_version: SnippetVersion,
+    // the module's source, which the names of requested imports are kept out
+    // of
+    pub source: &'a str,
+}
+
+const EVAL_FORBIDDEN: &str =
+    "this template would use eval, which `forbid_eval` doesn't allow; list its scope with @scope";
+
+const LOOSE_MODE: &str =
+    "precompileTemplate can only be converted in strict mode: template calls are always strict, so a `strictMode` that isn't `true` would change how its names resolve";
+
+const TEMPLATE_IN_DECORATOR: &str =
+    "a `<template>` can't be used in a decorator; declare it first (like `const Template = <template>...</template>;`) and pass that, or make it a member of the class";
+
+const SECOND_DEFAULT_EXPORT: &str =
+    "a `<template>` on its own at the top level is the module's default export, but the module already has one";
 
 pub struct TransformVisitor<'a> {
     template_identifier: Ident,
     found_it: Option<&'a mut bool>,
+    config: TransformConfig<'a>,
+    // spans of the content tags we replaced, in the order we visited them
+    replaced: Vec<Span>,
+    // the span of each template whose transformer failed, with its message
+    errors: Vec<(Span, String)>,
+    // the local names of `hbs`, when converting tagged templates
+    hbs_locals: Vec<JsWord>,
+    // the local names of `precompileTemplate`, when converting its calls
+    precompile_locals: Vec<JsWord>,
+    // the span of each template with an explicit scope, and of its comment
+    explicit_scopes: Vec<(Span, Span)>,
+    // when downleveling static blocks: the name of the class we're in (if
+    // it's a declaration), the template calls that belong after it, and
+    // those calls for each class we've finished, by class span
+    class_name: Option<Ident>,
+    hoisted: Vec<Stmt>,
+    after_class: Vec<(Span, Vec<Stmt>)>,
+    // whether `this` is, lexically, an instance of the enclosing class
+    this_is_instance: bool,
+    // imports that the transformer asked for, and the names they were given
+    requested_imports: Vec<ImportRequest>,
+    import_names: Rc<ImportNames>,
+    // edits that would fix some of the `errors`, by the error's span
+    suggested_edits: Vec<(Span, Vec<(Span, String)>)>,
+    // the span of each template with something to warn about, and the
+    // warning's code and message
+    warnings: Vec<(Span, &'static str, String)>,
+    // more places to point at for some of the `errors`: the error's span,
+    // and the span and message of the label
+    labels: Vec<(Span, Span, String)>,
+    // the span of the decorator we're in, if any
+    decorator: Option<Span>,
+}
+
+pub struct TransformResults {
+    pub replaced: Vec<Span>,
+    pub errors: Vec<(Span, String)>,
+    pub explicit_scopes: Vec<(Span, Span)>,
+    pub requested_imports: Vec<ImportRequest>,
+    pub suggested_edits: Vec<(Span, Vec<(Span, String)>)>,
+    pub warnings: Vec<(Span, &'static str, String)>,
+    pub labels: Vec<(Span, Span, String)>,
 }
 
 impl<'a> TransformVisitor<'a> {
     pub fn new(id: &Ident, found_it: Option<&'a mut bool>) -> Self {
+        Self::with_config(id, found_it, Default::default())
+    }
+
+    pub fn with_config(
+        id: &Ident,
+        found_it: Option<&'a mut bool>,
+        config: TransformConfig<'a>,
+    ) -> Self {
         TransformVisitor {
             template_identifier: id.clone(),
             found_it,
+            import_names: ImportNames::new(config.source),
+            config,
+            replaced: vec![],
+            errors: vec![],
+            hbs_locals: vec![],
+            precompile_locals: vec![],
+            explicit_scopes: vec![],
+            class_name: None,
+            hoisted: vec![],
+            after_class: vec![],
+            this_is_instance: false,
+            requested_imports: vec![],
+            suggested_edits: vec![],
+            warnings: vec![],
+            labels: vec![],
+            decorator: None,
         }
     }
-    fn set_found_it(&mut self) {
+
+    pub fn finish(self) -> TransformResults {
+        TransformResults {
+            replaced: self.replaced,
+            errors: self.errors,
+            explicit_scopes: self.explicit_scopes,
+            requested_imports: self.requested_imports,
+            suggested_edits: self.suggested_edits,
+            warnings: self.warnings,
+            labels: self.labels,
+        }
+    }
+
+    fn set_found_it(&mut self, span: Span) {
         match self.found_it.as_mut() {
             Some(flag) => **flag = true,
             None => {}
         }
+        self.replaced.push(span);
     }
+
+    // The names from a `@scope` comment if there is one, otherwise the
+    // `eval` trick.
+    fn scope_params(
+        &mut self,
+        contents: &str,
+        tag_span: Span,
+        closing_span: Span,
+        with_this: bool,
+    ) -> ExprOrSpread {
+        // the first version never passed the component
+        let with_this = with_this && self.config.snippet_version >= SnippetVersion::V2;
+        if let Some(comments) = self.config.explicit_scope {
+            match scope::explicit_scope(comments, tag_span.lo) {
+                Some(Ok((names, comment_span))) => {
+                    self.explicit_scopes.push((tag_span, comment_span));
+                    return snippets::explicit_scope_params(comment_span, &names, with_this).into();
+                }
+                Some(Err(message)) => self.errors.push((tag_span, message)),
+                None => {}
+            }
+        }
+        if self.config.forbid_eval {
+            self.errors.push((tag_span, EVAL_FORBIDDEN.to_owned()));
+            let names = references::template_references(contents);
+            self.suggested_edits.push((
+                tag_span,
+                vec![(
+                    Span::new(tag_span.lo, tag_span.lo, Default::default()),
+                    format!("/* {} {} */ ", scope::DIRECTIVE, names.join(", ")),
+                )],
+            ));
+        }
+        let mut params = if with_this {
+            snippets::scope_params_with_this(closing_span)
+        } else {
+            snippets::scope_params(closing_span)
+        };
+        if self.config.probe_references {
+            let names = references::template_references(contents);
+            if let (false, Expr::Object(object)) = (names.is_empty(), &mut *params) {
+                object.props.push(references::probe(tag_span, &names));
+            }
+        }
+        params.into()
+    }
+
+    fn visit_mut_hoisting_class(&mut self, name: Option<Ident>, class: &mut Class) {
+        let outer_name = std::mem::replace(&mut self.class_name, name);
+        let outer_hoisted = std::mem::take(&mut self.hoisted);
+        class.visit_mut_with(self);
+        let hoisted = std::mem::replace(&mut self.hoisted, outer_hoisted);
+        self.class_name = outer_name;
+
+        if !hoisted.is_empty() {
+            let spans: Vec<Span> = hoisted.iter().map(|stmt| stmt.span()).collect();
+            class.body.retain(|member| {
+                !(matches!(member, ClassMember::Empty(_)) && spans.contains(&member.span()))
+            });
+            self.after_class.push((class.span, hoisted));
+        }
+    }
+
+    // Puts the template calls that `visit_mut_hoisting_class` set aside right
+    // after their class.
+    fn insert_hoisted<T>(
+        &mut self,
+        items: &mut Vec<T>,
+        class_span: impl Fn(&T) -> Option<Span>,
+        wrap: impl Fn(Stmt) -> T,
+    ) {
+        if self.after_class.is_empty() {
+            return;
+        }
+        let mut items_updated = Vec::with_capacity(items.len());
+        for item in items.drain(..) {
+            let hoisted = class_span(&item).and_then(|span| {
+                let index = self.after_class.iter().position(|(s, _)| *s == span)?;
+                Some(self.after_class.remove(index).1)
+            });
+            items_updated.push(item);
+            items_updated.extend(hoisted.into_iter().flatten().map(&wrap));
+        }
+        *items = items_updated;
+    }
+
     fn transform_tag_expression(&mut self, expr: &ContentTagExpression) -> Expr {
         let ContentTagExpression {
             span,
@@ -44,21 +280,168 @@ impl<'a> TransformVisitor<'a> {
             span: *span,
             callee: Callee::Expr(Box::new(Expr::Ident(self.template_identifier.clone()))),
             args: vec![
-                self.content_literal(contents),
-                crate::snippets::scope_params(closing.span).into(),
+                self.content_literal(
+                    &contents.value,
+                    contents.span,
+                    ContentTagKind::Expression,
+                    *span,
+                ),
+                self.scope_params(
+                    &contents.value,
+                    *span,
+                    closing.span,
+                    self.config.component_this_in_methods && self.this_is_instance,
+                ),
             ],
             type_args: None,
         })
     }
 
-    fn content_literal(&self, contents: &Box<ContentTagContent>) -> ExprOrSpread {
+    fn with_this_is_instance(&mut self, this_is_instance: bool, visit: impl FnOnce(&mut Self)) {
+        let outer = std::mem::replace(&mut self.this_is_instance, this_is_instance);
+        visit(self);
+        self.this_is_instance = outer;
+    }
+
+    // A `hbs` tagged template becomes the same call as the equivalent
+    // `<template>`. We can't know what an interpolated value would mean inside
+    // the template, so those are reported as errors.
+    fn transform_hbs(&mut self, tagged: &TaggedTpl) -> Option<Expr> {
+        let ident = tagged.tag.as_ident()?;
+        if !self.hbs_locals.contains(&ident.sym) {
+            return None;
+        }
+        let span = tagged.span;
+        let tpl = &tagged.tpl;
+        if !tpl.exprs.is_empty() {
+            self.errors.push((
+                span,
+                "hbs templates that use ${} interpolation can't be converted".to_owned(),
+            ));
+            return None;
+        }
+        let (value, content_span) = static_template(tpl)?;
+        if self.config.forbid_eval {
+            self.errors.push((span, EVAL_FORBIDDEN.to_owned()));
+        }
+        Some(Expr::Call(CallExpr {
+            span,
+            callee: Callee::Expr(Box::new(Expr::Ident(self.template_identifier.clone()))),
+            args: vec![
+                self.content_literal(&value, content_span, ContentTagKind::Expression, span),
+                snippets::scope_params(span.with_lo(span.hi)).into(),
+            ],
+            type_args: None,
+        }))
+    }
+
+    // `precompileTemplate("...", { scope: () => ({ ... }) })` becomes a
+    // template call that keeps its explicit scope. Template calls are always
+    // in strict mode, so `strictMode: true` is dropped, and anything else is
+    // an error: in loose mode free names resolve differently, so converting
+    // the call would change what the template means.
+    fn transform_precompile_template(&mut self, call: &CallExpr) -> Option<Expr> {
+        let ident = call.callee.as_expr()?.as_ident()?;
+        if !self.precompile_locals.contains(&ident.sym) {
+            return None;
+        }
+        let span = call.span;
+        let Some((value, content_span)) = call.args.first().and_then(|arg| match &*arg.expr {
+            Expr::Lit(Lit::Str(str)) if arg.spread.is_none() => {
+                Some((str.value.to_string(), inside_delimiters(str.span)))
+            }
+            Expr::Tpl(tpl) if arg.spread.is_none() => static_template(tpl),
+            _ => None,
+        }) else {
+            self.errors.push((
+                span,
+                "precompileTemplate can only be converted when its template is a string literal"
+                    .to_owned(),
+            ));
+            return None;
+        };
+        let options = match call.args.get(1) {
+            None => None,
+            Some(ExprOrSpread {
+                spread: None,
+                expr: box Expr::Object(object),
+            }) => {
+                let strict_mode = object
+                    .props
+                    .iter()
+                    .find(|prop| is_prop_named(prop, "strictMode"));
+                if let Some(prop) = strict_mode {
+                    if !is_true(prop) {
+                        self.errors.push((span, LOOSE_MODE.to_owned()));
+                        return None;
+                    }
+                }
+                let mut object = object.clone();
+                object
+                    .props
+                    .retain(|prop| !is_prop_named(prop, "strictMode"));
+                Some(object)
+            }
+            Some(_) => {
+                self.errors.push((
+                    span,
+                    "precompileTemplate can only be converted when its options are an object literal"
+                        .to_owned(),
+                ));
+                return None;
+            }
+        };
+
+        let mut args =
+            vec![self.content_literal(&value, content_span, ContentTagKind::Expression, span)];
+        args.extend(options.map(|object| ExprOrSpread::from(Box::new(Expr::Object(object)))));
+        Some(Expr::Call(CallExpr {
+            span,
+            callee: Callee::Expr(Box::new(Expr::Ident(self.template_identifier.clone()))),
+            args,
+            type_args: None,
+        }))
+    }
+
+    fn content_literal(
+        &mut self,
+        contents: &str,
+        contents_span: Span,
+        kind: ContentTagKind,
+        tag_span: Span,
+    ) -> ExprOrSpread {
+        // The whole literal maps back to the start of the content, so
+        // dedenting doesn't need any source map adjustment beyond that.
+        let context = TemplateContext::with_names(kind, tag_span.into(), self.import_names.clone());
+        for (code, message) in content_warnings(&self.config, contents) {
+            self.warnings.push((tag_span, code, message));
+        }
+        let (value, requested) = content_value(&self.config, contents, context);
+        for request in requested {
+            if !self.requested_imports.contains(&request) {
+                self.requested_imports.push(request);
+            }
+        }
+        let value = value.unwrap_or_else(|message| {
+            // keep going so that we can report every failing template at once
+            self.errors.push((tag_span, message));
+            contents.to_owned()
+        });
+
+        // Trimming moves where the literal starts and ends in the original
+        // source, so the span follows it.
+        let (trim_start, trim_end) = content::trim_amounts(contents, self.config.trim_content);
+        let span = contents_span
+            .with_lo(contents_span.lo + BytePos(trim_start as u32))
+            .with_hi(contents_span.hi - BytePos(trim_end as u32));
+
         Box::new(Expr::Tpl(Tpl {
-            span: contents.span,
+            span,
             exprs: vec![],
             quasis: vec![TplElement {
-                span: contents.span,
+                span,
                 cooked: None,
-                raw: escape_template_literal(&contents.value),
+                raw: escape_template_literal(&value),
                 tail: false,
             }],
         }))
@@ -66,17 +449,165 @@ impl<'a> TransformVisitor<'a> {
     }
 }
 
-fn escape_template_literal(input: &Atom) -> Atom {
-    input.replace("\\", "\\\\").replace("`", "\\`").replace("$", "\\$").into()
+// The text of the literal for one template: its content, dedented and
+// trimmed (or collapsed) as configured and then passed through each
+// transformer in turn, along with the imports they asked for.
+pub(crate) fn content_value(
+    config: &TransformConfig,
+    contents: &str,
+    context: TemplateContext,
+) -> (Result<String, String>, Vec<ImportRequest>) {
+    let value = if config.collapse_whitespace_only && content::is_whitespace_only(contents) {
+        String::new()
+    } else if config.dedent_content {
+        content::dedent(contents)
+    } else {
+        contents.to_string()
+    };
+    let value = content::trim(&value, config.trim_content).to_owned();
+    // each transformer gets the output of the one before it
+    let result = config
+        .transformers
+        .iter()
+        .try_fold(value, |value, transformer| {
+            transformer.transform(&value, &context)
+        });
+    (result, context.take_imports())
+}
+
+// What to warn about in one template's content, as `(code, message)`.
+pub(crate) fn content_warnings(
+    config: &TransformConfig,
+    contents: &str,
+) -> Vec<(&'static str, String)> {
+    let mut warnings = vec![];
+    if config.collapse_whitespace_only && content::is_whitespace_only(contents) {
+        warnings.push((
+            "whitespace-only-template",
+            "this template is only whitespace, so it was emitted empty".to_owned(),
+        ));
+    }
+    let bytes = contents.len();
+    let lines = contents.lines().count().max(1);
+    let too_large = match (config.max_template_bytes, config.max_template_lines) {
+        (Some(max), _) if bytes > max => Some(format!("{bytes} bytes, over the limit of {max}")),
+        (_, Some(max)) if lines > max => Some(format!("{lines} lines, over the limit of {max}")),
+        _ => None,
+    };
+    if let Some(size) = too_large {
+        warnings.push((
+            "template-too-large",
+            format!("this template is {size}; consider splitting it into smaller components"),
+        ));
+    }
+    warnings
+}
+
+pub(crate) fn escape_template_literal(input: &str) -> Atom {
+    escape_with_offsets(input).0.into()
+}
+
+// `input` escaped for a template literal in one pass, and the offset in
+// `input` of each character that got a backslash in front of it, for
+// `escaped_offset`.
+pub(crate) fn escape_with_offsets(input: &str) -> (String, Vec<usize>) {
+    let mut escaped = String::with_capacity(input.len());
+    let mut escapes = vec![];
+    let mut copied = 0;
+    for (offset, c) in input.match_indices(['\\', '`', '$']) {
+        escaped.push_str(&input[copied..offset]);
+        escaped.push('\\');
+        escaped.push_str(c);
+        escapes.push(offset);
+        copied = offset + c.len();
+    }
+    escaped.push_str(&input[copied..]);
+    (escaped, escapes)
+}
+
+// Where `offset` in the input of `escape_with_offsets` ended up in its output.
+pub(crate) fn escaped_offset(escapes: &[usize], offset: usize) -> usize {
+    offset + escapes.partition_point(|&escape| escape < offset)
 }
 
 impl<'a> VisitMut for TransformVisitor<'a> {
     fn visit_mut_expr(&mut self, n: &mut Expr) {
         n.visit_mut_children_with(self);
-        if let Expr::ContentTagExpression(expr) = n {
-            *n = self.transform_tag_expression(expr);
-            self.set_found_it();
+        match n {
+            Expr::ContentTagExpression(expr) => {
+                let span = expr.span;
+                if let Some(decorator) = self.decorator {
+                    self.errors.push((span, TEMPLATE_IN_DECORATOR.to_owned()));
+                    self.labels
+                        .push((span, decorator, "the decorator it is in".to_owned()));
+                }
+                *n = self.transform_tag_expression(expr);
+                self.set_found_it(span);
+            }
+            Expr::TaggedTpl(tagged) => {
+                let span = tagged.span;
+                if let Some(call) = self.transform_hbs(tagged) {
+                    *n = call;
+                    self.set_found_it(span);
+                }
+            }
+            Expr::Call(call) => {
+                let span = call.span;
+                if let Some(call) = self.transform_precompile_template(call) {
+                    *n = call;
+                    self.set_found_it(span);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Decorators run once, when the class is defined, with whatever they
+    // are given, which is never what someone putting a template there meant.
+    fn visit_mut_decorator(&mut self, n: &mut Decorator) {
+        let outer = self.decorator.replace(n.span);
+        n.visit_mut_children_with(self);
+        self.decorator = outer;
+    }
+
+    fn visit_mut_new_expr(&mut self, n: &mut NewExpr) {
+        n.visit_mut_children_with(self);
+        // `new <template></template>()` constructs the template, but once the
+        // template has become a call, `new template(...)()` would construct
+        // `template` itself. The emitter doesn't parenthesize for us.
+        if needs_parens_as_new_callee(&n.callee) {
+            let callee = n.callee.take();
+            n.callee = Box::new(Expr::Paren(ParenExpr {
+                span: callee.span(),
+                expr: callee,
+            }));
+        }
+    }
+
+    fn visit_mut_class(&mut self, n: &mut Class) {
+        if let Some(module) = self.config.component_base {
+            let has_template = n
+                .body
+                .iter()
+                .any(|member| matches!(member, ClassMember::ContentTagMember(_)));
+            if has_template && n.super_class.is_none() {
+                // imported under a name of our own, like requested imports,
+                // so that it can't clash with anything in the module
+                let request = ImportRequest {
+                    module: module.to_owned(),
+                    specifier: "default".to_owned(),
+                    local: self.import_names.local(module, "default", "Component"),
+                };
+                n.super_class = Some(Box::new(Expr::Ident(Ident::new(
+                    request.local.as_str().into(),
+                    Default::default(),
+                ))));
+                if !self.requested_imports.contains(&request) {
+                    self.requested_imports.push(request);
+                }
+            }
         }
+        n.visit_mut_children_with(self);
     }
 
     fn visit_mut_class_member(&mut self, n: &mut ClassMember) {
@@ -88,48 +619,371 @@ impl<'a> VisitMut for TransformVisitor<'a> {
             closing,
         }) = n
         {
+            let span = *span;
             let call_expr = Expr::Call(CallExpr {
-                span: *span,
+                span,
                 callee: Callee::Expr(Box::new(Expr::Ident(self.template_identifier.clone()))),
                 args: vec![
-                    self.content_literal(contents),
-                    crate::snippets::scope_params_with_this(closing.span).into(),
+                    self.content_literal(
+                        &contents.value,
+                        contents.span,
+                        ContentTagKind::ClassMember,
+                        span,
+                    ),
+                    self.scope_params(&contents.value, span, closing.span, true),
                 ],
                 type_args: None,
             });
-            let call_statement = ExprStmt {
-                span: *span,
+            let mut call_statement = ExprStmt {
+                span,
                 expr: Box::new(call_expr),
             };
+            if self.config.downlevel_static_blocks {
+                match self.class_name.clone() {
+                    Some(name) => {
+                        // outside the class, `this` is no longer the class
+                        if let Expr::Call(call) = &mut *call_statement.expr {
+                            set_component(&mut call.args[1].expr, &name);
+                        }
+                        self.hoisted.push(Stmt::Expr(call_statement));
+                        *n = ClassMember::Empty(EmptyStmt { span });
+                        self.set_found_it(span);
+                        return;
+                    }
+                    None => self.errors.push((
+                        span,
+                        "templates in class expressions need static blocks (ES2022)".to_owned(),
+                    )),
+                }
+            }
             *n = ClassMember::StaticBlock(StaticBlock {
                 span: opening.span,
                 body: BlockStmt {
-                    span: *span,
+                    span,
                     stmts: vec![Stmt::Expr(call_statement)],
                 },
             });
-            self.set_found_it();
+            self.set_found_it(span);
+        }
+    }
+
+    // Methods, constructors and property initializers see the instance as
+    // `this`, and so do arrow functions within them. Any other function, as
+    // well as getters and setters in object literals, get their own `this`.
+    // Static members and static blocks see the class instead.
+    fn visit_mut_class_method(&mut self, n: &mut ClassMethod) {
+        n.key.visit_mut_with(self);
+        self.with_this_is_instance(!n.is_static, |v| n.function.visit_mut_children_with(v));
+    }
+
+    fn visit_mut_private_method(&mut self, n: &mut PrivateMethod) {
+        n.key.visit_mut_with(self);
+        self.with_this_is_instance(!n.is_static, |v| n.function.visit_mut_children_with(v));
+    }
+
+    fn visit_mut_constructor(&mut self, n: &mut Constructor) {
+        self.with_this_is_instance(true, |v| n.visit_mut_children_with(v));
+    }
+
+    fn visit_mut_class_prop(&mut self, n: &mut ClassProp) {
+        n.key.visit_mut_with(self);
+        self.with_this_is_instance(!n.is_static, |v| n.value.visit_mut_with(v));
+        n.decorators.visit_mut_with(self);
+    }
+
+    fn visit_mut_private_prop(&mut self, n: &mut PrivateProp) {
+        self.with_this_is_instance(!n.is_static, |v| n.value.visit_mut_with(v));
+        n.decorators.visit_mut_with(self);
+    }
+
+    fn visit_mut_static_block(&mut self, n: &mut StaticBlock) {
+        self.with_this_is_instance(false, |v| n.visit_mut_children_with(v));
+    }
+
+    fn visit_mut_function(&mut self, n: &mut Function) {
+        self.with_this_is_instance(false, |v| n.visit_mut_children_with(v));
+    }
+
+    fn visit_mut_getter_prop(&mut self, n: &mut GetterProp) {
+        self.with_this_is_instance(false, |v| n.visit_mut_children_with(v));
+    }
+
+    fn visit_mut_setter_prop(&mut self, n: &mut SetterProp) {
+        self.with_this_is_instance(false, |v| n.visit_mut_children_with(v));
+    }
+
+    fn visit_mut_class_decl(&mut self, n: &mut ClassDecl) {
+        if self.config.downlevel_static_blocks {
+            self.visit_mut_hoisting_class(Some(n.ident.clone()), &mut n.class);
+        } else {
+            n.visit_mut_children_with(self);
+        }
+    }
+
+    fn visit_mut_class_expr(&mut self, n: &mut ClassExpr) {
+        if self.config.downlevel_static_blocks {
+            self.visit_mut_hoisting_class(None, &mut n.class);
+        } else {
+            n.visit_mut_children_with(self);
+        }
+    }
+
+    fn visit_mut_export_default_decl(&mut self, n: &mut ExportDefaultDecl) {
+        match &mut n.decl {
+            // a named default export is still a declaration of that name
+            DefaultDecl::Class(ClassExpr {
+                ident: Some(ident),
+                class,
+            }) if self.config.downlevel_static_blocks => {
+                self.visit_mut_hoisting_class(Some(ident.clone()), class)
+            }
+            _ => n.visit_mut_children_with(self),
         }
     }
 
+    fn visit_mut_stmts(&mut self, stmts: &mut Vec<Stmt>) {
+        stmts.visit_mut_children_with(self);
+        self.insert_hoisted(stmts, stmt_class_span, |stmt| stmt);
+    }
+
     fn visit_mut_module_items(&mut self, items: &mut Vec<ModuleItem>) {
+        if self.config.hbs_tagged_templates {
+            self.hbs_locals = legacy::imported_locals(items, legacy::HBS_SOURCES);
+        }
+        if self.config.precompile_template_calls {
+            self.precompile_locals =
+                legacy::imported_locals(items, legacy::PRECOMPILE_TEMPLATE_SOURCES);
+        }
+
+        // a bare template becomes the default export, so there can only be
+        // one of those, and only when nothing else is exported as default
+        let mut has_default_export = items.iter().any(is_default_export);
+        let default_class = items.iter().find_map(default_export_class);
         let mut items_updated = Vec::with_capacity(items.len());
+        let mut moved_exports = vec![];
         for item in items.drain(..) {
             if let Some(content_tag) = content_tag_expression_statement(&item) {
-                items_updated.push(ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultExpr(
-                    ExportDefaultExpr {
+                if has_default_export {
+                    self.errors
+                        .push((content_tag.span, SECOND_DEFAULT_EXPORT.to_owned()));
+                    // when that's a class, the template can be its member
+                    if let Some(class) = default_class {
+                        let before_close = class.hi - BytePos(1);
+                        self.suggested_edits.push((
+                            content_tag.span,
+                            vec![
+                                (
+                                    Span::new(before_close, before_close, Default::default()),
+                                    format!(
+                                        "\n  <template>{}</template>\n",
+                                        content_tag.contents.value
+                                    ),
+                                ),
+                                (item.span(), String::new()),
+                            ],
+                        ));
+                    }
+                }
+                has_default_export = true;
+                let export =
+                    ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultExpr(ExportDefaultExpr {
                         span: content_tag.span,
                         expr: Box::new(self.transform_tag_expression(&content_tag)),
-                    },
-                )));
-                self.set_found_it();
+                    }));
+                if self.config.default_export_at_end {
+                    moved_exports.push(export);
+                } else {
+                    items_updated.push(export);
+                }
+                self.set_found_it(content_tag.span);
             } else {
                 items_updated.push(item);
             }
         }
+        items_updated.extend(moved_exports);
 
         *items = items_updated;
-        items.visit_mut_children_with(self)
+        items.visit_mut_children_with(self);
+        self.insert_hoisted(items, module_item_class_span, ModuleItem::Stmt);
+
+        // once every use has become a call to our own import, the old ones
+        // would be left unused
+        for (locals, sources) in [
+            (&self.hbs_locals, legacy::HBS_SOURCES),
+            (&self.precompile_locals, legacy::PRECOMPILE_TEMPLATE_SOURCES),
+        ] {
+            if !locals.is_empty()
+                && self.errors.is_empty()
+                && !legacy::still_referenced(items, locals)
+            {
+                legacy::remove_imports(items, sources);
+            }
+        }
+    }
+}
+
+fn stmt_class_span(stmt: &Stmt) -> Option<Span> {
+    match stmt {
+        Stmt::Decl(Decl::Class(decl)) => Some(decl.class.span),
+        _ => None,
+    }
+}
+
+fn module_item_class_span(item: &ModuleItem) -> Option<Span> {
+    match item {
+        ModuleItem::Stmt(stmt) => stmt_class_span(stmt),
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
+            decl: Decl::Class(decl),
+            ..
+        })) => Some(decl.class.span),
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(ExportDefaultDecl {
+            decl: DefaultDecl::Class(ClassExpr { class, .. }),
+            ..
+        })) => Some(class.span),
+        _ => None,
+    }
+}
+
+fn set_component(scope_params: &mut Expr, component: &Ident) {
+    if let Expr::Object(object) = scope_params {
+        for prop in object.props.iter_mut() {
+            if let PropOrSpread::Prop(box Prop::KeyValue(KeyValueProp {
+                key: PropName::Ident(key),
+                value,
+            })) = prop
+            {
+                if &*key.sym == "component" {
+                    *value = Box::new(Expr::Ident(component.clone()));
+                }
+            }
+        }
+    }
+}
+
+// Passes `moduleName: name` to every call of `template` (our import) in
+// `module`, ahead of its other options.
+pub(crate) fn add_module_name(module: &mut Module, template: &Ident, name: &str) {
+    struct ModuleName<'a> {
+        template: &'a Ident,
+        name: &'a str,
+    }
+    impl VisitMut for ModuleName<'_> {
+        fn visit_mut_call_expr(&mut self, n: &mut CallExpr) {
+            n.visit_mut_children_with(self);
+            let is_template = matches!(
+                &n.callee,
+                Callee::Expr(box Expr::Ident(callee)) if callee.sym == self.template.sym
+            );
+            if let (true, Some(Expr::Object(object))) =
+                (is_template, n.args.get_mut(1).map(|arg| &mut *arg.expr))
+            {
+                let span = object.span;
+                object.props.insert(
+                    0,
+                    PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+                        key: PropName::Ident(Ident::new("moduleName".into(), span)),
+                        value: Box::new(Expr::Lit(Lit::Str(Str {
+                            span,
+                            value: self.name.into(),
+                            raw: None,
+                        }))),
+                    }))),
+                );
+            }
+        }
+    }
+    module.visit_mut_with(&mut ModuleName { template, name });
+}
+
+// The content of a template literal without interpolation, and its span
+// without the backticks.
+fn static_template(tpl: &Tpl) -> Option<(String, Span)> {
+    if !tpl.exprs.is_empty() {
+        return None;
+    }
+    let quasi = tpl.quasis.first()?;
+    let value = quasi.cooked.as_ref().unwrap_or(&quasi.raw).to_string();
+    Some((value, inside_delimiters(tpl.span)))
+}
+
+fn inside_delimiters(span: Span) -> Span {
+    span.with_lo(span.lo + BytePos(1))
+        .with_hi(span.hi - BytePos(1))
+}
+
+fn is_prop_named(prop: &PropOrSpread, name: &str) -> bool {
+    match prop {
+        PropOrSpread::Prop(box Prop::KeyValue(KeyValueProp { key, .. })) => match key {
+            PropName::Ident(ident) => ident.sym == *name,
+            PropName::Str(str) => str.value == *name,
+            _ => false,
+        },
+        PropOrSpread::Prop(box Prop::Shorthand(ident)) => ident.sym == *name,
+        _ => false,
+    }
+}
+
+// Whether `prop` is `key: true`.
+fn is_true(prop: &PropOrSpread) -> bool {
+    matches!(
+        prop,
+        PropOrSpread::Prop(box Prop::KeyValue(KeyValueProp {
+            value: box Expr::Lit(Lit::Bool(bool)),
+            ..
+        })) if bool.value
+    )
+}
+
+fn needs_parens_as_new_callee(expr: &Expr) -> bool {
+    match expr {
+        Expr::Call(_) => true,
+        Expr::Member(MemberExpr { obj, .. }) => needs_parens_as_new_callee(obj),
+        _ => false,
+    }
+}
+
+// `export default`, `export { x as default }`, `export { default } from`
+// and TypeScript's `export =`.
+fn is_default_export(item: &ModuleItem) -> bool {
+    let ModuleItem::ModuleDecl(decl) = item else {
+        return false;
+    };
+    match decl {
+        ModuleDecl::ExportDefaultDecl(_)
+        | ModuleDecl::ExportDefaultExpr(_)
+        | ModuleDecl::TsExportAssignment(_) => true,
+        ModuleDecl::ExportNamed(export) => {
+            export.specifiers.iter().any(|specifier| match specifier {
+                ExportSpecifier::Default(_) => true,
+                ExportSpecifier::Named(named) => {
+                    export_name(named.exported.as_ref().unwrap_or(&named.orig)) == "default"
+                }
+                ExportSpecifier::Namespace(namespace) => export_name(&namespace.name) == "default",
+            })
+        }
+        _ => false,
+    }
+}
+
+// The span of the class in `export default class {}`.
+fn default_export_class(item: &ModuleItem) -> Option<Span> {
+    match item {
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(ExportDefaultDecl {
+            decl: DefaultDecl::Class(ClassExpr { class, .. }),
+            ..
+        })) => Some(class.span),
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultExpr(ExportDefaultExpr {
+            expr: box Expr::Class(ClassExpr { class, .. }),
+            ..
+        })) => Some(class.span),
+        _ => None,
+    }
+}
+
+fn export_name(name: &ModuleExportName) -> &str {
+    match name {
+        ModuleExportName::Ident(ident) => &ident.sym,
+        ModuleExportName::Str(s) => &s.value,
     }
 }
 
@@ -258,3 +1112,382 @@ test!(
     r#"let x = <template>Hello\nWorld\u1234</template>"#,
     r#"let x = template(`Hello\\nWorld\\u1234`, { eval() { return eval(arguments[0]) }})"#
 );
+
+#[test]
+fn test_escape_with_offsets() {
+    let (escaped, escapes) = escape_with_offsets("a`b\\c\n${d}");
+    assert_eq!(escaped, "a\\`b\\\\c\n\\${d}");
+    assert_eq!(escapes, vec![1, 3, 6]);
+    // the line break, the backslash in front of `$`, and what follows that
+    assert_eq!(escaped_offset(&escapes, 5), 7);
+    assert_eq!(escaped_offset(&escapes, 6), 8);
+    assert_eq!(escaped_offset(&escapes, 7), 10);
+}
+
+test!(
+    Default::default(),
+    |_| as_folder(TransformVisitor::new(
+        &Ident::new("template".into(), Default::default()),
+        None,
+    )),
+    conditional_expression,
+    r#"let x = cond ? <template>A</template> : <template>B</template>"#,
+    r#"let x = cond ? template(`A`, { eval() { return eval(arguments[0]) }}) : template(`B`, { eval() { return eval(arguments[0]) }})"#
+);
+
+test!(
+    Default::default(),
+    |_| as_folder(TransformVisitor::new(
+        &Ident::new("template".into(), Default::default()),
+        None,
+    )),
+    arrow_function_concise_body,
+    r#"let f = () => <template>X</template>"#,
+    r#"let f = () => template(`X`, { eval() { return eval(arguments[0]) }})"#
+);
+
+test!(
+    Default::default(),
+    |_| as_folder(TransformVisitor::new(
+        &Ident::new("template".into(), Default::default()),
+        None,
+    )),
+    default_parameter_value,
+    r#"function f(x = <template>X</template>) { return x; }"#,
+    r#"function f(x = template(`X`, { eval() { return eval(arguments[0]) }})) { return x; }"#
+);
+
+test!(
+    Default::default(),
+    |_| as_folder(TransformVisitor::new(
+        &Ident::new("template".into(), Default::default()),
+        None,
+    )),
+    parenthesizes_new_callee,
+    r#"let x = new <template>X</template>(); let y = new <template>Y</template>.extend();"#,
+    r#"let x = new (template(`X`, { eval() { return eval(arguments[0]) }}))(); let y = new (template(`Y`, { eval() { return eval(arguments[0]) }}).extend)();"#
+);
+
+test!(
+    Default::default(),
+    |_| as_folder(TransformVisitor::new(
+        &Ident::new("template".into(), Default::default()),
+        None,
+    )),
+    callee_and_tag_positions_need_no_parens,
+    r#"let x = <template>X</template>(); let y = <template>Y</template>`z`;"#,
+    r#"let x = template(`X`, { eval() { return eval(arguments[0]) }})(); let y = template(`Y`, { eval() { return eval(arguments[0]) }})`z`;"#
+);
+
+test!(
+    Default::default(),
+    |_| as_folder(TransformVisitor::with_config(
+        &Ident::new("template".into(), Default::default()),
+        None,
+        TransformConfig {
+            dedent_content: true,
+            ..Default::default()
+        },
+    )),
+    dedent_content,
+    r#"class X {
+      <template>
+        <h1>Hi</h1>
+          <p>`nested`</p>
+      </template>
+    }"#,
+    r#"class X {
+      static {
+          template(`
+<h1>Hi</h1>
+  <p>\`nested\`</p>
+`, { component: this, eval() { return eval(arguments[0]) }},);
+      }
+    }"#
+);
+
+test!(
+    Default::default(),
+    |_| as_folder(TransformVisitor::with_config(
+        &Ident::new("template".into(), Default::default()),
+        None,
+        TransformConfig {
+            trim_content: TrimContent::Both,
+            ..Default::default()
+        },
+    )),
+    trim_content,
+    r#"let x = <template>
+      Hello
+    </template>"#,
+    r#"let x = template(`Hello`, { eval() { return eval(arguments[0]) }})"#
+);
+
+#[test]
+fn test_transformer_errors_are_collected() {
+    use swc_ecma_parser::{Parser, StringInput, Syntax};
+
+    let source_map: swc_common::sync::Lrc<swc_common::SourceMap> = Default::default();
+    let source_file = source_map.new_source_file(
+        swc_common::FileName::Anon,
+        "let a = <template>ok</template>; let b = <template>bad</template>; class C { <template>bad</template> }"
+            .into(),
+    );
+    let mut module = Parser::new(
+        Syntax::Typescript(Default::default()),
+        StringInput::from(&*source_file),
+        None,
+    )
+    .parse_module()
+    .unwrap();
+
+    let transformer = |content: &str, _: &TemplateContext| {
+        if content == "bad" {
+            Err(format!("cannot compile {content}"))
+        } else {
+            Ok(content.to_uppercase())
+        }
+    };
+    let mut visitor = TransformVisitor::with_config(
+        &Ident::new("template".into(), Default::default()),
+        None,
+        TransformConfig {
+            transformers: vec![&transformer],
+            ..Default::default()
+        },
+    );
+    module.visit_mut_with(&mut visitor);
+    let results = visitor.finish();
+
+    assert_eq!(results.replaced.len(), 3);
+    let messages: Vec<&str> = results.errors.iter().map(|(_, m)| m.as_str()).collect();
+    assert_eq!(messages, vec!["cannot compile bad", "cannot compile bad"]);
+}
+
+#[cfg(test)]
+fn bracket_content(content: &str, _: &TemplateContext) -> Result<String, String> {
+    Ok(format!("[{content}]"))
+}
+
+test!(
+    Default::default(),
+    |_| as_folder(TransformVisitor::with_config(
+        &Ident::new("template".into(), Default::default()),
+        None,
+        TransformConfig {
+            transformers: vec![&bracket_content],
+            ..Default::default()
+        },
+    )),
+    transformer_rewrites_content,
+    r#"let x = <template>Hello</template>"#,
+    r#"let x = template(`[Hello]`, { eval() { return eval(arguments[0]) }})"#
+);
+
+test!(
+    Default::default(),
+    |_| as_folder(TransformVisitor::with_config(
+        &Ident::new("template".into(), Default::default()),
+        None,
+        TransformConfig {
+            hbs_tagged_templates: true,
+            ..Default::default()
+        },
+    )),
+    hbs_tagged_templates,
+    r#"import { hbs } from "ember-cli-htmlbars";
+       import other from "other";
+       let x = hbs`Hello {{name}}`;
+       let y = <template>Bye</template>;
+       let z = notHbs`Hello`;"#,
+    r#"import other from "other";
+       let x = template(`Hello {{name}}`, { eval() { return eval(arguments[0]) }});
+       let y = template(`Bye`, { eval() { return eval(arguments[0]) }});
+       let z = notHbs`Hello`;"#
+);
+
+test!(
+    Default::default(),
+    |_| as_folder(TransformVisitor::new(
+        &Ident::new("template".into(), Default::default()),
+        None,
+    )),
+    hbs_tagged_templates_are_opt_in,
+    r#"import { hbs } from "ember-cli-htmlbars";
+       let x = hbs`Hello`;"#,
+    r#"import { hbs } from "ember-cli-htmlbars";
+       let x = hbs`Hello`;"#
+);
+
+#[test]
+fn test_precompile_template_in_loose_mode_is_an_error() {
+    use swc_ecma_parser::{Parser, StringInput, Syntax};
+
+    let source_map: swc_common::sync::Lrc<swc_common::SourceMap> = Default::default();
+    let source_file = source_map.new_source_file(
+        swc_common::FileName::Anon,
+        r#"import { precompileTemplate } from "@ember/template-compilation";
+           let x = precompileTemplate("{{name}}", { strictMode: false });
+           let y = precompileTemplate("{{name}}", { strictMode: isStrict });"#
+            .into(),
+    );
+    let mut module = Parser::new(
+        Syntax::Typescript(Default::default()),
+        StringInput::from(&*source_file),
+        None,
+    )
+    .parse_module()
+    .unwrap();
+
+    let mut visitor = TransformVisitor::with_config(
+        &Ident::new("template".into(), Default::default()),
+        None,
+        TransformConfig {
+            precompile_template_calls: true,
+            ..Default::default()
+        },
+    );
+    module.visit_mut_with(&mut visitor);
+    let results = visitor.finish();
+
+    assert!(results.replaced.is_empty());
+    let messages: Vec<&str> = results.errors.iter().map(|(_, m)| m.as_str()).collect();
+    assert_eq!(messages, vec![LOOSE_MODE, LOOSE_MODE]);
+}
+
+#[test]
+fn test_hbs_interpolation_is_an_error() {
+    use swc_ecma_parser::{Parser, StringInput, Syntax};
+
+    let source_map: swc_common::sync::Lrc<swc_common::SourceMap> = Default::default();
+    let source_file = source_map.new_source_file(
+        swc_common::FileName::Anon,
+        r#"import { hbs } from "ember-cli-htmlbars"; let x = hbs`Hello ${name}`;"#.into(),
+    );
+    let mut module = Parser::new(
+        Syntax::Typescript(Default::default()),
+        StringInput::from(&*source_file),
+        None,
+    )
+    .parse_module()
+    .unwrap();
+
+    let mut visitor = TransformVisitor::with_config(
+        &Ident::new("template".into(), Default::default()),
+        None,
+        TransformConfig {
+            hbs_tagged_templates: true,
+            ..Default::default()
+        },
+    );
+    module.visit_mut_with(&mut visitor);
+    let results = visitor.finish();
+
+    assert!(results.replaced.is_empty());
+    let messages: Vec<&str> = results.errors.iter().map(|(_, m)| m.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec!["hbs templates that use ${} interpolation can't be converted"]
+    );
+}
+
+test!(
+    Default::default(),
+    |_| as_folder(TransformVisitor::with_config(
+        &Ident::new("template".into(), Default::default()),
+        None,
+        TransformConfig {
+            precompile_template_calls: true,
+            ..Default::default()
+        },
+    )),
+    precompile_template_calls,
+    r#"import { precompileTemplate } from "@ember/template-compilation";
+       import Greeting from "./greeting";
+       let x = precompileTemplate("<Greeting @name={{name}} />", {
+         strictMode: true,
+         scope: () => ({ Greeting }),
+       });
+       let y = precompileTemplate(`Hi`);"#,
+    r#"import Greeting from "./greeting";
+       let x = template(`<Greeting @name={{name}} />`, {
+         scope: () => ({ Greeting }),
+       });
+       let y = template(`Hi`);"#
+);
+
+test!(
+    Default::default(),
+    |_| as_folder(TransformVisitor::with_config(
+        &Ident::new("template".into(), Default::default()),
+        None,
+        TransformConfig {
+            downlevel_static_blocks: true,
+            ..Default::default()
+        },
+    )),
+    downlevel_static_blocks,
+    r#"export default class X { <template>Hello</template> }
+       function f() {
+         class Y { y = 1; <template>Inner</template> }
+         return Y;
+       }"#,
+    r#"export default class X {}
+       template(`Hello`, { component: X, eval() { return eval(arguments[0]) }});
+       function f() {
+         class Y { y = 1; }
+         template(`Inner`, { component: Y, eval() { return eval(arguments[0]) }});
+         return Y;
+       }"#
+);
+
+test!(
+    Default::default(),
+    |_| as_folder(TransformVisitor::with_config(
+        &Ident::new("template".into(), Default::default()),
+        None,
+        TransformConfig {
+            component_this_in_methods: true,
+            ..Default::default()
+        },
+    )),
+    component_this_in_methods,
+    r#"class X {
+         thing = <template>A</template>;
+         static other = <template>B</template>;
+         render() {
+           let f = () => <template>C</template>;
+           return function () { return <template>D</template>; };
+         }
+       }
+       let y = <template>E</template>;"#,
+    r#"class X {
+         thing = template(`A`, { component: this, eval() { return eval(arguments[0]) }});
+         static other = template(`B`, { eval() { return eval(arguments[0]) }});
+         render() {
+           let f = () => template(`C`, { component: this, eval() { return eval(arguments[0]) }});
+           return function () { return template(`D`, { eval() { return eval(arguments[0]) }}); };
+         }
+       }
+       let y = template(`E`, { eval() { return eval(arguments[0]) }});"#
+);
+
+#[cfg(test)]
+fn shout_content(content: &str, _: &TemplateContext) -> Result<String, String> {
+    Ok(content.to_uppercase())
+}
+
+test!(
+    Default::default(),
+    |_| as_folder(TransformVisitor::with_config(
+        &Ident::new("template".into(), Default::default()),
+        None,
+        TransformConfig {
+            transformers: vec![&shout_content, &bracket_content],
+            ..Default::default()
+        },
+    )),
+    transformers_apply_in_order,
+    r#"let x = <template>Hello</template>"#,
+    r#"let x = template(`[HELLO]`, { eval() { return eval(arguments[0]) }})"#
+);
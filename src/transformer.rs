@@ -0,0 +1,174 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::locate::{ContentTagKind, Range};
+use crate::scope::is_identifier;
+
+pub struct TemplateContext {
+    pub kind: ContentTagKind,
+    // the span of the whole content tag in the input
+    pub range: Range,
+    imports: RefCell<Vec<ImportRequest>>,
+    // shared by every template in the module
+    names: Rc<ImportNames>,
+}
+
+// An import that a transformer needs in order for its output to work.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ImportRequest {
+    pub module: String,
+    pub specifier: String,
+    pub local: String,
+}
+
+// Appended to the names of requested imports so that they can't collide with
+// anything in the module, like `crate::IMPORT_ALIAS`.
+pub(crate) const IMPORT_SUFFIX: &str = "fd9b2463e5f141cfb5666b64daa1f11a";
+
+impl TemplateContext {
+    pub fn new(kind: ContentTagKind, range: Range) -> Self {
+        Self::with_names(kind, range, Default::default())
+    }
+
+    pub(crate) fn with_names(kind: ContentTagKind, range: Range, names: Rc<ImportNames>) -> Self {
+        TemplateContext {
+            kind,
+            range,
+            imports: Default::default(),
+            names,
+        }
+    }
+
+    // Asks `process` to import `specifier` from `module`, returning the local
+    // name the output should refer to it by. `local_hint` is used as the
+    // start of that name when it is an identifier. Asking for the same import
+    // again, from any template in the module, gives the same name.
+    pub fn request_import(&self, module: &str, specifier: &str, local_hint: &str) -> String {
+        let local = self.names.local(module, specifier, local_hint);
+        let request = ImportRequest {
+            module: module.to_owned(),
+            specifier: specifier.to_owned(),
+            local: local.clone(),
+        };
+        let mut imports = self.imports.borrow_mut();
+        if !imports.contains(&request) {
+            imports.push(request);
+        }
+        local
+    }
+
+    #[cfg(feature = "transform")]
+    pub(crate) fn take_imports(self) -> Vec<ImportRequest> {
+        self.imports.into_inner()
+    }
+
+    // for the JS transformers' `addImport` too
+    #[cfg(any(feature = "transform", feature = "wasm"))]
+    pub(crate) fn names(&self) -> Rc<ImportNames> {
+        self.names.clone()
+    }
+}
+
+// The local names of the imports requested in one module. Each module and
+// specifier gets its own, which like `crate::unique_alias` is numbered until
+// it's in neither the source nor another request, so two imports never share
+// a binding and processing our own output doesn't declare one twice.
+#[derive(Debug, Default)]
+pub(crate) struct ImportNames {
+    src: String,
+    taken: RefCell<Vec<ImportRequest>>,
+}
+
+impl ImportNames {
+    #[cfg(feature = "transform")]
+    pub(crate) fn new(src: &str) -> Rc<Self> {
+        Rc::new(ImportNames {
+            src: src.to_owned(),
+            taken: Default::default(),
+        })
+    }
+
+    pub(crate) fn local(&self, module: &str, specifier: &str, local_hint: &str) -> String {
+        let mut taken = self.taken.borrow_mut();
+        if let Some(request) = taken
+            .iter()
+            .find(|request| request.module == module && request.specifier == specifier)
+        {
+            return request.local.clone();
+        }
+        let base = [local_hint, specifier]
+            .into_iter()
+            .find(|name| is_identifier(name))
+            .unwrap_or("import");
+        let base = format!("{base}_{IMPORT_SUFFIX}");
+        let mut local = base.clone();
+        let mut n = 1;
+        while self.src.contains(&local) || taken.iter().any(|request| request.local == local) {
+            local = format!("{base}_{n}");
+            n += 1;
+        }
+        taken.push(ImportRequest {
+            module: module.to_owned(),
+            specifier: specifier.to_owned(),
+            local: local.clone(),
+        });
+        local
+    }
+}
+
+// A hook that rewrites each template's content before it is emitted, for
+// example to precompile it. A failure for one template doesn't stop the rest
+// of the file from being processed; `process` reports all of them together
+// as `Error::Transformer`. Output that needs other modules can ask for them
+// with `TemplateContext::request_import`.
+pub trait Transformer {
+    fn transform(&self, content: &str, context: &TemplateContext) -> Result<String, String>;
+}
+
+impl<F> Transformer for F
+where
+    F: Fn(&str, &TemplateContext) -> Result<String, String>,
+{
+    fn transform(&self, content: &str, context: &TemplateContext) -> Result<String, String> {
+        self(content, context)
+    }
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_request_import() {
+    let context = TemplateContext::new(ContentTagKind::Expression, Range { start: 0, end: 1 });
+    let local = context.request_import("@ember/component", "setComponentTemplate", "setCT");
+    assert_eq!(local, format!("setCT_{IMPORT_SUFFIX}"));
+    context.request_import("@ember/component", "setComponentTemplate", "setCT");
+    context.request_import("m", "default", "not an identifier");
+    let imports = context.take_imports();
+    assert_eq!(imports.len(), 2);
+    assert_eq!(imports[1].local, format!("default_{IMPORT_SUFFIX}"));
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_requested_names_are_unique() {
+    let src = format!("import {{ a as helper_{IMPORT_SUFFIX} }} from 'earlier';");
+    let names = ImportNames::new(&src);
+    let first = TemplateContext::with_names(
+        ContentTagKind::Expression,
+        Range { start: 0, end: 1 },
+        names.clone(),
+    );
+    let second = TemplateContext::with_names(
+        ContentTagKind::Expression,
+        Range { start: 1, end: 2 },
+        names,
+    );
+    let a = first.request_import("a", "helper", "helper");
+    let b = first.request_import("b", "helper", "helper");
+    let c = first.request_import("a", "other", "helper");
+    // the same import from another template, whatever the hint
+    let again = second.request_import("a", "helper", "different");
+    assert_eq!(a, format!("helper_{IMPORT_SUFFIX}_1"));
+    assert_eq!(b, format!("helper_{IMPORT_SUFFIX}_2"));
+    assert_eq!(c, format!("helper_{IMPORT_SUFFIX}_3"));
+    assert_eq!(again, a);
+}
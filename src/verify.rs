@@ -0,0 +1,155 @@
+use swc_common::comments::SingleThreadedComments;
+use swc_common::{self, sync::Lrc, FileName, SourceMap, Span, DUMMY_SP};
+use swc_ecma_ast::{BigInt, Module, ModuleItem, Number, Str, TplElement};
+use swc_ecma_parser::{lexer::Lexer, Parser, StringInput};
+use swc_ecma_visit::{VisitMut, VisitMutWith};
+
+use crate::{swc_compat, Error};
+
+// Re-parses the printed `code` and checks that it means the same thing as the
+// `expected` module we printed it from. This is how we catch emitter edge
+// cases (like missing parentheses) that would silently change semantics.
+pub(crate) fn verify_output(expected: &Module, code: &str) -> Result<(), Error> {
+    let source_map: Lrc<SourceMap> = Default::default();
+    let source_file = source_map.new_source_file(
+        FileName::Custom("content-tag-output.js".into()),
+        code.to_string(),
+    );
+    let lexer = Lexer::new(
+        swc_compat::syntax(),
+        Default::default(),
+        StringInput::from(&*source_file),
+        None,
+    );
+    let mut parser = Parser::new_from(lexer);
+    let mut actual = parser.parse_module().map_err(|err| {
+        Error::internal(
+            "the generated code does not parse",
+            Some(format!("{}\n\n{}", err.kind().msg(), code)),
+        )
+    })?;
+
+    let mut expected = expected.clone();
+    expected.visit_mut_with(&mut Normalizer);
+    actual.visit_mut_with(&mut Normalizer);
+
+    if expected == actual {
+        return Ok(());
+    }
+
+    // Narrow the report down to the first top-level item that differs, which
+    // is usually small enough to paste into an issue.
+    let repro = expected
+        .body
+        .iter()
+        .zip(actual.body.iter())
+        .find(|(e, a)| e != a)
+        .map(|(e, a)| {
+            format!(
+                "expected:\n{}\nreparsed as:\n{}",
+                print_item(e),
+                print_item(a)
+            )
+        })
+        .unwrap_or_else(|| {
+            format!(
+                "expected {} top-level items, reparsed {}",
+                expected.body.len(),
+                actual.body.len()
+            )
+        });
+
+    Err(Error::internal(
+        "the generated code does not reparse to the transformed program",
+        Some(repro),
+    ))
+}
+
+// Strips everything that legitimately differs between a freshly built AST and
+// one that was parsed from text: positions, syntax contexts, and the raw
+// spellings of literals.
+struct Normalizer;
+
+impl VisitMut for Normalizer {
+    fn visit_mut_span(&mut self, n: &mut Span) {
+        *n = DUMMY_SP;
+    }
+
+    fn visit_mut_str(&mut self, n: &mut Str) {
+        n.visit_mut_children_with(self);
+        n.raw = None;
+    }
+
+    fn visit_mut_number(&mut self, n: &mut Number) {
+        n.visit_mut_children_with(self);
+        n.raw = None;
+    }
+
+    fn visit_mut_big_int(&mut self, n: &mut BigInt) {
+        n.visit_mut_children_with(self);
+        n.raw = None;
+    }
+
+    fn visit_mut_tpl_element(&mut self, n: &mut TplElement) {
+        n.visit_mut_children_with(self);
+        n.cooked = None;
+    }
+}
+
+fn print_item(item: &ModuleItem) -> String {
+    let source_map: Lrc<SourceMap> = Default::default();
+    let comments = SingleThreadedComments::default();
+    let module = Module {
+        span: DUMMY_SP,
+        body: vec![item.clone()],
+        shebang: None,
+    };
+    match swc_compat::print(&source_map, &module, false, Some(&comments), None) {
+        Ok(buf) => String::from_utf8_lossy(&buf).to_string(),
+        Err(_) => format!("{:#?}", item),
+    }
+}
+
+#[cfg(test)]
+use crate::{Options, Preprocessor};
+
+#[test]
+fn test_verifies_transformed_output() {
+    let p = Preprocessor::new();
+    let output = p.process(
+        r#"import x from "y";
+           export class A { static n = 1_000n + 0x10; <template>{{x}} `${n}`</template> }
+           export default new <template>Hi</template>();"#,
+        Options {
+            verify_output: true,
+            ..Default::default()
+        },
+    );
+    assert!(output.is_ok(), "{}", output.err().unwrap());
+}
+
+#[test]
+fn test_reports_mismatches() {
+    let source_map: Lrc<SourceMap> = Default::default();
+    let source_file =
+        source_map.new_source_file(FileName::Anon, "let a = 1; let b = 2;".to_string());
+    let lexer = Lexer::new(
+        swc_compat::syntax(),
+        Default::default(),
+        StringInput::from(&*source_file),
+        None,
+    );
+    let expected = Parser::new_from(lexer).parse_module().unwrap();
+
+    match verify_output(&expected, "let a = 1; let b = 3;") {
+        Err(Error::Internal {
+            detail: Some(detail),
+            ..
+        }) => {
+            assert!(detail.starts_with("expected:\nlet b = 2;"), "{detail}");
+            assert!(detail.contains("reparsed as:\nlet b = 3;"), "{detail}");
+            assert!(!detail.contains("let a"), "{detail}");
+        }
+        other => panic!("expected a mismatch, got {:?}", other.map(|_| ())),
+    }
+}
@@ -0,0 +1,96 @@
+#[cfg(feature = "transform")]
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::locate::Range;
+
+// Something worth telling the user about that doesn't stop us from
+// producing output.
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+pub struct Warning {
+    // identifies the kind of warning, like `unresolved-reference`
+    pub code: String,
+    pub message: String,
+    // the span of the template the warning is about
+    pub range: Range,
+    // where the template is, like `path/to/file.gjs:2:15: 2:30`
+    pub location: String,
+    // `Severity::Warning` unless `Options::severity_overrides` says otherwise
+    pub severity: Severity,
+}
+
+// How much a warning matters. Nothing we report is an error by default, but
+// CI can make some codes errors (which fail `content-tag <dir>`) and drop
+// others it doesn't care about.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    // not reported at all
+    Off,
+    #[default]
+    Warning,
+    Error,
+}
+
+// Gives each warning the severity `overrides` has for its code, dropping
+// the ones that are `Severity::Off`.
+#[cfg(feature = "transform")]
+pub(crate) fn apply_severity_overrides(
+    warnings: &mut Vec<Warning>,
+    overrides: &BTreeMap<String, Severity>,
+) {
+    if overrides.is_empty() {
+        return;
+    }
+    for warning in warnings.iter_mut() {
+        if let Some(severity) = overrides.get(&warning.code) {
+            warning.severity = *severity;
+        }
+    }
+    warnings.retain(|warning| warning.severity != Severity::Off);
+}
+
+#[cfg(all(test, feature = "transform"))]
+use crate::{Options, Preprocessor};
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_overrides_severity_by_code() {
+    let src = "let a = <template> </template>;\nlet b = <template>{{missing}}</template>;";
+    let warnings = |overrides: &[(&str, Severity)]| {
+        let output = Preprocessor::new()
+            .process(
+                src,
+                Options {
+                    collapse_whitespace_only: true,
+                    warn_unresolved: true,
+                    severity_overrides: overrides
+                        .iter()
+                        .map(|(code, severity)| (code.to_string(), *severity))
+                        .collect(),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        output
+            .warnings
+            .into_iter()
+            .map(|warning| (warning.code, warning.severity))
+            .collect::<BTreeMap<String, Severity>>()
+    };
+    assert_eq!(
+        warnings(&[]),
+        BTreeMap::from([
+            ("unresolved-reference".to_owned(), Severity::Warning),
+            ("whitespace-only-template".to_owned(), Severity::Warning),
+        ])
+    );
+    assert_eq!(
+        warnings(&[
+            ("whitespace-only-template", Severity::Error),
+            ("unresolved-reference", Severity::Off),
+        ]),
+        BTreeMap::from([("whitespace-only-template".to_owned(), Severity::Error)])
+    );
+}
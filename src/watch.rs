@@ -0,0 +1,152 @@
+// A stateful integration for watch modes in bundler plugins: it keeps the
+// output for each file it has processed, hands it back for as long as the
+// file's source is the same, and tells the embedder whenever an output it
+// handed out is no longer current.
+//
+// An entry is only ever returned for the exact source it was made from, so
+// a plugin that forgets to call `invalidate` gets a fresh result anyway.
+// `invalidate` is for dropping entries early (say, when a file is deleted)
+// and for having the callbacks run as soon as the watcher notices a change.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::{CodeMapPair, Error, ExtensionOptions, Preprocessor};
+
+struct Entry {
+    src: String,
+    output: Arc<CodeMapPair>,
+}
+
+pub struct WatchSession {
+    options: ExtensionOptions,
+    entries: HashMap<PathBuf, Entry>,
+    on_invalidate: Vec<Box<dyn FnMut(&Path)>>,
+    // the preprocessor from the most recent file that wasn't cached
+    last_preprocessor: Option<Preprocessor>,
+}
+
+impl WatchSession {
+    // `options.filename` is ignored; each file is named by its path. Takes
+    // `Options`, or `ExtensionOptions` for options that depend on each
+    // file's extension.
+    pub fn new(options: impl Into<ExtensionOptions>) -> Result<Self, Error> {
+        let options = options.into();
+        options.validate()?;
+        Ok(WatchSession {
+            options,
+            entries: HashMap::new(),
+            on_invalidate: vec![],
+            last_preprocessor: None,
+        })
+    }
+
+    // Called with the path of each file whose output stops being current:
+    // the ones passed to `invalidate`, and the ones `process` sees with a new
+    // source. Not called for files that were never processed.
+    pub fn on_invalidate(&mut self, callback: impl FnMut(&Path) + 'static) {
+        self.on_invalidate.push(Box::new(callback));
+    }
+
+    // The output for `src`, from the cache if the file was last processed
+    // with the same source. Errors aren't cached.
+    pub fn process(
+        &mut self,
+        path: impl Into<PathBuf>,
+        src: &str,
+    ) -> Result<Arc<CodeMapPair>, Error> {
+        let path = path.into();
+        if let Some(entry) = self.entries.get(&path) {
+            if entry.src == src {
+                return Ok(entry.output.clone());
+            }
+            self.invalidate(&path);
+        }
+
+        let file = self.options.process(&path, src);
+        self.last_preprocessor = Some(file.preprocessor);
+        let output = Arc::new(file.result?);
+        self.entries.insert(
+            path,
+            Entry {
+                src: src.to_owned(),
+                output: output.clone(),
+            },
+        );
+        Ok(output)
+    }
+
+    // Drops what's cached for `path`, returning whether there was anything.
+    pub fn invalidate(&mut self, path: &Path) -> bool {
+        if self.entries.remove(path).is_none() {
+            return false;
+        }
+        for callback in &mut self.on_invalidate {
+            callback(path);
+        }
+        true
+    }
+
+    pub fn invalidate_all(&mut self) {
+        let paths: Vec<PathBuf> = self.entries.keys().cloned().collect();
+        for path in paths {
+            self.invalidate(&path);
+        }
+    }
+
+    pub fn is_cached(&self, path: &Path) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // For rendering the errors of the most recent `process` call that didn't
+    // come from the cache.
+    pub fn last_preprocessor(&self) -> Option<&Preprocessor> {
+        self.last_preprocessor.as_ref()
+    }
+}
+
+#[cfg(test)]
+use crate::Options;
+#[cfg(test)]
+use std::cell::RefCell;
+#[cfg(test)]
+use std::rc::Rc;
+
+#[test]
+fn test_caches_until_the_source_changes() {
+    let mut session = WatchSession::new(Options::default()).unwrap();
+    let invalidated = Rc::new(RefCell::new(vec![]));
+    let seen = invalidated.clone();
+    session.on_invalidate(move |path| seen.borrow_mut().push(path.to_owned()));
+
+    let first = session.process("a.gjs", "<template>A</template>").unwrap();
+    let again = session.process("a.gjs", "<template>A</template>").unwrap();
+    assert!(Arc::ptr_eq(&first, &again));
+    assert!(invalidated.borrow().is_empty());
+
+    let changed = session.process("a.gjs", "<template>B</template>").unwrap();
+    assert!(changed.code.contains("`B`"));
+    assert_eq!(*invalidated.borrow(), vec![PathBuf::from("a.gjs")]);
+
+    assert!(session.invalidate(Path::new("a.gjs")));
+    assert!(!session.invalidate(Path::new("a.gjs")));
+    assert_eq!(invalidated.borrow().len(), 2);
+    assert!(session.is_empty());
+}
+
+#[test]
+fn test_errors_are_not_cached() {
+    let mut session = WatchSession::new(Options::default()).unwrap();
+    assert!(session.process("a.gjs", "<template>A").is_err());
+    assert!(!session.is_cached(Path::new("a.gjs")));
+    assert!(session.last_preprocessor().is_some());
+}
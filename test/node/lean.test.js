@@ -0,0 +1,37 @@
+import chai from "chai";
+import { Preprocessor } from "content-tag/lean";
+import { Preprocessor as Full } from "content-tag";
+
+const { expect } = chai;
+
+const p = new Preprocessor();
+
+describe("content-tag/lean", function () {
+  it("processes like the full build", function () {
+    let src = "export default <template>Hi</template>";
+    expect(p.process(src).code).to.equal(new Full().process(src).code);
+  });
+
+  it("renders parse errors as plain text", function () {
+    let err;
+    try {
+      p.process(`const thing = "face";\n  <template>Hi`, {
+        filename: "path/to/my/component.gjs",
+      });
+    } catch (e) {
+      err = e;
+    }
+    expect(err.message).to.equal(
+      "Parse Error at path/to/my/component.gjs:2:15: 2:15"
+    );
+    expect(err.source_code).to.match(/^path\/to\/my\/component\.gjs:2:15: /);
+    expect(err.loc.startLine).to.equal(2);
+  });
+
+  it("doesn't list pretty-errors", function () {
+    expect(Preprocessor.capabilities().features).not.to.include(
+      "pretty-errors"
+    );
+    expect(Full.capabilities().features).to.include("pretty-errors");
+  });
+});
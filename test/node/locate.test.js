@@ -0,0 +1,19 @@
+import chai from "chai";
+import { Preprocessor } from "content-tag/locate";
+import { Preprocessor as Full } from "content-tag";
+
+const { expect } = chai;
+
+const p = new Preprocessor();
+
+describe("content-tag/locate", function () {
+  it("parses like the full build", function () {
+    let src = "export class A { <template>Hi</template> }";
+    expect(p.parse(src)).to.eql(new Full().parse(src));
+  });
+
+  it("has no process", function () {
+    expect(p.process).to.equal(undefined);
+    expect(Preprocessor.capabilities().features).not.to.include("transform");
+  });
+});
@@ -178,3 +178,17 @@ describe(`parse`, function () {
     }).to.throw(`Parse Error at path/to/my/component.gjs:2:15: 2:15`);
   });
 });
+
+describe(`schemaVersion`, function () {
+  it("reports the parse schema version", function () {
+    expect(Preprocessor.schemaVersion()).to.equal(1);
+  });
+});
+
+describe(`capabilities`, function () {
+  it("lists the supported options", function () {
+    let capabilities = Preprocessor.capabilities();
+    expect(capabilities.schemaVersion).to.equal(Preprocessor.schemaVersion());
+    expect(capabilities.options).to.include("target");
+  });
+});
@@ -69,6 +69,53 @@ describe(`process`, function () {
     }).to.throw(`Parse Error at path/to/my/component.gjs:2:15: 2:15`);
   });
 
+  it("processes many sources in one call", function () {
+    let [a, b] = p.processMany(["<template>A</template>", "<template>B"], {
+      filename: "fixture.gjs",
+    });
+    expect(a.code).to.contain("`A`");
+    expect(b.error.message).to.contain("Parse Error at fixture.gjs");
+  });
+
+  it("decodes bytes that aren't valid UTF-8, with a warning", function () {
+    let src = new Uint8Array([
+      ...new TextEncoder().encode("let s = '"),
+      0xe9,
+      ...new TextEncoder().encode("';\n<template>Hi</template>"),
+    ]);
+    let { code, warnings } = p.processBytes(src);
+    expect(code).to.contain("'\uFFFD'");
+    expect(warnings.map((w) => w.code)).to.deep.equal(["invalid-utf8"]);
+  });
+
+  it("applies severity overrides to warnings", function () {
+    let { warnings } = p.process(`<template> </template>`, {
+      collapse_whitespace_only: true,
+      severity_overrides: { "whitespace-only-template": "error" },
+    });
+    expect(warnings.map((w) => [w.code, w.severity])).to.deep.equal([
+      ["whitespace-only-template", "error"],
+    ]);
+
+    ({ warnings } = p.process(`<template> </template>`, {
+      collapse_whitespace_only: true,
+      severity_overrides: { "whitespace-only-template": "off" },
+    }));
+    expect(warnings).to.deep.equal([]);
+  });
+
+  it("throws what an option getter throws, and keeps working", function () {
+    let options = {
+      get inline_source_map() {
+        throw new Error("no options for you");
+      },
+    };
+    expect(function () {
+      p.process("<template>Hi</template>", options);
+    }).to.throw("no options for you");
+    expect(p.process("<template>Hi</template>").code).to.contain("template_");
+  });
+
   it("Offers source_code snippet on parse errors", function () {
     let parseError;
     try {
@@ -94,6 +141,89 @@ describe(`process`, function () {
       .matches(/Expected ident.*[\u001b].*class \{/s);
   });
 
+  it("Offers sourceInfo on parse errors", function () {
+    let parseError;
+    try {
+      p.process(`let x;\nclass {`, { filename: "a.gjs" });
+    } catch (err) {
+      parseError = err;
+    }
+    expect(parseError).to.have.deep.property("sourceInfo", {
+      file: "a.gjs",
+      sourceText: `let x;\nclass {`,
+      lineStarts: [0, 7],
+    });
+  });
+
+  it("Offers a structured loc on parse errors", function () {
+    let parseError;
+    try {
+      p.process(`let x;\nlet y = ;`, { filename: "a.gjs" });
+    } catch (err) {
+      parseError = err;
+    }
+    expect(parseError).to.have.deep.property("loc", {
+      file: "a.gjs",
+      startLine: 2,
+      startColumn: 8,
+      endLine: 2,
+      endColumn: 9,
+      byteStart: 15,
+      byteEnd: 16,
+    });
+  });
+
+  it("returns files without templates unchanged", function () {
+    let input = `/* keep me */ import   x from "x" // héllo\r\nexport { x as  y };\n`;
+    let output = p.process(input, { inline_source_map: true });
+
+    expect(output.code).to.equal(input);
+    expect(output.metadata.dependencies).to.eql(["x"]);
+  });
+
+  it("lists the module's exports", function () {
+    let output = p.process(
+      `export const helper = () => 1;\nexport { a as b } from "./a";\n<template>Hi</template>`,
+      { collect_exports: true }
+    );
+
+    expect(output.metadata.exports).to.deep.equal({
+      named: [{ name: "helper", local: "helper", kind: "variable" }],
+      default: { kind: "template" },
+      reExports: [{ module: "./a", name: "b", imported: "a" }],
+    });
+  });
+
+  it("marks generated regions", function () {
+    let output = p.process(`<template>Hi</template>`, { mark_generated_regions: true });
+    let [replacement] = output.metadata.replacements;
+
+    expect(output.code.slice(0, replacement.range.start)).to.match(
+      /\/\*#__CONTENT_TAG_START__\*\/$/
+    );
+    expect(output.code.slice(replacement.range.end)).to.match(
+      /^\/\*#__CONTENT_TAG_END__\*\//
+    );
+  });
+
+  it("marks assigned template calls as pure", function () {
+    let output = p.process(`export const A = <template>A</template>;\nf(<template>B</template>);`, {
+      pure_annotations: true,
+    });
+
+    expect(output.code).to.match(/A = \/\*#__PURE__\*\/template/);
+    expect(output.code.match(/#__PURE__/g)).to.have.length(1);
+  });
+
+  it("detects output that was already transformed", function () {
+    let once = p.process(`<template>Hi</template>`);
+    let twice = p.process(once.code, { detect_already_transformed: true });
+
+    expect(once.metadata.alreadyTransformed).to.equal(false);
+    expect(twice.metadata.alreadyTransformed).to.equal(true);
+    expect(twice.code).to.equal(once.code);
+  });
+
   it("Provides inline source maps if inline_source_map option is set to true", function () {
     let output = p.process(`<template>Hi</template>`, { inline_source_map: true });
 